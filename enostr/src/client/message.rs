@@ -15,6 +15,11 @@ pub enum ClientMessage {
     Close {
         sub_id: String,
     },
+    /// NIP-45 request for the number of events matching the filters
+    Count {
+        sub_id: String,
+        filters: Vec<Filter>,
+    },
     Raw(String),
 }
 
@@ -35,25 +40,37 @@ impl ClientMessage {
         ClientMessage::Close { sub_id }
     }
 
+    pub fn count(sub_id: String, filters: Vec<Filter>) -> Self {
+        ClientMessage::Count { sub_id, filters }
+    }
+
     pub fn to_json(&self) -> Result<String, Error> {
         Ok(match self {
             Self::Event { note } => json!(["EVENT", note]).to_string(),
             Self::Raw(raw) => raw.clone(),
-            Self::Req { sub_id, filters } => {
-                if filters.is_empty() {
-                    format!("[\"REQ\",\"{}\",{{ }}]", sub_id)
-                } else if filters.len() == 1 {
-                    let filters_json_str = filters[0].json()?;
-                    format!("[\"REQ\",\"{}\",{}]", sub_id, filters_json_str)
-                } else {
-                    let filters_json_str: Result<Vec<String>, Error> = filters
-                        .iter()
-                        .map(|f| f.json().map_err(Into::<Error>::into))
-                        .collect();
-                    format!("[\"REQ\",\"{}\",{}]", sub_id, filters_json_str?.join(","))
-                }
-            }
+            Self::Req { sub_id, filters } => filters_message("REQ", sub_id, filters)?,
+            Self::Count { sub_id, filters } => filters_message("COUNT", sub_id, filters)?,
             Self::Close { sub_id } => json!(["CLOSE", sub_id]).to_string(),
         })
     }
 }
+
+fn filters_message(cmd: &str, sub_id: &str, filters: &[Filter]) -> Result<String, Error> {
+    Ok(if filters.is_empty() {
+        format!("[\"{}\",\"{}\",{{ }}]", cmd, sub_id)
+    } else if filters.len() == 1 {
+        let filters_json_str = filters[0].json()?;
+        format!("[\"{}\",\"{}\",{}]", cmd, sub_id, filters_json_str)
+    } else {
+        let filters_json_str: Result<Vec<String>, Error> = filters
+            .iter()
+            .map(|f| f.json().map_err(Into::<Error>::into))
+            .collect();
+        format!(
+            "[\"{}\",\"{}\",{}]",
+            cmd,
+            sub_id,
+            filters_json_str?.join(",")
+        )
+    })
+}
@@ -1,14 +1,25 @@
+use nostr::bech32::Hrp;
+use nostr::nips::nip44;
 use nostr::nips::nip49::EncryptedSecretKey;
 use serde::Deserialize;
 use serde::Serialize;
 
+use crate::nip46::RemoteSigner;
+use crate::Error;
 use crate::Pubkey;
+use crate::Result;
 use crate::SecretKey;
 
+static HRP_NSEC: Hrp = Hrp::parse_unchecked("nsec");
+
 #[derive(Debug, Eq, PartialEq)]
 pub struct Keypair {
     pub pubkey: Pubkey,
     pub secret_key: Option<SecretKey>,
+
+    /// A NIP-46 signer that signs for this account, when we don't have
+    /// its secret key
+    pub remote_signer: Option<RemoteSigner>,
 }
 
 impl Keypair {
@@ -18,20 +29,41 @@ impl Keypair {
         Keypair {
             pubkey: Pubkey::new(nostr_keys.public_key().to_bytes()),
             secret_key: Some(cloned_secret_key),
+            remote_signer: None,
         }
     }
 
     pub fn new(pubkey: Pubkey, secret_key: Option<SecretKey>) -> Self {
-        Keypair { pubkey, secret_key }
+        Keypair {
+            pubkey,
+            secret_key,
+            remote_signer: None,
+        }
     }
 
     pub fn only_pubkey(pubkey: Pubkey) -> Self {
         Keypair {
             pubkey,
             secret_key: None,
+            remote_signer: None,
+        }
+    }
+
+    /// An account whose notes are signed by a remote signer
+    pub fn with_remote_signer(pubkey: Pubkey, remote_signer: RemoteSigner) -> Self {
+        Keypair {
+            pubkey,
+            secret_key: None,
+            remote_signer: Some(remote_signer),
         }
     }
 
+    /// Whether we can sign notes for this account, either ourselves or
+    /// with its remote signer
+    pub fn can_sign(&self) -> bool {
+        self.secret_key.is_some() || self.remote_signer.is_some()
+    }
+
     pub fn to_full<'a>(&'a self) -> Option<FilledKeypair<'a>> {
         if let Some(secret_key) = &self.secret_key {
             Some(FilledKeypair {
@@ -67,6 +99,24 @@ impl<'a> FilledKeypair<'a> {
             secret_key: self.secret_key.to_owned(),
         }
     }
+
+    /// NIP-44 encrypt something only we can read, like the private
+    /// entries of a NIP-51 list
+    pub fn encrypt_to_self(&self, content: &str) -> Result<String> {
+        let pk = nostr::PublicKey::from_slice(self.pubkey.bytes())
+            .map_err(|_| Error::InvalidPublicKey)?;
+
+        nip44::encrypt(self.secret_key, &pk, content, nip44::Version::V2)
+            .map_err(|e| Error::Generic(e.to_string()))
+    }
+
+    /// Decrypt a NIP-44 payload we encrypted to ourselves
+    pub fn decrypt_from_self(&self, payload: &str) -> Result<String> {
+        let pk = nostr::PublicKey::from_slice(self.pubkey.bytes())
+            .map_err(|_| Error::InvalidPublicKey)?;
+
+        nip44::decrypt(self.secret_key, &pk, payload).map_err(|e| Error::Generic(e.to_string()))
+    }
 }
 
 impl FullKeypair {
@@ -89,10 +139,17 @@ impl FullKeypair {
         }
     }
 
+    /// The bech32 encoded secret key, for the user to back up
+    pub fn nsec(&self) -> Option<String> {
+        nostr::bech32::encode::<nostr::bech32::Bech32>(HRP_NSEC, &self.secret_key.to_secret_bytes())
+            .ok()
+    }
+
     pub fn to_keypair(self) -> Keypair {
         Keypair {
             pubkey: self.pubkey,
             secret_key: Some(self.secret_key),
+            remote_signer: None,
         }
     }
 }
@@ -103,9 +160,10 @@ impl std::fmt::Display for Keypair {
             f,
             "Keypair:\n\tpublic: {}\n\tsecret: {}",
             self.pubkey,
-            match self.secret_key {
-                Some(_) => "Some(<hidden>)",
-                None => "None",
+            match (&self.secret_key, &self.remote_signer) {
+                (Some(_), _) => "Some(<hidden>)",
+                (None, Some(_)) => "remote signer",
+                (None, None) => "None",
             }
         )
     }
@@ -121,23 +179,111 @@ impl std::fmt::Display for FullKeypair {
 pub struct SerializableKeypair {
     pub pubkey: Pubkey,
     pub encrypted_secret_key: Option<EncryptedSecretKey>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remote_signer: Option<SerializableRemoteSigner>,
+}
+
+/// A [`RemoteSigner`] as it's stored, with the secret key we talk to it
+/// with encrypted like our own
+#[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SerializableRemoteSigner {
+    pub remote_pubkey: Pubkey,
+    pub relays: Vec<String>,
+    pub client_pubkey: Pubkey,
+    pub encrypted_client_key: EncryptedSecretKey,
 }
 
 impl SerializableKeypair {
     pub fn from_keypair(kp: &Keypair, pass: &str, log_n: u8) -> Self {
         Self {
             pubkey: kp.pubkey.clone(),
-            encrypted_secret_key: kp.secret_key.clone().and_then(|s| {
-                EncryptedSecretKey::new(&s, pass, log_n, nostr::nips::nip49::KeySecurity::Weak).ok()
+            encrypted_secret_key: kp
+                .secret_key
+                .clone()
+                .and_then(|s| encrypt_secret_key(&s, pass, log_n)),
+            remote_signer: kp.remote_signer.as_ref().and_then(|signer| {
+                Some(SerializableRemoteSigner {
+                    remote_pubkey: signer.remote_pubkey,
+                    relays: signer.relays.clone(),
+                    client_pubkey: signer.client.pubkey,
+                    encrypted_client_key: encrypt_secret_key(
+                        &signer.client.secret_key,
+                        pass,
+                        log_n,
+                    )?,
+                })
             }),
         }
     }
 
     pub fn to_keypair(&self, pass: &str) -> Keypair {
-        Keypair::new(
+        let mut kp = Keypair::new(
             self.pubkey.clone(),
             self.encrypted_secret_key
                 .and_then(|e| e.to_secret_key(pass).ok()),
-        )
+        );
+
+        kp.remote_signer = self.remote_signer.as_ref().and_then(|signer| {
+            let client_key = signer.encrypted_client_key.to_secret_key(pass).ok()?;
+            Some(RemoteSigner::new(
+                signer.remote_pubkey,
+                signer.relays.clone(),
+                FullKeypair::new(signer.client_pubkey, client_key),
+            ))
+        });
+
+        kp
+    }
+}
+
+fn encrypt_secret_key(key: &SecretKey, pass: &str, log_n: u8) -> Option<EncryptedSecretKey> {
+    EncryptedSecretKey::new(key, pass, log_n, nostr::nips::nip49::KeySecurity::Weak).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serialize_remote_signer() {
+        let signer = RemoteSigner::new(
+            FullKeypair::generate().pubkey,
+            vec!["wss://relay.example.com".to_owned()],
+            FullKeypair::generate(),
+        );
+        let kp = Keypair::with_remote_signer(FullKeypair::generate().pubkey, signer);
+
+        let json = serde_json::to_string(&SerializableKeypair::from_keypair(&kp, "", 7)).unwrap();
+        let restored = serde_json::from_str::<SerializableKeypair>(&json)
+            .unwrap()
+            .to_keypair("");
+
+        assert_eq!(restored, kp);
+        assert!(restored.can_sign());
+    }
+
+    #[test]
+    fn test_nsec() {
+        use nostr::nips::nip19::FromBech32;
+
+        let kp = FullKeypair::generate();
+        let nsec = kp.nsec().unwrap();
+
+        assert!(nsec.starts_with("nsec1"));
+        assert_eq!(SecretKey::from_bech32(&nsec).unwrap(), kp.secret_key);
+    }
+
+    #[test]
+    fn test_encrypt_to_self_roundtrip() {
+        let kp = FullKeypair::generate();
+        let filled = kp.to_filled();
+
+        let payload = filled.encrypt_to_self("[[\"e\",\"abcd\"]]").unwrap();
+        assert_ne!(payload, "[[\"e\",\"abcd\"]]");
+        assert_eq!(
+            filled.decrypt_from_self(&payload).unwrap(),
+            "[[\"e\",\"abcd\"]]"
+        );
     }
 }
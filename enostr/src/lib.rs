@@ -2,6 +2,8 @@ mod client;
 mod error;
 mod filter;
 mod keypair;
+mod nip19;
+pub mod nip46;
 mod note;
 mod profile;
 mod pubkey;
@@ -11,13 +13,26 @@ pub use client::ClientMessage;
 pub use error::Error;
 pub use ewebsock;
 pub use filter::Filter;
-pub use keypair::{FilledKeypair, FullKeypair, Keypair, SerializableKeypair};
+pub use keypair::{
+    FilledKeypair, FullKeypair, Keypair, SerializableKeypair, SerializableRemoteSigner,
+};
+pub use nip19::{Nip19, NoteAddress};
+pub use nip46::{
+    check_signed, BunkerUri, Nip46Request, Nip46Response, NostrConnectUri, RemoteSigner,
+    KIND_NOSTR_CONNECT,
+};
+pub use nostr::hashes;
 pub use nostr::SecretKey;
 pub use note::{Note, NoteId};
 pub use profile::Profile;
 pub use pubkey::Pubkey;
-pub use relay::message::{RelayEvent, RelayMessage};
-pub use relay::pool::{PoolEvent, RelayPool};
+pub use relay::info::{RelayFee, RelayFees, RelayInformation, RelayLimitation};
+pub use relay::message::{CommandResult, RelayEvent, RelayMessage};
+pub use relay::pool::{PoolEvent, PoolRelay, RelayPool};
+pub use relay::proxy::{is_onion, Proxy};
+pub use relay::stats::{
+    ConnectionEvent, ConnectionEventKind, Frame, FrameDirection, RelayStats, SubscriptionStats,
+};
 pub use relay::{Relay, RelayStatus};
 
 pub type Result<T> = std::result::Result<T, error::Error>;
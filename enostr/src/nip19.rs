@@ -0,0 +1,259 @@
+use crate::{Error, NoteId, Pubkey};
+use nostr::bech32::{self, Bech32, Hrp};
+
+static HRP_NPUB: Hrp = Hrp::parse_unchecked("npub");
+static HRP_NPROFILE: Hrp = Hrp::parse_unchecked("nprofile");
+static HRP_NOTE: Hrp = Hrp::parse_unchecked("note");
+static HRP_NEVENT: Hrp = Hrp::parse_unchecked("nevent");
+static HRP_NADDR: Hrp = Hrp::parse_unchecked("naddr");
+static HRP_NRELAY: Hrp = Hrp::parse_unchecked("nrelay");
+
+const TLV_SPECIAL: u8 = 0;
+const TLV_RELAY: u8 = 1;
+const TLV_AUTHOR: u8 = 2;
+const TLV_KIND: u8 = 3;
+
+/// An addressable (parameterized replaceable) event: the newest event of
+/// this kind by this author with this d tag
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct NoteAddress {
+    pub kind: u32,
+    pub pubkey: Pubkey,
+    pub identifier: String,
+
+    /// Relays the event is supposed to be on
+    pub relays: Vec<String>,
+}
+
+impl NoteAddress {
+    pub fn new(kind: u32, pubkey: Pubkey, identifier: String) -> Self {
+        NoteAddress {
+            kind,
+            pubkey,
+            identifier,
+            relays: vec![],
+        }
+    }
+
+    /// Parse an `a` tag value, ie. `30023:<hex pubkey>:<d tag>`
+    pub fn from_coordinate(coordinate: &str) -> Result<Self, Error> {
+        let mut parts = coordinate.splitn(3, ':');
+        let kind = parts
+            .next()
+            .and_then(|kind| kind.parse().ok())
+            .ok_or(Error::DecodeFailed)?;
+        let pubkey = Pubkey::from_hex(parts.next().ok_or(Error::DecodeFailed)?)?;
+        let identifier = parts.next().ok_or(Error::DecodeFailed)?.to_owned();
+
+        Ok(NoteAddress::new(kind, pubkey, identifier))
+    }
+
+    /// The `a` tag value for this address
+    pub fn coordinate(&self) -> String {
+        format!("{}:{}:{}", self.kind, self.pubkey.hex(), self.identifier)
+    }
+
+    pub fn to_bech(&self) -> Option<String> {
+        let mut data = vec![];
+        push_tlv(&mut data, TLV_SPECIAL, self.identifier.as_bytes())?;
+        for relay in &self.relays {
+            push_tlv(&mut data, TLV_RELAY, relay.as_bytes())?;
+        }
+        push_tlv(&mut data, TLV_AUTHOR, self.pubkey.bytes())?;
+        push_tlv(&mut data, TLV_KIND, &self.kind.to_be_bytes())?;
+
+        bech32::encode::<Bech32>(HRP_NADDR, &data).ok()
+    }
+}
+
+/// A decoded NIP-19 entity, as found in `nostr:` URIs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Nip19 {
+    Pubkey(Pubkey),
+    Profile {
+        pubkey: Pubkey,
+        relays: Vec<String>,
+    },
+    Note(NoteId),
+    Event {
+        id: NoteId,
+        relays: Vec<String>,
+        author: Option<Pubkey>,
+        kind: Option<u32>,
+    },
+    Address(NoteAddress),
+    Relay(String),
+}
+
+impl Nip19 {
+    /// Parse a bech32 entity, with or without the `nostr:` prefix
+    pub fn parse(s: &str) -> Result<Self, Error> {
+        let s = s.strip_prefix("nostr:").unwrap_or(s);
+        let (hrp, data) = bech32::decode(s).map_err(|_| Error::InvalidBech32)?;
+
+        if hrp == HRP_NPUB {
+            Ok(Nip19::Pubkey(Pubkey::new(bytes32(&data)?)))
+        } else if hrp == HRP_NOTE {
+            Ok(Nip19::Note(NoteId::new(bytes32(&data)?)))
+        } else if hrp == HRP_NPROFILE {
+            let tlv = Tlv::parse(&data)?;
+            Ok(Nip19::Profile {
+                pubkey: Pubkey::new(bytes32(tlv.special.ok_or(Error::DecodeFailed)?)?),
+                relays: tlv.relays,
+            })
+        } else if hrp == HRP_NEVENT {
+            let tlv = Tlv::parse(&data)?;
+            Ok(Nip19::Event {
+                id: NoteId::new(bytes32(tlv.special.ok_or(Error::DecodeFailed)?)?),
+                relays: tlv.relays,
+                author: tlv.author,
+                kind: tlv.kind,
+            })
+        } else if hrp == HRP_NADDR {
+            let tlv = Tlv::parse(&data)?;
+            let identifier = std::str::from_utf8(tlv.special.unwrap_or_default())
+                .map_err(|_| Error::DecodeFailed)?
+                .to_owned();
+            Ok(Nip19::Address(NoteAddress {
+                kind: tlv.kind.ok_or(Error::DecodeFailed)?,
+                pubkey: tlv.author.ok_or(Error::DecodeFailed)?,
+                identifier,
+                relays: tlv.relays,
+            }))
+        } else if hrp == HRP_NRELAY {
+            let tlv = Tlv::parse(&data)?;
+            let relay = std::str::from_utf8(tlv.special.ok_or(Error::DecodeFailed)?)
+                .map_err(|_| Error::DecodeFailed)?;
+            Ok(Nip19::Relay(relay.to_owned()))
+        } else {
+            Err(Error::InvalidBech32)
+        }
+    }
+
+    /// The pubkey this entity points at, or the author of the event it
+    /// points at if we know it
+    pub fn pubkey(&self) -> Option<&Pubkey> {
+        match self {
+            Nip19::Pubkey(pubkey) | Nip19::Profile { pubkey, .. } => Some(pubkey),
+            Nip19::Event { author, .. } => author.as_ref(),
+            Nip19::Address(address) => Some(&address.pubkey),
+            Nip19::Note(_) | Nip19::Relay(_) => None,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Tlv<'a> {
+    special: Option<&'a [u8]>,
+    relays: Vec<String>,
+    author: Option<Pubkey>,
+    kind: Option<u32>,
+}
+
+impl<'a> Tlv<'a> {
+    fn parse(mut data: &'a [u8]) -> Result<Self, Error> {
+        let mut tlv = Tlv::default();
+
+        while data.len() >= 2 {
+            let (typ, len) = (data[0], data[1] as usize);
+            let value = data.get(2..2 + len).ok_or(Error::DecodeFailed)?;
+            data = &data[2 + len..];
+
+            match typ {
+                TLV_SPECIAL => tlv.special = tlv.special.or(Some(value)),
+                TLV_RELAY => {
+                    if let Ok(relay) = std::str::from_utf8(value) {
+                        tlv.relays.push(relay.to_owned());
+                    }
+                }
+                TLV_AUTHOR => tlv.author = Some(Pubkey::new(bytes32(value)?)),
+                TLV_KIND => {
+                    let kind: [u8; 4] = value.try_into().map_err(|_| Error::InvalidByteSize)?;
+                    tlv.kind = Some(u32::from_be_bytes(kind));
+                }
+                // unknown types are to be ignored
+                _ => {}
+            }
+        }
+
+        Ok(tlv)
+    }
+}
+
+/// None if the value doesn't fit in a TLV, which can be 255 bytes at most
+fn push_tlv(data: &mut Vec<u8>, typ: u8, value: &[u8]) -> Option<()> {
+    let len = u8::try_from(value.len()).ok()?;
+    data.push(typ);
+    data.push(len);
+    data.extend_from_slice(value);
+    Some(())
+}
+
+fn bytes32(data: &[u8]) -> Result<[u8; 32], Error> {
+    data.try_into().map_err(|_| Error::InvalidByteSize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_naddr_roundtrip() {
+        let mut address = NoteAddress::new(30023, Pubkey::new([7; 32]), "my-article".to_owned());
+        address.relays.push("wss://relay.damus.io".to_owned());
+
+        let naddr = address.to_bech().unwrap();
+        assert!(naddr.starts_with("naddr1"));
+
+        let parsed = Nip19::parse(&format!("nostr:{naddr}")).unwrap();
+        assert_eq!(parsed, Nip19::Address(address.clone()));
+        assert_eq!(parsed.pubkey(), Some(&address.pubkey));
+    }
+
+    #[test]
+    fn test_naddr_too_long() {
+        let address = NoteAddress::new(30023, Pubkey::new([7; 32]), "a".repeat(255));
+        assert!(address.to_bech().is_some());
+
+        let address = NoteAddress::new(30023, Pubkey::new([7; 32]), "a".repeat(256));
+        assert_eq!(address.to_bech(), None);
+
+        let mut address = NoteAddress::new(30023, Pubkey::new([7; 32]), "a".to_owned());
+        address.relays.push(format!("wss://{}", "r".repeat(250)));
+        assert_eq!(address.to_bech(), None);
+    }
+
+    #[test]
+    fn test_parse_npub_and_note() {
+        let pubkey = Pubkey::new([1; 32]);
+        let npub = pubkey.to_bech().unwrap();
+        assert_eq!(Nip19::parse(&npub).unwrap(), Nip19::Pubkey(pubkey));
+
+        let id = NoteId::new([2; 32]);
+        let note = id.to_bech().unwrap();
+        assert_eq!(Nip19::parse(&note).unwrap(), Nip19::Note(id));
+
+        assert!(Nip19::parse("nostr:nope").is_err());
+    }
+
+    #[test]
+    fn test_parse_nrelay() {
+        let mut data = vec![];
+        push_tlv(&mut data, TLV_SPECIAL, b"wss://relay.damus.io").unwrap();
+        let nrelay = bech32::encode::<Bech32>(HRP_NRELAY, &data).unwrap();
+
+        assert_eq!(
+            Nip19::parse(&nrelay).unwrap(),
+            Nip19::Relay("wss://relay.damus.io".to_owned())
+        );
+        assert_eq!(Nip19::parse(&nrelay).unwrap().pubkey(), None);
+    }
+
+    #[test]
+    fn test_coordinate() {
+        let address = NoteAddress::new(30023, Pubkey::new([7; 32]), "a:b".to_owned());
+        let coordinate = address.coordinate();
+        assert_eq!(NoteAddress::from_coordinate(&coordinate).unwrap(), address);
+        assert!(NoteAddress::from_coordinate("30023:nothex:d").is_err());
+    }
+}
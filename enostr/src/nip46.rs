@@ -0,0 +1,388 @@
+use nostr::nips::{nip04, nip44};
+use nostr::JsonUtil;
+use nostrdb::{Filter, Note, NoteBuilder};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use url::Url;
+
+use crate::{Error, FullKeypair, Pubkey, Result};
+
+/// NIP-46 requests and responses are sent as this kind
+pub const KIND_NOSTR_CONNECT: u64 = 24133;
+
+/// A `bunker://<remote-signer-pubkey>?relay=wss://...&secret=...` uri, as
+/// given out by remote signers
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct BunkerUri {
+    pub remote_pubkey: Pubkey,
+    pub relays: Vec<String>,
+    pub secret: Option<String>,
+}
+
+impl BunkerUri {
+    pub fn parse(uri: &str) -> Result<Self> {
+        let url = Url::parse(uri.trim()).map_err(|e| Error::Generic(e.to_string()))?;
+        if url.scheme() != "bunker" {
+            return Err(Error::Generic(format!("not a bunker:// uri: {uri}")));
+        }
+
+        let remote_pubkey = url
+            .host_str()
+            .ok_or_else(|| Error::Generic("bunker uri is missing a pubkey".to_owned()))
+            .and_then(Pubkey::from_hex)?;
+
+        let mut relays = vec![];
+        let mut secret = None;
+        for (key, value) in url.query_pairs() {
+            match key.as_ref() {
+                "relay" => relays.push(value.into_owned()),
+                "secret" => secret = Some(value.into_owned()),
+                _ => {}
+            }
+        }
+
+        if relays.is_empty() {
+            return Err(Error::Generic("bunker uri has no relays".to_owned()));
+        }
+
+        Ok(BunkerUri {
+            remote_pubkey,
+            relays,
+            secret,
+        })
+    }
+}
+
+/// A `nostrconnect://` uri we give to a signer, for when the user starts
+/// the connection from their signer instead of pasting a bunker uri
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct NostrConnectUri {
+    pub client: FullKeypair,
+    pub relays: Vec<String>,
+    pub secret: String,
+}
+
+impl NostrConnectUri {
+    pub fn new(relays: Vec<String>, secret: String) -> Self {
+        NostrConnectUri {
+            client: FullKeypair::generate(),
+            relays,
+            secret,
+        }
+    }
+
+    /// The uri to show the user, naming us as `name`
+    pub fn uri(&self, name: &str) -> String {
+        let mut url = Url::parse(&format!("nostrconnect://{}", self.client.pubkey.hex()))
+            .expect("nostrconnect url");
+
+        {
+            let mut query = url.query_pairs_mut();
+            for relay in &self.relays {
+                query.append_pair("relay", relay);
+            }
+            query.append_pair("secret", &self.secret);
+            query.append_pair("name", name);
+        }
+
+        url.to_string()
+    }
+
+    /// Check if an event is a signer accepting our uri. If it is, we now
+    /// know who the signer is.
+    pub fn read_connect(&self, event: &str) -> Result<RemoteSigner> {
+        let (author, json) = read_message(&self.client, event)?;
+        let response: Nip46Response =
+            serde_json::from_str(&json).map_err(|e| Error::Generic(e.to_string()))?;
+
+        if response.result.as_deref() != Some(self.secret.as_str()) {
+            return Err(Error::Generic(
+                "connect response doesn't have our secret".to_owned(),
+            ));
+        }
+
+        Ok(RemoteSigner::new(
+            author,
+            self.relays.clone(),
+            self.client.clone(),
+        ))
+    }
+}
+
+/// A remote signer holding one of our accounts' keys. We talk to it over
+/// its relays with a keypair of our own, which is all we keep locally.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct RemoteSigner {
+    pub remote_pubkey: Pubkey,
+    pub relays: Vec<String>,
+    pub client: FullKeypair,
+}
+
+/// A NIP-46 request, before it's encrypted
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Nip46Request {
+    pub id: String,
+    pub method: String,
+    pub params: Vec<String>,
+}
+
+/// A remote signer's answer to one of our requests
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Nip46Response {
+    pub id: String,
+    #[serde(default)]
+    pub result: Option<String>,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+impl RemoteSigner {
+    pub fn new(remote_pubkey: Pubkey, relays: Vec<String>, client: FullKeypair) -> Self {
+        RemoteSigner {
+            remote_pubkey,
+            relays,
+            client,
+        }
+    }
+
+    /// Start talking to the signer from a bunker uri, with a fresh keypair
+    pub fn from_bunker_uri(uri: &BunkerUri) -> Self {
+        RemoteSigner::new(
+            uri.remote_pubkey,
+            uri.relays.clone(),
+            FullKeypair::generate(),
+        )
+    }
+
+    /// Responses to our requests, from any signer
+    pub fn filter(client: &Pubkey) -> Filter {
+        Filter::new()
+            .kinds([KIND_NOSTR_CONNECT])
+            .pubkeys([client.bytes()])
+            .limit(0)
+            .build()
+    }
+
+    /// The kind 24133 note carrying a request to the signer
+    pub fn request_note(&self, request: &Nip46Request) -> Result<Note<'static>> {
+        let json = serde_json::to_string(request).map_err(|e| Error::Generic(e.to_string()))?;
+        let content = encrypt(&self.client, &self.remote_pubkey, &json)?;
+        request_note(&self.client, &self.remote_pubkey, &content)
+    }
+
+    /// Read a response to one of our requests. Returns an error if it's not
+    /// from our signer or we can't decrypt it.
+    pub fn read_response(&self, event: &str) -> Result<Nip46Response> {
+        let (author, response) = read_message(&self.client, event)?;
+        if author != self.remote_pubkey {
+            return Err(Error::Generic(format!(
+                "nip46 message from {author}, not our signer"
+            )));
+        }
+
+        serde_json::from_str(&response).map_err(|e| Error::Generic(e.to_string()))
+    }
+}
+
+/// Check a note a remote signer signed for `account`, returning its id
+pub fn check_signed(account: &Pubkey, event: &str) -> Result<String> {
+    let verified = nostr::Event::from_json(event).map_err(|e| Error::Generic(e.to_string()))?;
+    verified
+        .verify()
+        .map_err(|e| Error::Generic(e.to_string()))?;
+
+    let json: Value = serde_json::from_str(event).map_err(|e| Error::Generic(e.to_string()))?;
+    if json.get("pubkey").and_then(|pk| pk.as_str()) != Some(account.hex().as_str()) {
+        return Err(Error::Generic(
+            "the signer signed for a different pubkey".to_owned(),
+        ));
+    }
+
+    json.get("id")
+        .and_then(|id| id.as_str())
+        .map(|id| id.to_owned())
+        .ok_or_else(|| Error::Generic("signed note has no id".to_owned()))
+}
+
+/// Verify and decrypt a kind 24133 event sent to `to`, returning its
+/// author and the decrypted content
+pub fn read_message(to: &FullKeypair, event: &str) -> Result<(Pubkey, String)> {
+    let verified = nostr::Event::from_json(event).map_err(|e| Error::Generic(e.to_string()))?;
+    verified
+        .verify()
+        .map_err(|e| Error::Generic(e.to_string()))?;
+
+    let event: Value = serde_json::from_str(event).map_err(|e| Error::Generic(e.to_string()))?;
+    if event.get("kind").and_then(|k| k.as_u64()) != Some(KIND_NOSTR_CONNECT) {
+        return Err(Error::Generic("not a nip46 message".to_owned()));
+    }
+
+    let author = event
+        .get("pubkey")
+        .and_then(|pk| pk.as_str())
+        .ok_or(Error::InvalidPublicKey)
+        .and_then(Pubkey::from_hex)?;
+    let content = event
+        .get("content")
+        .and_then(|c| c.as_str())
+        .unwrap_or_default();
+
+    let content = decrypt(to, &author, content)?;
+    Ok((author, content))
+}
+
+/// Build a signed kind 24133 note from `from` to `to`, with already
+/// encrypted content
+pub fn request_note(from: &FullKeypair, to: &Pubkey, content: &str) -> Result<Note<'static>> {
+    let seckey = from.secret_key.to_secret_bytes();
+    NoteBuilder::new()
+        .kind(KIND_NOSTR_CONNECT as u32)
+        .content(content)
+        .start_tag()
+        .tag_str("p")
+        .tag_str(&to.hex())
+        .sign(&seckey)
+        .build()
+        .ok_or_else(|| Error::Generic("couldn't build nip46 note".to_owned()))
+}
+
+/// NIP-44 encrypt a message to the other side
+pub fn encrypt(from: &FullKeypair, to: &Pubkey, content: &str) -> Result<String> {
+    let pk = nostr::PublicKey::from_slice(to.bytes()).map_err(|_| Error::InvalidPublicKey)?;
+    nip44::encrypt(&from.secret_key, &pk, content, nip44::Version::V2)
+        .map_err(|e| Error::Generic(e.to_string()))
+}
+
+/// Decrypt a message from the other side. Older signers still use NIP-04,
+/// so we accept that too.
+pub fn decrypt(to: &FullKeypair, from: &Pubkey, payload: &str) -> Result<String> {
+    let pk = nostr::PublicKey::from_slice(from.bytes()).map_err(|_| Error::InvalidPublicKey)?;
+
+    if payload.contains("?iv=") {
+        nip04::decrypt(&to.secret_key, &pk, payload).map_err(|e| Error::Generic(e.to_string()))
+    } else {
+        nip44::decrypt(&to.secret_key, &pk, payload).map_err(|e| Error::Generic(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A stand-in for a bunker: reads our request and answers it
+    fn bunker_answer(
+        bunker: &FullKeypair,
+        client: &Pubkey,
+        request: &Note,
+        result: &str,
+    ) -> String {
+        let (author, json) = read_message(bunker, &request.json().unwrap()).unwrap();
+        assert_eq!(&author, client);
+
+        let request: Nip46Request = serde_json::from_str(&json).unwrap();
+        let response = Nip46Response {
+            id: request.id,
+            result: Some(result.to_owned()),
+            error: None,
+        };
+
+        let content = encrypt(bunker, client, &serde_json::to_string(&response).unwrap()).unwrap();
+        request_note(bunker, client, &content)
+            .unwrap()
+            .json()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_check_signed() {
+        let account = FullKeypair::generate();
+        let note = request_note(&account, &account.pubkey, "hi").unwrap();
+        let json = note.json().unwrap();
+
+        assert_eq!(
+            check_signed(&account.pubkey, &json).unwrap(),
+            hex::encode(note.id())
+        );
+        assert!(check_signed(&FullKeypair::generate().pubkey, &json).is_err());
+    }
+
+    #[test]
+    fn test_parse_bunker_uri() {
+        let pk = FullKeypair::generate().pubkey;
+        let uri = BunkerUri::parse(&format!(
+            "bunker://{}?relay=wss%3A%2F%2Frelay.example.com&relay=wss://other.example.com&secret=abc",
+            pk.hex()
+        ))
+        .unwrap();
+
+        assert_eq!(uri.remote_pubkey, pk);
+        assert_eq!(
+            uri.relays,
+            vec!["wss://relay.example.com", "wss://other.example.com"]
+        );
+        assert_eq!(uri.secret.as_deref(), Some("abc"));
+
+        assert!(BunkerUri::parse(&format!("bunker://{}", pk.hex())).is_err());
+        assert!(BunkerUri::parse("nostrconnect://abc?relay=wss://r.example.com").is_err());
+    }
+
+    #[test]
+    fn test_nostrconnect() {
+        let connect = NostrConnectUri::new(
+            vec!["wss://relay.example.com".to_owned()],
+            "s3cret".to_owned(),
+        );
+        assert!(connect.uri("notedeck").starts_with(&format!(
+            "nostrconnect://{}?relay=wss%3A%2F%2Frelay.example.com&secret=s3cret",
+            connect.client.pubkey.hex()
+        )));
+
+        let bunker = FullKeypair::generate();
+        let answer = |result: &str| {
+            let response = format!("{{\"id\":\"x\",\"result\":\"{result}\"}}");
+            let content = encrypt(&bunker, &connect.client.pubkey, &response).unwrap();
+            request_note(&bunker, &connect.client.pubkey, &content)
+                .unwrap()
+                .json()
+                .unwrap()
+        };
+
+        let signer = connect.read_connect(&answer("s3cret")).unwrap();
+        assert_eq!(signer.remote_pubkey, bunker.pubkey);
+        assert_eq!(signer.client, connect.client);
+
+        assert!(connect.read_connect(&answer("ack")).is_err());
+    }
+
+    #[test]
+    fn test_request_roundtrip() {
+        let bunker = FullKeypair::generate();
+        let signer = RemoteSigner::new(
+            bunker.pubkey,
+            vec!["wss://relay.example.com".to_owned()],
+            FullKeypair::generate(),
+        );
+
+        let request = Nip46Request {
+            id: "1".to_owned(),
+            method: "get_public_key".to_owned(),
+            params: vec![],
+        };
+        let note = signer.request_note(&request).unwrap();
+        let answer = bunker_answer(&bunker, &signer.client.pubkey, &note, "ack");
+
+        let response = signer.read_response(&answer).unwrap();
+        assert_eq!(response.id, "1");
+        assert_eq!(response.result.as_deref(), Some("ack"));
+
+        // someone else can't answer for our signer
+        let impostor = FullKeypair::generate();
+        let content = encrypt(&impostor, &signer.client.pubkey, "{\"id\":\"1\"}").unwrap();
+        let answer = request_note(&impostor, &signer.client.pubkey, &content)
+            .unwrap()
+            .json()
+            .unwrap();
+        assert!(read_message(&signer.client, &answer).is_ok());
+        assert!(signer.read_response(&answer).is_err());
+    }
+}
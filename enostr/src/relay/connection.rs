@@ -0,0 +1,65 @@
+use ewebsock::{WsEvent, WsMessage, WsReceiver, WsSender};
+
+use super::proxy::Proxy;
+use crate::Result;
+
+#[cfg(not(target_arch = "wasm32"))]
+use super::proxy::ProxiedSocket;
+
+/// The websocket a relay talks to us over. Usually this is ewebsock, but
+/// relays behind a proxy get a connection we open ourselves.
+pub(crate) enum RelayConnection {
+    Direct {
+        sender: WsSender,
+        receiver: WsReceiver,
+    },
+
+    #[cfg(not(target_arch = "wasm32"))]
+    Proxied(ProxiedSocket),
+
+    /// We're not allowed to connect to this relay right now
+    Closed,
+}
+
+impl RelayConnection {
+    pub(crate) fn open(
+        url: &str,
+        proxy: Option<&Proxy>,
+        wakeup: impl Fn() + Send + Sync + 'static,
+    ) -> Result<Self> {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(proxy) = proxy {
+            return Ok(RelayConnection::Proxied(ProxiedSocket::connect(
+                proxy, url, wakeup,
+            )?));
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        if proxy.is_some() {
+            return Err(crate::Error::Generic(
+                "proxies aren't supported on the web".to_owned(),
+            ));
+        }
+
+        let (sender, receiver) = ewebsock::connect_with_wakeup(url, wakeup)?;
+        Ok(RelayConnection::Direct { sender, receiver })
+    }
+
+    pub(crate) fn send(&mut self, msg: WsMessage) {
+        match self {
+            RelayConnection::Direct { sender, .. } => sender.send(msg),
+            #[cfg(not(target_arch = "wasm32"))]
+            RelayConnection::Proxied(socket) => socket.send(msg),
+            RelayConnection::Closed => {}
+        }
+    }
+
+    pub(crate) fn try_recv(&self) -> Option<WsEvent> {
+        match self {
+            RelayConnection::Direct { receiver, .. } => receiver.try_recv(),
+            #[cfg(not(target_arch = "wasm32"))]
+            RelayConnection::Proxied(socket) => socket.try_recv(),
+            RelayConnection::Closed => None,
+        }
+    }
+}
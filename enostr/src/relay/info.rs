@@ -0,0 +1,113 @@
+use crate::Result;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+/// A NIP-11 relay information document. Everything is optional since
+/// relays are pretty inconsistent about what they fill out.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RelayInformation {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub pubkey: Option<String>,
+    pub contact: Option<String>,
+    pub supported_nips: Option<Vec<u32>>,
+    pub software: Option<String>,
+    pub version: Option<String>,
+    pub limitation: Option<RelayLimitation>,
+    pub payments_url: Option<String>,
+    pub fees: Option<RelayFees>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RelayLimitation {
+    pub max_message_length: Option<u64>,
+    pub max_subscriptions: Option<u64>,
+    pub max_filters: Option<u64>,
+    pub max_limit: Option<u64>,
+    pub max_subid_length: Option<u64>,
+    pub auth_required: Option<bool>,
+    pub payment_required: Option<bool>,
+}
+
+/// What a relay charges, if it's a paid relay
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RelayFees {
+    pub admission: Option<Vec<RelayFee>>,
+    pub subscription: Option<Vec<RelayFee>>,
+    pub publication: Option<Vec<RelayFee>>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RelayFee {
+    pub amount: u64,
+    pub unit: String,
+    /// For subscriptions, how many seconds a payment lasts
+    pub period: Option<u64>,
+    /// For publication fees, which kinds it applies to
+    pub kinds: Option<Vec<u32>>,
+}
+
+impl RelayInformation {
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn supports_nip(&self, nip: u32) -> bool {
+        self.supported_nips
+            .as_ref()
+            .map_or(false, |nips| nips.contains(&nip))
+    }
+
+    /// The http(s) url where the information document of a websocket
+    /// relay url lives.
+    pub fn http_url(relay_url: &str) -> Option<String> {
+        let mut url = Url::parse(relay_url).ok()?;
+        let scheme = match url.scheme() {
+            "wss" => "https",
+            "ws" => "http",
+            _ => return None,
+        };
+        url.set_scheme(scheme).ok()?;
+        Some(url.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_relay_information() {
+        let json = r#"{"name":"damus","supported_nips":[1,11,50],"limitation":{"max_filters":10,"max_limit":500},"icon":"unknown fields are ignored"}"#;
+        let info = RelayInformation::from_json(json).unwrap();
+        assert_eq!(info.name.as_deref(), Some("damus"));
+        assert!(info.supports_nip(50));
+        assert!(!info.supports_nip(42));
+        assert_eq!(info.limitation.unwrap().max_filters, Some(10));
+    }
+
+    #[test]
+    fn test_parse_relay_fees() {
+        let json = r#"{"payments_url":"https://relay.example/pay","limitation":{"payment_required":true},"fees":{"admission":[{"amount":21000,"unit":"msats"}],"subscription":[{"amount":5000000,"unit":"msats","period":2592000}]}}"#;
+        let info = RelayInformation::from_json(json).unwrap();
+        assert_eq!(info.limitation.unwrap().payment_required, Some(true));
+
+        let fees = info.fees.unwrap();
+        assert_eq!(fees.admission.unwrap()[0].amount, 21000);
+        assert_eq!(fees.subscription.unwrap()[0].period, Some(2592000));
+        assert_eq!(fees.publication, None);
+    }
+
+    #[test]
+    fn test_http_url() {
+        assert_eq!(
+            RelayInformation::http_url("wss://relay.damus.io").as_deref(),
+            Some("https://relay.damus.io/")
+        );
+        assert_eq!(
+            RelayInformation::http_url("ws://localhost:8080").as_deref(),
+            Some("http://localhost:8080/")
+        );
+        assert_eq!(RelayInformation::http_url("https://example.com"), None);
+    }
+}
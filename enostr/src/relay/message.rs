@@ -1,11 +1,28 @@
 use crate::{Error, Result};
 use ewebsock::{WsEvent, WsMessage};
+use serde::Deserialize;
 
+/// A relay's answer to an EVENT we sent (NIP-01 OK message)
 #[derive(Debug, Eq, PartialEq)]
 pub struct CommandResult<'a> {
-    event_id: &'a str,
-    status: bool,
-    message: &'a str,
+    pub event_id: &'a str,
+    pub status: bool,
+    pub message: String,
+}
+
+impl<'a> CommandResult<'a> {
+    /// The machine readable prefix of the message, ie. "duplicate" or
+    /// "rate-limited"
+    pub fn prefix(&self) -> Option<&str> {
+        self.message
+            .split_once(':')
+            .map(|(prefix, _)| prefix.trim())
+    }
+}
+
+#[derive(Deserialize)]
+struct CountResult {
+    count: u64,
 }
 
 #[derive(Debug, Eq, PartialEq)]
@@ -14,6 +31,12 @@ pub enum RelayMessage<'a> {
     Eose(&'a str),
     Event(&'a str, &'a str),
     Notice(&'a str),
+
+    /// NIP-45 answer to a COUNT request: the subscription id and the count
+    Count(&'a str, u64),
+
+    /// The relay ended one of our subscriptions: its id and the reason
+    Closed(&'a str, String),
 }
 
 #[derive(Debug)]
@@ -57,7 +80,7 @@ impl<'a> RelayMessage<'a> {
         RelayMessage::Notice(msg)
     }
 
-    pub fn ok(event_id: &'a str, status: bool, message: &'a str) -> Self {
+    pub fn ok(event_id: &'a str, status: bool, message: String) -> Self {
         RelayMessage::OK(CommandResult {
             event_id,
             status,
@@ -69,6 +92,14 @@ impl<'a> RelayMessage<'a> {
         RelayMessage::Event(sub_id, ev)
     }
 
+    pub fn count(sub_id: &'a str, count: u64) -> Self {
+        RelayMessage::Count(sub_id, count)
+    }
+
+    pub fn closed(sub_id: &'a str, message: String) -> Self {
+        RelayMessage::Closed(sub_id, message)
+    }
+
     pub fn from_json(msg: &'a str) -> Result<RelayMessage<'a>> {
         if msg.is_empty() {
             return Err(Error::Empty);
@@ -118,18 +149,28 @@ impl<'a> RelayMessage<'a> {
         // OK (NIP-20)
         // Relay response format: ["OK",<event_id>, <true|false>, <message>]
         if &msg[0..=5] == "[\"OK\"," {
-            // TODO: fix this
-            let event_id = &msg[7..71];
-            let booly = &msg[73..77];
-            let status: bool = if booly == "true" {
-                true
-            } else if booly == "false" {
-                false
-            } else {
-                return Err(Error::DecodeFailed);
-            };
+            let (_, event_id, status, message): (&str, &'a str, bool, String) =
+                serde_json::from_str(msg).map_err(|_| Error::DecodeFailed)?;
+
+            return Ok(Self::ok(event_id, status, message));
+        }
+
+        // COUNT (NIP-45)
+        // Relay response format: ["COUNT", <subscription_id>, {"count": <integer>}]
+        if msg.starts_with("[\"COUNT\",") {
+            let (_, sub_id, result): (&str, &'a str, CountResult) =
+                serde_json::from_str(msg).map_err(|_| Error::DecodeFailed)?;
+
+            return Ok(Self::count(sub_id, result.count));
+        }
+
+        // CLOSED
+        // Relay response format: ["CLOSED", <subscription_id>, <message>]
+        if msg.starts_with("[\"CLOSED\",") {
+            let (_, sub_id, message): (&str, &'a str, String) =
+                serde_json::from_str(msg).map_err(|_| Error::DecodeFailed)?;
 
-            return Ok(Self::ok(event_id, status, "fixme"));
+            return Ok(Self::closed(sub_id, message));
         }
 
         Err(Error::DecodeFailed)
@@ -279,4 +320,30 @@ mod tests {
             Error::DecodeFailed
         );
     }
+
+    #[test]
+    fn test_handle_count() -> Result<()> {
+        assert_eq!(
+            RelayMessage::from_json(r#"["COUNT","followers",{"count":238}]"#)?,
+            RelayMessage::count("followers", 238)
+        );
+
+        // Missing count
+        assert_eq!(
+            RelayMessage::from_json(r#"["COUNT","followers",{}]"#).unwrap_err(),
+            Error::DecodeFailed
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_handle_closed() -> Result<()> {
+        assert_eq!(
+            RelayMessage::from_json(r#"["CLOSED","sub:1","error: too many filters"]"#)?,
+            RelayMessage::closed("sub:1", "error: too many filters".to_owned())
+        );
+
+        Ok(())
+    }
 }
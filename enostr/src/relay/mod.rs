@@ -1,13 +1,28 @@
-use ewebsock::{WsMessage, WsReceiver, WsSender};
+use ewebsock::WsMessage;
 
-use crate::{ClientMessage, Result};
+use crate::{ClientMessage, RelayMessage, Result};
 use nostrdb::Filter;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::hash::{Hash, Hasher};
+use std::time::{SystemTime, UNIX_EPOCH};
 use tracing::{debug, error, info};
 
+mod connection;
+pub mod info;
 pub mod message;
 pub mod pool;
+pub mod proxy;
+pub mod stats;
+
+use connection::RelayConnection;
+use info::{RelayInformation, RelayLimitation};
+use proxy::Proxy;
+use stats::{ConnectionEventKind, RelayStats};
+
+/// How far back before a disconnect we ask for notes when we replay a
+/// subscription, to cover clock skew and notes that were in flight
+const REPLAY_SINCE_GAP: u64 = 120;
 
 #[derive(Debug)]
 pub enum RelayStatus {
@@ -16,11 +31,56 @@ pub enum RelayStatus {
     Disconnected,
 }
 
+/// A subscription that is open on a relay. We keep these around so we can
+/// send them again when we reconnect.
+#[derive(Debug, Clone)]
+pub struct RelaySubscription {
+    pub filters: Vec<Filter>,
+
+    /// Whether the relay has sent us everything it had stored for this
+    /// subscription. After this, notes arrive as they are published.
+    pub eose: bool,
+
+    /// How many REQs this went out as
+    splits: usize,
+
+    /// The REQs that have sent EOSE or were closed by the relay
+    finished_splits: HashSet<usize>,
+}
+
+impl RelaySubscription {
+    fn new(filters: Vec<Filter>) -> Self {
+        RelaySubscription {
+            filters,
+            eose: false,
+            splits: 0,
+            finished_splits: HashSet::new(),
+        }
+    }
+}
+
 pub struct Relay {
     pub url: String,
     pub status: RelayStatus,
-    pub sender: WsSender,
-    pub receiver: WsReceiver,
+    pub(crate) connection: RelayConnection,
+
+    /// The proxy we connect through, if any
+    proxy: Option<Proxy>,
+
+    /// NIP-11 information document, if we've fetched it
+    pub info: Option<RelayInformation>,
+
+    /// Every REQ we've sent that hasn't been closed yet, by subscription id
+    pub subscriptions: HashMap<String, RelaySubscription>,
+
+    /// How many times we've connected to this relay
+    pub connections: u32,
+
+    /// When we last lost our connection, as a unix timestamp
+    disconnected_at: Option<u64>,
+
+    /// Traffic counters and recent frames, for debugging
+    pub stats: RelayStats,
 }
 
 impl fmt::Debug for Relay {
@@ -48,19 +108,54 @@ impl PartialEq for Relay {
 impl Eq for Relay {}
 
 impl Relay {
-    pub fn new(url: String, wakeup: impl Fn() + Send + Sync + 'static) -> Result<Self> {
-        let status = RelayStatus::Connecting;
-        let (sender, receiver) = ewebsock::connect_with_wakeup(&url, wakeup)?;
+    pub fn new(
+        url: String,
+        proxy: Option<Proxy>,
+        wakeup: impl Fn() + Send + Sync + 'static,
+    ) -> Result<Self> {
+        let mut relay = Self::new_disconnected(url, proxy);
+        relay.connect(wakeup)?;
+        Ok(relay)
+    }
 
-        Ok(Self {
+    /// A relay we don't connect to until `connect` is called
+    pub fn new_disconnected(url: String, proxy: Option<Proxy>) -> Self {
+        Self {
             url,
-            sender,
-            receiver,
-            status,
-        })
+            connection: RelayConnection::Closed,
+            proxy,
+            status: RelayStatus::Disconnected,
+            info: None,
+            subscriptions: HashMap::new(),
+            connections: 0,
+            disconnected_at: None,
+            stats: RelayStats::default(),
+        }
     }
 
     pub fn send(&mut self, msg: &ClientMessage) {
+        match msg {
+            ClientMessage::Req { sub_id, filters } => {
+                self.subscriptions
+                    .insert(sub_id.clone(), RelaySubscription::new(filters.clone()));
+                self.send_req(sub_id, filters);
+            }
+
+            ClientMessage::Close { sub_id } => {
+                if let Some(sub) = self.subscriptions.remove(sub_id) {
+                    for n in 0..sub.splits {
+                        self.send_json(&ClientMessage::close(split_subid(sub_id, n)));
+                    }
+                } else {
+                    self.send_json(msg);
+                }
+            }
+
+            _ => self.send_json(msg),
+        }
+    }
+
+    fn send_json(&mut self, msg: &ClientMessage) {
         let json = match msg.to_json() {
             Ok(json) => {
                 debug!("sending {} to {}", json, self.url);
@@ -72,21 +167,156 @@ impl Relay {
             }
         };
 
+        self.stats.record_out(&json);
         let txt = WsMessage::Text(json);
-        self.sender.send(txt);
+        self.connection.send(txt);
+    }
+
+    /// The limits from the relay's information document, if we have it
+    pub fn limitation(&self) -> Option<&RelayLimitation> {
+        self.info.as_ref().and_then(|info| info.limitation.as_ref())
+    }
+
+    /// Send a REQ shaped to fit the relay's limits. It may go out as
+    /// several REQs if it has more filters than the relay accepts.
+    fn send_req(&mut self, sub_id: &str, filters: &[Filter]) {
+        let reqs = shape_filters(filters, self.limitation());
+        if let Some(sub) = self.subscriptions.get_mut(sub_id) {
+            sub.splits = reqs.len();
+            sub.finished_splits.clear();
+        }
+
+        for (n, filters) in reqs.into_iter().enumerate() {
+            let subid = split_subid(sub_id, n);
+            self.stats.record_req(&subid);
+            self.send_json(&ClientMessage::req(subid, filters));
+        }
     }
 
     pub fn connect(&mut self, wakeup: impl Fn() + Send + Sync + 'static) -> Result<()> {
-        let (sender, receiver) = ewebsock::connect_with_wakeup(&self.url, wakeup)?;
+        self.connection = RelayConnection::open(&self.url, self.proxy.as_ref(), wakeup)?;
+        self.stats
+            .record_connection(ConnectionEventKind::Connecting);
         self.status = RelayStatus::Connecting;
-        self.sender = sender;
-        self.receiver = receiver;
         Ok(())
     }
 
+    /// Switch to a different proxy. Takes effect the next time we connect.
+    pub fn set_proxy(&mut self, proxy: Option<Proxy>) {
+        self.proxy = proxy;
+    }
+
+    /// Drop our connection and stay disconnected until `connect` is
+    /// called again
+    pub fn disconnect(&mut self) {
+        self.connection = RelayConnection::Closed;
+        self.stats.record_connection(ConnectionEventKind::Closed);
+        self.handle_disconnected();
+    }
+
+    /// Whether the relay advertises support for a NIP. We only know this
+    /// once its information document has been fetched.
+    pub fn supports_nip(&self, nip: u32) -> bool {
+        self.info
+            .as_ref()
+            .map_or(false, |info| info.supports_nip(nip))
+    }
+
     pub fn ping(&mut self) {
         let msg = WsMessage::Ping(vec![]);
-        self.sender.send(msg);
+        self.connection.send(msg);
+    }
+
+    /// Whether we've been connected to this relay before the current
+    /// connection
+    pub fn is_reconnect(&self) -> bool {
+        self.connections > 1
+    }
+
+    pub fn handle_opened(&mut self) {
+        self.stats.record_connection(ConnectionEventKind::Opened);
+        self.status = RelayStatus::Connected;
+        self.connections += 1;
+
+        if self.is_reconnect() {
+            self.replay_subscriptions();
+        }
+    }
+
+    pub fn handle_disconnected(&mut self) {
+        if let RelayStatus::Connected = self.status {
+            self.disconnected_at = Some(unix_now());
+        }
+        self.status = RelayStatus::Disconnected;
+    }
+
+    /// The subscription a REQ we sent belongs to. Subscriptions split
+    /// to fit the relay's limits go out as "sub", "sub:1", "sub:2", ...
+    pub fn subscription_id<'a>(&self, wire_id: &'a str) -> &'a str {
+        self.split_of(wire_id).0
+    }
+
+    fn split_of<'a>(&self, wire_id: &'a str) -> (&'a str, usize) {
+        if self.subscriptions.contains_key(wire_id) {
+            return (wire_id, 0);
+        }
+
+        match wire_id.rsplit_once(':') {
+            Some((subid, n)) if self.subscriptions.contains_key(subid) => match n.parse() {
+                Ok(n) if n > 0 => (subid, n),
+                _ => (wire_id, 0),
+            },
+            _ => (wire_id, 0),
+        }
+    }
+
+    /// One of our REQs sent EOSE, or was closed by the relay. Returns
+    /// whether that finished the subscription, ie. this was the last of
+    /// the REQs it was split into. Ids we don't know are passed along.
+    pub fn handle_eose(&mut self, wire_id: &str) -> bool {
+        let (subid, n) = self.split_of(wire_id);
+        let sub = if let Some(sub) = self.subscriptions.get_mut(subid) {
+            sub
+        } else {
+            return true;
+        };
+
+        if sub.eose || !sub.finished_splits.insert(n) {
+            // we already passed this one along
+            return false;
+        }
+
+        sub.eose = sub.finished_splits.len() >= sub.splits.max(1);
+        sub.eose
+    }
+
+    /// Keep track of which subscriptions are caught up, for when we replay
+    /// them after a reconnect. Returns false for EOSE and CLOSED frames we
+    /// hold back until every REQ of a split subscription has sent one.
+    fn handle_text(&mut self, txt: &str) -> bool {
+        if !txt.starts_with("[\"EOSE\"") && !txt.starts_with("[\"CLOSED\"") {
+            return true;
+        }
+
+        match RelayMessage::from_json(txt) {
+            Ok(RelayMessage::Eose(subid)) | Ok(RelayMessage::Closed(subid, _)) => {
+                self.handle_eose(subid)
+            }
+            _ => true,
+        }
+    }
+
+    /// Send all of our open subscriptions again after a reconnect.
+    /// Subscriptions that were caught up only ask for what was published
+    /// since we lost the connection.
+    fn replay_subscriptions(&mut self) {
+        let subs: Vec<(String, RelaySubscription)> = self.subscriptions.drain().collect();
+        info!("replaying {} subscriptions to {}", subs.len(), self.url);
+
+        for (subid, sub) in subs {
+            let filters = replay_filters(sub, self.disconnected_at);
+            self.send(&ClientMessage::req(subid, filters));
+        }
     }
 
     pub fn subscribe(&mut self, subid: String, filters: Vec<Filter>) {
@@ -97,3 +327,148 @@ impl Relay {
         self.send(&ClientMessage::req(subid, filters));
     }
 }
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// The subscription id of the nth REQ a subscription was split into
+fn split_subid(sub_id: &str, n: usize) -> String {
+    if n == 0 {
+        sub_id.to_owned()
+    } else {
+        format!("{sub_id}:{n}")
+    }
+}
+
+fn max_filters(limitation: Option<&RelayLimitation>) -> Option<usize> {
+    limitation
+        .and_then(|l| l.max_filters)
+        .filter(|max| *max > 0)
+        .map(|max| max as usize)
+}
+
+/// Clamp filter limits to the relay's `max_limit` and split filters into
+/// groups of at most `max_filters`. Each group is sent as its own REQ.
+fn shape_filters(filters: &[Filter], limitation: Option<&RelayLimitation>) -> Vec<Vec<Filter>> {
+    let max_limit = limitation.and_then(|l| l.max_limit);
+    let filters: Vec<Filter> = filters
+        .iter()
+        .cloned()
+        .map(|filter| match (filter.limit(), max_limit) {
+            (Some(limit), Some(max_limit)) if limit > max_limit => filter.limit_mut(max_limit),
+            _ => filter,
+        })
+        .collect();
+
+    match max_filters(limitation) {
+        Some(max) if filters.len() > max => filters.chunks(max).map(|c| c.to_vec()).collect(),
+        _ => vec![filters],
+    }
+}
+
+/// The filters to send when replaying a subscription
+fn replay_filters(sub: RelaySubscription, disconnected_at: Option<u64>) -> Vec<Filter> {
+    let since = match disconnected_at {
+        Some(disconnected_at) if sub.eose => disconnected_at.saturating_sub(REPLAY_SINCE_GAP),
+        // we never got everything, ask for it all again
+        _ => return sub.filters,
+    };
+
+    sub.filters
+        .into_iter()
+        .map(|filter| filter.since_mut(since))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sub(eose: bool) -> RelaySubscription {
+        RelaySubscription {
+            eose,
+            ..RelaySubscription::new(vec![Filter::new().kinds([1]).limit(100).build()])
+        }
+    }
+
+    #[test]
+    fn test_replay_caught_up_subscription() {
+        let filters = replay_filters(sub(true), Some(10_000));
+        let json = filters[0].json().unwrap();
+        assert!(json.contains(&format!("\"since\":{}", 10_000 - REPLAY_SINCE_GAP)));
+    }
+
+    #[test]
+    fn test_shape_filters() {
+        let filters: Vec<Filter> = (0..5)
+            .map(|_| Filter::new().kinds([1]).limit(1000).build())
+            .collect();
+        let limitation = RelayLimitation {
+            max_filters: Some(2),
+            max_limit: Some(100),
+            ..Default::default()
+        };
+
+        let reqs = shape_filters(&filters, Some(&limitation));
+        assert_eq!(reqs.len(), 3);
+        assert_eq!(reqs[2].len(), 1);
+        assert_eq!(reqs[0][0].limit(), Some(100));
+
+        // no information document, send as is
+        let reqs = shape_filters(&filters, None);
+        assert_eq!(reqs.len(), 1);
+        assert_eq!(reqs[0][0].limit(), Some(1000));
+    }
+
+    #[test]
+    fn test_split_subid() {
+        assert_eq!(split_subid("sub", 0), "sub");
+        assert_eq!(split_subid("sub", 2), "sub:2");
+    }
+
+    #[test]
+    fn test_split_subscription_eose() {
+        let mut relay = Relay::new_disconnected("wss://relay.example.com".to_owned(), None);
+        relay.info = Some(RelayInformation {
+            limitation: Some(RelayLimitation {
+                max_filters: Some(2),
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+
+        let filters: Vec<Filter> = (0..5).map(|_| Filter::new().kinds([1]).build()).collect();
+        relay.subscribe("sub".to_owned(), filters);
+
+        assert_eq!(relay.subscription_id("sub:2"), "sub");
+        assert_eq!(relay.subscription_id("sub"), "sub");
+        assert_eq!(relay.subscription_id("other:1"), "other:1");
+
+        // held back until every REQ is done, duplicates don't count
+        assert!(!relay.handle_text(r#"["EOSE","sub:1"]"#));
+        assert!(!relay.handle_text(r#"["EOSE","sub"]"#));
+        assert!(!relay.handle_text(r#"["EOSE","sub"]"#));
+        assert!(!relay.subscriptions["sub"].eose);
+
+        assert!(relay.handle_text(r#"["CLOSED","sub:2","error: slow down"]"#));
+        assert!(relay.subscriptions["sub"].eose);
+        assert!(!relay.handle_text(r#"["EOSE","sub:2"]"#));
+
+        // not ours, or not an EOSE
+        assert!(relay.handle_text(r#"["EOSE","nope"]"#));
+        assert!(relay.handle_text(r#"["NOTICE","hi"]"#));
+    }
+
+    #[test]
+    fn test_replay_unfinished_subscription() {
+        let filters = replay_filters(sub(false), Some(10_000));
+        assert!(!filters[0].json().unwrap().contains("since"));
+
+        let filters = replay_filters(sub(true), None);
+        assert!(!filters[0].json().unwrap().contains("since"));
+    }
+}
@@ -1,5 +1,7 @@
+use crate::relay::proxy::{is_onion, Proxy};
+use crate::relay::stats::ConnectionEventKind;
 use crate::relay::{Relay, RelayStatus};
-use crate::{ClientMessage, Result};
+use crate::{ClientMessage, Error, Result};
 use nostrdb::Filter;
 
 use std::time::{Duration, Instant};
@@ -52,11 +54,46 @@ impl PoolRelay {
     pub fn initial_reconnect_duration() -> Duration {
         Duration::from_secs(5)
     }
+
+    /// We never wait longer than this between reconnect attempts
+    pub fn max_reconnect_duration() -> Duration {
+        Duration::from_secs(5 * 60)
+    }
+
+    /// Back off exponentially, up to the max. The result is jittered so a
+    /// network blip doesn't make us reconnect to every relay in lockstep.
+    fn next_reconnect_duration(current: Duration) -> Duration {
+        let next = current.mul_f64(1.5).min(Self::max_reconnect_duration());
+        jitter(next)
+    }
+}
+
+/// Randomly scale a duration to between 80% and 100% of itself
+fn jitter(duration: Duration) -> Duration {
+    use std::collections::hash_map::RandomState;
+    use std::hash::{BuildHasher, Hasher};
+
+    let random = RandomState::new().build_hasher().finish();
+    let scale = 0.8 + 0.2 * (random % 1000) as f64 / 1000.0;
+    duration.mul_f64(scale)
 }
 
 pub struct RelayPool {
     pub relays: Vec<PoolRelay>,
     pub ping_rate: Duration,
+
+    /// Every relay connection goes through this proxy if set
+    proxy: Option<Proxy>,
+
+    /// Only connect to .onion relays while we have a proxy
+    block_clearnet: bool,
+
+    /// Set when the proxy we were given is broken. We don't connect to
+    /// anything until it's fixed, rather than connect in the clear.
+    proxy_error: Option<String>,
+
+    /// Stay disconnected from every relay
+    offline: bool,
 }
 
 impl Default for RelayPool {
@@ -71,9 +108,144 @@ impl RelayPool {
         RelayPool {
             relays: vec![],
             ping_rate: Duration::from_secs(25),
+            proxy: None,
+            block_clearnet: false,
+            proxy_error: None,
+            offline: false,
+        }
+    }
+
+    pub fn is_offline(&self) -> bool {
+        self.offline
+    }
+
+    /// Disconnect from all relays and stay disconnected, or reconnect to
+    /// them again
+    pub fn set_offline(
+        &mut self,
+        offline: bool,
+        wakeup: impl Fn() + Send + Sync + Clone + 'static,
+    ) {
+        if self.offline == offline {
+            return;
+        }
+        self.offline = offline;
+
+        for index in 0..self.relays.len() {
+            let allowed = self.check_url(&self.relays[index].relay.url).is_ok();
+            let relay = &mut self.relays[index];
+
+            if offline {
+                relay.relay.disconnect();
+            } else if allowed {
+                relay.retry_connect_after = PoolRelay::initial_reconnect_duration();
+                relay.last_connect_attempt = Instant::now();
+                if let Err(err) = relay.relay.connect(wakeup.clone()) {
+                    error!("error connecting to relay: {}", err);
+                }
+            }
+        }
+    }
+
+    pub fn proxy(&self) -> Option<&Proxy> {
+        self.proxy.as_ref()
+    }
+
+    pub fn blocks_clearnet(&self) -> bool {
+        self.block_clearnet
+    }
+
+    /// Why we aren't connecting to anything, if our proxy is broken
+    pub fn proxy_error(&self) -> Option<&str> {
+        self.proxy_error.as_deref()
+    }
+
+    /// Our proxy as [`RelayPool::set_proxy`] takes it, so other pools can
+    /// follow this one
+    pub fn proxy_setting(&self) -> Result<Option<Proxy>> {
+        match &self.proxy_error {
+            Some(err) => Err(Error::Generic(err.clone())),
+            None => Ok(self.proxy.clone()),
         }
     }
 
+    /// Route relay connections through a proxy, or connect directly again
+    /// if `proxy` is None. `block_clearnet` keeps us from connecting to
+    /// anything but .onion relays while the proxy is on. A broken proxy
+    /// disconnects us from everything. Relays we're connected to are
+    /// reconnected with the new settings.
+    pub fn set_proxy(
+        &mut self,
+        proxy: Result<Option<Proxy>>,
+        block_clearnet: bool,
+        wakeup: impl Fn() + Send + Sync + Clone + 'static,
+    ) {
+        let (proxy, proxy_error) = match proxy {
+            Ok(proxy) => (proxy, None),
+            Err(err) => (None, Some(err.to_string())),
+        };
+
+        if self.proxy == proxy
+            && self.proxy_error == proxy_error
+            && self.block_clearnet == block_clearnet
+        {
+            return;
+        }
+
+        self.proxy = proxy;
+        self.proxy_error = proxy_error;
+        self.block_clearnet = block_clearnet;
+
+        for relay in &mut self.relays {
+            relay.relay.set_proxy(self.proxy.clone());
+        }
+
+        for index in 0..self.relays.len() {
+            let allowed = self.check_url(&self.relays[index].relay.url);
+            let relay = &mut self.relays[index];
+
+            if let Err(err) = allowed {
+                debug!("disconnecting from {}: {}", relay.relay.url, err);
+                relay.relay.disconnect();
+                continue;
+            }
+
+            if self.offline {
+                continue;
+            }
+
+            relay.retry_connect_after = PoolRelay::initial_reconnect_duration();
+            relay.last_connect_attempt = Instant::now();
+            if let Err(err) = relay.relay.connect(wakeup.clone()) {
+                error!("error connecting to relay: {}", err);
+            }
+        }
+    }
+
+    /// Whether we're allowed to connect to this relay with our current
+    /// proxy settings
+    pub fn check_url(&self, url: &str) -> Result<()> {
+        if let Some(err) = &self.proxy_error {
+            return Err(Error::Generic(format!(
+                "not connecting while the proxy is broken: {err}"
+            )));
+        }
+
+        if is_onion(url) {
+            if self.proxy.is_none() {
+                return Err(Error::Generic(
+                    "onion relays can only be reached through a Tor proxy".to_owned(),
+                ));
+            }
+        } else if self.proxy.is_some() && self.block_clearnet {
+            return Err(Error::Generic(
+                "clearnet relays are blocked while the proxy is on".to_owned(),
+            ));
+        }
+
+        Ok(())
+    }
+
     pub fn ping_rate(&mut self, duration: Duration) -> &mut Self {
         self.ping_rate = duration;
         self
@@ -110,17 +282,23 @@ impl RelayPool {
     /// Keep relay connectiongs alive by pinging relays that haven't been
     /// pinged in awhile. Adjust ping rate with [`ping_rate`].
     pub fn keepalive_ping(&mut self, wakeup: impl Fn() + Send + Sync + Clone + 'static) {
-        for relay in &mut self.relays {
+        for index in 0..self.relays.len() {
+            let allowed = !self.offline && self.check_url(&self.relays[index].relay.url).is_ok();
+            let relay = &mut self.relays[index];
             let now = std::time::Instant::now();
 
             match relay.relay.status {
+                RelayStatus::Disconnected if !allowed => {
+                    // stay disconnected until we go online or the proxy
+                    // settings change
+                }
+
                 RelayStatus::Disconnected => {
                     let reconnect_at = relay.last_connect_attempt + relay.retry_connect_after;
                     if now > reconnect_at {
                         relay.last_connect_attempt = now;
-                        let next_duration = Duration::from_millis(
-                            ((relay.retry_connect_after.as_millis() as f64) * 1.5) as u64,
-                        );
+                        let next_duration =
+                            PoolRelay::next_reconnect_duration(relay.retry_connect_after);
                         debug!(
                             "bumping reconnect duration from {:?} to {:?} and retrying connect",
                             relay.retry_connect_after, next_duration
@@ -152,6 +330,30 @@ impl RelayPool {
         }
     }
 
+    /// Our network changed (ie. we switched wifi networks or woke up from
+    /// sleep). Connections may be dead without us knowing, so reconnect
+    /// to everything right away instead of waiting for backoffs or pings.
+    pub fn network_changed(&mut self, wakeup: impl Fn() + Send + Sync + Clone + 'static) {
+        if self.offline {
+            return;
+        }
+
+        for index in 0..self.relays.len() {
+            if self.check_url(&self.relays[index].relay.url).is_err() {
+                continue;
+            }
+
+            let relay = &mut self.relays[index];
+            debug!("network changed, reconnecting to {}", relay.relay.url);
+            relay.relay.handle_disconnected();
+            relay.retry_connect_after = PoolRelay::initial_reconnect_duration();
+            relay.last_connect_attempt = Instant::now();
+            if let Err(err) = relay.relay.connect(wakeup.clone()) {
+                error!("error connecting to relay: {}", err);
+            }
+        }
+    }
+
     pub fn send_to(&mut self, cmd: &ClientMessage, relay_url: &str) {
         for relay in &mut self.relays {
             let relay = &mut relay.relay;
@@ -173,7 +375,12 @@ impl RelayPool {
         if self.has(&url) {
             return Ok(());
         }
-        let relay = Relay::new(url, wakeup)?;
+        self.check_url(&url)?;
+        let relay = if self.offline {
+            Relay::new_disconnected(url, self.proxy.clone())
+        } else {
+            Relay::new(url, self.proxy.clone(), wakeup)?
+        };
         let pool_relay = PoolRelay::new(relay);
 
         self.relays.push(pool_relay);
@@ -182,13 +389,22 @@ impl RelayPool {
     }
 
     // standardize the format (ie, trailing slashes)
-    fn canonicalize_url(url: String) -> String {
+    pub fn canonicalize_url(url: String) -> String {
         match Url::parse(&url) {
             Ok(parsed_url) => parsed_url.to_string(),
             Err(_) => url, // If parsing fails, return the original URL.
         }
     }
 
+    /// The subscription one of our REQs to a relay belongs to. REQs we
+    /// split to fit a relay's limits come back with their own ids.
+    pub fn subscription_id<'a>(&self, relay_url: &str, wire_id: &'a str) -> &'a str {
+        self.relays
+            .iter()
+            .find(|relay| relay.relay.url == relay_url)
+            .map_or(wire_id, |relay| relay.relay.subscription_id(wire_id))
+    }
+
     /// Attempts to receive a pool event from a list of relays. The
     /// function searches each relay in the list in order, attempting to
     /// receive a message from each. If a message is received, return it.
@@ -196,28 +412,53 @@ impl RelayPool {
     pub fn try_recv(&mut self) -> Option<PoolEvent<'_>> {
         for relay in &mut self.relays {
             let relay = &mut relay.relay;
-            if let Some(event) = relay.receiver.try_recv() {
+
+            let event = loop {
+                let event = if let Some(event) = relay.connection.try_recv() {
+                    event
+                } else {
+                    break None;
+                };
+
                 match &event {
                     WsEvent::Opened => {
-                        relay.status = RelayStatus::Connected;
+                        relay.handle_opened();
                     }
                     WsEvent::Closed => {
-                        relay.status = RelayStatus::Disconnected;
+                        relay.stats.record_connection(ConnectionEventKind::Closed);
+                        relay.handle_disconnected();
                     }
                     WsEvent::Error(err) => {
                         error!("{:?}", err);
-                        relay.status = RelayStatus::Disconnected;
+                        relay
+                            .stats
+                            .record_connection(ConnectionEventKind::Error(err.to_owned()));
+                        relay.handle_disconnected();
                     }
                     WsEvent::Message(ev) => {
+                        relay.stats.record_in(ev);
+
+                        // split subscriptions only get one EOSE through
+                        if let WsMessage::Text(txt) = ev {
+                            if !relay.handle_text(txt) {
+                                continue;
+                            }
+                        }
+
                         // let's just handle pongs here.
                         // We only need to do this natively.
                         #[cfg(not(target_arch = "wasm32"))]
                         if let WsMessage::Ping(ref bs) = ev {
                             debug!("pong {}", &relay.url);
-                            relay.sender.send(WsMessage::Pong(bs.to_owned()));
+                            relay.connection.send(WsMessage::Pong(bs.to_owned()));
                         }
                     }
                 }
+
+                break Some(event);
+            };
+
+            if let Some(event) = event {
                 return Some(PoolEvent {
                     event,
                     relay: &relay.url,
@@ -228,3 +469,63 @@ impl RelayPool {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_url() {
+        let mut pool = RelayPool::new();
+        assert!(pool.check_url("wss://relay.damus.io").is_ok());
+        assert!(pool.check_url("ws://abcdefghijklmnop.onion").is_err());
+
+        pool.proxy = Some(Proxy::parse("socks5h://127.0.0.1:9050").unwrap());
+        assert!(pool.check_url("wss://relay.damus.io").is_ok());
+        assert!(pool.check_url("ws://abcdefghijklmnop.onion").is_ok());
+
+        pool.block_clearnet = true;
+        assert!(pool.check_url("wss://relay.damus.io").is_err());
+        assert!(pool.check_url("ws://abcdefghijklmnop.onion").is_ok());
+    }
+
+    #[test]
+    fn test_broken_proxy_fails_closed() {
+        let mut pool = RelayPool::new();
+        pool.set_proxy(
+            Proxy::parse("http://127.0.0.1:8080").map(Some),
+            false,
+            || {},
+        );
+
+        assert!(pool.proxy().is_none());
+        assert!(pool.proxy_error().is_some());
+        assert!(pool.check_url("wss://relay.damus.io").is_err());
+        assert!(pool
+            .add_url("wss://relay.damus.io".to_owned(), || {})
+            .is_err());
+
+        pool.set_proxy(Ok(None), false, || {});
+        assert!(pool.check_url("wss://relay.damus.io").is_ok());
+    }
+
+    #[test]
+    fn test_reconnect_backoff_is_capped() {
+        let mut duration = PoolRelay::initial_reconnect_duration();
+        for _ in 0..100 {
+            duration = PoolRelay::next_reconnect_duration(duration);
+        }
+        assert!(duration <= PoolRelay::max_reconnect_duration());
+        assert!(duration >= PoolRelay::max_reconnect_duration().mul_f64(0.8));
+    }
+
+    #[test]
+    fn test_jitter_bounds() {
+        let duration = Duration::from_secs(10);
+        for _ in 0..100 {
+            let jittered = jitter(duration);
+            assert!(jittered <= duration);
+            assert!(jittered >= duration.mul_f64(0.8));
+        }
+    }
+}
@@ -0,0 +1,272 @@
+use ewebsock::WsMessage;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::{Duration, Instant, SystemTime};
+
+use super::message::RelayMessage;
+
+/// How many raw frames we keep around per relay for debugging
+pub const MAX_FRAMES: usize = 100;
+
+/// Frames longer than this are cut off in the frame log
+const MAX_FRAME_LEN: usize = 1024;
+
+const MAX_CONNECTION_EVENTS: usize = 50;
+
+/// Once we're tracking this many subscriptions, we forget the ones that
+/// are caught up
+const MAX_SUBSCRIPTION_STATS: usize = 200;
+
+/// How many event ids we remember per relay to spot duplicates
+const MAX_SEEN_IDS: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameDirection {
+    In,
+    Out,
+}
+
+/// A raw websocket frame sent to or received from a relay
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub at: SystemTime,
+    pub direction: FrameDirection,
+    /// The size of the whole frame, even if `text` was cut off
+    pub len: usize,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectionEventKind {
+    Connecting,
+    Opened,
+    Closed,
+    Error(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct ConnectionEvent {
+    pub at: SystemTime,
+    pub kind: ConnectionEventKind,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct SubscriptionStats {
+    pub events: u64,
+    pub duplicates: u64,
+    /// How long the relay took to send everything it had stored
+    pub eose_latency: Option<Duration>,
+    requested_at: Option<Instant>,
+}
+
+/// Counters for everything that went over a relay connection, so we can
+/// tell which relay is misbehaving when a column stays empty
+#[derive(Debug, Default)]
+pub struct RelayStats {
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+    pub messages_in: u64,
+    pub messages_out: u64,
+    pub events: u64,
+    pub duplicates: u64,
+    pub notices: u64,
+    pub errors: u64,
+    pub last_notice: Option<String>,
+    pub subscriptions: HashMap<String, SubscriptionStats>,
+    pub connection_history: VecDeque<ConnectionEvent>,
+    pub frames: VecDeque<Frame>,
+    seen_ids: HashSet<String>,
+    seen_order: VecDeque<String>,
+}
+
+impl RelayStats {
+    pub fn record_connection(&mut self, kind: ConnectionEventKind) {
+        if let ConnectionEventKind::Error(_) = kind {
+            self.errors += 1;
+        }
+
+        self.connection_history.push_back(ConnectionEvent {
+            at: SystemTime::now(),
+            kind,
+        });
+        if self.connection_history.len() > MAX_CONNECTION_EVENTS {
+            self.connection_history.pop_front();
+        }
+    }
+
+    /// We sent a REQ for this subscription, start timing its EOSE
+    pub fn record_req(&mut self, subid: &str) {
+        if self.subscriptions.len() >= MAX_SUBSCRIPTION_STATS {
+            self.subscriptions
+                .retain(|_, sub| sub.eose_latency.is_none());
+        }
+
+        let sub = self.subscriptions.entry(subid.to_owned()).or_default();
+        sub.requested_at = Some(Instant::now());
+        sub.eose_latency = None;
+    }
+
+    pub fn record_out(&mut self, text: &str) {
+        self.messages_out += 1;
+        self.bytes_out += text.len() as u64;
+        self.push_frame(FrameDirection::Out, text);
+    }
+
+    pub fn record_in(&mut self, msg: &WsMessage) {
+        self.messages_in += 1;
+
+        let text = match msg {
+            WsMessage::Text(text) => text,
+            WsMessage::Binary(bytes) => {
+                self.bytes_in += bytes.len() as u64;
+                self.push_frame(
+                    FrameDirection::In,
+                    &format!("<{} binary bytes>", bytes.len()),
+                );
+                return;
+            }
+            WsMessage::Ping(bytes) | WsMessage::Pong(bytes) => {
+                self.bytes_in += bytes.len() as u64;
+                return;
+            }
+            WsMessage::Unknown(text) => text,
+        };
+
+        self.bytes_in += text.len() as u64;
+        self.push_frame(FrameDirection::In, text);
+
+        if text.starts_with("[\"EVENT\"") {
+            if let Ok(RelayMessage::Event(subid, json)) = RelayMessage::from_json(text) {
+                self.record_event(subid, json);
+            }
+        } else if text.starts_with("[\"EOSE\"") {
+            if let Ok(RelayMessage::Eose(subid)) = RelayMessage::from_json(text) {
+                if let Some(sub) = self.subscriptions.get_mut(subid) {
+                    if sub.eose_latency.is_none() {
+                        sub.eose_latency = sub.requested_at.map(|at| at.elapsed());
+                    }
+                }
+            }
+        } else if text.starts_with("[\"NOTICE\"") {
+            self.notices += 1;
+            if let Ok(RelayMessage::Notice(notice)) = RelayMessage::from_json(text) {
+                self.last_notice = Some(notice.to_owned());
+            }
+        }
+    }
+
+    fn record_event(&mut self, subid: &str, json: &str) {
+        self.events += 1;
+
+        let duplicate = event_id(json).map_or(false, |id| !self.remember(id));
+        if duplicate {
+            self.duplicates += 1;
+        }
+
+        let sub = self.subscriptions.entry(subid.to_owned()).or_default();
+        sub.events += 1;
+        if duplicate {
+            sub.duplicates += 1;
+        }
+    }
+
+    /// Remember an event id. Returns false if we had already seen it.
+    fn remember(&mut self, id: &str) -> bool {
+        if self.seen_ids.contains(id) {
+            return false;
+        }
+
+        self.seen_ids.insert(id.to_owned());
+        self.seen_order.push_back(id.to_owned());
+        if self.seen_order.len() > MAX_SEEN_IDS {
+            if let Some(oldest) = self.seen_order.pop_front() {
+                self.seen_ids.remove(&oldest);
+            }
+        }
+
+        true
+    }
+
+    fn push_frame(&mut self, direction: FrameDirection, text: &str) {
+        let len = text.len();
+        let text = match text.char_indices().nth(MAX_FRAME_LEN) {
+            Some((end, _)) => format!("{}…", &text[..end]),
+            None => text.to_owned(),
+        };
+
+        self.frames.push_back(Frame {
+            at: SystemTime::now(),
+            direction,
+            len,
+            text,
+        });
+        if self.frames.len() > MAX_FRAMES {
+            self.frames.pop_front();
+        }
+    }
+}
+
+/// Pull the id out of an EVENT message without parsing the whole note.
+/// Quotes inside strings are escaped, so `"id":"` can only be a key.
+fn event_id(json: &str) -> Option<&str> {
+    let needle = "\"id\":\"";
+    let start = json.find(needle)? + needle.len();
+    json.get(start..start + 64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event_msg(subid: &str, id: char) -> WsMessage {
+        let id: String = std::iter::repeat(id).take(64).collect();
+        WsMessage::Text(format!(
+            "[\"EVENT\",\"{subid}\",{{\"id\":\"{id}\",\"content\":\"\\\"id\\\":\\\"nope\"}}]"
+        ))
+    }
+
+    #[test]
+    fn test_counts_events_and_duplicates() {
+        let mut stats = RelayStats::default();
+        stats.record_in(&event_msg("sub", 'a'));
+        stats.record_in(&event_msg("sub", 'b'));
+        stats.record_in(&event_msg("other", 'a'));
+
+        assert_eq!(stats.events, 3);
+        assert_eq!(stats.duplicates, 1);
+        assert_eq!(stats.messages_in, 3);
+        assert_eq!(stats.subscriptions["sub"].events, 2);
+        assert_eq!(stats.subscriptions["other"].duplicates, 1);
+    }
+
+    #[test]
+    fn test_eose_latency() {
+        let mut stats = RelayStats::default();
+        stats.record_req("sub");
+        assert_eq!(stats.subscriptions["sub"].eose_latency, None);
+
+        stats.record_in(&WsMessage::Text("[\"EOSE\",\"sub\"]".to_owned()));
+        assert!(stats.subscriptions["sub"].eose_latency.is_some());
+    }
+
+    #[test]
+    fn test_notices_and_frame_log() {
+        let mut stats = RelayStats::default();
+        for _ in 0..MAX_FRAMES + 10 {
+            stats.record_in(&WsMessage::Text("[\"NOTICE\",\"slow down\"]".to_owned()));
+        }
+        stats.record_out("[\"CLOSE\",\"sub\"]");
+
+        assert_eq!(stats.notices as usize, MAX_FRAMES + 10);
+        assert_eq!(stats.last_notice.as_deref(), Some("slow down"));
+        assert_eq!(stats.frames.len(), MAX_FRAMES);
+        assert_eq!(stats.frames.back().unwrap().direction, FrameDirection::Out);
+    }
+
+    #[test]
+    fn test_event_id() {
+        let id: String = std::iter::repeat('f').take(64).collect();
+        let json = format!("[\"EVENT\",\"s\",{{\"content\":\"hi\",\"id\":\"{id}\"}}]");
+        assert_eq!(event_id(&json), Some(id.as_str()));
+        assert_eq!(event_id("[\"EVENT\",\"s\",{}]"), None);
+    }
+}
@@ -0,0 +1,177 @@
+use enostr::{FullKeypair, Pubkey, RelayPool};
+use nostrdb::{Ndb, Note, NoteBuilder};
+use serde_json::{Map, Value};
+use tracing::error;
+
+use crate::{outbox::Outbox, Error, Result};
+
+/// The relays a new account starts out with in its relay list
+pub const STARTER_RELAYS: [&str; 3] = [
+    "wss://relay.damus.io",
+    "wss://nos.lol",
+    "wss://relay.nostr.band",
+];
+
+/// Profiles we suggest new accounts follow, as (name, hex pubkey)
+pub const SUGGESTED_FOLLOWS: [(&str, &str); 4] = [
+    (
+        "Damus",
+        "3efdaebb1d8923ebd99c9e7ace3b4194ab45512e2be79c1b7d68d9243e0d2681",
+    ),
+    (
+        "jb55",
+        "32e1827635450ebb3c5a7d12c1f8e7b2b514439ac10a67eef3d9fd9c5c68e245",
+    ),
+    (
+        "fiatjaf",
+        "3bf0c63fcb93463407af97a5e5ee64fa883d107ef9e558472c4eb9aaaefa459d",
+    ),
+    (
+        "pablof7z",
+        "fa984bd7dbb282f07e16e7ae87b26a2a7b9b90b7246a44771f0cf5ae58018f52",
+    ),
+];
+
+/// The kind 0 fields we ask for when creating an account
+#[derive(Default, Debug, Clone)]
+pub struct ProfileFields {
+    pub name: String,
+    pub display_name: String,
+    pub about: String,
+    pub picture: String,
+}
+
+impl ProfileFields {
+    /// The kind 0 content. Empty fields are left out.
+    pub fn to_json(&self) -> String {
+        let mut fields = Map::new();
+        for (key, value) in [
+            ("name", &self.name),
+            ("display_name", &self.display_name),
+            ("about", &self.about),
+            ("picture", &self.picture),
+        ] {
+            let value = value.trim();
+            if !value.is_empty() {
+                fields.insert(key.to_owned(), Value::String(value.to_owned()));
+            }
+        }
+
+        Value::Object(fields).to_string()
+    }
+}
+
+/// A freshly generated account and everything we publish for it
+pub struct NewAccount {
+    pub keypair: FullKeypair,
+    pub profile: ProfileFields,
+    pub follows: Vec<Pubkey>,
+}
+
+impl NewAccount {
+    /// The account's profile, starter relay list and, if it follows
+    /// anyone, its contact list
+    pub fn notes(&self) -> Result<Vec<Note<'static>>> {
+        let seckey = self.keypair.secret_key.to_secret_bytes();
+
+        let profile = self.profile.to_json();
+        let mut notes = vec![build(
+            NoteBuilder::new().kind(0).content(&profile),
+            &seckey,
+        )?];
+
+        let mut relays = NoteBuilder::new().kind(10002).content("");
+        for relay in STARTER_RELAYS {
+            relays = relays.start_tag().tag_str("r").tag_str(relay);
+        }
+        notes.push(build(relays, &seckey)?);
+
+        if !self.follows.is_empty() {
+            let mut contacts = NoteBuilder::new().kind(3).content("");
+            for pk in &self.follows {
+                contacts = contacts.start_tag().tag_str("p").tag_str(&pk.hex());
+            }
+            notes.push(build(contacts, &seckey)?);
+        }
+
+        Ok(notes)
+    }
+
+    pub fn publish(&self, ndb: &Ndb, pool: &mut RelayPool, outbox: &mut Outbox) {
+        match self.notes() {
+            Ok(notes) => {
+                for note in &notes {
+                    outbox.publish(ndb, pool, note);
+                }
+            }
+            Err(err) => error!("error building new account notes: {err}"),
+        }
+    }
+}
+
+fn build<'a>(builder: NoteBuilder<'a>, seckey: &'a [u8; 32]) -> Result<Note<'static>> {
+    builder
+        .sign(seckey)
+        .build()
+        .ok_or_else(|| Error::Generic("failed to build note".to_owned()))
+}
+
+/// The suggested profiles, as pubkeys
+pub fn suggested_follows() -> Vec<(&'static str, Pubkey)> {
+    SUGGESTED_FOLLOWS
+        .iter()
+        .filter_map(|(name, hex)| Some((*name, Pubkey::from_hex(hex).ok()?)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_json_skips_empty_fields() {
+        let profile = ProfileFields {
+            name: "satoshi".to_owned(),
+            about: "  ".to_owned(),
+            ..Default::default()
+        };
+
+        assert_eq!(profile.to_json(), r#"{"name":"satoshi"}"#);
+    }
+
+    #[test]
+    fn test_new_account_notes() {
+        let follows: Vec<Pubkey> = suggested_follows().into_iter().map(|(_, pk)| pk).collect();
+        assert_eq!(follows.len(), SUGGESTED_FOLLOWS.len());
+
+        let account = NewAccount {
+            keypair: FullKeypair::generate(),
+            profile: ProfileFields {
+                name: "satoshi".to_owned(),
+                ..Default::default()
+            },
+            follows,
+        };
+
+        let notes = account.notes().unwrap();
+        let kinds: Vec<u32> = notes.iter().map(|n| n.kind()).collect();
+        assert_eq!(kinds, vec![0, 10002, 3]);
+
+        for note in &notes {
+            assert_eq!(note.pubkey(), account.keypair.pubkey.bytes());
+        }
+        assert_eq!(notes[1].tags().iter().count(), STARTER_RELAYS.len());
+        assert_eq!(notes[2].tags().iter().count(), SUGGESTED_FOLLOWS.len());
+    }
+
+    #[test]
+    fn test_no_contact_list_without_follows() {
+        let account = NewAccount {
+            keypair: FullKeypair::generate(),
+            profile: ProfileFields::default(),
+            follows: vec![],
+        };
+
+        assert_eq!(account.notes().unwrap().len(), 2);
+    }
+}
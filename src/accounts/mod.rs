@@ -1,12 +1,13 @@
 use std::cmp::Ordering;
 
-use enostr::{FilledKeypair, FullKeypair, Keypair};
+use enostr::{FilledKeypair, Keypair};
 use nostrdb::Ndb;
 
 use crate::{
     column::Columns,
     imgcache::ImageCache,
     login_manager::AcquireKeyState,
+    proxy::Http,
     route::{Route, Router},
     storage::{KeyStorageResponse, KeyStorageType},
     ui::{
@@ -18,8 +19,10 @@ use crate::{
 };
 use tracing::{error, info};
 
+mod create;
 mod route;
 
+pub use create::{suggested_follows, NewAccount, ProfileFields};
 pub use route::{AccountsRoute, AccountsRouteResponse};
 
 /// The interface for managing the user's accounts.
@@ -40,6 +43,7 @@ pub fn render_accounts_route(
     img_cache: &mut ImageCache,
     accounts: &mut Accounts,
     login_state: &mut AcquireKeyState,
+    http: &Http,
     route: AccountsRoute,
 ) -> SingleUnkIdAction {
     let router = columns.column_mut(col).router_mut();
@@ -49,10 +53,13 @@ pub fn render_accounts_route(
             .inner
             .map(AccountsRouteResponse::Accounts),
 
-        AccountsRoute::AddAccount => AccountLoginView::new(login_state)
+        AccountsRoute::AddAccount => AccountLoginView::new(login_state, http)
             .ui(ui)
             .inner
             .map(AccountsRouteResponse::AddAccount),
+
+        // rendered by the nav, since it needs to publish
+        AccountsRoute::CreateAccount => None,
     };
 
     if let Some(resp) = resp {
@@ -62,10 +69,8 @@ pub fn render_accounts_route(
                 SingleUnkIdAction::no_action()
             }
             AccountsRouteResponse::AddAccount(response) => {
-                let action = process_login_view_response(accounts, response);
                 *login_state = Default::default();
-                router.go_back();
-                action
+                process_login_view_response(accounts, response, router)
             }
         }
     } else {
@@ -88,6 +93,9 @@ pub fn process_accounts_view_response(
         AccountsViewResponse::RouteToLogin => {
             router.route_to(Route::add_account());
         }
+        AccountsViewResponse::RouteToMediaSettings => {
+            router.route_to(Route::MediaSettings);
+        }
     }
 }
 
@@ -175,6 +183,14 @@ impl Accounts {
             .or_else(|| self.accounts.iter().find_map(|a| a.to_full()))
     }
 
+    /// The selected account, or the first one, that we can sign notes for,
+    /// either with its key or its remote signer
+    pub fn selected_or_first_signer(&self) -> Option<&UserAccount> {
+        self.get_selected_account()
+            .filter(|kp| kp.can_sign())
+            .or_else(|| self.accounts.iter().find(|a| a.can_sign()))
+    }
+
     pub fn get_selected_account(&self) -> Option<&UserAccount> {
         if let Some(account_index) = self.currently_selected_account {
             if let Some(account) = self.get_account(account_index) {
@@ -216,13 +232,31 @@ fn get_selected_index(accounts: &[UserAccount], keystore: &KeyStorageType) -> Op
 pub fn process_login_view_response(
     manager: &mut Accounts,
     response: AccountLoginResponse,
+    router: &mut Router<Route>,
 ) -> SingleUnkIdAction {
-    let r = match response {
+    match response {
         AccountLoginResponse::CreateNew => {
-            manager.add_account(FullKeypair::generate().to_keypair())
+            router.route_to(Route::create_account());
+            SingleUnkIdAction::no_action()
         }
-        AccountLoginResponse::LoginWith(keypair) => manager.add_account(keypair),
-    };
-    manager.select_account(manager.num_accounts() - 1);
+        AccountLoginResponse::LoginWith(keypair) => {
+            let r = add_and_select_account(manager, keypair);
+            router.go_back();
+            r
+        }
+    }
+}
+
+/// Add an account and switch to it
+pub fn add_and_select_account(manager: &mut Accounts, account: Keypair) -> SingleUnkIdAction {
+    let pk = account.pubkey;
+    let r = manager.add_account(account);
+    if let Some(index) = manager
+        .get_accounts()
+        .iter()
+        .position(|acc| acc.pubkey == pk)
+    {
+        manager.select_account(index);
+    }
     r
 }
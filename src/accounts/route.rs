@@ -10,4 +10,5 @@ pub enum AccountsRouteResponse {
 pub enum AccountsRoute {
     Accounts,
     AddAccount,
+    CreateAccount,
 }
@@ -1,13 +1,16 @@
 use crate::{
+    article,
+    bookmarks::BookmarkAction,
     column::Columns,
     note::NoteRef,
     notecache::NoteCache,
     notes_holder::{NotesHolder, NotesHolderStorage},
     profile::Profile,
     route::{Route, Router},
+    subscription_manager::SubscriptionManager,
     thread::Thread,
 };
-use enostr::{NoteId, Pubkey, RelayPool};
+use enostr::{NoteId, Pubkey};
 use nostrdb::{Ndb, Transaction};
 
 #[derive(Debug, Eq, PartialEq, Copy, Clone)]
@@ -16,6 +19,13 @@ pub enum NoteAction {
     Quote(NoteId),
     OpenThread(NoteId),
     OpenProfile(Pubkey),
+
+    /// Handled by the nav, since it needs the account's keys
+    Bookmark(BookmarkAction),
+
+    /// Send one of our notes to the relays that refused it again. Handled
+    /// by the nav, since it needs the outbox.
+    Republish(NoteId),
 }
 
 pub struct NewNotes {
@@ -37,14 +47,14 @@ fn open_thread(
     txn: &Transaction,
     router: &mut Router<Route>,
     note_cache: &mut NoteCache,
-    pool: &mut RelayPool,
+    subs: &mut SubscriptionManager,
     threads: &mut NotesHolderStorage<Thread>,
     selected_note: &[u8; 32],
 ) -> Option<NotesHolderResult> {
     router.route_to(Route::thread(NoteId::new(selected_note.to_owned())));
 
     let root_id = crate::note::root_note_id_from_selected_id(ndb, note_cache, txn, selected_note);
-    Thread::open(ndb, note_cache, txn, pool, threads, root_id)
+    Thread::open(ndb, note_cache, txn, subs, threads, root_id)
 }
 
 impl NoteAction {
@@ -56,7 +66,7 @@ impl NoteAction {
         threads: &mut NotesHolderStorage<Thread>,
         profiles: &mut NotesHolderStorage<Profile>,
         note_cache: &mut NoteCache,
-        pool: &mut RelayPool,
+        subs: &mut SubscriptionManager,
         txn: &Transaction,
     ) -> Option<NotesHolderResult> {
         match self {
@@ -66,18 +76,28 @@ impl NoteAction {
             }
 
             NoteAction::OpenThread(note_id) => {
-                open_thread(ndb, txn, router, note_cache, pool, threads, note_id.bytes())
+                let is_article = ndb
+                    .get_note_by_id(txn, note_id.bytes())
+                    .map_or(false, |note| article::is_article(&note));
+                if is_article {
+                    router.route_to(Route::article(note_id));
+                    return None;
+                }
+
+                open_thread(ndb, txn, router, note_cache, subs, threads, note_id.bytes())
             }
 
             NoteAction::OpenProfile(pubkey) => {
                 router.route_to(Route::profile(pubkey));
-                Profile::open(ndb, note_cache, txn, pool, profiles, pubkey.bytes())
+                Profile::open(ndb, note_cache, txn, subs, profiles, pubkey.bytes())
             }
 
             NoteAction::Quote(note_id) => {
                 router.route_to(Route::quote(note_id));
                 None
             }
+
+            NoteAction::Bookmark(_) | NoteAction::Republish(_) => None,
         }
     }
 
@@ -91,11 +111,11 @@ impl NoteAction {
         threads: &mut NotesHolderStorage<Thread>,
         profiles: &mut NotesHolderStorage<Profile>,
         note_cache: &mut NoteCache,
-        pool: &mut RelayPool,
+        subs: &mut SubscriptionManager,
         txn: &Transaction,
     ) {
         let router = columns.column_mut(col).router_mut();
-        if let Some(br) = self.execute(ndb, router, threads, profiles, note_cache, pool, txn) {
+        if let Some(br) = self.execute(ndb, router, threads, profiles, note_cache, subs, txn) {
             br.process(ndb, note_cache, txn, threads);
         }
     }
@@ -4,30 +4,56 @@ use crate::{
     app_size_handler::AppSizeHandler,
     app_style::user_requested_visuals_change,
     args::Args,
+    bookmarks::{AccountBookmarks, BookmarkEdits, BookmarkTimelines},
     column::{Column, Columns},
+    contacts::ContactEdits,
+    desktop_notifications::{DesktopNotifications, NotificationClick},
     draft::Drafts,
     filter::FilterState,
     frame_history::FrameHistory,
     imgcache::ImageCache,
+    key_parsing::AcquireKeyError,
+    lists,
+    local_relay::LocalRelay,
+    media_settings::MediaManager,
+    missing_notes::MissingNotes,
     nav,
+    network_monitor::NetworkMonitor,
+    nip05::Nip05Verifier,
     notecache::NoteCache,
     notes_holder::NotesHolderStorage,
+    offline::OfflineMode,
+    outbox::Outbox,
     profile::Profile,
+    profile_stats::ProfileStats,
+    proxy::ProxyManager,
+    relay_information::RelayInformationFetcher,
     route::Route,
+    search::Searches,
+    signer::{RemoteSigners, Signer, SignerEvent},
     storage::{self, DataPath, DataPathType, Directory, FileKeyStorage, KeyStorageType},
-    subscriptions::{SubKind, Subscriptions},
+    subscription_manager::SubLifetime,
+    subscriptions::{self, SubKind, Subscriptions},
     support::Support,
     thread::Thread,
     timeline::{self, Timeline, TimelineKind},
-    ui::{self, add_column::AddColumnRoute, DesktopSidePanel},
+    ui::{
+        self,
+        add_column::AddColumnRoute,
+        side_panel::SidePanelAction,
+        signer_notice::{SignerNotice, SignerNoticeView},
+        DesktopSidePanel,
+    },
     unknowns::UnknownIds,
     view_state::ViewState,
+    wot::WebOfTrust,
     Result,
 };
 
-use enostr::{ClientMessage, RelayEvent, RelayMessage, RelayPool};
+use enostr::{RelayEvent, RelayMessage, RelayPool};
 use uuid::Uuid;
 
+use chrono::Timelike;
 use egui::{Context, Frame, Style};
 use egui_extras::{Size, StripBuilder};
 
@@ -55,11 +81,33 @@ pub struct Damus {
     pub view_state: ViewState,
     pub unknown_ids: UnknownIds,
     pub drafts: Drafts,
+    pub outbox: Outbox,
     pub threads: NotesHolderStorage<Thread>,
     pub profiles: NotesHolderStorage<Profile>,
+    pub profile_stats: ProfileStats,
+    pub missing_notes: MissingNotes,
+    pub wot: WebOfTrust,
     pub img_cache: ImageCache,
     pub accounts: Accounts,
     pub subscriptions: Subscriptions,
+    pub searches: Searches,
+    pub bookmark_timelines: BookmarkTimelines,
+    pub account_bookmarks: AccountBookmarks,
+    pub bookmark_edits: BookmarkEdits,
+    pub desktop_notifications: DesktopNotifications,
+    pub relay_information: RelayInformationFetcher,
+    pub nip05: Nip05Verifier,
+    pub network_monitor: NetworkMonitor,
+    pub proxy: ProxyManager,
+    pub media: MediaManager,
+
+    /// Our database served as a relay, if we were started with `--serve`
+    pub local_relay: Option<LocalRelay>,
+
+    pub remote_signers: RemoteSigners,
+    pub contact_edits: ContactEdits,
+
+    pub offline: OfflineMode,
     pub app_rect_handler: AppSizeHandler,
     pub support: Support,
 
@@ -130,7 +178,53 @@ fn try_process_event(damus: &mut Damus, ctx: &egui::Context) -> Result<()> {
     let wakeup = move || {
         ctx2.request_repaint();
     };
-    damus.pool.keepalive_ping(wakeup);
+    if damus.network_monitor.poll() {
+        damus.pool.network_changed(wakeup.clone());
+    }
+    damus.pool.keepalive_ping(wakeup.clone());
+
+    for event in damus.remote_signers.poll(&damus.pool, wakeup.clone()) {
+        process_signer_event(damus, event);
+    }
+
+    damus.offline.update(&damus.pool);
+    if damus.offline.should_check_local() {
+        for timeline in damus.columns.timelines_mut() {
+            timeline::setup_timeline_from_local_list(
+                &damus.ndb,
+                &mut damus.note_cache,
+                &mut damus.subscriptions,
+                timeline,
+            );
+        }
+    }
+
+    // no lookups while we're asked to stay offline
+    let relay_information_urls = if damus.offline.is_forced() {
+        vec![]
+    } else {
+        damus.nip05.poll(ctx, &damus.proxy.http);
+        damus
+            .profile_stats
+            .poll(&damus.ndb, &mut damus.subscriptions, &damus.pool);
+        damus
+            .missing_notes
+            .poll(&damus.ndb, &mut damus.subscriptions, &damus.pool, wakeup);
+        damus
+            .relay_information
+            .poll(ctx, &damus.proxy.http, &mut damus.pool)
+    };
+
+    for relay_url in relay_information_urls {
+        timeline::send_search_timeline_filters(
+            &damus.ndb,
+            damus.since_optimize,
+            &mut damus.columns,
+            &mut damus.subscriptions,
+            &mut damus.pool,
+            &relay_url,
+        );
+    }
 
     // NOTE: we don't use the while let loop due to borrow issues
     #[allow(clippy::while_let_loop)]
@@ -143,16 +237,34 @@ fn try_process_event(damus: &mut Damus, ctx: &egui::Context) -> Result<()> {
 
         match (&ev.event).into() {
             RelayEvent::Opened => {
-                timeline::send_initial_timeline_filters(
-                    &damus.ndb,
-                    damus.since_optimize,
-                    &mut damus.columns,
-                    &mut damus.subscriptions,
-                    &mut damus.pool,
-                    &ev.relay,
-                );
+                let is_reconnect = damus
+                    .pool
+                    .relays
+                    .iter()
+                    .find(|r| r.relay.url == ev.relay)
+                    .map_or(false, |r| r.relay.is_reconnect());
+
+                // the pool replays our open subscriptions when we
+                // reconnect, including the timeline ones
+                if !is_reconnect {
+                    timeline::send_initial_timeline_filters(
+                        &damus.ndb,
+                        damus.since_optimize,
+                        &mut damus.columns,
+                        &mut damus.subscriptions,
+                        &mut damus.pool,
+                        &ev.relay,
+                    );
+                }
+
+                if let Some(acc) = damus.accounts.get_selected_account() {
+                    lists::fetch_lists_from(
+                        &mut damus.subscriptions,
+                        &ev.relay,
+                        acc.pubkey.bytes(),
+                    );
+                }
             }
-            // TODO: handle reconnects
             RelayEvent::Closed => warn!("{} connection closed", &ev.relay),
             RelayEvent::Error(e) => error!("{}: {}", &ev.relay, e),
             RelayEvent::Other(msg) => trace!("other event {:?}", &msg),
@@ -160,13 +272,68 @@ fn try_process_event(damus: &mut Damus, ctx: &egui::Context) -> Result<()> {
         }
     }
 
+    damus.contact_edits.poll(
+        &damus.ndb,
+        &damus.accounts,
+        &mut damus.pool,
+        &mut damus.outbox,
+        &mut damus.remote_signers,
+    );
+    timeline::refresh_list_timelines(
+        &damus.ndb,
+        &mut damus.note_cache,
+        &mut damus.subscriptions,
+        &mut damus.pool,
+        &mut damus.columns,
+        damus
+            .accounts
+            .get_selected_account()
+            .map(|acc| acc.pubkey.bytes()),
+        damus.since_optimize,
+    );
+    damus.wot.poll(
+        &damus.ndb,
+        &mut damus.subscriptions,
+        damus.accounts.get_selected_account().map(|acc| &acc.pubkey),
+    );
+    if let Some(blur) = damus.media.poll(
+        &damus.ndb,
+        damus.accounts.get_selected_account().map(|acc| &acc.pubkey),
+    ) {
+        damus.img_cache.media_blur = blur;
+    }
+    damus.account_bookmarks.poll(
+        ctx,
+        &damus.ndb,
+        damus
+            .accounts
+            .selected_or_first_signer()
+            .and_then(Signer::new)
+            .as_ref(),
+        &mut damus.remote_signers,
+    );
+    damus.bookmark_edits.poll(
+        &damus.ndb,
+        &damus.accounts,
+        &mut damus.pool,
+        &mut damus.outbox,
+        &mut damus.remote_signers,
+    );
+    damus.bookmark_timelines.prune(&damus.columns);
+    damus.subscriptions.manager.poll(&mut damus.pool);
+    for subid in damus.subscriptions.manager.take_finished() {
+        handle_one_shot_done(damus, &subid);
+    }
+    damus.outbox.poll(&mut damus.pool);
+
+    let focused = ctx.input(|i| i.focused);
     let n_timelines = damus.columns.timelines().len();
     for timeline_ind in 0..n_timelines {
         let is_ready = {
             let timeline = &mut damus.columns.timelines[timeline_ind];
             timeline::is_timeline_ready(
                 &damus.ndb,
-                &mut damus.pool,
+                &mut damus.subscriptions,
                 &mut damus.note_cache,
                 timeline,
             )
@@ -175,15 +342,46 @@ fn try_process_event(damus: &mut Damus, ctx: &egui::Context) -> Result<()> {
         if is_ready {
             let txn = Transaction::new(&damus.ndb).expect("txn");
 
-            if let Err(err) = Timeline::poll_notes_into_view(
-                timeline_ind,
-                damus.columns.timelines_mut(),
-                &damus.ndb,
-                &txn,
-                &mut damus.unknown_ids,
-                &mut damus.note_cache,
-            ) {
-                error!("poll_notes_into_view: {err}");
+            if damus.columns.timelines[timeline_ind].kind.is_bookmarks() {
+                if let Err(err) = damus.bookmark_timelines.poll(
+                    &mut damus.columns.timelines[timeline_ind],
+                    &damus.ndb,
+                    &txn,
+                    &mut damus.unknown_ids,
+                    &mut damus.note_cache,
+                    damus
+                        .accounts
+                        .selected_or_first_signer()
+                        .and_then(Signer::new)
+                        .as_ref(),
+                    &mut damus.remote_signers,
+                ) {
+                    error!("bookmark_timelines.poll: {err}");
+                }
+            } else {
+                match Timeline::poll_notes_into_view(
+                    timeline_ind,
+                    damus.columns.timelines_mut(),
+                    &damus.ndb,
+                    &txn,
+                    &mut damus.unknown_ids,
+                    &mut damus.note_cache,
+                ) {
+                    Ok(new_notes) => {
+                        if !new_notes.is_empty() {
+                            damus.desktop_notifications.process_new_notes(
+                                &damus.ndb,
+                                &txn,
+                                &damus.columns.timelines[timeline_ind],
+                                damus.accounts.get_selected_account().map(|acc| &acc.pubkey),
+                                &new_notes,
+                                focused,
+                                chrono::Local::now().hour(),
+                            );
+                        }
+                    }
+                    Err(err) => error!("poll_notes_into_view: {err}"),
+                }
             }
         } else {
             // TODO: show loading?
@@ -197,15 +395,60 @@ fn try_process_event(damus: &mut Damus, ctx: &egui::Context) -> Result<()> {
     Ok(())
 }
 
+fn process_signer_event(damus: &mut Damus, event: SignerEvent) {
+    // the signer answered, so whatever it wanted approved is approved
+    if !matches!(event, SignerEvent::NeedsApproval(_))
+        && matches!(
+            damus.view_state.signer_notice,
+            Some(SignerNotice::Approve(_))
+        )
+    {
+        damus.view_state.signer_notice = None;
+    }
+
+    match event {
+        SignerEvent::LoggedIn(account) => damus.view_state.login.remote_login_finished(Ok(account)),
+        SignerEvent::LoginFailed(err) => {
+            error!("remote signer login failed: {err}");
+            damus
+                .view_state
+                .login
+                .remote_login_finished(Err(AcquireKeyError::RemoteSignerFailed(err)));
+        }
+        SignerEvent::NeedsApproval(url) => {
+            damus.view_state.signer_notice = Some(SignerNotice::Approve(url));
+        }
+        SignerEvent::Signed { request, id, event } => {
+            damus.drafts.signed(&request);
+            damus
+                .outbox
+                .publish_event(&damus.ndb, &mut damus.pool, id, event)
+        }
+        SignerEvent::SignFailed { request, err } => {
+            error!("{err}");
+            // posts show this on their draft
+            if !damus.drafts.sign_failed(&request, &err) {
+                damus.view_state.signer_notice = Some(SignerNotice::Failed(err));
+            }
+        }
+    }
+}
+
 fn unknown_id_send(damus: &mut Damus) {
     let filter = damus.unknown_ids.filter().expect("filter");
     info!(
         "Getting {} unknown ids from relays",
         damus.unknown_ids.ids().len()
     );
-    let msg = ClientMessage::req("unknownids".to_string(), filter);
+    let subid = subscriptions::new_sub_id();
+    damus
+        .subscriptions()
+        .insert(subid.clone(), SubKind::OneShot);
     damus.unknown_ids.clear();
-    damus.pool.send(&msg);
+    damus
+        .subscriptions
+        .manager
+        .subscribe(subid, filter, SubLifetime::OneShot);
 }
 
 #[cfg(feature = "profiling")]
@@ -220,10 +463,6 @@ fn update_damus(damus: &mut Damus, ctx: &egui::Context) {
             setup_profiling();
 
             damus.state = DamusState::Initialized;
-            // this lets our eose handler know to close unknownids right away
-            damus
-                .subscriptions()
-                .insert("unknownids".to_string(), SubKind::OneShot);
             if let Err(err) = timeline::setup_initial_nostrdb_subs(
                 &damus.ndb,
                 &mut damus.note_cache,
@@ -240,19 +479,74 @@ fn update_damus(damus: &mut Damus, ctx: &egui::Context) {
         error!("error processing event: {}", err);
     }
 
+    for click in damus.desktop_notifications.poll_clicks() {
+        handle_notification_click(damus, ctx, click);
+    }
+
+    damus.outbox.publish_statuses(ctx);
+
     damus.app_rect_handler.try_save_app_size(ctx);
 }
 
-fn process_event(damus: &mut Damus, _subid: &str, event: &str) {
+/// Bring the window to the front and open whatever the notification was
+/// about in the column it came from
+fn handle_notification_click(damus: &mut Damus, ctx: &egui::Context, click: NotificationClick) {
+    ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+
+    let col = if let Some(col) = damus.columns.find_column_index_for_timeline(click.timeline) {
+        col
+    } else {
+        warn!("notification column {} is gone", click.timeline);
+        return;
+    };
+
+    let txn = Transaction::new(&damus.ndb).expect("txn");
+    click.action.execute_and_process_result(
+        &damus.ndb,
+        &mut damus.columns,
+        col,
+        &mut damus.threads,
+        &mut damus.profiles,
+        &mut damus.note_cache,
+        &mut damus.subscriptions.manager,
+        &txn,
+    );
+}
+
+fn process_event(damus: &mut Damus, subids: &[String], event: &str) {
     #[cfg(feature = "profiling")]
     puffin::profile_function!();
 
+    for subid in subids {
+        damus.contact_edits.handle_event(subid, event);
+        if let Some(draft) = &mut damus.view_state.edit_profile {
+            draft.handle_event(subid, event);
+        }
+    }
+
     //info!("processing event {}", event);
     if let Err(_err) = damus.ndb.process_event(event) {
         error!("error processing event {}", event);
     }
 }
 
+/// Every relay is done with a one-shot subscription, either by sending
+/// EOSE or by going down
+fn handle_one_shot_done(damus: &mut Damus, subid: &str) {
+    match damus.subscriptions().get(subid).cloned() {
+        Some(SubKind::ContactList) => damus.contact_edits.fetched(subid),
+        Some(SubKind::Metadata) => {
+            if let Some(draft) = &mut damus.view_state.edit_profile {
+                draft.fetched(subid);
+            }
+        }
+        Some(SubKind::FetchingNote) => damus.missing_notes.fetched(subid),
+        _ => {}
+    }
+
+    damus.subscriptions().remove(subid);
+}
+
 fn handle_eose(damus: &mut Damus, subid: &str, relay_url: &str) -> Result<()> {
     let sub_kind = if let Some(sub_kind) = damus.subscriptions().get(subid) {
         sub_kind
@@ -284,20 +578,24 @@ fn handle_eose(damus: &mut Damus, subid: &str, relay_url: &str) -> Result<()> {
             }
         }
 
-        // oneshot subs just close when they're done
-        SubKind::OneShot => {
-            let msg = ClientMessage::close(subid.to_string());
-            damus.pool.send_to(&msg, relay_url);
+        // oneshot subs are closed by the subscription manager, we just
+        // forget about them once every relay is done
+        SubKind::OneShot | SubKind::ContactList | SubKind::Metadata | SubKind::FetchingNote => {
+            if !damus.subscriptions.manager.is_active(subid) {
+                handle_one_shot_done(damus, subid);
+            }
+        }
+
+        SubKind::Search => {
+            damus.searches.remote_eose(subid);
+            damus.subscriptions().remove(subid);
         }
 
-        SubKind::FetchingContactList(timeline_uid) => {
+        SubKind::FetchingList(timeline_uid) => {
             let timeline = if let Some(tl) = damus.columns.find_timeline_mut(timeline_uid) {
                 tl
             } else {
-                error!(
-                    "timeline uid:{} not found for FetchingContactList",
-                    timeline_uid
-                );
+                error!("timeline uid:{} not found for FetchingList", timeline_uid);
                 return Ok(());
             };
 
@@ -319,7 +617,7 @@ fn handle_eose(damus: &mut Damus, subid: &str, relay_url: &str) -> Result<()> {
             };
 
             info!(
-                "got contact list from {}, updating filter_state to got_remote",
+                "got list from {}, updating filter_state to got_remote",
                 relay_url
             );
 
@@ -337,18 +635,62 @@ fn handle_eose(damus: &mut Damus, subid: &str, relay_url: &str) -> Result<()> {
 
 fn process_message(damus: &mut Damus, relay: &str, msg: &RelayMessage) {
     match msg {
-        RelayMessage::Event(subid, ev) => process_event(damus, subid, ev),
+        RelayMessage::Event(wire_id, ev) => {
+            let subids = subscription_ids(damus, relay, wire_id);
+            process_event(damus, &subids, ev)
+        }
         RelayMessage::Notice(msg) => warn!("Notice from {}: {}", relay, msg),
-        RelayMessage::OK(cr) => info!("OK {:?}", cr),
-        RelayMessage::Eose(sid) => {
-            if let Err(err) = handle_eose(damus, sid, relay) {
-                error!("error handling eose: {}", err);
+        RelayMessage::Count(subid, count) => {
+            if damus.subscriptions.manager.handle_count(relay, subid) {
+                damus.profile_stats.handle_count(subid, *count)
             }
         }
+        RelayMessage::OK(cr) => {
+            info!("OK {:?}", cr);
+            damus.outbox.handle_ok(relay, cr);
+        }
+        RelayMessage::Eose(wire_id) => process_eose(damus, relay, wire_id),
+        RelayMessage::Closed(wire_id, reason) => {
+            // the relay won't send anything more for this REQ, treat it
+            // like an EOSE so one-shots and waiting timelines move on
+            warn!("{} closed {}: {}", relay, wire_id, reason);
+            process_eose(damus, relay, wire_id);
+        }
+    }
+}
+
+/// The subscriptions a message from a relay is for. A REQ may have been
+/// split by the relay pool and may be carrying several coalesced
+/// subscriptions.
+fn subscription_ids(damus: &Damus, relay: &str, wire_id: &str) -> Vec<String> {
+    let subid = damus.pool.subscription_id(relay, wire_id);
+    damus.subscriptions.manager.members(relay, subid)
+}
+
+fn process_eose(damus: &mut Damus, relay: &str, wire_id: &str) {
+    let sid = damus.pool.subscription_id(relay, wire_id);
+    let subids = damus
+        .subscriptions
+        .manager
+        .handle_eose(&mut damus.pool, relay, sid);
+
+    for subid in subids {
+        if let Err(err) = handle_eose(damus, &subid, relay) {
+            error!("error handling eose: {}", err);
+        }
     }
 }
 
 fn render_damus(damus: &mut Damus, ctx: &Context) {
+    if let Some(notice) = &damus.view_state.signer_notice {
+        let dismissed = egui::TopBottomPanel::bottom("signer_notice")
+            .show(ctx, |ui| SignerNoticeView::new(notice).ui(ui))
+            .inner;
+        if dismissed {
+            damus.view_state.signer_notice = None;
+        }
+    }
+
     if ui::is_narrow(ctx) {
         render_damus_mobile(ctx, damus);
     } else {
@@ -437,27 +779,41 @@ impl Damus {
             accounts.select_account(0);
         }
 
+        let local_relay = parsed_args.serve.as_ref().and_then(|url| {
+            let allowed = (!parsed_args.serve_allow.is_empty())
+                .then(|| parsed_args.serve_allow.iter().copied().collect());
+
+            LocalRelay::start(&ndb, url, allowed)
+                .map_err(|err| error!("couldn't serve relay on {url}: {err}"))
+                .ok()
+        });
+
+        let wakeup = {
+            let ctx = ctx.clone();
+            move || {
+                ctx.request_repaint();
+            }
+        };
+
+        // the proxy has to be set up before we add any relays, so we never
+        // connect to them directly
+        let proxy = ProxyManager::new(&path, parsed_args.proxy);
+        let mut pool = RelayPool::new();
+        proxy.setup_pool(&mut pool, wakeup.clone());
+
+        let offline = OfflineMode::new(parsed_args.offline);
+        pool.set_offline(offline.is_forced(), wakeup.clone());
+
         // setup relays if we have them
-        let pool = if parsed_args.relays.is_empty() {
-            let mut pool = RelayPool::new();
+        if parsed_args.relays.is_empty() {
             relay_setup(&mut pool, ctx);
-            pool
         } else {
-            let wakeup = {
-                let ctx = ctx.clone();
-                move || {
-                    ctx.request_repaint();
-                }
-            };
-
-            let mut pool = RelayPool::new();
             for relay in parsed_args.relays {
                 if let Err(e) = pool.add_url(relay.clone(), wakeup.clone()) {
                     error!("error adding relay {}: {}", relay, e);
                 }
             }
-            pool
-        };
+        }
 
         let account = accounts
             .get_selected_account()
@@ -502,18 +858,40 @@ impl Damus {
 
         let app_rect_handler = AppSizeHandler::new(&path);
         let support = Support::new(&path);
+        let desktop_notifications = DesktopNotifications::new(ctx, &path);
+        let outbox = Outbox::new(&path);
+        let mut img_cache = ImageCache::new(imgcache_dir);
+        img_cache.http = proxy.http.clone();
 
         Self {
             pool,
             debug,
             unknown_ids,
             subscriptions: Subscriptions::default(),
+            searches: Searches::default(),
+            bookmark_timelines: BookmarkTimelines::default(),
+            account_bookmarks: AccountBookmarks::default(),
+            bookmark_edits: BookmarkEdits::default(),
+            desktop_notifications,
+            outbox,
+            relay_information: RelayInformationFetcher::new(&path),
+            nip05: Nip05Verifier::new(&path),
+            network_monitor: NetworkMonitor::default(),
+            proxy,
+            media: MediaManager::new(&path),
+            local_relay,
+            remote_signers: RemoteSigners::default(),
+            contact_edits: ContactEdits::default(),
+            offline,
             since_optimize: parsed_args.since_optimize,
             threads: NotesHolderStorage::default(),
             profiles: NotesHolderStorage::default(),
+            profile_stats: ProfileStats::default(),
+            missing_notes: MissingNotes::default(),
+            wot: WebOfTrust::default(),
             drafts: Drafts::default(),
             state: DamusState::Initializing,
-            img_cache: ImageCache::new(imgcache_dir),
+            img_cache,
             note_cache: NoteCache::default(),
             columns,
             textmode: parsed_args.textmode,
@@ -594,9 +972,27 @@ impl Damus {
             debug,
             unknown_ids: UnknownIds::default(),
             subscriptions: Subscriptions::default(),
+            searches: Searches::default(),
+            bookmark_timelines: BookmarkTimelines::default(),
+            account_bookmarks: AccountBookmarks::default(),
+            bookmark_edits: BookmarkEdits::default(),
+            desktop_notifications: DesktopNotifications::disabled(Default::default()),
+            outbox: Outbox::default(),
+            relay_information: RelayInformationFetcher::new(&path),
+            nip05: Nip05Verifier::new(&path),
+            network_monitor: NetworkMonitor::default(),
+            proxy: ProxyManager::disabled(),
+            media: MediaManager::disabled(),
+            local_relay: None,
+            remote_signers: RemoteSigners::default(),
+            contact_edits: ContactEdits::default(),
+            offline: OfflineMode::new(false),
             since_optimize: true,
             threads: NotesHolderStorage::default(),
             profiles: NotesHolderStorage::default(),
+            profile_stats: ProfileStats::default(),
+            missing_notes: MissingNotes::default(),
+            wot: WebOfTrust::default(),
             drafts: Drafts::default(),
             state: DamusState::Initializing,
             pool: RelayPool::new(),
@@ -805,15 +1201,23 @@ fn timelines_view(ui: &mut egui::Ui, sizes: Size, app: &mut Damus) {
                     &app.ndb,
                     &mut app.img_cache,
                     app.accounts.get_selected_account(),
+                    &app.offline,
                 )
                 .show(ui);
 
                 if side_panel.response.clicked() {
-                    DesktopSidePanel::perform_action(
-                        &mut app.columns,
-                        &mut app.support,
-                        side_panel.action,
-                    );
+                    if side_panel.action == SidePanelAction::ToggleOffline {
+                        let ctx = ui.ctx().clone();
+                        let forced = !app.offline.is_forced();
+                        app.offline
+                            .set_forced(forced, &mut app.pool, move || ctx.request_repaint());
+                    } else {
+                        DesktopSidePanel::perform_action(
+                            &mut app.columns,
+                            &mut app.support,
+                            side_panel.action,
+                        );
+                    }
                 }
 
                 // vertical sidebar line
@@ -871,3 +1275,49 @@ impl eframe::App for Damus {
         render_damus(self, ctx);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{actionbar::NoteAction, timeline::TimelineId};
+    use enostr::Pubkey;
+
+    fn focus_requested(ctx: &egui::Context) -> bool {
+        ctx.viewport(|viewport| viewport.commands.contains(&egui::ViewportCommand::Focus))
+    }
+
+    #[test]
+    fn test_notification_click() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let mut damus = Damus::mock(tmp.path());
+        let timeline = damus.columns.timelines()[0].id;
+        let pubkey = Pubkey::new([7; 32]);
+
+        let ctx = egui::Context::default();
+        handle_notification_click(
+            &mut damus,
+            &ctx,
+            NotificationClick {
+                timeline,
+                action: NoteAction::OpenProfile(pubkey),
+            },
+        );
+        assert!(focus_requested(&ctx));
+        assert_eq!(
+            damus.columns.column(0).router().top(),
+            &Route::profile(pubkey)
+        );
+
+        // the column is gone, we still come to the front
+        let ctx = egui::Context::default();
+        handle_notification_click(
+            &mut damus,
+            &ctx,
+            NotificationClick {
+                timeline: TimelineId::new(u32::MAX),
+                action: NoteAction::OpenProfile(pubkey),
+            },
+        );
+        assert!(focus_requested(&ctx));
+    }
+}
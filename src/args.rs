@@ -16,6 +16,16 @@ pub struct Args {
     pub use_keystore: bool,
     pub dbpath: Option<String>,
     pub datapath: Option<String>,
+    pub proxy: Option<String>,
+
+    /// Don't connect to relays, only show what's in our database
+    pub offline: bool,
+
+    /// Serve our database as a relay on this `ws://` url
+    pub serve: Option<String>,
+
+    /// Only accept notes from these pubkeys when serving
+    pub serve_allow: Vec<Pubkey>,
 }
 
 impl Args {
@@ -32,6 +42,10 @@ impl Args {
             use_keystore: true,
             dbpath: None,
             datapath: None,
+            proxy: None,
+            offline: false,
+            serve: None,
+            serve_allow: vec![],
         };
 
         let mut i = 0;
@@ -117,6 +131,41 @@ impl Args {
                     continue;
                 };
                 res.datapath = Some(path.clone());
+            } else if arg == "--proxy" {
+                i += 1;
+                let proxy = if let Some(next_arg) = args.get(i) {
+                    next_arg
+                } else {
+                    error!("proxy argument missing?");
+                    continue;
+                };
+                res.proxy = Some(proxy.clone());
+            } else if arg == "--serve" {
+                i += 1;
+                let url = if let Some(next_arg) = args.get(i) {
+                    next_arg
+                } else {
+                    error!("serve argument missing?");
+                    continue;
+                };
+                res.serve = Some(url.clone());
+            } else if arg == "--serve-allow" {
+                i += 1;
+                let pubstr = if let Some(next_arg) = args.get(i) {
+                    next_arg
+                } else {
+                    error!("serve-allow argument missing?");
+                    continue;
+                };
+
+                if let Ok(pk) = Pubkey::parse(pubstr) {
+                    res.serve_allow.push(pk);
+                } else {
+                    error!(
+                        "failed to parse {} argument. Make sure to use hex or npub.",
+                        arg
+                    );
+                }
             } else if arg == "-r" || arg == "--relay" {
                 i += 1;
                 let relay = if let Some(next_arg) = args.get(i) {
@@ -212,6 +261,8 @@ impl Args {
                 } else {
                     error!("failed to parse filter in '{}'", filter_file);
                 }
+            } else if arg == "--offline" {
+                res.offline = true;
             } else if arg == "--no-keystore" {
                 res.use_keystore = false;
             }
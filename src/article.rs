@@ -0,0 +1,115 @@
+//! NIP-23 long-form articles: Markdown notes with their title, summary
+//! and cover image in tags
+
+use crate::lists::tag_str;
+use enostr::{NoteAddress, Pubkey};
+use nostrdb::Note;
+
+pub const KIND_ARTICLE: u32 = 30023;
+
+const WORDS_PER_MINUTE: usize = 200;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ArticleInfo<'a> {
+    pub title: Option<&'a str>,
+    pub summary: Option<&'a str>,
+    pub image: Option<&'a str>,
+    pub published_at: Option<u64>,
+
+    /// The d tag
+    pub identifier: &'a str,
+}
+
+impl<'a> ArticleInfo<'a> {
+    pub fn new(note: &Note<'a>) -> Self {
+        let mut info = ArticleInfo::default();
+
+        for tag in note.tags() {
+            let value = tag_str(&tag, 1).map(str::trim);
+            match tag_str(&tag, 0) {
+                Some("title") => info.title = value.filter(|s| !s.is_empty()),
+                Some("summary") => info.summary = value.filter(|s| !s.is_empty()),
+                Some("image") => info.image = value.filter(|s| !s.is_empty()),
+                Some("published_at") => {
+                    info.published_at = value.and_then(|s| s.parse().ok());
+                }
+                Some("d") => info.identifier = value.unwrap_or(""),
+                _ => {}
+            }
+        }
+
+        info
+    }
+
+    pub fn title_or_untitled(&self) -> &'a str {
+        self.title.unwrap_or("Untitled article")
+    }
+}
+
+pub fn is_article(note: &Note) -> bool {
+    note.kind() == KIND_ARTICLE
+}
+
+/// The address other notes use to refer to this article
+pub fn article_address(note: &Note) -> NoteAddress {
+    NoteAddress::new(
+        note.kind(),
+        Pubkey::new(*note.pubkey()),
+        ArticleInfo::new(note).identifier.to_owned(),
+    )
+}
+
+/// Estimated minutes it takes to read `content`
+pub fn reading_time(content: &str) -> usize {
+    let words = content.split_whitespace().count();
+    words.div_ceil(WORDS_PER_MINUTE).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{local_note, with_signer};
+    use enostr::FullKeypair;
+    use nostrdb::NoteBuilder;
+
+    #[test]
+    fn test_reading_time() {
+        assert_eq!(reading_time(""), 1);
+        assert_eq!(reading_time(&"word ".repeat(200)), 1);
+        assert_eq!(reading_time(&"word\n".repeat(201)), 2);
+    }
+
+    #[test]
+    fn test_article_info() {
+        let kp = FullKeypair::generate();
+        let builder = NoteBuilder::new()
+            .kind(KIND_ARTICLE)
+            .content("# Hello")
+            .start_tag()
+            .tag_str("d")
+            .tag_str("hello")
+            .start_tag()
+            .tag_str("title")
+            .tag_str(" Hello world ")
+            .start_tag()
+            .tag_str("summary")
+            .tag_str("")
+            .start_tag()
+            .tag_str("published_at")
+            .tag_str("1700000000");
+        let note = with_signer(&kp, |signer| local_note(signer.sign(builder).unwrap()));
+
+        let info = ArticleInfo::new(&note);
+        assert_eq!(info.title, Some("Hello world"));
+        assert_eq!(info.summary, None);
+        assert_eq!(info.image, None);
+        assert_eq!(info.published_at, Some(1700000000));
+        assert_eq!(info.identifier, "hello");
+
+        let address = article_address(&note);
+        assert_eq!(
+            address.coordinate(),
+            format!("30023:{}:hello", kp.pubkey.hex())
+        );
+    }
+}
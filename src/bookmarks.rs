@@ -0,0 +1,691 @@
+use crate::{
+    accounts::Accounts,
+    column::Columns,
+    error::Error,
+    lists,
+    note::NoteRef,
+    notecache::NoteCache,
+    outbox::Outbox,
+    signer::{RemoteSigners, SignedNote, Signer},
+    timeline::{ListKind, PubkeySource, Timeline, TimelineId},
+    unknowns::UnknownIds,
+    Result,
+};
+
+use enostr::{Filter, NoteId, Pubkey, RelayPool};
+use nostrdb::{Ndb, Note, NoteBuilder, Subscription, Tag, Transaction};
+use std::collections::HashSet;
+use std::sync::Arc;
+use tracing::{debug, error, warn};
+
+/// Something to do to the account's kind 10003 bookmark list
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum BookmarkAction {
+    /// Bookmark a note. Private bookmarks are NIP-44 encrypted into the
+    /// content of the list, so only we can see them.
+    Add { note_id: NoteId, private: bool },
+
+    /// Remove a note from our bookmarks, public or private
+    Remove(NoteId),
+}
+
+impl BookmarkAction {
+    pub fn note_id(&self) -> &NoteId {
+        match self {
+            BookmarkAction::Add { note_id, .. } => note_id,
+            BookmarkAction::Remove(note_id) => note_id,
+        }
+    }
+
+    /// Publish our bookmark list with this action applied. Returns false
+    /// while a remote signer is still decrypting or encrypting our private
+    /// bookmarks: call it again later.
+    pub fn execute(
+        &self,
+        ndb: &Ndb,
+        txn: &Transaction,
+        pool: &mut RelayPool,
+        outbox: &mut Outbox,
+        remote_signers: &mut RemoteSigners,
+        signer: &Signer,
+    ) -> Result<bool> {
+        let current = latest_bookmarks(ndb, txn, signer.pubkey());
+        let note = if let Some(note) = self.rebuild(current.as_ref(), signer, remote_signers)? {
+            note
+        } else {
+            return Ok(false);
+        };
+
+        signer.publish(note, ndb, pool, outbox, remote_signers)?;
+        Ok(true)
+    }
+
+    /// Our bookmark list with this action applied. We keep all of the
+    /// other tags, since other clients may have put things in there that
+    /// we don't understand.
+    fn rebuild(
+        &self,
+        current: Option<&Note>,
+        signer: &Signer,
+        remote_signers: &mut RemoteSigners,
+    ) -> Result<Option<SignedNote>> {
+        let note_id = self.note_id();
+
+        let content = if let Some(content) = private_content(self, current, signer, remote_signers)?
+        {
+            content
+        } else {
+            return Ok(None);
+        };
+
+        let mut builder = NoteBuilder::new().kind(10003).content(&content);
+
+        if let Some(current) = current {
+            for tag in current.tags() {
+                if tag_note_id(&tag).as_ref() == Some(note_id) {
+                    continue;
+                }
+                builder = lists::copy_tag(builder, &tag);
+            }
+        }
+
+        if let BookmarkAction::Add { private: false, .. } = self {
+            builder = builder.start_tag().tag_str("e").tag_str(&note_id.hex());
+        }
+
+        signer.sign(builder).map(Some)
+    }
+}
+
+/// Bookmark edits waiting to be published. Edits wait here while a remote
+/// signer decrypts and encrypts our private bookmarks for us, and go out
+/// in the order they were made.
+#[derive(Default)]
+pub struct BookmarkEdits {
+    pending: Vec<(Pubkey, BookmarkAction)>,
+}
+
+impl BookmarkEdits {
+    pub fn request(&mut self, account: Pubkey, action: BookmarkAction) {
+        self.pending.push((account, action));
+    }
+
+    pub fn poll(
+        &mut self,
+        ndb: &Ndb,
+        accounts: &Accounts,
+        pool: &mut RelayPool,
+        outbox: &mut Outbox,
+        remote_signers: &mut RemoteSigners,
+    ) {
+        while let Some((account, action)) = self.pending.first() {
+            let signer = accounts.find_account(account.bytes()).and_then(Signer::new);
+
+            if let Some(signer) = signer {
+                let txn = Transaction::new(ndb).expect("txn");
+                match action.execute(ndb, &txn, pool, outbox, remote_signers, &signer) {
+                    // still waiting on the remote signer
+                    Ok(false) => return,
+                    Ok(true) => {}
+                    Err(err) => error!("error updating bookmarks: {err}"),
+                }
+            } else {
+                warn!("can't sign {:?} for {}", action, account);
+            }
+
+            self.pending.remove(0);
+        }
+    }
+}
+
+/// The encrypted content of our bookmark list with the action applied,
+/// or None while a remote signer is working on it
+fn private_content(
+    action: &BookmarkAction,
+    current: Option<&Note>,
+    signer: &Signer,
+    remote_signers: &mut RemoteSigners,
+) -> Result<Option<String>> {
+    let note_id = action.note_id();
+
+    // we'd lose the private entries if we published a list without them
+    let private = match current {
+        Some(list) => private_tags(list, signer, remote_signers)
+            .map_err(|err| Error::Generic(format!("can't read our private bookmarks: {err}")))?,
+        None => Some(vec![]),
+    };
+    let mut private = if let Some(private) = private {
+        private
+    } else {
+        return Ok(None);
+    };
+
+    private.retain(|tag| !is_bookmark_tag(tag, note_id));
+    if let BookmarkAction::Add { private: true, .. } = action {
+        private.push(vec!["e".to_owned(), note_id.hex()]);
+    }
+
+    if private.is_empty() {
+        return Ok(Some(String::new()));
+    }
+
+    let json = serde_json::to_string(&private)
+        .map_err(|e| Error::Generic(format!("private bookmarks json: {e}")))?;
+    signer.encrypt_to_self(remote_signers, &json)
+}
+
+/// The newest copy of a pubkey's bookmark list in nostrdb
+pub fn latest_bookmarks<'a>(ndb: &Ndb, txn: &'a Transaction, pk: &Pubkey) -> Option<Note<'a>> {
+    let list_kind = ListKind::Bookmarks(PubkeySource::Explicit(*pk));
+    let results = ndb
+        .query(txn, &[list_kind.list_filter(pk.bytes())], 10)
+        .ok()?;
+
+    results
+        .into_iter()
+        .map(|r| r.note)
+        .max_by_key(|note| note.created_at())
+}
+
+fn tag_note_id(tag: &Tag) -> Option<NoteId> {
+    if tag.count() < 2 || lists::tag_str(tag, 0) != Some("e") {
+        return None;
+    }
+
+    tag.get(1)
+        .and_then(|s| s.variant().id())
+        .map(|id| NoteId::new(*id))
+}
+
+fn is_bookmark_tag(tag: &[String], note_id: &NoteId) -> bool {
+    tag.len() >= 2 && tag[0] == "e" && tag[1] == note_id.hex()
+}
+
+/// Decrypt the private entries of one of our lists. These are stored as a
+/// NIP-44 encrypted json array of tags. None while a remote signer is
+/// decrypting them.
+fn private_tags(
+    list: &Note,
+    signer: &Signer,
+    remote_signers: &mut RemoteSigners,
+) -> Result<Option<Vec<Vec<String>>>> {
+    let content = list.content();
+    if content.is_empty() {
+        return Ok(Some(vec![]));
+    }
+
+    // NIP-04 encrypted lists from older clients
+    if content.contains("?iv=") {
+        return Err(Error::Generic(
+            "NIP-04 encrypted private list entries are not supported".to_owned(),
+        ));
+    }
+
+    let json = if let Some(json) = signer.decrypt_from_self(remote_signers, content)? {
+        json
+    } else {
+        return Ok(None);
+    };
+
+    serde_json::from_str(&json)
+        .map(Some)
+        .map_err(|e| Error::Generic(format!("invalid private list entries: {e}")))
+}
+
+/// The ids on a bookmark list's public "e" tags, in the order they were saved
+fn public_ids(list: &Note) -> Vec<NoteId> {
+    list.tags()
+        .iter()
+        .filter_map(|tag| tag_note_id(&tag))
+        .collect()
+}
+
+/// The ids in a bookmark list's encrypted content, in the order they were
+/// saved
+fn private_ids(
+    list: &Note,
+    signer: &Signer,
+    remote_signers: &mut RemoteSigners,
+) -> Result<Option<Vec<NoteId>>> {
+    let ids = private_tags(list, signer, remote_signers)?.map(|tags| {
+        tags.into_iter()
+            .filter(|tag| tag.len() >= 2 && tag[0] == "e")
+            .filter_map(|tag| {
+                hex::decode(&tag[1])
+                    .ok()
+                    .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+                    .map(NoteId::new)
+            })
+            .collect()
+    });
+
+    Ok(ids)
+}
+
+/// The ids of the notes in a bookmark list, in the order they were saved.
+/// Private entries are only included when we can sign for the list owner.
+/// None while a remote signer is decrypting them.
+pub fn bookmarked_ids(
+    list: &Note,
+    signer: Option<&Signer>,
+    remote_signers: &mut RemoteSigners,
+) -> Option<Vec<NoteId>> {
+    let mut seen = HashSet::new();
+    let mut ids: Vec<NoteId> = public_ids(list)
+        .into_iter()
+        .filter(|id| seen.insert(*id))
+        .collect();
+
+    let signer = if let Some(signer) = signer.filter(|s| s.pubkey().bytes() == list.pubkey()) {
+        signer
+    } else {
+        return Some(ids);
+    };
+
+    match private_ids(list, signer, remote_signers) {
+        Ok(Some(private)) => ids.extend(private.into_iter().filter(|id| seen.insert(*id))),
+        Ok(None) => return None,
+        Err(err) => error!("error reading private bookmarks: {err}"),
+    }
+
+    Some(ids)
+}
+
+/// Whether one of our bookmarks points to a note
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Bookmarked {
+    No,
+    Publicly,
+    Privately,
+}
+
+/// What the account we'd bookmark with has bookmarked
+#[derive(Default)]
+struct BookmarkSet {
+    public: HashSet<NoteId>,
+    private: HashSet<NoteId>,
+}
+
+/// Keeps track of what the account we bookmark with has bookmarked, for
+/// the note context menus
+#[derive(Default)]
+pub struct AccountBookmarks {
+    account: Option<Pubkey>,
+
+    /// Watches for new copies of the account's bookmark list
+    sub: Option<Subscription>,
+
+    /// Whether a remote signer is still decrypting our private bookmarks
+    decrypting: bool,
+}
+
+impl AccountBookmarks {
+    pub fn poll(
+        &mut self,
+        ctx: &egui::Context,
+        ndb: &Ndb,
+        signer: Option<&Signer>,
+        remote_signers: &mut RemoteSigners,
+    ) {
+        let account = signer.map(|s| *s.pubkey());
+        let switched = self.account != account;
+        if switched {
+            self.account = account;
+            self.watch(ndb);
+        }
+
+        let changed = self
+            .sub
+            .map_or(false, |sub| !ndb.poll_for_notes(sub, 10).is_empty());
+        if !switched && !changed && !self.decrypting {
+            return;
+        }
+        self.decrypting = false;
+
+        let signer = if let Some(signer) = signer {
+            signer
+        } else {
+            ctx.data_mut(|d| d.remove::<Arc<BookmarkSet>>(bookmarks_id()));
+            return;
+        };
+
+        let txn = if let Ok(txn) = Transaction::new(ndb) {
+            txn
+        } else {
+            return;
+        };
+
+        let mut set = BookmarkSet::default();
+        if let Some(list) = latest_bookmarks(ndb, &txn, signer.pubkey()) {
+            set.public = public_ids(&list).into_iter().collect();
+            match private_ids(&list, signer, remote_signers) {
+                Ok(Some(private)) => set.private = private.into_iter().collect(),
+                Ok(None) => self.decrypting = true,
+                Err(err) => debug!("can't show private bookmarks in menus: {err}"),
+            }
+        }
+
+        ctx.data_mut(|d| d.insert_temp(bookmarks_id(), Arc::new(set)));
+    }
+
+    fn watch(&mut self, ndb: &Ndb) {
+        if let Some(sub) = self.sub.take() {
+            if let Err(err) = ndb.unsubscribe(sub) {
+                error!("error unsubscribing from bookmarks: {err}");
+            }
+        }
+
+        if let Some(account) = &self.account {
+            let list_kind = ListKind::Bookmarks(PubkeySource::Explicit(*account));
+            match ndb.subscribe(&[list_kind.list_filter(account.bytes())]) {
+                Ok(sub) => self.sub = Some(sub),
+                Err(err) => error!("error watching bookmarks: {err}"),
+            }
+        }
+    }
+}
+
+fn bookmarks_id() -> egui::Id {
+    egui::Id::new("account_bookmarks")
+}
+
+/// Whether we bookmarked a note, or None if there's no account we can
+/// bookmark with
+pub fn bookmarked(ctx: &egui::Context, note_id: &NoteId) -> Option<Bookmarked> {
+    ctx.data(|d| {
+        d.get_temp::<Arc<BookmarkSet>>(bookmarks_id()).map(|set| {
+            if set.private.contains(note_id) {
+                Bookmarked::Privately
+            } else if set.public.contains(note_id) {
+                Bookmarked::Publicly
+            } else {
+                Bookmarked::No
+            }
+        })
+    })
+}
+
+/// Bookmark columns don't show what their subscription returns. Their
+/// subscription is on the bookmark list itself, and whenever it changes we
+/// rebuild the column from the notes the list points to, in saved order.
+#[derive(Default)]
+pub struct BookmarkTimelines {
+    built: HashSet<TimelineId>,
+}
+
+impl BookmarkTimelines {
+    /// Forget about bookmark columns that were removed
+    pub fn prune(&mut self, columns: &Columns) {
+        let timelines = columns.timelines();
+        self.built
+            .retain(|id| timelines.iter().any(|timeline| timeline.id == *id));
+    }
+
+    pub fn poll(
+        &mut self,
+        timeline: &mut Timeline,
+        ndb: &Ndb,
+        txn: &Transaction,
+        unknown_ids: &mut UnknownIds,
+        note_cache: &mut NoteCache,
+        signer: Option<&Signer>,
+        remote_signers: &mut RemoteSigners,
+    ) -> Result<()> {
+        let sub = timeline.subscription.ok_or(Error::no_active_sub())?;
+
+        let changed = !ndb.poll_for_notes(sub, 100).is_empty();
+        if !changed && self.built.contains(&timeline.id) {
+            return Ok(());
+        }
+        self.built.insert(timeline.id);
+
+        // the first filter is always the list itself
+        let list_filter = timeline
+            .filter
+            .get_any_ready()
+            .and_then(|filters| filters.first())
+            .ok_or(Error::empty_list())?
+            .clone();
+
+        let results = ndb.query(txn, &[list_filter.clone()], 1)?;
+        let list = if let Some(result) = results.first() {
+            &result.note
+        } else {
+            return Ok(());
+        };
+
+        let ids = if let Some(ids) = bookmarked_ids(list, signer, remote_signers) {
+            ids
+        } else {
+            // we rebuild once the remote signer has decrypted our private
+            // bookmarks
+            self.built.remove(&timeline.id);
+            return Ok(());
+        };
+
+        // most recently saved first
+        let mut notes = Vec::with_capacity(ids.len());
+        let mut missing = vec![];
+        for id in ids.iter().rev() {
+            if let Ok(note) = ndb.get_note_by_id(txn, id.bytes()) {
+                let key = note.key().expect("note key");
+                let created_at = note.created_at();
+                notes.push((note, NoteRef { key, created_at }));
+            } else {
+                unknown_ids.add_note_id_if_missing(ndb, txn, id);
+                missing.push(*id.bytes());
+            }
+        }
+
+        debug!(
+            "rebuilt bookmarks timeline {}: {} notes, {} missing",
+            timeline.id,
+            notes.len(),
+            missing.len()
+        );
+
+        for view in &mut timeline.views {
+            let filter = view.filter.filter();
+            view.notes = notes
+                .iter()
+                .filter(|(note, nr)| filter(note_cache.cached_note_or_insert(nr.key, note), note))
+                .map(|(_note, nr)| *nr)
+                .collect();
+            view.list.borrow_mut().reset();
+        }
+
+        // Watch for the notes we're missing too, so we can rebuild once
+        // they've been fetched
+        let mut filters = vec![list_filter];
+        if !missing.is_empty() {
+            filters.push(Filter::new().ids(missing.iter()).build());
+        }
+        timeline.subscription = Some(ndb.subscribe(&filters)?);
+        ndb.unsubscribe(sub)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{local_note, with_signer};
+    use enostr::FullKeypair;
+
+    fn ids(n: u8) -> Vec<NoteId> {
+        (1..=n).map(|i| NoteId::new([i; 32])).collect()
+    }
+
+    fn rebuilt(action: BookmarkAction, current: Option<&Note>, signer: &Signer) -> Note<'static> {
+        let note = action
+            .rebuild(current, signer, &mut RemoteSigners::default())
+            .unwrap()
+            .expect("local signers don't wait");
+        local_note(note)
+    }
+
+    fn encrypted_to_self(kp: &FullKeypair, private: &str) -> String {
+        with_signer(kp, |signer| {
+            signer
+                .encrypt_to_self(&mut RemoteSigners::default(), private)
+                .unwrap()
+                .unwrap()
+        })
+    }
+
+    #[test]
+    fn test_bookmarked_ids() {
+        let kp = FullKeypair::generate();
+        let ids = ids(3);
+        let mut remote_signers = RemoteSigners::default();
+
+        let private = serde_json::to_string(&[["e".to_owned(), ids[2].hex()]]).unwrap();
+        let content = encrypted_to_self(&kp, &private);
+        let list = NoteBuilder::new()
+            .kind(10003)
+            .content(&content)
+            .start_tag()
+            .tag_str("e")
+            .tag_str(&ids[1].hex())
+            .start_tag()
+            .tag_str("t")
+            .tag_str("nostr")
+            .start_tag()
+            .tag_str("e")
+            .tag_str(&ids[0].hex())
+            .start_tag()
+            .tag_str("e")
+            .tag_str(&ids[1].hex())
+            .sign(&kp.secret_key.to_secret_bytes())
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            with_signer(&kp, |signer| bookmarked_ids(
+                &list,
+                Some(signer),
+                &mut remote_signers
+            )),
+            Some(vec![ids[1], ids[0], ids[2]])
+        );
+
+        // only the owner sees the private entries
+        assert_eq!(
+            bookmarked_ids(&list, None, &mut remote_signers),
+            Some(vec![ids[1], ids[0]])
+        );
+        assert_eq!(
+            with_signer(&FullKeypair::generate(), |other| {
+                bookmarked_ids(&list, Some(other), &mut remote_signers)
+            }),
+            Some(vec![ids[1], ids[0]])
+        );
+    }
+
+    #[test]
+    fn test_execute() {
+        let kp = FullKeypair::generate();
+        let ids = ids(2);
+        let mut remote_signers = RemoteSigners::default();
+
+        with_signer(&kp, |signer| {
+            let add = |current: Option<&Note>, note_id: NoteId, private: bool| {
+                rebuilt(BookmarkAction::Add { note_id, private }, current, signer)
+            };
+
+            let list = add(None, ids[0], false);
+            assert_eq!(list.kind(), 10003);
+            assert_eq!(list.content(), "");
+            assert_eq!(
+                bookmarked_ids(&list, Some(signer), &mut remote_signers),
+                Some(vec![ids[0]])
+            );
+
+            let list = add(Some(&list), ids[1], true);
+            assert_eq!(public_ids(&list), vec![ids[0]]);
+            assert_eq!(
+                private_ids(&list, signer, &mut remote_signers).unwrap(),
+                Some(vec![ids[1]])
+            );
+
+            // bookmarking again moves it, it doesn't add it twice
+            let list = add(Some(&list), ids[1], false);
+            assert_eq!(public_ids(&list), vec![ids[0], ids[1]]);
+            assert_eq!(list.content(), "");
+
+            let list = rebuilt(BookmarkAction::Remove(ids[0]), Some(&list), signer);
+            assert_eq!(
+                bookmarked_ids(&list, Some(signer), &mut remote_signers),
+                Some(vec![ids[1]])
+            );
+
+            // we don't publish over private entries we can't read
+            let nip04 = NoteBuilder::new()
+                .kind(10003)
+                .content("abc?iv=def")
+                .sign(&kp.secret_key.to_secret_bytes())
+                .build()
+                .unwrap();
+            assert!(BookmarkAction::Remove(ids[0])
+                .rebuild(Some(&nip04), signer, &mut remote_signers)
+                .is_err());
+        });
+    }
+
+    #[test]
+    fn test_remote_signer_waits_for_private_entries() {
+        let kp = FullKeypair::generate();
+        let ids = ids(2);
+        let uri = enostr::BunkerUri::parse(&format!(
+            "bunker://{}?relay=wss://relay.example.com",
+            FullKeypair::generate().pubkey.hex()
+        ))
+        .unwrap();
+        let remote = enostr::RemoteSigner::from_bunker_uri(&uri);
+        let signer = Signer::Remote {
+            pubkey: &kp.pubkey,
+            signer: &remote,
+        };
+        let mut remote_signers = RemoteSigners::default();
+
+        let public = BookmarkAction::Add {
+            note_id: ids[0],
+            private: false,
+        };
+        let private = BookmarkAction::Add {
+            note_id: ids[0],
+            private: true,
+        };
+
+        // nothing to encrypt, the signer only signs
+        let unsigned = match public.rebuild(None, &signer, &mut remote_signers).unwrap() {
+            Some(SignedNote::Remote(unsigned)) => unsigned,
+            _ => panic!("expected a note for the remote signer"),
+        };
+        let unsigned: serde_json::Value = serde_json::from_str(&unsigned).unwrap();
+        assert_eq!(unsigned["content"], "");
+
+        // private entries wait for the signer to encrypt or decrypt them
+        assert!(private
+            .rebuild(None, &signer, &mut remote_signers)
+            .unwrap()
+            .is_none());
+
+        let entries = serde_json::to_string(&[["e".to_owned(), ids[1].hex()]]).unwrap();
+        let list = NoteBuilder::new()
+            .kind(10003)
+            .content(&encrypted_to_self(&kp, &entries))
+            .sign(&kp.secret_key.to_secret_bytes())
+            .build()
+            .unwrap();
+        assert!(public
+            .rebuild(Some(&list), &signer, &mut remote_signers)
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            bookmarked_ids(&list, Some(&signer), &mut remote_signers),
+            None
+        );
+    }
+}
@@ -179,6 +179,13 @@ impl Columns {
             .1
     }
 
+    /// The index of the column a timeline is in
+    pub fn find_column_index_for_timeline(&self, id: TimelineId) -> Option<usize> {
+        self.columns
+            .keys()
+            .position(|col_id| self.timelines.get(col_id).map_or(false, |tl| tl.id == id))
+    }
+
     pub fn find_timeline_for_column_index(&self, ind: usize) -> Option<&Timeline> {
         let col_id = self.get_column_id_at_index(ind);
         self.timelines.get(&col_id)
@@ -261,7 +268,7 @@ impl SerializableColumns {
                     Route::Timeline(TimelineRoute::Thread(_thread)) => {
                         // TODO: open thread before pushing route
                     }
-                    _ => routes.push(*route),
+                    _ => routes.push(route.clone()),
                 }
             }
             columns.add_column_at(Column::new(routes), id);
@@ -0,0 +1,338 @@
+//! Following and unfollowing from our accounts.
+//!
+//! Each edit waits for the newest copy of the contact list, so we don't
+//! wipe out follows made from other clients. See [`crate::replaceable`].
+
+use crate::{
+    accounts::Accounts,
+    lists::{copy_tag, tag_str},
+    outbox::Outbox,
+    replaceable::{self, FetchError, NewestFetch},
+    signer::{RemoteSigners, SignedNote, Signer},
+    subscriptions::{SubKind, Subscriptions},
+    Result,
+};
+
+use enostr::{Filter, Pubkey, RelayPool};
+use nostrdb::{Ndb, Note, NoteBuilder, Transaction};
+use std::time::Instant;
+use tracing::{error, info, warn};
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ContactAction {
+    Follow(Pubkey),
+    Unfollow(Pubkey),
+}
+
+impl ContactAction {
+    /// Who we're following or unfollowing
+    pub fn pubkey(&self) -> &Pubkey {
+        match self {
+            ContactAction::Follow(pk) | ContactAction::Unfollow(pk) => pk,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum EditState {
+    /// Waiting on the newest copy of the contact list
+    Fetching,
+
+    /// We didn't apply the edit. It stays around so it can be retried.
+    Failed(ContactAction, FetchError),
+}
+
+/// An edit waiting on the newest copy of the account's contact list
+struct PendingEdit {
+    account: Pubkey,
+    action: ContactAction,
+    fetch: NewestFetch,
+    error: Option<FetchError>,
+}
+
+/// Follows and unfollows waiting to be applied
+#[derive(Default)]
+pub struct ContactEdits {
+    pending: Vec<PendingEdit>,
+}
+
+impl ContactEdits {
+    /// Fetch the account's newest contact list, and apply the action to
+    /// it once we have it. This replaces any edit of the same pubkey that
+    /// is still waiting or failed.
+    pub fn request(&mut self, subs: &mut Subscriptions, account: Pubkey, action: ContactAction) {
+        self.pending
+            .retain(|edit| edit.account != account || edit.action.pubkey() != action.pubkey());
+
+        let fetch = NewestFetch::request(subs, SubKind::ContactList, account, 3);
+        self.pending.push(PendingEdit {
+            account,
+            action,
+            fetch,
+            error: None,
+        });
+    }
+
+    /// How the account's edit of a pubkey is going, if it has one we
+    /// haven't applied
+    pub fn state(&self, account: &Pubkey, pubkey: &Pubkey) -> Option<EditState> {
+        self.pending
+            .iter()
+            .find(|edit| &edit.account == account && edit.action.pubkey() == pubkey)
+            .map(|edit| match edit.error {
+                Some(err) => EditState::Failed(edit.action, err),
+                None => EditState::Fetching,
+            })
+    }
+
+    /// Keep track of how new the contact lists relays are sending us are,
+    /// so we don't edit an older copy
+    pub fn handle_event(&mut self, subid: &str, msg: &str) {
+        for edit in &mut self.pending {
+            edit.fetch.handle_event(subid, msg);
+        }
+    }
+
+    /// Every relay is done sending us the contact list
+    pub fn fetched(&mut self, subid: &str) {
+        for edit in &mut self.pending {
+            edit.fetch.fetched(subid);
+        }
+    }
+
+    /// Apply the edits whose contact lists have arrived
+    pub fn poll(
+        &mut self,
+        ndb: &Ndb,
+        accounts: &Accounts,
+        pool: &mut RelayPool,
+        outbox: &mut Outbox,
+        remote_signers: &mut RemoteSigners,
+    ) {
+        let now = Instant::now();
+        let mut ready = Vec::new();
+
+        let mut i = 0;
+        while i < self.pending.len() {
+            let edit = &mut self.pending[i];
+            if edit.error.is_some() {
+                i += 1;
+                continue;
+            }
+
+            match edit.fetch.poll(ndb, now) {
+                Some(Ok(())) => ready.push(self.pending.remove(i)),
+                Some(Err(err)) => {
+                    warn!(
+                        "not applying {:?} for {}: {err:?}",
+                        edit.action, edit.account
+                    );
+                    edit.error = Some(err);
+                    i += 1;
+                }
+                None => i += 1,
+            }
+        }
+
+        for edit in ready {
+            let signer = if let Some(signer) = accounts
+                .find_account(edit.account.bytes())
+                .and_then(Signer::new)
+            {
+                signer
+            } else {
+                warn!("can't sign {:?} for {}", edit.action, edit.account);
+                continue;
+            };
+
+            let txn = Transaction::new(ndb).expect("txn");
+            let current = latest_contact_list(ndb, &txn, &edit.account);
+            match rebuild_contact_list(current.as_ref(), &signer, &edit.action) {
+                Ok(Some(note)) => {
+                    info!("publishing contact list for {:?}", edit.action);
+                    if let Err(err) = signer.publish(note, ndb, pool, outbox, remote_signers) {
+                        error!("error publishing contact list: {err}");
+                    }
+                }
+                Ok(None) => info!("{:?} didn't change the contact list", edit.action),
+                Err(err) => error!("error building contact list: {err}"),
+            }
+        }
+    }
+}
+
+pub fn contact_list_filter(pk: &[u8; 32]) -> Filter {
+    replaceable::newest_filter(pk, 3)
+}
+
+/// The newest copy of an account's contact list in nostrdb
+pub fn latest_contact_list<'a>(ndb: &Ndb, txn: &'a Transaction, pk: &Pubkey) -> Option<Note<'a>> {
+    replaceable::latest_note(ndb, txn, pk, 3)
+}
+
+pub fn is_following(ndb: &Ndb, txn: &Transaction, account: &Pubkey, pubkey: &Pubkey) -> bool {
+    latest_contact_list(ndb, txn, account).map_or(false, |list| {
+        crate::lists::list_has_pubkey(&list, pubkey.bytes())
+    })
+}
+
+fn p_tag_pubkey<'a>(tag: &nostrdb::Tag<'a>) -> Option<&'a [u8; 32]> {
+    if tag.count() >= 2 && tag_str(tag, 0) == Some("p") {
+        tag.get(1).and_then(|s| s.variant().id())
+    } else {
+        None
+    }
+}
+
+/// Rebuild a contact list with the action applied. We keep every other
+/// tag and the content as they are: other clients keep relay lists and
+/// petnames in there. None if the list already has the change.
+fn rebuild_contact_list(
+    current: Option<&Note>,
+    signer: &Signer,
+    action: &ContactAction,
+) -> Result<Option<SignedNote>> {
+    let mut builder = NoteBuilder::new()
+        .kind(3)
+        .content(current.map_or("", |note| note.content()));
+    let mut changed = false;
+    let mut has_followed = false;
+
+    if let Some(current) = current {
+        for tag in current.tags() {
+            match (action, p_tag_pubkey(&tag)) {
+                (ContactAction::Unfollow(pk), Some(tag_pk)) if pk.bytes() == tag_pk => {
+                    changed = true;
+                    continue;
+                }
+                (ContactAction::Follow(pk), Some(tag_pk)) if pk.bytes() == tag_pk => {
+                    has_followed = true;
+                }
+                _ => {}
+            }
+
+            builder = copy_tag(builder, &tag);
+        }
+    }
+
+    if let ContactAction::Follow(pk) = action {
+        if !has_followed {
+            builder = builder.start_tag().tag_str("p").tag_str(&pk.hex());
+            changed = true;
+        }
+    }
+
+    if !changed {
+        return Ok(None);
+    }
+
+    signer.sign(builder).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{local_note, with_signer};
+    use enostr::FullKeypair;
+
+    fn contact_list(kp: &FullKeypair, follows: &[&Pubkey]) -> Note<'static> {
+        let seckey = kp.secret_key.to_secret_bytes();
+        let mut builder = NoteBuilder::new()
+            .kind(3)
+            .content(r#"{"wss://relay.damus.io":{"read":true,"write":true}}"#)
+            .start_tag()
+            .tag_str("t")
+            .tag_str("nostr");
+        for pk in follows {
+            builder = builder
+                .start_tag()
+                .tag_str("p")
+                .tag_str(&pk.hex())
+                .tag_str("wss://nos.lol")
+                .tag_str("alice");
+        }
+        builder.sign(&seckey).build().unwrap()
+    }
+
+    fn rebuilt(
+        current: Option<&Note>,
+        kp: &FullKeypair,
+        action: ContactAction,
+    ) -> Option<Note<'static>> {
+        with_signer(kp, |signer| {
+            rebuild_contact_list(current, signer, &action)
+                .unwrap()
+                .map(local_note)
+        })
+    }
+
+    #[test]
+    fn test_follow_keeps_other_tags_and_content() {
+        let kp = FullKeypair::generate();
+        let alice = FullKeypair::generate().pubkey;
+        let bob = FullKeypair::generate().pubkey;
+        let current = contact_list(&kp, &[&alice]);
+
+        let note = rebuilt(Some(&current), &kp, ContactAction::Follow(bob)).unwrap();
+
+        assert_eq!(note.content(), current.content());
+        assert!(crate::lists::list_has_pubkey(&note, alice.bytes()));
+        assert!(crate::lists::list_has_pubkey(&note, bob.bytes()));
+
+        // the petname and relay hint on alice survive, and so does the t tag
+        let alice_tag = note
+            .tags()
+            .iter()
+            .find(|t| p_tag_pubkey(t) == Some(alice.bytes()))
+            .unwrap();
+        assert_eq!(alice_tag.count(), 4);
+        assert!(note.tags().iter().any(|t| tag_str(&t, 0) == Some("t")));
+    }
+
+    #[test]
+    fn test_unfollow() {
+        let kp = FullKeypair::generate();
+        let alice = FullKeypair::generate().pubkey;
+        let bob = FullKeypair::generate().pubkey;
+        let current = contact_list(&kp, &[&alice, &bob]);
+
+        let note = rebuilt(Some(&current), &kp, ContactAction::Unfollow(alice)).unwrap();
+
+        assert!(!crate::lists::list_has_pubkey(&note, alice.bytes()));
+        assert!(crate::lists::list_has_pubkey(&note, bob.bytes()));
+    }
+
+    #[test]
+    fn test_no_change() {
+        let kp = FullKeypair::generate();
+        let alice = FullKeypair::generate().pubkey;
+        let current = contact_list(&kp, &[&alice]);
+
+        assert!(rebuilt(Some(&current), &kp, ContactAction::Follow(alice)).is_none());
+        assert!(rebuilt(None, &kp, ContactAction::Unfollow(alice)).is_none());
+        assert!(rebuilt(None, &kp, ContactAction::Follow(alice)).is_some());
+    }
+
+    #[test]
+    fn test_retry_replaces_failed_edit() {
+        let mut edits = ContactEdits::default();
+        let mut subs = Subscriptions::default();
+        let account = FullKeypair::generate().pubkey;
+        let alice = FullKeypair::generate().pubkey;
+        let follow = ContactAction::Follow(alice);
+
+        edits.request(&mut subs, account, follow);
+        assert_eq!(edits.state(&account, &alice), Some(EditState::Fetching));
+        assert_eq!(edits.state(&alice, &account), None);
+
+        edits.pending[0].error = Some(FetchError::Unreachable);
+        assert_eq!(
+            edits.state(&account, &alice),
+            Some(EditState::Failed(follow, FetchError::Unreachable))
+        );
+
+        edits.request(&mut subs, account, follow);
+        assert_eq!(edits.pending.len(), 1);
+        assert_eq!(edits.state(&account, &alice), Some(EditState::Fetching));
+    }
+}
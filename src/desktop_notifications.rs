@@ -0,0 +1,824 @@
+use crate::{
+    actionbar::NoteAction,
+    note::NoteRef,
+    notifications::{notification_author, notification_target, zap_msats, NotificationKind},
+    storage::{write_file, DataPath, DataPathType, Directory},
+    timeline::{Timeline, TimelineId, TimelineKind},
+    ui::profile::preview::get_profile_displayname_string,
+    Result,
+};
+
+use enostr::Pubkey;
+use nostrdb::{Ndb, Note, NoteKey, NoteReply, Transaction};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::mpsc::{self, Receiver, Sender};
+use tracing::{error, info};
+
+static FILE_NAME: &str = "notifications.json";
+
+/// How much of a note we show in the body of a notification
+const SNIPPET_CHARS: usize = 140;
+
+/// The things we can raise a desktop notification for. These can be
+/// toggled individually in the notification settings.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum DesktopNotificationKind {
+    Mention,
+    Reply,
+    Reaction,
+    Repost,
+    Zap,
+    Follow,
+}
+
+impl DesktopNotificationKind {
+    pub const ALL: [DesktopNotificationKind; 6] = [
+        DesktopNotificationKind::Mention,
+        DesktopNotificationKind::Reply,
+        DesktopNotificationKind::Reaction,
+        DesktopNotificationKind::Repost,
+        DesktopNotificationKind::Zap,
+        DesktopNotificationKind::Follow,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DesktopNotificationKind::Mention => "Mentions",
+            DesktopNotificationKind::Reply => "Replies",
+            DesktopNotificationKind::Reaction => "Reactions",
+            DesktopNotificationKind::Repost => "Reposts",
+            DesktopNotificationKind::Zap => "Zaps",
+            DesktopNotificationKind::Follow => "Follows",
+        }
+    }
+}
+
+/// Local hours during which we don't raise notifications. The range is
+/// [start, end) and wraps around midnight when start is after end, so
+/// 22..7 is quiet overnight.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub struct QuietHours {
+    pub start: u32,
+    pub end: u32,
+}
+
+impl Default for QuietHours {
+    fn default() -> Self {
+        QuietHours { start: 22, end: 7 }
+    }
+}
+
+impl QuietHours {
+    pub fn contains(&self, hour: u32) -> bool {
+        if self.start <= self.end {
+            hour >= self.start && hour < self.end
+        } else {
+            hour >= self.start || hour < self.end
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationSettings {
+    pub enabled: bool,
+
+    pub mentions: bool,
+    pub replies: bool,
+    pub reactions: bool,
+    pub reposts: bool,
+    pub zaps: bool,
+    pub follows: bool,
+
+    /// Notification columns we don't raise notifications for
+    pub muted_columns: Vec<TimelineKind>,
+
+    /// Do not disturb
+    pub quiet_hours: Option<QuietHours>,
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        NotificationSettings {
+            enabled: true,
+            mentions: true,
+            replies: true,
+            reactions: false,
+            reposts: false,
+            zaps: false,
+            follows: false,
+            muted_columns: vec![],
+            quiet_hours: None,
+        }
+    }
+}
+
+impl NotificationSettings {
+    pub fn kind_enabled_mut(&mut self, kind: DesktopNotificationKind) -> &mut bool {
+        match kind {
+            DesktopNotificationKind::Mention => &mut self.mentions,
+            DesktopNotificationKind::Reply => &mut self.replies,
+            DesktopNotificationKind::Reaction => &mut self.reactions,
+            DesktopNotificationKind::Repost => &mut self.reposts,
+            DesktopNotificationKind::Zap => &mut self.zaps,
+            DesktopNotificationKind::Follow => &mut self.follows,
+        }
+    }
+
+    pub fn kind_enabled(&self, kind: DesktopNotificationKind) -> bool {
+        match kind {
+            DesktopNotificationKind::Mention => self.mentions,
+            DesktopNotificationKind::Reply => self.replies,
+            DesktopNotificationKind::Reaction => self.reactions,
+            DesktopNotificationKind::Repost => self.reposts,
+            DesktopNotificationKind::Zap => self.zaps,
+            DesktopNotificationKind::Follow => self.follows,
+        }
+    }
+
+    pub fn is_column_muted(&self, kind: &TimelineKind) -> bool {
+        self.muted_columns.contains(kind)
+    }
+
+    pub fn set_column_muted(&mut self, kind: &TimelineKind, muted: bool) {
+        self.muted_columns.retain(|k| k != kind);
+        if muted {
+            self.muted_columns.push(kind.clone());
+        }
+    }
+
+    pub fn is_quiet(&self, hour: u32) -> bool {
+        self.quiet_hours.map_or(false, |quiet| quiet.contains(hour))
+    }
+}
+
+/// What to do when a notification is clicked
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct NotificationClick {
+    /// The column the notification came from, this is where we open the
+    /// thread or profile
+    pub timeline: TimelineId,
+    pub action: NoteAction,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct DesktopNotification {
+    pub kind: DesktopNotificationKind,
+    pub summary: String,
+    pub body: String,
+    pub click: NotificationClick,
+}
+
+/// Something that can show notifications to the user. Backends send a
+/// NotificationClick down the channel they were created with when one of
+/// their notifications is clicked.
+pub trait NotificationBackend {
+    fn show(&mut self, notification: &DesktopNotification) -> Result<()>;
+}
+
+/// Raises desktop notifications for new notes in notification columns
+/// while the window isn't focused
+pub struct DesktopNotifications {
+    pub settings: NotificationSettings,
+    directory: Option<Directory>,
+    backend: Option<Box<dyn NotificationBackend>>,
+    clicks: Receiver<NotificationClick>,
+
+    /// We only notify about notes created after we started, otherwise we'd
+    /// notify about everything our relays send us on startup
+    since: u64,
+    notified: HashSet<NoteKey>,
+}
+
+impl DesktopNotifications {
+    pub fn new(ctx: &egui::Context, path: &DataPath) -> Self {
+        let directory = Directory::new(path.path(DataPathType::Setting));
+        let settings = load_settings(&directory);
+
+        #[cfg(target_os = "linux")]
+        let mut notifications = {
+            let ctx = ctx.clone();
+            Self::with_backend(settings, move |clicks| {
+                Box::new(dbus::DbusNotifications::new(ctx, clicks))
+            })
+        };
+
+        #[cfg(not(target_os = "linux"))]
+        let mut notifications = {
+            let _ = ctx;
+            Self::disabled(settings)
+        };
+
+        notifications.directory = Some(directory);
+        notifications
+    }
+
+    /// Settings without a backend, nothing will be shown
+    pub fn disabled(settings: NotificationSettings) -> Self {
+        let (_sender, clicks) = mpsc::channel();
+        DesktopNotifications {
+            settings,
+            directory: None,
+            backend: None,
+            clicks,
+            since: now(),
+            notified: HashSet::new(),
+        }
+    }
+
+    pub fn with_backend<F>(settings: NotificationSettings, make_backend: F) -> Self
+    where
+        F: FnOnce(Sender<NotificationClick>) -> Box<dyn NotificationBackend>,
+    {
+        let (sender, clicks) = mpsc::channel();
+        DesktopNotifications {
+            settings,
+            directory: None,
+            backend: Some(make_backend(sender)),
+            clicks,
+            since: now(),
+            notified: HashSet::new(),
+        }
+    }
+
+    pub fn save_settings(&self) {
+        let directory = if let Some(directory) = &self.directory {
+            directory
+        } else {
+            return;
+        };
+
+        match serde_json::to_string(&self.settings) {
+            Ok(json) => {
+                if let Err(err) = write_file(&directory.file_path, FILE_NAME.to_owned(), &json) {
+                    error!("error saving notification settings: {err}");
+                }
+            }
+            Err(err) => error!("error serializing notification settings: {err}"),
+        }
+    }
+
+    /// Notifications that were clicked since we last checked
+    pub fn poll_clicks(&self) -> Vec<NotificationClick> {
+        self.clicks.try_iter().collect()
+    }
+
+    /// Look at notes that just landed in a timeline and raise notifications
+    /// for the ones the user wants to know about. `hour` is the current
+    /// local hour, for do not disturb.
+    #[allow(clippy::too_many_arguments)]
+    pub fn process_new_notes(
+        &mut self,
+        ndb: &Ndb,
+        txn: &Transaction,
+        timeline: &Timeline,
+        account: Option<&Pubkey>,
+        new_notes: &[NoteRef],
+        focused: bool,
+        hour: u32,
+    ) {
+        if self.backend.is_none() || !self.settings.enabled || focused {
+            return;
+        }
+
+        let account = if let Some(account) = account {
+            account
+        } else {
+            return;
+        };
+
+        // only the selected account's notifications
+        if let TimelineKind::Notifications(pk_src) = &timeline.kind {
+            if pk_src.to_pubkey(Some(account.bytes())) != Some(account.bytes()) {
+                return;
+            }
+        } else {
+            return;
+        }
+
+        if self.settings.is_column_muted(&timeline.kind) || self.settings.is_quiet(hour) {
+            return;
+        }
+
+        for note_ref in new_notes {
+            if note_ref.created_at < self.since || !self.notified.insert(note_ref.key) {
+                continue;
+            }
+
+            let note = if let Ok(note) = ndb.get_note_by_key(txn, note_ref.key) {
+                note
+            } else {
+                continue;
+            };
+
+            if note.pubkey() == account.bytes() {
+                continue;
+            }
+
+            if let Some(notification) = describe_note(ndb, txn, timeline.id, &note) {
+                self.raise(&notification);
+            }
+        }
+    }
+
+    fn raise(&mut self, notification: &DesktopNotification) {
+        if !self.settings.kind_enabled(notification.kind) {
+            return;
+        }
+
+        if let Some(backend) = &mut self.backend {
+            info!("raising notification: {}", notification.summary);
+            if let Err(err) = backend.show(notification) {
+                error!("error raising notification: {err}");
+            }
+        }
+    }
+}
+
+fn load_settings(directory: &Directory) -> NotificationSettings {
+    match directory.get_file(FILE_NAME.to_owned()) {
+        Ok(json) => serde_json::from_str(&json).unwrap_or_else(|err| {
+            error!("invalid notification settings, using defaults: {err}");
+            NotificationSettings::default()
+        }),
+        Err(_) => NotificationSettings::default(),
+    }
+}
+
+fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn snippet(content: &str) -> String {
+    let mut snippet: String = content.chars().take(SNIPPET_CHARS).collect();
+    if snippet.len() < content.len() {
+        snippet.push('…');
+    }
+    snippet
+}
+
+/// Turn a note from a notifications timeline into a notification
+fn describe_note(
+    ndb: &Ndb,
+    txn: &Transaction,
+    timeline: TimelineId,
+    note: &Note,
+) -> Option<DesktopNotification> {
+    let kind = NotificationKind::from_note_kind(note.kind())?;
+    let author = notification_author(note, kind);
+    let name = get_profile_displayname_string(ndb, &author);
+    let target = notification_target(note, kind);
+
+    let target_snippet = || {
+        target
+            .and_then(|id| ndb.get_note_by_id(txn, id.bytes()).ok())
+            .map(|note| snippet(note.content()))
+            .unwrap_or_default()
+    };
+
+    let (kind, summary, body) = match kind {
+        NotificationKind::Mention => {
+            if NoteReply::new(note.tags()).is_reply() {
+                (
+                    DesktopNotificationKind::Reply,
+                    format!("{name} replied"),
+                    snippet(note.content()),
+                )
+            } else {
+                (
+                    DesktopNotificationKind::Mention,
+                    format!("{name} mentioned you"),
+                    snippet(note.content()),
+                )
+            }
+        }
+
+        NotificationKind::Reaction => {
+            let summary = match note.content() {
+                "" | "+" => format!("{name} liked your note"),
+                reaction => format!("{name} reacted {reaction}"),
+            };
+            (DesktopNotificationKind::Reaction, summary, target_snippet())
+        }
+
+        NotificationKind::Repost => (
+            DesktopNotificationKind::Repost,
+            format!("{name} reposted your note"),
+            target_snippet(),
+        ),
+
+        NotificationKind::Zap => {
+            let sats = zap_msats(note).unwrap_or(0) / 1000;
+            (
+                DesktopNotificationKind::Zap,
+                format!("{name} zapped you {sats} sats"),
+                target_snippet(),
+            )
+        }
+
+        NotificationKind::Follow => (
+            DesktopNotificationKind::Follow,
+            format!("{name} followed you"),
+            String::new(),
+        ),
+    };
+
+    let action = match target {
+        Some(id) => NoteAction::OpenThread(id),
+        None => NoteAction::OpenProfile(author),
+    };
+
+    Some(DesktopNotification {
+        kind,
+        summary,
+        body,
+        click: NotificationClick { timeline, action },
+    })
+}
+
+/// Waiting for clicks on the notifications we show
+#[cfg(any(target_os = "linux", test))]
+mod worker {
+    use super::NotificationClick;
+    use crate::{Error, Result};
+    use std::sync::mpsc::{self, Sender};
+    use std::sync::{Arc, Mutex};
+    use tracing::error;
+
+    /// A notification on screen
+    pub trait ShownNotification: Send + 'static {
+        fn id(&self) -> u32;
+
+        /// Blocks until the notification is clicked or closed
+        fn wait_for_action(self, on_action: impl FnOnce(&str));
+    }
+
+    /// The notification the worker is waiting on, and what clicking it
+    /// opens. Newer notifications replace it on screen, so this changes
+    /// while the worker waits.
+    #[derive(Default)]
+    pub struct OnScreen {
+        pub id: Option<u32>,
+        click: Option<NotificationClick>,
+    }
+
+    /// Waiting for a click blocks, so a single worker thread does it for
+    /// whatever notification is on screen
+    pub struct ClickWorker<H> {
+        pub on_screen: Arc<Mutex<OnScreen>>,
+        handles: Option<Sender<H>>,
+    }
+
+    impl<H: ShownNotification> ClickWorker<H> {
+        /// Start the worker. Clicks go down `clicks`, followed by a call
+        /// to `wakeup`.
+        pub fn spawn(
+            clicks: Sender<NotificationClick>,
+            wakeup: impl Fn() + Send + 'static,
+        ) -> Self {
+            let on_screen: Arc<Mutex<OnScreen>> = Arc::default();
+            let (handles, waiting) = mpsc::channel::<H>();
+
+            let worker_on_screen = on_screen.clone();
+            let spawned = std::thread::Builder::new()
+                .name("notifications".to_owned())
+                .spawn(move || {
+                    for handle in waiting {
+                        let id = handle.id();
+
+                        handle.wait_for_action(|action| {
+                            let click = worker_on_screen.lock().ok().and_then(|s| s.click);
+                            if let (Some(click), "default") = (click, action) {
+                                let _ = clicks.send(click);
+                                wakeup();
+                            }
+                        });
+
+                        if let Ok(mut on_screen) = worker_on_screen.lock() {
+                            // unless a newer one is already waiting in line
+                            if on_screen.id == Some(id) {
+                                *on_screen = OnScreen::default();
+                            }
+                        }
+                    }
+                });
+
+            let handles = match spawned {
+                Ok(_) => Some(handles),
+                Err(err) => {
+                    error!("couldn't start the notification worker: {err}");
+                    None
+                }
+            };
+
+            ClickWorker { on_screen, handles }
+        }
+
+        /// Show a notification in place of the one on screen. `show` gets
+        /// the id of the one to replace, if there is one.
+        pub fn show(
+            &self,
+            click: NotificationClick,
+            show: impl FnOnce(Option<u32>) -> Result<H>,
+        ) -> Result<()> {
+            let handles = self
+                .handles
+                .as_ref()
+                .ok_or_else(|| Error::Generic("no notification worker".to_owned()))?;
+
+            let mut on_screen = self
+                .on_screen
+                .lock()
+                .map_err(|err| Error::Generic(format!("notification state: {err}")))?;
+
+            let handle = show(on_screen.id)?;
+
+            let replaced = on_screen.id == Some(handle.id());
+            on_screen.id = Some(handle.id());
+            on_screen.click = Some(click);
+
+            // the worker is already waiting on the one we replaced
+            if !replaced {
+                handles
+                    .send(handle)
+                    .map_err(|_| Error::Generic("notification worker is gone".to_owned()))?;
+            }
+
+            Ok(())
+        }
+    }
+}
+
+/// org.freedesktop.Notifications over D-Bus
+#[cfg(target_os = "linux")]
+mod dbus {
+    use super::worker::{ClickWorker, ShownNotification};
+    use super::{DesktopNotification, NotificationBackend, NotificationClick};
+    use crate::{Error, Result};
+    use notify_rust::NotificationHandle;
+    use std::sync::mpsc::Sender;
+
+    impl ShownNotification for NotificationHandle {
+        fn id(&self) -> u32 {
+            NotificationHandle::id(self)
+        }
+
+        fn wait_for_action(self, on_action: impl FnOnce(&str)) {
+            NotificationHandle::wait_for_action(self, on_action)
+        }
+    }
+
+    /// Shows one notification at a time, replacing the one on screen
+    pub struct DbusNotifications {
+        worker: ClickWorker<NotificationHandle>,
+    }
+
+    impl DbusNotifications {
+        pub fn new(ctx: egui::Context, clicks: Sender<NotificationClick>) -> Self {
+            DbusNotifications {
+                worker: ClickWorker::spawn(clicks, move || ctx.request_repaint()),
+            }
+        }
+    }
+
+    impl NotificationBackend for DbusNotifications {
+        fn show(&mut self, notification: &DesktopNotification) -> Result<()> {
+            let mut builder = notify_rust::Notification::new();
+            builder
+                .appname("notedeck")
+                .summary(&notification.summary)
+                .body(&notification.body)
+                // the default action is invoked when the notification
+                // itself is clicked
+                .action("default", "Open");
+
+            self.worker.show(notification.click, |replaces| {
+                if let Some(id) = replaces {
+                    builder.id(id);
+                }
+
+                builder
+                    .show()
+                    .map_err(|err| Error::Generic(format!("dbus notification: {err}")))
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use enostr::NoteId;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::time::{Duration, Instant};
+
+    /// Stands in for the notification daemon. Clicks every notification it
+    /// is shown.
+    struct StubDaemon {
+        shown: Rc<RefCell<Vec<DesktopNotification>>>,
+        clicks: Sender<NotificationClick>,
+    }
+
+    impl NotificationBackend for StubDaemon {
+        fn show(&mut self, notification: &DesktopNotification) -> Result<()> {
+            self.shown.borrow_mut().push(notification.clone());
+            self.clicks.send(notification.click).unwrap();
+            Ok(())
+        }
+    }
+
+    fn stub(
+        settings: NotificationSettings,
+    ) -> (DesktopNotifications, Rc<RefCell<Vec<DesktopNotification>>>) {
+        let shown = Rc::new(RefCell::new(vec![]));
+        let daemon_shown = shown.clone();
+        let notifications = DesktopNotifications::with_backend(settings, move |clicks| {
+            Box::new(StubDaemon {
+                shown: daemon_shown,
+                clicks,
+            })
+        });
+        (notifications, shown)
+    }
+
+    fn notification(kind: DesktopNotificationKind) -> DesktopNotification {
+        DesktopNotification {
+            kind,
+            summary: "jb55 replied".to_owned(),
+            body: "gm".to_owned(),
+            click: NotificationClick {
+                timeline: TimelineId::new(1),
+                action: NoteAction::OpenThread(NoteId::new([1; 32])),
+            },
+        }
+    }
+
+    #[test]
+    fn test_quiet_hours() {
+        let overnight = QuietHours { start: 22, end: 7 };
+        assert!(overnight.contains(23));
+        assert!(overnight.contains(0));
+        assert!(overnight.contains(6));
+        assert!(!overnight.contains(7));
+        assert!(!overnight.contains(12));
+
+        let afternoon = QuietHours { start: 13, end: 15 };
+        assert!(afternoon.contains(13));
+        assert!(afternoon.contains(14));
+        assert!(!afternoon.contains(15));
+        assert!(!afternoon.contains(9));
+    }
+
+    #[test]
+    fn test_kind_toggles() {
+        let (mut notifications, shown) = stub(NotificationSettings::default());
+
+        notifications.raise(&notification(DesktopNotificationKind::Reply));
+        notifications.raise(&notification(DesktopNotificationKind::Reaction));
+        assert_eq!(shown.borrow().len(), 1);
+        assert_eq!(shown.borrow()[0].kind, DesktopNotificationKind::Reply);
+
+        *notifications
+            .settings
+            .kind_enabled_mut(DesktopNotificationKind::Reaction) = true;
+        notifications.raise(&notification(DesktopNotificationKind::Reaction));
+        assert_eq!(shown.borrow().len(), 2);
+    }
+
+    #[test]
+    fn test_clicks_come_back() {
+        let (mut notifications, _shown) = stub(NotificationSettings::default());
+        let reply = notification(DesktopNotificationKind::Reply);
+
+        notifications.raise(&reply);
+        assert_eq!(notifications.poll_clicks(), vec![reply.click]);
+        assert!(notifications.poll_clicks().is_empty());
+    }
+
+    /// A notification the test clicks by sending its action. Records the
+    /// thread that waited on it.
+    struct FakeHandle {
+        id: u32,
+        actions: Receiver<&'static str>,
+        waited_on: Sender<std::thread::ThreadId>,
+    }
+
+    impl worker::ShownNotification for FakeHandle {
+        fn id(&self) -> u32 {
+            self.id
+        }
+
+        fn wait_for_action(self, on_action: impl FnOnce(&str)) {
+            let _ = self.waited_on.send(std::thread::current().id());
+            if let Ok(action) = self.actions.recv() {
+                on_action(action);
+            }
+        }
+    }
+
+    #[test]
+    fn test_click_worker() {
+        let timeout = Duration::from_secs(5);
+        let (clicks, clicked) = mpsc::channel();
+        let (waited_on, waiters) = mpsc::channel();
+        let worker = worker::ClickWorker::spawn(clicks, || {});
+
+        // the daemon keeps the id of a notification it replaces
+        let show = |id: u32, click: NotificationClick| {
+            let (actions, handle_actions) = mpsc::channel();
+            let waited_on = waited_on.clone();
+            worker
+                .show(click, move |replaces| {
+                    Ok(FakeHandle {
+                        id: replaces.unwrap_or(id),
+                        actions: handle_actions,
+                        waited_on,
+                    })
+                })
+                .unwrap();
+            actions
+        };
+
+        let first = notification(DesktopNotificationKind::Reply).click;
+        let second = NotificationClick {
+            action: NoteAction::OpenThread(NoteId::new([2; 32])),
+            ..first
+        };
+        let third = NotificationClick {
+            action: NoteAction::OpenThread(NoteId::new([3; 32])),
+            ..first
+        };
+
+        // until the worker is done with the notification on screen
+        let wait_for_worker = || {
+            let start = Instant::now();
+            while worker.on_screen.lock().unwrap().id.is_some() {
+                assert!(start.elapsed() < timeout);
+                std::thread::yield_now();
+            }
+        };
+
+        show(1, first).send("default").unwrap();
+        assert_eq!(clicked.recv_timeout(timeout), Ok(first));
+        wait_for_worker();
+
+        // a newer notification replaces the one on screen, clicking opens
+        // the newer one
+        let on_screen = show(2, second);
+        let _replaced = show(3, third);
+        on_screen.send("default").unwrap();
+        assert_eq!(clicked.recv_timeout(timeout), Ok(third));
+        wait_for_worker();
+
+        // closing isn't a click
+        show(4, first).send("__closed").unwrap();
+        assert!(clicked.recv_timeout(Duration::from_millis(100)).is_err());
+
+        // every notification was waited on by the same worker thread
+        let threads: Vec<_> = (0..3)
+            .map(|_| waiters.recv_timeout(timeout).unwrap())
+            .collect();
+        assert!(waiters.try_recv().is_err());
+        assert!(threads.iter().all(|id| *id == threads[0]));
+        assert_ne!(threads[0], std::thread::current().id());
+    }
+
+    #[test]
+    fn test_muted_columns() {
+        let mut settings = NotificationSettings::default();
+        let kind = TimelineKind::Universe;
+
+        settings.set_column_muted(&kind, true);
+        settings.set_column_muted(&kind, true);
+        assert!(settings.is_column_muted(&kind));
+        assert_eq!(settings.muted_columns.len(), 1);
+
+        settings.set_column_muted(&kind, false);
+        assert!(!settings.is_column_muted(&kind));
+    }
+
+    #[test]
+    fn test_settings_roundtrip() {
+        let settings = NotificationSettings {
+            zaps: true,
+            quiet_hours: Some(QuietHours::default()),
+            ..Default::default()
+        };
+
+        let json = serde_json::to_string(&settings).unwrap();
+        assert_eq!(
+            serde_json::from_str::<NotificationSettings>(&json).unwrap(),
+            settings
+        );
+
+        // missing fields use their defaults
+        let partial: NotificationSettings = serde_json::from_str(r#"{"zaps":true}"#).unwrap();
+        assert!(partial.zaps);
+        assert!(partial.mentions);
+    }
+}
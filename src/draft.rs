@@ -4,6 +4,16 @@ use std::collections::HashMap;
 #[derive(Default)]
 pub struct Draft {
     pub buffer: String,
+
+    /// Set when the post gets a content warning, the reason can be empty
+    pub content_warning: Option<String>,
+
+    /// The remote signer request for this post. We keep the draft until
+    /// the signed note comes back.
+    pub signing: Option<String>,
+
+    /// Why the remote signer didn't sign the last post
+    pub sign_error: Option<String>,
 }
 
 #[derive(Default)]
@@ -33,6 +43,32 @@ impl Drafts {
     pub fn quote_mut(&mut self, id: &[u8; 32]) -> &mut Draft {
         self.quotes.entry(*id).or_default()
     }
+
+    fn waiting_on(&mut self, request: &str) -> Option<&mut Draft> {
+        std::iter::once(&mut self.compose)
+            .chain(self.replies.values_mut())
+            .chain(self.quotes.values_mut())
+            .find(|draft| draft.signing.as_deref() == Some(request))
+    }
+
+    /// A post came back from the remote signer, we're done with its draft
+    pub fn signed(&mut self, request: &str) {
+        if let Some(draft) = self.waiting_on(request) {
+            draft.clear();
+        }
+    }
+
+    /// The remote signer didn't sign a post. Returns false if it wasn't
+    /// one of ours.
+    pub fn sign_failed(&mut self, request: &str, err: &str) -> bool {
+        if let Some(draft) = self.waiting_on(request) {
+            draft.signing = None;
+            draft.sign_error = Some(err.to_owned());
+            true
+        } else {
+            false
+        }
+    }
 }
 
 impl Draft {
@@ -42,5 +78,34 @@ impl Draft {
 
     pub fn clear(&mut self) {
         self.buffer = "".to_string();
+        self.content_warning = None;
+        self.signing = None;
+        self.sign_error = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_draft_kept_until_signed() {
+        let mut drafts = Drafts::default();
+        let draft = drafts.reply_mut(&[1; 32]);
+        draft.buffer = "gm".to_owned();
+        draft.signing = Some("req".to_owned());
+
+        assert!(!drafts.sign_failed("other", "nope"));
+        assert!(drafts.sign_failed("req", "nope"));
+        let draft = drafts.reply_mut(&[1; 32]);
+        assert_eq!(draft.buffer, "gm");
+        assert_eq!(draft.sign_error.as_deref(), Some("nope"));
+        assert!(draft.signing.is_none());
+
+        draft.signing = Some("req2".to_owned());
+        drafts.signed("req2");
+        let draft = drafts.reply_mut(&[1; 32]);
+        assert!(draft.buffer.is_empty());
+        assert!(draft.sign_error.is_none());
     }
 }
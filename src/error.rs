@@ -3,6 +3,7 @@ use std::{fmt, io};
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum FilterError {
     EmptyContactList,
+    EmptyList,
 }
 
 #[derive(Debug, Eq, PartialEq, Copy, Clone)]
@@ -55,6 +56,10 @@ impl Error {
     pub fn empty_contact_list() -> Self {
         Error::Filter(FilterError::EmptyContactList)
     }
+
+    pub fn empty_list() -> Self {
+        Error::Filter(FilterError::EmptyList)
+    }
 }
 
 impl fmt::Display for FilterError {
@@ -63,6 +68,9 @@ impl fmt::Display for FilterError {
             Self::EmptyContactList => {
                 write!(f, "empty contact list")
             }
+            Self::EmptyList => {
+                write!(f, "empty list")
+            }
         }
     }
 }
@@ -164,6 +164,7 @@ pub fn default_remote_limit() -> u64 {
 pub struct FilteredTags {
     pub authors: Option<FilterBuilder>,
     pub hashtags: Option<FilterBuilder>,
+    pub ids: Option<FilterBuilder>,
 }
 
 impl FilteredTags {
@@ -188,22 +189,34 @@ impl FilteredTags {
 
         filters
     }
+
+    /// The notes referenced by the list, ie. for bookmarks and pinned
+    /// notes. Any kind goes here, so we don't filter on them.
+    pub fn into_ids_filter(self, limit: u64) -> Vec<Filter> {
+        self.ids
+            .map(|ids| vec![ids.limit(limit).build()])
+            .unwrap_or_default()
+    }
 }
 
 /// Create a filter from tags. This can be used to create a filter
-/// from a contact list
+/// from a contact list, or any of the NIP-51 lists
 pub fn filter_from_tags(note: &Note) -> Result<FilteredTags> {
     let mut author_filter = Filter::new();
     let mut hashtag_filter = Filter::new();
+    let mut ids_filter = Filter::new();
     let mut author_res: Option<FilterBuilder> = None;
     let mut hashtag_res: Option<FilterBuilder> = None;
+    let mut ids_res: Option<FilterBuilder> = None;
     let mut author_count = 0i32;
     let mut hashtag_count = 0i32;
+    let mut ids_count = 0i32;
 
     let tags = note.tags();
 
     author_filter.start_authors_field()?;
     hashtag_filter.start_tags_field('t')?;
+    ids_filter.start_ids_field()?;
 
     for tag in tags {
         if tag.count() < 2 {
@@ -234,20 +247,34 @@ pub fn filter_from_tags(note: &Note) -> Result<FilteredTags> {
 
             hashtag_filter.add_str_element(hashtag)?;
             hashtag_count += 1;
+        } else if t == "e" {
+            let id = if let Some(id) = tag.get_unchecked(1).variant().id() {
+                id
+            } else {
+                continue;
+            };
+
+            ids_filter.add_id_element(id)?;
+            ids_count += 1;
         }
     }
 
     author_filter.end_field();
     hashtag_filter.end_field();
+    ids_filter.end_field();
 
-    if author_count == 0 && hashtag_count == 0 {
-        warn!("no authors or hashtags found in contact list");
-        return Err(Error::empty_contact_list());
+    if author_count == 0 && hashtag_count == 0 && ids_count == 0 {
+        warn!("no authors, hashtags or ids found in list");
+        return if note.kind() == 3 {
+            Err(Error::empty_contact_list())
+        } else {
+            Err(Error::empty_list())
+        };
     }
 
     debug!(
-        "adding {} authors and {} hashtags to contact filter",
-        author_count, hashtag_count
+        "adding {} authors, {} hashtags and {} ids to list filter",
+        author_count, hashtag_count, ids_count
     );
 
     // if we hit these ooms, we need to expand filter buffer size
@@ -259,8 +286,13 @@ pub fn filter_from_tags(note: &Note) -> Result<FilteredTags> {
         hashtag_res = Some(hashtag_filter)
     }
 
+    if ids_count > 0 {
+        ids_res = Some(ids_filter)
+    }
+
     Ok(FilteredTags {
         authors: author_res,
         hashtags: hashtag_res,
+        ids: ids_res,
     })
 }
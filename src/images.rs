@@ -1,5 +1,6 @@
 use crate::error::Error;
 use crate::imgcache::ImageCache;
+use crate::proxy::Http;
 use crate::result::Result;
 use egui::{pos2, Color32, ColorImage, Rect, Sense, SizeHint, TextureHandle};
 use image::imageops::FilterType;
@@ -220,13 +221,14 @@ pub fn fetch_img(
     if path.exists() {
         fetch_img_from_disk(ctx, url, &path)
     } else {
-        fetch_img_from_net(&img_cache.cache_dir, ctx, url, imgtyp)
+        fetch_img_from_net(&img_cache.http, &img_cache.cache_dir, ctx, url, imgtyp)
     }
 
     // TODO: fetch image from local cache
 }
 
 fn fetch_img_from_net(
+    http: &Http,
     cache_path: &path::Path,
     ctx: &egui::Context,
     url: &str,
@@ -237,7 +239,7 @@ fn fetch_img_from_net(
     let ctx = ctx.clone();
     let cloned_url = url.to_owned();
     let cache_path = cache_path.to_owned();
-    ehttp::fetch(request, move |response| {
+    http.fetch(request, move |response| {
         let handle = response
             .map_err(Error::Generic)
             .and_then(|resp| parse_img_response(resp, imgtyp))
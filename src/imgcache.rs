@@ -1,3 +1,5 @@
+use crate::media_settings::MediaBlur;
+use crate::proxy::Http;
 use crate::Result;
 use egui::TextureHandle;
 use poll_promise::Promise;
@@ -15,6 +17,12 @@ pub type ImageCacheMap = HashMap<String, ImageCacheValue>;
 pub struct ImageCache {
     pub cache_dir: path::PathBuf,
     url_imgs: ImageCacheMap,
+
+    /// How we fetch images, see [`crate::proxy::Http`]
+    pub http: Http,
+
+    /// Whose media we hold off on fetching until it's clicked
+    pub media_blur: MediaBlur,
 }
 
 impl ImageCache {
@@ -22,6 +30,8 @@ impl ImageCache {
         Self {
             cache_dir,
             url_imgs: HashMap::new(),
+            http: Http::default(),
+            media_blur: MediaBlur::default(),
         }
     }
 
@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 use std::str::FromStr;
 
-use crate::Error;
+use crate::{proxy::Http, Error};
 use enostr::{Keypair, Pubkey, SecretKey};
 use poll_promise::Promise;
 use reqwest::{Request, Response};
@@ -11,6 +11,7 @@ use serde::{Deserialize, Serialize};
 pub enum AcquireKeyError {
     InvalidKey,
     Nip05Failed(String),
+    RemoteSignerFailed(String),
 }
 
 impl std::fmt::Display for AcquireKeyError {
@@ -20,6 +21,9 @@ impl std::fmt::Display for AcquireKeyError {
             AcquireKeyError::Nip05Failed(e) => {
                 write!(f, "Failed to get pubkey from Nip05 address: {e}")
             }
+            AcquireKeyError::RemoteSignerFailed(e) => {
+                write!(f, "Failed to log in with remote signer: {e}")
+            }
         }
     }
 }
@@ -41,29 +45,33 @@ async fn parse_nip05_response(response: Response) -> Result<Nip05Result, Error>
     }
 }
 
-fn get_pubkey_from_result(result: Nip05Result, user: String) -> Result<Pubkey, Error> {
+/// The pubkey a name points to, or None when the server doesn't list it
+fn get_pubkey_from_result(result: Nip05Result, user: String) -> Result<Option<Pubkey>, Error> {
     match result.names.get(&user).to_owned() {
-        Some(pubkey_str) => Pubkey::from_hex(pubkey_str).map_err(|e| {
+        Some(pubkey_str) => Pubkey::from_hex(pubkey_str).map(Some).map_err(|e| {
             Error::Generic("Could not parse pubkey: ".to_string() + e.to_string().as_str())
         }),
-        None => Err(Error::Generic("Could not find user in json.".to_string())),
+        None => Ok(None),
     }
 }
 
-async fn get_nip05_pubkey(id: &str) -> Result<Pubkey, Error> {
-    let mut parts = id.split('@');
+/// Split a NIP-05 address into its name and domain. Addresses people put
+/// in their profiles can be anything, so check them before we build a url
+/// out of them.
+pub fn parse_nip05(address: &str) -> Result<(&str, &str), Error> {
+    let mut parts = address.trim().split('@');
 
     let user = match parts.next() {
-        Some(user) => user,
-        None => {
+        Some(user) if !user.is_empty() => user,
+        _ => {
             return Err(Error::Generic(
                 "Address does not contain username.".to_string(),
             ));
         }
     };
     let host = match parts.next() {
-        Some(host) => host,
-        None => {
+        Some(host) if !host.is_empty() => host,
+        _ => {
             return Err(Error::Generic(
                 "Nip05 address does not contain host.".to_string(),
             ));
@@ -76,11 +84,44 @@ async fn get_nip05_pubkey(id: &str) -> Result<Pubkey, Error> {
         ));
     }
 
+    let valid_user = user
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !valid_user {
+        return Err(Error::Generic(format!(
+            "Nip05 username '{user}' contains invalid characters."
+        )));
+    }
+
+    let valid_host = host
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | ':'))
+        && !host.starts_with(['.', '-', ':'])
+        && !host.ends_with(['.', '-', ':']);
+    if !valid_host {
+        return Err(Error::Generic(format!("Nip05 host '{host}' is invalid.")));
+    }
+
+    Ok((user, host))
+}
+
+async fn get_nip05_pubkey(http: &Http, id: &str) -> Result<Pubkey, Error> {
+    lookup_nip05(http, id)
+        .await?
+        .ok_or_else(|| Error::Generic("Could not find user in json.".to_string()))
+}
+
+/// The pubkey a NIP-05 address points to. None when the server answered
+/// but doesn't list the name.
+async fn lookup_nip05(http: &Http, id: &str) -> Result<Option<Pubkey>, Error> {
+    let (user, host) = parse_nip05(id)?;
+
     let url = format!("https://{host}/.well-known/nostr.json?name={user}");
-    let request = Request::new(reqwest::Method::GET, url.parse().unwrap());
+    let url = reqwest::Url::parse(&url).map_err(|e| Error::Generic(e.to_string()))?;
+    let request = Request::new(reqwest::Method::GET, url);
     let cloned_user = user.to_string();
 
-    let client = reqwest::Client::new();
+    let client = http.client()?;
     match client.execute(request).await {
         Ok(resp) => match parse_nip05_response(resp).await {
             Ok(result) => match get_pubkey_from_result(result, cloned_user) {
@@ -93,13 +134,27 @@ async fn get_nip05_pubkey(id: &str) -> Result<Pubkey, Error> {
     }
 }
 
+/// Look up the pubkey a NIP-05 address points to, ie. to check that our
+/// own address points back at us. None when the address's server doesn't
+/// list the name.
+pub fn perform_nip05_lookup(http: &Http, address: &str) -> Promise<Result<Option<Pubkey>, Error>> {
+    if let Err(err) = parse_nip05(address) {
+        return Promise::from_ready(Err(err));
+    }
+
+    let http = http.clone();
+    let address = address.to_owned();
+    Promise::spawn_async(async move { lookup_nip05(&http, &address).await })
+}
+
 fn retrieving_nip05_pubkey(key: &str) -> bool {
     key.contains('@')
 }
 
-pub fn perform_key_retrieval(key: &str) -> Promise<Result<Keypair, AcquireKeyError>> {
+pub fn perform_key_retrieval(http: &Http, key: &str) -> Promise<Result<Keypair, AcquireKeyError>> {
+    let http = http.clone();
     let key_string = String::from(key);
-    Promise::spawn_async(async move { get_key(&key_string).await })
+    Promise::spawn_async(async move { get_key(&http, &key_string).await })
 }
 
 /// Attempts to turn a string slice key from the user into a Nostr-Sdk Keypair object.
@@ -110,7 +165,7 @@ pub fn perform_key_retrieval(key: &str) -> Promise<Result<Keypair, AcquireKeyErr
 /// - Private hex key: "5dab..."
 /// - NIP-05 address: "example@nostr.com"
 ///
-pub async fn get_key(key: &str) -> Result<Keypair, AcquireKeyError> {
+pub async fn get_key(http: &Http, key: &str) -> Result<Keypair, AcquireKeyError> {
     let tmp_key: &str = if let Some(stripped) = key.strip_prefix('@') {
         stripped
     } else {
@@ -118,7 +173,7 @@ pub async fn get_key(key: &str) -> Result<Keypair, AcquireKeyError> {
     };
 
     if retrieving_nip05_pubkey(tmp_key) {
-        match get_nip05_pubkey(tmp_key).await {
+        match get_nip05_pubkey(http, tmp_key).await {
             Ok(pubkey) => Ok(Keypair::only_pubkey(pubkey)),
             Err(e) => Err(AcquireKeyError::Nip05Failed(e.to_string())),
         }
@@ -143,7 +198,7 @@ mod tests {
         let pubkey_str = "npub1xtscya34g58tk0z605fvr788k263gsu6cy9x0mhnm87echrgufzsevkk5s";
         let expected_pubkey =
             Pubkey::try_from_bech32_string(pubkey_str, false).expect("Should not have errored.");
-        let login_key_result = get_key(pubkey_str).await;
+        let login_key_result = get_key(&Http::default(), pubkey_str).await;
 
         assert_eq!(Ok(Keypair::only_pubkey(expected_pubkey)), login_key_result);
     }
@@ -153,7 +208,7 @@ mod tests {
         let pubkey_str = "npub1xtscya34g58tk0z605fvr788k263gsu6cy9x0mhnm87echrgufzsevkk5s";
         let expected_pubkey =
             Pubkey::try_from_bech32_string(pubkey_str, false).expect("Should not have errored.");
-        let login_key_result = perform_key_retrieval(pubkey_str);
+        let login_key_result = perform_key_retrieval(&Http::default(), pubkey_str);
 
         promise_assert!(
             assert_eq,
@@ -166,7 +221,7 @@ mod tests {
     async fn test_hex_pubkey() {
         let pubkey_str = "32e1827635450ebb3c5a7d12c1f8e7b2b514439ac10a67eef3d9fd9c5c68e245";
         let expected_pubkey = Pubkey::from_hex(pubkey_str).expect("Should not have errored.");
-        let login_key_result = perform_key_retrieval(pubkey_str);
+        let login_key_result = perform_key_retrieval(&Http::default(), pubkey_str);
 
         promise_assert!(
             assert_eq,
@@ -179,7 +234,7 @@ mod tests {
     async fn test_privkey() {
         let privkey_str = "nsec1g8wt3hlwjpa4827xylr3r0lccufxltyekhraexes8lqmpp2hensq5aujhs";
         let expected_privkey = SecretKey::from_str(privkey_str).expect("Should not have errored.");
-        let login_key_result = perform_key_retrieval(privkey_str);
+        let login_key_result = perform_key_retrieval(&Http::default(), privkey_str);
 
         promise_assert!(
             assert_eq,
@@ -192,7 +247,7 @@ mod tests {
     async fn test_hex_privkey() {
         let privkey_str = "41dcb8dfee907b53abc627c711bff8c7126fac99b5c7dc9b303fc1b08557cce0";
         let expected_privkey = SecretKey::from_str(privkey_str).expect("Should not have errored.");
-        let login_key_result = perform_key_retrieval(privkey_str);
+        let login_key_result = perform_key_retrieval(&Http::default(), privkey_str);
 
         promise_assert!(
             assert_eq,
@@ -209,7 +264,7 @@ mod tests {
             false,
         )
         .expect("Should not have errored.");
-        let login_key_result = perform_key_retrieval(nip05_str);
+        let login_key_result = perform_key_retrieval(&Http::default(), nip05_str);
 
         promise_assert!(
             assert_eq,
@@ -217,4 +272,33 @@ mod tests {
             &login_key_result
         );
     }
+
+    #[test]
+    fn test_parse_nip05() {
+        assert_eq!(parse_nip05(" jb55@jb55.com").unwrap(), ("jb55", "jb55.com"));
+        assert_eq!(parse_nip05("_@jb55.com").unwrap(), ("_", "jb55.com"));
+
+        let malformed = [
+            "",
+            "me@",
+            "@damus.io",
+            "damus.io",
+            "me@damus.io@damus.io",
+            "me@dam us.io",
+            "me@damus.io/path?name=x",
+            "m e@damus.io",
+            "me@.damus.io",
+            "me@damus.io.",
+        ];
+        for address in malformed {
+            assert!(parse_nip05(address).is_err(), "{address} should be invalid");
+        }
+    }
+
+    #[test]
+    fn test_malformed_nip05_lookup() {
+        // fails right away instead of panicking on the lookup thread
+        let lookup = perform_nip05_lookup(&Http::default(), "me@");
+        assert!(matches!(lookup.ready(), Some(Err(_))));
+    }
 }
@@ -10,8 +10,12 @@ pub mod app_creation;
 mod app_size_handler;
 mod app_style;
 mod args;
+mod article;
+mod bookmarks;
 mod colors;
 mod column;
+mod contacts;
+mod desktop_notifications;
 mod draft;
 mod filter;
 mod fonts;
@@ -19,17 +23,34 @@ mod frame_history;
 mod images;
 mod imgcache;
 mod key_parsing;
+mod lists;
+mod local_relay;
 pub mod login_manager;
+mod media_settings;
+mod metadata;
+mod missing_notes;
 mod multi_subscriber;
 mod nav;
+mod network_monitor;
+mod nip05;
 mod note;
 mod notecache;
 mod notes_holder;
+mod notifications;
+mod offline;
+mod outbox;
 mod post;
 mod profile;
+mod profile_stats;
+mod proxy;
+mod relay_information;
 pub mod relay_pool_manager;
+mod replaceable;
 mod result;
 mod route;
+mod search;
+mod signer;
+mod subscription_manager;
 mod subscriptions;
 mod support;
 mod test_data;
@@ -39,8 +60,10 @@ mod timecache;
 mod timeline;
 pub mod ui;
 mod unknowns;
+mod upload;
 mod user_account;
 mod view_state;
+mod wot;
 
 #[cfg(test)]
 #[macro_use]
@@ -0,0 +1,448 @@
+use crate::{
+    outbox::Outbox,
+    signer::{RemoteSigners, SignedNote, Signer},
+    subscription_manager::SubLifetime,
+    subscriptions::{self, SubKind, Subscriptions},
+    timeline::{ListKind, PubkeySource},
+    Result,
+};
+
+use enostr::{Filter, Pubkey, RelayPool};
+use nostrdb::{Ndb, NdbStrVariant, Note, NoteBuilder, Tag, Transaction};
+use std::collections::HashSet;
+use tracing::error;
+
+/// The NIP-51 list kinds we can use as column sources
+const LIST_KINDS: [u64; 4] = [30000, 10003, 10001, 10015];
+
+/// One of an account's lists, as shown in the list pickers
+pub struct AccountList {
+    pub list_kind: ListKind,
+    pub title: String,
+    pub size: usize,
+}
+
+/// Filter for all of the lists owned by a pubkey
+pub fn lists_filter(pk: &[u8; 32]) -> Filter {
+    Filter::new()
+        .authors([pk])
+        .kinds(LIST_KINDS)
+        .limit(crate::filter::default_limit())
+        .build()
+}
+
+/// Ask our relays for the lists owned by a pubkey. Results land in
+/// nostrdb where the list pickers will find them.
+pub fn fetch_lists(subs: &mut Subscriptions, pk: &[u8; 32]) {
+    let subid = subscriptions::new_sub_id();
+    subs.subs.insert(subid.clone(), SubKind::OneShot);
+    subs.manager
+        .subscribe(subid, vec![lists_filter(pk)], SubLifetime::OneShot);
+}
+
+/// Fetch our lists from a relay we just connected to. We need the latest
+/// versions of our lists before we can edit them, otherwise we'd
+/// overwrite them with whatever we had locally.
+pub fn fetch_lists_from(subs: &mut Subscriptions, relay_url: &str, pk: &[u8; 32]) {
+    let subid = subscriptions::new_sub_id();
+    subs.subs.insert(subid.clone(), SubKind::OneShot);
+    subs.manager.subscribe_to(
+        relay_url,
+        subid,
+        vec![lists_filter(pk)],
+        SubLifetime::OneShot,
+    );
+}
+
+pub fn tag_str<'a>(tag: &Tag<'a>, ind: u16) -> Option<&'a str> {
+    tag.get(ind).and_then(|s| s.variant().str())
+}
+
+/// The d tag of a parameterized replaceable note
+pub fn note_identifier<'a>(note: &Note<'a>) -> Option<&'a str> {
+    note.tags()
+        .iter()
+        .find(|tag| tag.count() >= 2 && tag_str(tag, 0) == Some("d"))
+        .and_then(|tag| tag_str(&tag, 1))
+}
+
+/// The human readable title of a list. NIP-51 sets have a title tag, older
+/// clients sometimes only used the d tag.
+pub fn note_title<'a>(note: &Note<'a>) -> Option<&'a str> {
+    note.tags()
+        .iter()
+        .find(|tag| tag.count() >= 2 && tag_str(tag, 0) == Some("title"))
+        .and_then(|tag| tag_str(&tag, 1))
+        .filter(|title| !title.is_empty())
+        .or_else(|| note_identifier(note))
+}
+
+/// Count the tags of a given type, ie. the number of "p" tags in a follow set
+pub fn count_tags(note: &Note, tag_name: &str) -> usize {
+    note.tags()
+        .iter()
+        .filter(|tag| tag.count() >= 2 && tag_str(tag, 0) == Some(tag_name))
+        .count()
+}
+
+/// Whether a list note has a "p" tag for a given pubkey
+pub fn list_has_pubkey(note: &Note, pk: &[u8; 32]) -> bool {
+    note.tags().iter().any(|tag| {
+        tag.count() >= 2
+            && tag_str(&tag, 0) == Some("p")
+            && tag.get(1).and_then(|s| s.variant().id()) == Some(pk)
+    })
+}
+
+/// The pubkeys on a list's "p" tags, in list order
+pub fn list_pubkeys(note: &Note) -> Vec<Pubkey> {
+    note.tags()
+        .iter()
+        .filter(|tag| tag.count() >= 2 && tag_str(tag, 0) == Some("p"))
+        .filter_map(|tag| tag.get(1).and_then(|s| s.variant().id()))
+        .map(|pk| Pubkey::new(*pk))
+        .collect()
+}
+
+/// Look up the title of one of a pubkey's lists
+pub fn list_title(ndb: &Ndb, pk: &Pubkey, list_kind: &ListKind) -> Option<String> {
+    let txn = Transaction::new(ndb).ok()?;
+    let results = ndb
+        .query(&txn, &[list_kind.list_filter(pk.bytes())], 1)
+        .ok()?;
+    let note = &results.first()?.note;
+    note_title(note).map(|t| t.to_owned())
+}
+
+/// The newest version of each of the lists owned by a pubkey
+pub fn account_lists(ndb: &Ndb, txn: &Transaction, pk: &Pubkey) -> Vec<AccountList> {
+    let filter = lists_filter(pk.bytes());
+    let lim = filter.limit().unwrap_or(crate::filter::default_limit()) as i32;
+    let mut results = match ndb.query(txn, &[filter], lim) {
+        Ok(results) => results,
+        Err(err) => {
+            error!("error querying account lists: {err}");
+            return vec![];
+        }
+    };
+
+    // replaceable events, newest wins
+    results.sort_by_key(|r| std::cmp::Reverse(r.note.created_at()));
+
+    let mut seen: HashSet<(u32, String)> = HashSet::new();
+    let mut lists = vec![];
+    let pk_src = PubkeySource::Explicit(*pk);
+
+    for result in results {
+        let note = &result.note;
+        let d = note_identifier(note).unwrap_or_default();
+        if !seen.insert((note.kind(), d.to_owned())) {
+            continue;
+        }
+
+        let list = match note.kind() {
+            30000 => {
+                // deleted sets are published as empty sets
+                if d.is_empty() {
+                    continue;
+                }
+
+                AccountList {
+                    list_kind: ListKind::FollowSet(pk_src.clone(), d.to_owned()),
+                    title: note_title(note).unwrap_or(d).to_owned(),
+                    size: count_tags(note, "p"),
+                }
+            }
+            10003 => AccountList {
+                list_kind: ListKind::Bookmarks(pk_src.clone()),
+                title: "Bookmarks".to_owned(),
+                size: count_tags(note, "e"),
+            },
+            10001 => AccountList {
+                list_kind: ListKind::Pinned(pk_src.clone()),
+                title: "Pinned notes".to_owned(),
+                size: count_tags(note, "e"),
+            },
+            10015 => AccountList {
+                list_kind: ListKind::Interests(pk_src.clone()),
+                title: "Interests".to_owned(),
+                size: count_tags(note, "t"),
+            },
+            _ => continue,
+        };
+
+        lists.push(list);
+    }
+
+    lists
+}
+
+/// Copy a tag from an existing note into a note we're building
+pub fn copy_tag<'a>(builder: NoteBuilder<'a>, tag: &Tag) -> NoteBuilder<'a> {
+    let mut builder = builder.start_tag();
+    for ind in 0..tag.count() {
+        builder = match tag.get_unchecked(ind).variant() {
+            NdbStrVariant::Str(s) => builder.tag_str(s),
+            NdbStrVariant::Id(id) => builder.tag_str(&hex::encode(id)),
+        };
+    }
+    builder
+}
+
+/// Edits to the account's follow sets
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ListAction {
+    /// Add a pubkey to the follow set with the given d tag
+    AddToFollowSet { identifier: String, pubkey: Pubkey },
+
+    /// Remove a pubkey from the follow set with the given d tag
+    RemoveFromFollowSet { identifier: String, pubkey: Pubkey },
+
+    /// Create a new follow set containing a pubkey
+    NewFollowSet { title: String, pubkey: Pubkey },
+}
+
+impl ListAction {
+    pub fn execute(
+        &self,
+        ndb: &Ndb,
+        txn: &Transaction,
+        pool: &mut RelayPool,
+        outbox: &mut Outbox,
+        remote_signers: &mut RemoteSigners,
+        signer: &Signer,
+    ) {
+        let note = match self {
+            ListAction::AddToFollowSet { identifier, pubkey } => {
+                let current = latest_follow_set(ndb, txn, signer.pubkey(), identifier);
+                rebuild_follow_set(current.as_ref(), identifier, signer, Some(pubkey), None)
+            }
+
+            ListAction::RemoveFromFollowSet { identifier, pubkey } => {
+                let current = latest_follow_set(ndb, txn, signer.pubkey(), identifier);
+                if current.is_none() {
+                    error!("tried to remove from follow set '{identifier}' that doesn't exist");
+                    return;
+                }
+                rebuild_follow_set(current.as_ref(), identifier, signer, None, Some(pubkey))
+            }
+
+            ListAction::NewFollowSet { title, pubkey } => signer.sign(
+                NoteBuilder::new()
+                    .kind(30000)
+                    .content("")
+                    .start_tag()
+                    .tag_str("d")
+                    .tag_str(&subscriptions::new_sub_id())
+                    .start_tag()
+                    .tag_str("title")
+                    .tag_str(title)
+                    .start_tag()
+                    .tag_str("p")
+                    .tag_str(&pubkey.hex()),
+            ),
+        };
+
+        match note {
+            Ok(note) => {
+                if let Err(err) = signer.publish(note, ndb, pool, outbox, remote_signers) {
+                    error!("error publishing follow set: {err}");
+                }
+            }
+            Err(err) => error!("error signing follow set: {err}"),
+        }
+    }
+}
+
+/// The newest copy of one of our follow sets in nostrdb
+pub fn latest_follow_set<'a>(
+    ndb: &Ndb,
+    txn: &'a Transaction,
+    pk: &Pubkey,
+    identifier: &str,
+) -> Option<Note<'a>> {
+    let list_kind = ListKind::FollowSet(PubkeySource::Explicit(*pk), identifier.to_owned());
+    let results = ndb
+        .query(txn, &[list_kind.list_filter(pk.bytes())], 10)
+        .ok()?;
+
+    results
+        .into_iter()
+        .map(|r| r.note)
+        .max_by_key(|note| note.created_at())
+}
+
+/// Rebuild a follow set with a pubkey added or removed. We keep all of the
+/// existing tags and content, since other clients may have put things in
+/// there that we don't understand.
+fn rebuild_follow_set(
+    current: Option<&Note>,
+    identifier: &str,
+    signer: &Signer,
+    add: Option<&Pubkey>,
+    remove: Option<&Pubkey>,
+) -> Result<SignedNote> {
+    let mut builder = NoteBuilder::new().kind(30000);
+    let mut has_added = false;
+
+    if let Some(current) = current {
+        builder = builder.content(current.content());
+
+        for tag in current.tags() {
+            let pk = if tag.count() >= 2 && tag_str(&tag, 0) == Some("p") {
+                tag.get(1).and_then(|s| s.variant().id())
+            } else {
+                None
+            };
+
+            if let Some(pk) = pk {
+                if remove.map_or(false, |r| r.bytes() == pk) {
+                    continue;
+                }
+
+                if add.map_or(false, |a| a.bytes() == pk) {
+                    has_added = true;
+                }
+            }
+
+            builder = copy_tag(builder, &tag);
+        }
+    } else {
+        builder = builder
+            .content("")
+            .start_tag()
+            .tag_str("d")
+            .tag_str(identifier);
+    }
+
+    if let Some(add) = add {
+        if !has_added {
+            builder = builder.start_tag().tag_str("p").tag_str(&add.hex());
+        }
+    }
+
+    signer.sign(builder)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{local_note, with_signer};
+    use enostr::FullKeypair;
+
+    fn follow_set(kp: &FullKeypair, tags: &[&[&str]]) -> Note<'static> {
+        let mut builder = NoteBuilder::new().kind(30000).content("private stuff");
+        for tag in tags {
+            builder = builder.start_tag();
+            for s in *tag {
+                builder = builder.tag_str(s);
+            }
+        }
+        builder
+            .sign(&kp.secret_key.to_secret_bytes())
+            .build()
+            .unwrap()
+    }
+
+    fn rebuilt(
+        current: Option<&Note>,
+        kp: &FullKeypair,
+        add: Option<&Pubkey>,
+        remove: Option<&Pubkey>,
+    ) -> Note<'static> {
+        with_signer(kp, |signer| {
+            local_note(rebuild_follow_set(current, "friends", signer, add, remove).unwrap())
+        })
+    }
+
+    #[test]
+    fn test_list_parsing() {
+        let kp = FullKeypair::generate();
+        let alice = FullKeypair::generate().pubkey;
+        let bob = FullKeypair::generate().pubkey;
+
+        let note = follow_set(
+            &kp,
+            &[
+                &["d", "friends"],
+                &["title", "Good friends"],
+                &["p", &alice.hex()],
+                &["t", "nostr"],
+                &["p", &bob.hex()],
+                &["p"],
+            ],
+        );
+
+        assert_eq!(note_identifier(&note), Some("friends"));
+        assert_eq!(note_title(&note), Some("Good friends"));
+        assert_eq!(count_tags(&note, "p"), 2);
+        assert_eq!(count_tags(&note, "t"), 1);
+        assert!(list_has_pubkey(&note, alice.bytes()));
+        assert!(!list_has_pubkey(&note, kp.pubkey.bytes()));
+        assert_eq!(list_pubkeys(&note), vec![alice, bob]);
+
+        // older clients only set the d tag
+        let note = follow_set(&kp, &[&["d", "friends"], &["title", ""]]);
+        assert_eq!(note_title(&note), Some("friends"));
+    }
+
+    #[test]
+    fn test_empty_list_errors() {
+        use crate::error::{Error, FilterError};
+        use crate::filter::filter_from_tags;
+
+        let kp = FullKeypair::generate();
+        let empty_set = follow_set(&kp, &[&["d", "friends"]]);
+        assert!(matches!(
+            filter_from_tags(&empty_set),
+            Err(Error::Filter(FilterError::EmptyList))
+        ));
+
+        let empty_contacts = NoteBuilder::new()
+            .kind(3)
+            .content("")
+            .sign(&kp.secret_key.to_secret_bytes())
+            .build()
+            .unwrap();
+        assert!(matches!(
+            filter_from_tags(&empty_contacts),
+            Err(Error::Filter(FilterError::EmptyContactList))
+        ));
+    }
+
+    #[test]
+    fn test_list_editing() {
+        let kp = FullKeypair::generate();
+        let alice = FullKeypair::generate().pubkey;
+        let bob = FullKeypair::generate().pubkey;
+
+        let created = rebuilt(None, &kp, Some(&alice), None);
+        assert_eq!(note_identifier(&created), Some("friends"));
+        assert_eq!(list_pubkeys(&created), vec![alice]);
+
+        let current = follow_set(
+            &kp,
+            &[
+                &["d", "friends"],
+                &["title", "Good friends"],
+                &["p", &alice.hex()],
+                &["unknown", "tag"],
+            ],
+        );
+
+        // adding someone who's already there doesn't add them twice
+        let added = rebuilt(Some(&current), &kp, Some(&alice), None);
+        assert_eq!(list_pubkeys(&added), vec![alice]);
+
+        // everything we don't understand is kept
+        let added = rebuilt(Some(&current), &kp, Some(&bob), None);
+        assert_eq!(list_pubkeys(&added), vec![alice, bob]);
+        assert_eq!(added.content(), "private stuff");
+        assert_eq!(note_title(&added), Some("Good friends"));
+        assert_eq!(count_tags(&added, "unknown"), 1);
+
+        let removed = rebuilt(Some(&added), &kp, None, Some(&alice));
+        assert_eq!(list_pubkeys(&removed), vec![bob]);
+        assert_eq!(count_tags(&removed, "unknown"), 1);
+    }
+}
@@ -0,0 +1,378 @@
+use crate::{Error, Result};
+use enostr::{Filter, Pubkey};
+use nostrdb::{Ndb, Subscription, Transaction};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::io::ErrorKind;
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+use tracing::{debug, error, info};
+use tungstenite::Message;
+use url::Url;
+
+/// How long we block on reads before checking subscriptions for new notes
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How many notes we return for a REQ without a limit
+const DEFAULT_LIMIT: i32 = 500;
+const MAX_LIMIT: i32 = 5000;
+
+/// Serves our nostrdb as a NIP-01 relay, so other tools on this machine
+/// can use notedeck as their cache relay
+pub struct LocalRelay {
+    pub url: String,
+}
+
+impl LocalRelay {
+    /// Start listening on a `ws://host:port` url. If `allowed` is set,
+    /// only notes by those pubkeys are accepted.
+    pub fn start(ndb: &Ndb, url: &str, allowed: Option<HashSet<Pubkey>>) -> Result<Self> {
+        let addr = listen_addr(url)?;
+        let listener = TcpListener::bind(addr)
+            .map_err(|e| Error::Generic(format!("couldn't listen on {addr}: {e}")))?;
+        let url = format!("ws://{}", listener.local_addr()?);
+        info!("serving nostrdb as a relay on {url}");
+
+        let ndb = ndb.clone();
+        std::thread::Builder::new()
+            .name("local relay".to_owned())
+            .spawn(move || accept_connections(listener, ndb, allowed))?;
+
+        Ok(LocalRelay { url })
+    }
+}
+
+fn listen_addr(url: &str) -> Result<SocketAddr> {
+    let parsed =
+        Url::parse(url).map_err(|e| Error::Generic(format!("invalid relay url '{url}': {e}")))?;
+
+    if parsed.scheme() != "ws" {
+        return Err(Error::Generic(format!(
+            "can only serve ws:// urls, not '{url}'"
+        )));
+    }
+
+    let host = parsed
+        .host_str()
+        .ok_or_else(|| Error::Generic(format!("relay url '{url}' has no host")))?
+        .trim_matches(['[', ']']);
+    let port = parsed.port_or_known_default().unwrap_or(80);
+
+    (host, port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| Error::Generic(format!("couldn't resolve '{host}'")))
+}
+
+fn accept_connections(listener: TcpListener, ndb: Ndb, allowed: Option<HashSet<Pubkey>>) {
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                error!("local relay accept error: {err}");
+                continue;
+            }
+        };
+
+        let session = Session::new(ndb.clone(), allowed.clone());
+        let spawned = std::thread::Builder::new()
+            .name("local relay connection".to_owned())
+            .spawn(move || {
+                let peer = stream.peer_addr().ok();
+                if let Err(err) = serve_connection(stream, session) {
+                    debug!("local relay connection {peer:?} ended: {err}");
+                }
+            });
+
+        if let Err(err) = spawned {
+            error!("couldn't spawn local relay connection thread: {err}");
+        }
+    }
+}
+
+fn serve_connection(stream: TcpStream, mut session: Session) -> std::result::Result<(), String> {
+    let tcp = stream.try_clone().map_err(|e| e.to_string())?;
+    let mut socket = tungstenite::accept(stream).map_err(|e| e.to_string())?;
+    tcp.set_read_timeout(Some(POLL_INTERVAL))
+        .map_err(|e| e.to_string())?;
+
+    loop {
+        let replies = match socket.read_message() {
+            Ok(Message::Text(text)) => session.handle(&text),
+            Ok(_) => vec![],
+            Err(tungstenite::Error::Io(err))
+                if err.kind() == ErrorKind::WouldBlock || err.kind() == ErrorKind::TimedOut =>
+            {
+                session.poll()
+            }
+            Err(tungstenite::Error::ConnectionClosed | tungstenite::Error::AlreadyClosed) => {
+                return Ok(())
+            }
+            Err(err) => return Err(err.to_string()),
+        };
+
+        for reply in replies {
+            socket
+                .write_message(Message::Text(reply))
+                .map_err(|e| e.to_string())?;
+        }
+    }
+}
+
+/// A client connected to our relay, and its open subscriptions
+struct Session {
+    ndb: Ndb,
+    allowed: Option<HashSet<Pubkey>>,
+    subscriptions: HashMap<String, Subscription>,
+}
+
+impl Session {
+    fn new(ndb: Ndb, allowed: Option<HashSet<Pubkey>>) -> Self {
+        Session {
+            ndb,
+            allowed,
+            subscriptions: HashMap::new(),
+        }
+    }
+
+    /// Handle a message from the client, returning our replies
+    fn handle(&mut self, text: &str) -> Vec<String> {
+        let msg: Vec<Value> = match serde_json::from_str(text) {
+            Ok(msg) => msg,
+            Err(err) => return vec![notice(&format!("invalid message: {err}"))],
+        };
+
+        match msg.first().and_then(|v| v.as_str()) {
+            Some("REQ") => match msg.get(1).and_then(|v| v.as_str()) {
+                Some(subid) => self.req(subid.to_owned(), &msg[2..]),
+                None => vec![notice("REQ is missing a subscription id")],
+            },
+            Some("CLOSE") => {
+                if let Some(subid) = msg.get(1).and_then(|v| v.as_str()) {
+                    self.close(subid);
+                }
+                vec![]
+            }
+            Some("EVENT") => match msg.get(1) {
+                Some(note) => vec![self.event(text, note)],
+                None => vec![notice("EVENT is missing a note")],
+            },
+            _ => vec![notice("unsupported message")],
+        }
+    }
+
+    fn req(&mut self, subid: String, filters: &[Value]) -> Vec<String> {
+        self.close(&subid);
+
+        let filters: Vec<Filter> = match filters
+            .iter()
+            .map(|filter| Filter::from_json(&filter.to_string()))
+            .collect()
+        {
+            Ok(filters) => filters,
+            Err(err) => return vec![closed(&subid, &format!("invalid: {err}"))],
+        };
+
+        if filters.is_empty() {
+            return vec![closed(&subid, "invalid: no filters")];
+        }
+
+        // subscribe before querying so we don't miss notes that arrive
+        // in between
+        let sub = match self.ndb.subscribe(&filters) {
+            Ok(sub) => sub,
+            Err(err) => return vec![closed(&subid, &format!("error: {err}"))],
+        };
+
+        let mut replies = self.query(&subid, &filters);
+        replies.push(format!("[\"EOSE\",{}]", json_string(&subid)));
+
+        self.subscriptions.insert(subid, sub);
+        replies
+    }
+
+    fn query(&self, subid: &str, filters: &[Filter]) -> Vec<String> {
+        let limit = filters
+            .iter()
+            .map(|filter| {
+                filter
+                    .limit()
+                    .map_or(DEFAULT_LIMIT, |l| l.min(MAX_LIMIT as u64) as i32)
+            })
+            .fold(0i32, |sum, limit| sum.saturating_add(limit))
+            .min(MAX_LIMIT);
+
+        let txn = match Transaction::new(&self.ndb) {
+            Ok(txn) => txn,
+            Err(err) => return vec![notice(&format!("error: {err}"))],
+        };
+
+        match self.ndb.query(&txn, filters, limit) {
+            Ok(results) => results
+                .iter()
+                .filter_map(|result| result.note.json().ok())
+                .map(|json| event(subid, &json))
+                .collect(),
+            Err(err) => vec![notice(&format!("error: {err}"))],
+        }
+    }
+
+    fn close(&mut self, subid: &str) {
+        if let Some(sub) = self.subscriptions.remove(subid) {
+            if let Err(err) = self.ndb.unsubscribe(sub) {
+                error!("local relay unsubscribe error: {err}");
+            }
+        }
+    }
+
+    fn event(&self, text: &str, note: &Value) -> String {
+        let id = note.get("id").and_then(|v| v.as_str()).unwrap_or_default();
+
+        if let Some(allowed) = &self.allowed {
+            let author = note
+                .get("pubkey")
+                .and_then(|v| v.as_str())
+                .and_then(|pk| Pubkey::from_hex(pk).ok());
+
+            if !author.map_or(false, |pk| allowed.contains(&pk)) {
+                return ok(
+                    id,
+                    false,
+                    "blocked: this relay only accepts notes from its owners",
+                );
+            }
+        }
+
+        // nostrdb checks the signature when it ingests the note, so this
+        // only tells the client it was queued
+        match self.ndb.process_client_event(text) {
+            Ok(()) => ok(id, true, ""),
+            Err(err) => ok(id, false, &format!("invalid: {err}")),
+        }
+    }
+
+    /// New notes for live subscriptions
+    fn poll(&mut self) -> Vec<String> {
+        let mut replies = vec![];
+
+        for (subid, sub) in &self.subscriptions {
+            let keys = self.ndb.poll_for_notes(*sub, 100);
+            if keys.is_empty() {
+                continue;
+            }
+
+            let txn = match Transaction::new(&self.ndb) {
+                Ok(txn) => txn,
+                Err(err) => {
+                    error!("local relay transaction error: {err}");
+                    return replies;
+                }
+            };
+
+            for key in keys {
+                let json = self
+                    .ndb
+                    .get_note_by_key(&txn, key)
+                    .ok()
+                    .and_then(|note| note.json().ok());
+
+                if let Some(json) = json {
+                    replies.push(event(subid, &json));
+                }
+            }
+        }
+
+        replies
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        let subids: Vec<String> = self.subscriptions.keys().cloned().collect();
+        for subid in subids {
+            self.close(&subid);
+        }
+    }
+}
+
+fn json_string(s: &str) -> String {
+    Value::String(s.to_owned()).to_string()
+}
+
+fn event(subid: &str, note_json: &str) -> String {
+    format!("[\"EVENT\",{},{}]", json_string(subid), note_json)
+}
+
+fn ok(id: &str, accepted: bool, message: &str) -> String {
+    format!(
+        "[\"OK\",{},{},{}]",
+        json_string(id),
+        accepted,
+        json_string(message)
+    )
+}
+
+fn closed(subid: &str, message: &str) -> String {
+    format!(
+        "[\"CLOSED\",{},{}]",
+        json_string(subid),
+        json_string(message)
+    )
+}
+
+fn notice(message: &str) -> String {
+    format!("[\"NOTICE\",{}]", json_string(message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nostrdb::Config;
+
+    fn session(allowed: Option<HashSet<Pubkey>>) -> (Session, tempfile::TempDir) {
+        let dir = tempfile::TempDir::new().expect("tmp dir");
+        let ndb = Ndb::new(dir.path().to_str().unwrap(), &Config::new()).expect("ndb");
+        (Session::new(ndb, allowed), dir)
+    }
+
+    #[test]
+    fn test_listen_addr() {
+        assert_eq!(
+            listen_addr("ws://127.0.0.1:8080").unwrap(),
+            "127.0.0.1:8080".parse().unwrap()
+        );
+        assert!(listen_addr("wss://127.0.0.1:8080").is_err());
+    }
+
+    #[test]
+    fn test_req_on_empty_db() {
+        let (mut session, _dir) = session(None);
+        let replies = session.handle(r#"["REQ","sub",{"kinds":[1],"limit":10}]"#);
+        assert_eq!(replies, vec![r#"["EOSE","sub"]"#.to_owned()]);
+        assert!(session.subscriptions.contains_key("sub"));
+
+        session.handle(r#"["CLOSE","sub"]"#);
+        assert!(session.subscriptions.is_empty());
+    }
+
+    #[test]
+    fn test_rejects_notes_from_strangers() {
+        let owner = Pubkey::new([1; 32]);
+        let (mut session, _dir) = session(Some(HashSet::from([owner])));
+
+        let stranger = Pubkey::new([2; 32]).hex();
+        let msg = format!(r#"["EVENT",{{"id":"abc","pubkey":"{stranger}"}}]"#);
+        let replies = session.handle(&msg);
+
+        assert_eq!(replies.len(), 1);
+        assert!(replies[0].starts_with(r#"["OK","abc",false,"blocked:"#));
+    }
+
+    #[test]
+    fn test_bad_messages_get_notices() {
+        let (mut session, _dir) = session(None);
+        assert!(session.handle("not json")[0].starts_with("[\"NOTICE\""));
+        assert!(session.handle(r#"["AUTH","x"]"#)[0].starts_with("[\"NOTICE\""));
+        assert!(session.handle(r#"["REQ","sub"]"#)[0].starts_with("[\"CLOSED\""));
+    }
+}
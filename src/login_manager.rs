@@ -1,9 +1,16 @@
 use crate::key_parsing::perform_key_retrieval;
 use crate::key_parsing::AcquireKeyError;
+use crate::proxy::Http;
 use egui::{TextBuffer, TextEdit};
-use enostr::Keypair;
+use enostr::{BunkerUri, Keypair};
 use poll_promise::Promise;
 
+/// A login that needs a remote signer, which the app has to start for us
+pub enum RemoteLogin {
+    Bunker(BunkerUri),
+    NostrConnect,
+}
+
 /// The state data for acquiring a nostr key
 #[derive(Default)]
 pub struct AcquireKeyState {
@@ -12,6 +19,14 @@ pub struct AcquireKeyState {
     error: Option<AcquireKeyError>,
     key_on_error: Option<String>,
     should_create_new: bool,
+
+    /// A remote signer login waiting to be started
+    remote_login: Option<RemoteLogin>,
+    awaiting_remote_signer: bool,
+    remote_account: Option<Keypair>,
+
+    /// The uri to give a signer app, once we're listening for it
+    nostrconnect_uri: Option<String>,
 }
 
 impl<'a> AcquireKeyState {
@@ -28,16 +43,30 @@ impl<'a> AcquireKeyState {
     }
 
     /// User pressed the 'acquire' button
-    pub fn apply_acquire(&'a mut self) {
+    pub fn apply_acquire(&'a mut self, http: &Http) {
+        if self.desired_key.trim().starts_with("bunker://") {
+            match BunkerUri::parse(&self.desired_key) {
+                Ok(uri) => {
+                    self.remote_login = Some(RemoteLogin::Bunker(uri));
+                    self.awaiting_remote_signer = true;
+                }
+                Err(_) => {
+                    self.error = Some(AcquireKeyError::InvalidKey);
+                    self.key_on_error = Some(self.desired_key.clone());
+                }
+            }
+            return;
+        }
+
         let new_promise = match &self.promise_query {
             Some((query, _)) => {
                 if query != &self.desired_key {
-                    Some(perform_key_retrieval(&self.desired_key))
+                    Some(perform_key_retrieval(http, &self.desired_key))
                 } else {
                     None
                 }
             }
-            None => Some(perform_key_retrieval(&self.desired_key)),
+            None => Some(perform_key_retrieval(http, &self.desired_key)),
         };
 
         if let Some(new_promise) = new_promise {
@@ -47,7 +76,40 @@ impl<'a> AcquireKeyState {
 
     /// Whether to indicate to the user that there is a network operation occuring
     pub fn is_awaiting_network(&self) -> bool {
-        self.promise_query.is_some()
+        self.promise_query.is_some() || self.awaiting_remote_signer
+    }
+
+    /// User wants to log in by connecting from their signer app
+    pub fn start_nostrconnect(&mut self) {
+        self.remote_login = Some(RemoteLogin::NostrConnect);
+        self.awaiting_remote_signer = true;
+    }
+
+    /// The remote signer login the app should start, if any
+    pub fn take_remote_login(&mut self) -> Option<RemoteLogin> {
+        self.remote_login.take()
+    }
+
+    pub fn set_nostrconnect_uri(&mut self, uri: String) {
+        self.nostrconnect_uri = Some(uri);
+    }
+
+    pub fn nostrconnect_uri(&self) -> Option<&str> {
+        self.nostrconnect_uri.as_deref()
+    }
+
+    /// The app finished a remote signer login for us
+    pub fn remote_login_finished(&mut self, result: Result<Keypair, AcquireKeyError>) {
+        self.awaiting_remote_signer = false;
+        self.nostrconnect_uri = None;
+
+        match result {
+            Ok(account) => self.remote_account = Some(account),
+            Err(err) => {
+                self.error = Some(err);
+                self.key_on_error = Some(self.desired_key.clone());
+            }
+        }
     }
 
     /// Whether to indicate to the user that a login error occured
@@ -64,6 +126,10 @@ impl<'a> AcquireKeyState {
 
     /// Whether to indicate to the user that a successful login occured
     pub fn check_for_successful_login(&mut self) -> Option<Keypair> {
+        if let Some(account) = self.remote_account.take() {
+            return Some(account);
+        }
+
         if let Some((_, promise)) = &mut self.promise_query {
             if promise.ready().is_some() {
                 if let Some((_, promise)) = self.promise_query.take() {
@@ -115,14 +181,14 @@ mod tests {
                     text.insert_text("test", 0);
                     egui::TextEdit::singleline(text)
                 });
-                manager.apply_acquire();
+                manager.apply_acquire(&Http::default());
             } else if cur_time < Duration::from_millis(30u64) {
                 let _ = manager.get_acquire_textedit(|text| {
                     text.clear();
                     text.insert_text("test2", 0);
                     egui::TextEdit::singleline(text)
                 });
-                manager.apply_acquire();
+                manager.apply_acquire(&Http::default());
             } else {
                 let _ = manager.get_acquire_textedit(|text| {
                     text.clear();
@@ -132,7 +198,7 @@ mod tests {
                     );
                     egui::TextEdit::singleline(text)
                 });
-                manager.apply_acquire();
+                manager.apply_acquire(&Http::default());
             }
 
             if let Some(key) = manager.check_for_successful_login() {
@@ -0,0 +1,247 @@
+use crate::contacts::{contact_list_filter, latest_contact_list};
+use crate::lists::list_pubkeys;
+use crate::storage::{write_file, DataPath, DataPathType, Directory};
+use enostr::Pubkey;
+use nostrdb::{Ndb, Subscription, Transaction};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+use tracing::error;
+
+const FILE_NAME: &str = "media.json";
+
+/// Which media in notes we show right away. Blurred media isn't fetched
+/// until it's clicked.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub enum MediaVisibility {
+    BlurAll,
+    BlurNonFollows,
+    #[default]
+    ShowAll,
+}
+
+impl MediaVisibility {
+    pub const ALL: [MediaVisibility; 3] = [
+        MediaVisibility::BlurAll,
+        MediaVisibility::BlurNonFollows,
+        MediaVisibility::ShowAll,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MediaVisibility::BlurAll => "Blur all media until I click it",
+            MediaVisibility::BlurNonFollows => "Blur media from people I don't follow",
+            MediaVisibility::ShowAll => "Show all media",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MediaSettings {
+    /// Keyed by the account's hex pubkey
+    pub accounts: HashMap<String, MediaVisibility>,
+}
+
+impl MediaSettings {
+    pub fn visibility(&self, account: &Pubkey) -> MediaVisibility {
+        self.accounts
+            .get(&account.hex())
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+/// What the selected account wants blurred. Every note with media checks
+/// this, so the follow set is only rebuilt when the contact list changes.
+#[derive(Debug, Clone, Default)]
+pub struct MediaBlur {
+    account: Option<Pubkey>,
+    visibility: MediaVisibility,
+    follows: Rc<HashSet<Pubkey>>,
+}
+
+impl MediaBlur {
+    /// Whether media in a note by `author` should be blurred
+    pub fn should_blur(&self, author: &[u8; 32]) -> bool {
+        match (self.visibility, &self.account) {
+            (MediaVisibility::ShowAll, _) | (_, None) => false,
+            (MediaVisibility::BlurAll, _) => true,
+            (MediaVisibility::BlurNonFollows, Some(account)) => {
+                account.bytes() != author && !self.follows.contains(&Pubkey::new(*author))
+            }
+        }
+    }
+}
+
+/// Each account's media settings, and where they're saved
+pub struct MediaManager {
+    pub settings: MediaSettings,
+    directory: Option<Directory>,
+
+    /// What we last told notes to blur
+    blur: MediaBlur,
+
+    /// Watches for new copies of the selected account's contact list
+    contact_list_sub: Option<Subscription>,
+}
+
+impl MediaManager {
+    pub fn new(path: &DataPath) -> Self {
+        let directory = Directory::new(path.path(DataPathType::Setting));
+        let settings = load_settings(&directory);
+
+        MediaManager {
+            settings,
+            directory: Some(directory),
+            blur: MediaBlur::default(),
+            contact_list_sub: None,
+        }
+    }
+
+    pub fn disabled() -> Self {
+        MediaManager {
+            settings: MediaSettings::default(),
+            directory: None,
+            blur: MediaBlur::default(),
+            contact_list_sub: None,
+        }
+    }
+
+    pub fn set_visibility(&mut self, account: &Pubkey, visibility: MediaVisibility) {
+        self.settings.accounts.insert(account.hex(), visibility);
+        self.save_settings();
+    }
+
+    /// Keep what notes blur up to date with the selected account, its
+    /// settings and its contact list. Returns what to blur when it changed.
+    pub fn poll(&mut self, ndb: &Ndb, account: Option<&Pubkey>) -> Option<MediaBlur> {
+        let switched = self.blur.account.as_ref() != account;
+        if switched {
+            self.watch_contact_list(ndb, account);
+        }
+
+        let list_changed = self
+            .contact_list_sub
+            .map_or(false, |sub| !ndb.poll_for_notes(sub, 10).is_empty());
+
+        let visibility = account.map_or(MediaVisibility::default(), |pk| {
+            self.settings.visibility(pk)
+        });
+        if !switched && !list_changed && visibility == self.blur.visibility {
+            return None;
+        }
+
+        let follows = if switched || list_changed {
+            Rc::new(account.map_or_else(HashSet::new, |pk| follows(ndb, pk)))
+        } else {
+            self.blur.follows.clone()
+        };
+
+        self.blur = MediaBlur {
+            account: account.copied(),
+            visibility,
+            follows,
+        };
+        Some(self.blur.clone())
+    }
+
+    fn watch_contact_list(&mut self, ndb: &Ndb, account: Option<&Pubkey>) {
+        if let Some(sub) = self.contact_list_sub.take() {
+            if let Err(err) = ndb.unsubscribe(sub) {
+                error!("error unsubscribing from contact list: {err}");
+            }
+        }
+
+        if let Some(account) = account {
+            match ndb.subscribe(&[contact_list_filter(account.bytes())]) {
+                Ok(sub) => self.contact_list_sub = Some(sub),
+                Err(err) => error!("error watching contact list: {err}"),
+            }
+        }
+    }
+
+    fn save_settings(&self) {
+        let directory = if let Some(directory) = &self.directory {
+            directory
+        } else {
+            return;
+        };
+
+        match serde_json::to_string(&self.settings) {
+            Ok(json) => {
+                if let Err(err) = write_file(&directory.file_path, FILE_NAME.to_owned(), &json) {
+                    error!("error saving media settings: {err}");
+                }
+            }
+            Err(err) => error!("error serializing media settings: {err}"),
+        }
+    }
+}
+
+fn load_settings(directory: &Directory) -> MediaSettings {
+    match directory.get_file(FILE_NAME.to_owned()) {
+        Ok(json) => serde_json::from_str(&json).unwrap_or_else(|err| {
+            error!("invalid media settings, using defaults: {err}");
+            MediaSettings::default()
+        }),
+        Err(_) => MediaSettings::default(),
+    }
+}
+
+fn follows(ndb: &Ndb, account: &Pubkey) -> HashSet<Pubkey> {
+    let txn = if let Ok(txn) = Transaction::new(ndb) {
+        txn
+    } else {
+        return HashSet::new();
+    };
+
+    latest_contact_list(ndb, &txn, account)
+        .map(|list| list_pubkeys(&list).into_iter().collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_visibility_per_account() {
+        let alice = Pubkey::new([1; 32]);
+        let bob = Pubkey::new([2; 32]);
+        let mut settings = MediaSettings::default();
+        settings
+            .accounts
+            .insert(alice.hex(), MediaVisibility::BlurNonFollows);
+
+        assert_eq!(settings.visibility(&alice), MediaVisibility::BlurNonFollows);
+        assert_eq!(settings.visibility(&bob), MediaVisibility::ShowAll);
+
+        let json = serde_json::to_string(&settings).unwrap();
+        assert_eq!(
+            serde_json::from_str::<MediaSettings>(&json).unwrap(),
+            settings
+        );
+    }
+
+    #[test]
+    fn test_should_blur() {
+        let account = Pubkey::new([1; 32]);
+        let friend = Pubkey::new([2; 32]);
+        let stranger = Pubkey::new([3; 32]);
+        let blur = |visibility| MediaBlur {
+            account: Some(account),
+            visibility,
+            follows: Rc::new([friend].into_iter().collect()),
+        };
+
+        let non_follows = blur(MediaVisibility::BlurNonFollows);
+        assert!(!non_follows.should_blur(account.bytes()));
+        assert!(!non_follows.should_blur(friend.bytes()));
+        assert!(non_follows.should_blur(stranger.bytes()));
+
+        assert!(blur(MediaVisibility::BlurAll).should_blur(friend.bytes()));
+        assert!(!blur(MediaVisibility::ShowAll).should_blur(stranger.bytes()));
+        assert!(!MediaBlur::default().should_blur(stranger.bytes()));
+    }
+}
@@ -0,0 +1,96 @@
+//! Editing our own profile metadata (kind 0)
+
+use crate::{
+    lists::copy_tag,
+    replaceable,
+    signer::{SignedNote, Signer},
+    Result,
+};
+
+use enostr::Pubkey;
+use nostrdb::{Ndb, Note, NoteBuilder, Transaction};
+use serde_json::{Map, Value};
+
+/// The newest copy of a pubkey's metadata in nostrdb
+pub fn latest_metadata<'a>(ndb: &Ndb, txn: &'a Transaction, pk: &Pubkey) -> Option<Note<'a>> {
+    replaceable::latest_note(ndb, txn, pk, 0)
+}
+
+/// The metadata content as a json object. Broken content reads as empty.
+pub fn parse_metadata(content: &str) -> Map<String, Value> {
+    match serde_json::from_str(content) {
+        Ok(Value::Object(fields)) => fields,
+        _ => Map::new(),
+    }
+}
+
+/// Apply our edits to the metadata content. Fields we don't know about are
+/// kept as they are, and fields set to nothing are removed.
+pub fn merge_metadata(content: &str, edits: &[(&str, &str)]) -> String {
+    let mut fields = parse_metadata(content);
+
+    for (key, value) in edits {
+        let value = value.trim();
+        if value.is_empty() {
+            fields.remove(*key);
+        } else {
+            fields.insert((*key).to_owned(), Value::String(value.to_owned()));
+        }
+    }
+
+    Value::Object(fields).to_string()
+}
+
+/// A new metadata note with our edits applied on top of the current one,
+/// keeping its tags
+pub fn metadata_note(
+    current: Option<&Note>,
+    edits: &[(&str, &str)],
+    signer: &Signer,
+) -> Result<SignedNote> {
+    let content = merge_metadata(current.map_or("", |note| note.content()), edits);
+    let mut builder = NoteBuilder::new().kind(0).content(&content);
+
+    if let Some(current) = current {
+        for tag in current.tags() {
+            builder = copy_tag(builder, &tag);
+        }
+    }
+
+    signer.sign(builder)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_keeps_unknown_fields() {
+        let content =
+            r#"{"name":"jb55","website":"https://jb55.com","pronouns":"he/him","bot":false}"#;
+        let merged = merge_metadata(
+            content,
+            &[
+                ("name", "will"),
+                ("website", " "),
+                ("lud16", "jb55@sendsats.lol"),
+            ],
+        );
+
+        let fields = parse_metadata(&merged);
+        assert_eq!(fields["name"], "will");
+        assert_eq!(fields["lud16"], "jb55@sendsats.lol");
+        assert_eq!(fields["pronouns"], "he/him");
+        assert_eq!(fields["bot"], false);
+        assert!(!fields.contains_key("website"));
+    }
+
+    #[test]
+    fn test_merge_broken_content() {
+        assert_eq!(
+            merge_metadata("not json", &[("name", "jb55")]),
+            r#"{"name":"jb55"}"#
+        );
+        assert_eq!(merge_metadata("", &[]), "{}");
+    }
+}
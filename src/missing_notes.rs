@@ -0,0 +1,396 @@
+//! Fetching the notes a thread needs that nostrdb doesn't have: the note
+//! someone opened, and the notes above it in the thread.
+//!
+//! We ask all of our relays for them by id. Replies usually say which
+//! relay their parent is on, so we ask those relays too. They get a pool
+//! of their own so they don't get the rest of our subscriptions.
+
+use crate::{
+    subscription_manager::SubLifetime,
+    subscriptions::{self, SubKind, Subscriptions},
+};
+
+use enostr::{ClientMessage, Filter, NoteId, RelayEvent, RelayMessage, RelayPool, RelayStatus};
+use nostrdb::{Ndb, Transaction};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tracing::{debug, error, warn};
+
+/// How long we look for a note before we say it can't be found
+const FETCH_TIMEOUT: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum FetchState {
+    Fetching,
+
+    /// None of the relays we asked had it
+    NotFound,
+}
+
+struct Fetch {
+    state: FetchState,
+
+    /// Relays the note is supposed to be on
+    hints: Vec<String>,
+
+    /// When we asked for it, None until we do
+    started: Option<Instant>,
+
+    /// Whether all of our relays that are up answered
+    relays_done: bool,
+
+    /// Hinted relays we're waiting on, and our subscription id on them
+    /// once we've asked
+    waiting_hints: HashMap<String, Option<String>>,
+}
+
+impl Fetch {
+    fn new() -> Self {
+        Fetch {
+            state: FetchState::Fetching,
+            hints: vec![],
+            started: None,
+            relays_done: false,
+            waiting_hints: HashMap::new(),
+        }
+    }
+
+    fn give_up(&mut self) {
+        self.state = FetchState::NotFound;
+        self.waiting_hints.clear();
+    }
+}
+
+#[derive(Default)]
+pub struct MissingNotes {
+    fetches: HashMap<NoteId, Fetch>,
+
+    /// One-shot subscriptions on our relays and the note they fetch
+    subids: HashMap<String, NoteId>,
+
+    /// Connections to hinted relays that aren't ours
+    hint_pool: Option<RelayPool>,
+}
+
+impl MissingNotes {
+    /// Look for a note we don't have. `hints` are relays it might be on.
+    pub fn want(&mut self, id: &NoteId, hints: &[&str]) {
+        let fetch = self.fetches.entry(*id).or_insert_with(Fetch::new);
+
+        for hint in hints {
+            let hint = hint.trim();
+            if !hint.starts_with("wss://") && !hint.starts_with("ws://") {
+                continue;
+            }
+
+            // so they match the urls in our pools
+            let hint = RelayPool::canonicalize_url(hint.to_owned());
+            if !fetch.hints.contains(&hint) {
+                fetch.hints.push(hint);
+            }
+        }
+    }
+
+    /// How our search for a note is going, if we're looking for it
+    pub fn state(&self, id: &NoteId) -> Option<FetchState> {
+        self.fetches.get(id).map(|fetch| fetch.state)
+    }
+
+    /// Ask everyone again for a note we couldn't find
+    pub fn retry(&mut self, id: &NoteId) {
+        if let Some(fetch) = self.fetches.get_mut(id) {
+            let hints = std::mem::take(&mut fetch.hints);
+            *fetch = Fetch::new();
+            fetch.hints = hints;
+        }
+    }
+
+    /// All of our relays that are up answered one of our requests. The
+    /// ones that are down don't count, or we'd only ever find out a note
+    /// is missing when the fetch times out.
+    pub fn fetched(&mut self, subid: &str) {
+        let id = if let Some(id) = self.subids.remove(subid) {
+            id
+        } else {
+            return;
+        };
+
+        if let Some(fetch) = self.fetches.get_mut(&id) {
+            fetch.relays_done = true;
+        }
+    }
+
+    pub fn poll(
+        &mut self,
+        ndb: &Ndb,
+        subs: &mut Subscriptions,
+        pool: &RelayPool,
+        wakeup: impl Fn() + Send + Sync + Clone + 'static,
+    ) {
+        if self.fetches.is_empty() {
+            self.hint_pool = None;
+            return;
+        }
+
+        // we're done with notes that showed up
+        if let Ok(txn) = Transaction::new(ndb) {
+            self.fetches
+                .retain(|id, _| ndb.get_note_by_id(&txn, id.bytes()).is_err());
+            let fetches = &self.fetches;
+            self.subids.retain(|_, id| fetches.contains_key(id));
+        }
+
+        let now = Instant::now();
+        for (id, fetch) in &mut self.fetches {
+            if fetch.state != FetchState::Fetching || fetch.started.is_some() {
+                continue;
+            }
+
+            debug!("fetching missing note {}", id.hex());
+            let subid = subscriptions::new_sub_id();
+            subs.subs.insert(subid.clone(), SubKind::FetchingNote);
+            subs.manager.subscribe(
+                subid.clone(),
+                vec![Filter::new().ids([id.bytes()]).limit(1).build()],
+                SubLifetime::OneShot,
+            );
+            self.subids.insert(subid, *id);
+
+            // our own relays already got asked
+            for hint in &fetch.hints {
+                if pool.has(hint) {
+                    continue;
+                }
+
+                if let Err(err) = pool.check_url(hint) {
+                    debug!("not asking {hint} for {}: {err}", id.hex());
+                    continue;
+                }
+
+                fetch.waiting_hints.insert(hint.clone(), None);
+            }
+
+            fetch.started = Some(now);
+        }
+
+        self.poll_hints(ndb, pool, wakeup);
+
+        for fetch in self.fetches.values_mut() {
+            if fetch.state != FetchState::Fetching {
+                continue;
+            }
+
+            let timed_out = fetch
+                .started
+                .map_or(false, |started| now.duration_since(started) > FETCH_TIMEOUT);
+
+            if timed_out || (fetch.relays_done && fetch.waiting_hints.is_empty()) {
+                fetch.give_up();
+            }
+        }
+    }
+
+    /// Connect to the hinted relays we're waiting on, ask them for their
+    /// notes and hang up once they've answered
+    fn poll_hints(
+        &mut self,
+        ndb: &Ndb,
+        main: &RelayPool,
+        wakeup: impl Fn() + Send + Sync + Clone + 'static,
+    ) {
+        let mut wanted: Vec<&String> = self
+            .fetches
+            .values()
+            .flat_map(|fetch| fetch.waiting_hints.keys())
+            .collect();
+        wanted.sort();
+        wanted.dedup();
+
+        if wanted.is_empty() {
+            self.hint_pool = None;
+            return;
+        }
+
+        let pool = self.hint_pool.get_or_insert_with(RelayPool::new);
+        pool.set_proxy(main.proxy_setting(), main.blocks_clearnet(), wakeup.clone());
+        pool.set_offline(main.is_offline(), wakeup.clone());
+
+        pool.relays
+            .retain(|pool_relay| wanted.contains(&&pool_relay.relay.url));
+        for url in wanted {
+            if let Err(err) = pool.add_url(url.clone(), wakeup.clone()) {
+                error!("can't reach hinted relay {url}: {err}");
+            }
+        }
+
+        while let Some(ev) = pool.try_recv() {
+            let ev = ev.into_owned();
+            match (&ev.event).into() {
+                RelayEvent::Message(RelayMessage::Event(_, msg)) => {
+                    if let Err(err) = ndb.process_event(msg) {
+                        error!("error processing event from {}: {err}", ev.relay);
+                    }
+                }
+
+                RelayEvent::Message(RelayMessage::Eose(sid)) => {
+                    pool.send_to(&ClientMessage::close(sid.to_owned()), &ev.relay);
+                    for fetch in self.fetches.values_mut() {
+                        let answered = fetch
+                            .waiting_hints
+                            .get(&ev.relay)
+                            .map_or(false, |asked| asked.as_deref() == Some(sid));
+                        if answered {
+                            fetch.waiting_hints.remove(&ev.relay);
+                        }
+                    }
+                }
+
+                RelayEvent::Error(err) => {
+                    // relays that don't work have nothing to tell us
+                    warn!("hinted relay {}: {err}", ev.relay);
+                    for fetch in self.fetches.values_mut() {
+                        fetch.waiting_hints.remove(&ev.relay);
+                    }
+                }
+
+                _ => {}
+            }
+        }
+
+        // ask the relays that are up for the notes we want from them
+        for pool_relay in &mut pool.relays {
+            let relay = &mut pool_relay.relay;
+            if !matches!(relay.status, RelayStatus::Connected) {
+                continue;
+            }
+
+            for (id, fetch) in &mut self.fetches {
+                let asked = if let Some(asked) = fetch.waiting_hints.get_mut(&relay.url) {
+                    asked
+                } else {
+                    continue;
+                };
+
+                if asked.is_none() {
+                    let subid = subscriptions::new_sub_id();
+                    let filter = Filter::new().ids([id.bytes()]).limit(1).build();
+                    relay.send(&ClientMessage::req(subid.clone(), vec![filter]));
+                    *asked = Some(subid);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::subscription_manager::{RelayTarget, SubscriptionTransport};
+
+    /// One relay that's up and one that's down. Remembers the REQ ids we
+    /// send.
+    struct OneRelayDown(Vec<String>);
+
+    impl SubscriptionTransport for OneRelayDown {
+        fn relays(&self) -> Vec<RelayTarget> {
+            vec![
+                RelayTarget {
+                    url: "wss://a".to_owned(),
+                    connected: true,
+                    max_filters: None,
+                    max_subscriptions: None,
+                },
+                RelayTarget {
+                    url: "ws://localhost:8080".to_owned(),
+                    connected: false,
+                    max_filters: None,
+                    max_subscriptions: None,
+                },
+            ]
+        }
+
+        fn send_to(&mut self, relay_url: &str, msg: &ClientMessage) {
+            assert_eq!(relay_url, "wss://a");
+            if let ClientMessage::Req { sub_id, .. } = msg {
+                self.0.push(sub_id.clone());
+            }
+        }
+    }
+
+    #[test]
+    fn test_hints_are_relays() {
+        let mut missing = MissingNotes::default();
+        let id = NoteId::new([3; 32]);
+
+        missing.want(&id, &["wss://relay.damus.io", "", "https://example.com"]);
+        missing.want(&id, &[" wss://relay.damus.io", "ws://localhost:8080"]);
+
+        assert_eq!(
+            missing.fetches[&id].hints,
+            vec!["wss://relay.damus.io/", "ws://localhost:8080/"]
+        );
+        assert_eq!(missing.state(&id), Some(FetchState::Fetching));
+    }
+
+    #[test]
+    fn test_retry() {
+        let mut missing = MissingNotes::default();
+        let id = NoteId::new([3; 32]);
+        missing.want(&id, &["wss://nos.lol/"]);
+
+        let fetch = missing.fetches.get_mut(&id).unwrap();
+        fetch.started = Some(Instant::now());
+        fetch.relays_done = true;
+        fetch.give_up();
+        assert_eq!(missing.state(&id), Some(FetchState::NotFound));
+
+        missing.retry(&id);
+        let fetch = &missing.fetches[&id];
+        assert_eq!(fetch.state, FetchState::Fetching);
+        assert!(fetch.started.is_none());
+        assert!(!fetch.relays_done);
+        assert_eq!(fetch.hints, vec!["wss://nos.lol/"]);
+    }
+
+    #[test]
+    fn test_fetched_marks_our_relays_done() {
+        let mut missing = MissingNotes::default();
+        let id = NoteId::new([3; 32]);
+        missing.want(&id, &[]);
+        missing.subids.insert("sub".to_owned(), id);
+
+        missing.fetched("other");
+        assert!(!missing.fetches[&id].relays_done);
+
+        missing.fetched("sub");
+        assert!(missing.fetches[&id].relays_done);
+        assert!(missing.subids.is_empty());
+    }
+
+    #[test]
+    fn test_not_found_once_the_relays_that_are_up_answer() {
+        let dir = tempfile::TempDir::new().expect("tmp dir");
+        let ndb = Ndb::new(dir.path().to_str().unwrap(), &nostrdb::Config::new()).expect("ndb");
+        let pool = RelayPool::new();
+        let mut subs = Subscriptions::default();
+        let mut relays = OneRelayDown(vec![]);
+
+        let mut missing = MissingNotes::default();
+        let id = NoteId::new([3; 32]);
+        missing.want(&id, &[]);
+        missing.poll(&ndb, &mut subs, &pool, || {});
+        subs.manager.poll(&mut relays);
+        assert_eq!(relays.0.len(), 1);
+
+        // the relay that's down doesn't keep us waiting for the timeout
+        let wire_id = relays.0[0].clone();
+        for subid in subs.manager.handle_eose(&mut relays, "wss://a", &wire_id) {
+            if !subs.manager.is_active(&subid) {
+                missing.fetched(&subid);
+            }
+        }
+        missing.poll(&ndb, &mut subs, &pool, || {});
+
+        assert_eq!(missing.state(&id), Some(FetchState::NotFound));
+    }
+}
@@ -1,9 +1,13 @@
-use enostr::{Filter, RelayPool};
+use enostr::Filter;
 use nostrdb::{Ndb, Note, Transaction};
 use tracing::{debug, error, info};
-use uuid::Uuid;
 
-use crate::{filter::UnifiedSubscription, note::NoteRef, Error};
+use crate::{
+    filter::UnifiedSubscription,
+    note::NoteRef,
+    subscription_manager::{SubLifetime, SubscriptionManager},
+    Error,
+};
 
 pub struct MultiSubscriber {
     filters: Vec<Filter>,
@@ -22,13 +26,14 @@ impl MultiSubscriber {
 
     fn real_subscribe(
         ndb: &Ndb,
-        pool: &mut RelayPool,
+        subs: &mut SubscriptionManager,
         filters: Vec<Filter>,
     ) -> Option<UnifiedSubscription> {
-        let subid = Uuid::new_v4().to_string();
         let sub = ndb.subscribe(&filters).ok()?;
 
-        pool.subscribe(subid.clone(), filters);
+        // the same thread or profile can be open in more than one place,
+        // share the remote subscription between them
+        let subid = subs.subscribe_shared(filters, SubLifetime::Persistent);
 
         Some(UnifiedSubscription {
             local: sub,
@@ -36,7 +41,7 @@ impl MultiSubscriber {
         })
     }
 
-    pub fn unsubscribe(&mut self, ndb: &Ndb, pool: &mut RelayPool) {
+    pub fn unsubscribe(&mut self, ndb: &Ndb, subs: &mut SubscriptionManager) {
         if self.subscribers == 0 {
             error!("No subscribers to unsubscribe from");
             return;
@@ -67,7 +72,7 @@ impl MultiSubscriber {
             }
 
             // unsub from remote
-            pool.unsubscribe(sub.remote.clone());
+            subs.unsubscribe(&sub.remote);
             self.sub = None;
         } else {
             info!(
@@ -78,7 +83,7 @@ impl MultiSubscriber {
         }
     }
 
-    pub fn subscribe(&mut self, ndb: &Ndb, pool: &mut RelayPool) {
+    pub fn subscribe(&mut self, ndb: &Ndb, subs: &mut SubscriptionManager) {
         self.subscribers += 1;
         if self.subscribers == 1 {
             if self.sub.is_some() {
@@ -86,7 +91,7 @@ impl MultiSubscriber {
                 return;
             }
 
-            self.sub = Self::real_subscribe(ndb, pool, self.filters.clone());
+            self.sub = Self::real_subscribe(ndb, subs, self.filters.clone());
             info!(
                 "Remotely subscribing to object. {} total active subscriptions, {} on this object",
                 ndb.subscription_count(),
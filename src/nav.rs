@@ -1,12 +1,17 @@
 use crate::{
-    accounts::render_accounts_route,
+    accounts::{add_and_select_account, render_accounts_route, AccountsRoute},
     actionbar::NoteAction,
     app_style::{get_font_size, NotedeckTextStyle},
+    contacts::ContactAction,
     fonts::NamedFontFamily,
+    lists::ListAction,
+    login_manager::RemoteLogin,
+    metadata,
     notes_holder::NotesHolder,
     profile::Profile,
     relay_pool_manager::RelayPoolManager,
     route::Route,
+    signer::Signer,
     thread::Thread,
     timeline::{
         route::{render_timeline_route, TimelineRoute},
@@ -15,10 +20,20 @@ use crate::{
     ui::{
         self,
         add_column::render_add_column_routes,
+        add_relay::AddRelayView,
         anim::{AnimationHelper, ICON_EXPANSION_MULTIPLE},
+        create_account::CreateAccountView,
+        media_settings::MediaSettingsView,
+        network::NetworkView,
         note::{PostAction, PostType},
+        notification_settings::NotificationSettingsView,
+        profile::edit::{EditProfileDraft, EditProfileView},
+        proxy_settings::{ProxySettingsDraft, ProxySettingsView},
+        relay::RelayViewAction,
+        relay_detail::RelayDetailView,
+        search::render_search_route,
         support::SupportView,
-        RelayView, View,
+        RelayView,
     },
     Damus,
 };
@@ -31,6 +46,14 @@ use tracing::{error, info};
 pub enum RenderNavAction {
     PostAction(PostAction),
     NoteAction(NoteAction),
+    ListAction(ListAction),
+    ContactAction(ContactAction),
+
+    /// Open the profile editor for the selected account
+    EditProfile,
+
+    /// A column setting changed, so the columns need saving
+    ColumnSettingsChanged,
 }
 
 impl From<PostAction> for RenderNavAction {
@@ -45,6 +68,18 @@ impl From<NoteAction> for RenderNavAction {
     }
 }
 
+impl From<ContactAction> for RenderNavAction {
+    fn from(contact_action: ContactAction) -> RenderNavAction {
+        Self::ContactAction(contact_action)
+    }
+}
+
+impl From<ListAction> for RenderNavAction {
+    fn from(list_action: ListAction) -> RenderNavAction {
+        Self::ListAction(list_action)
+    }
+}
+
 pub struct RenderNavResponse {
     column: usize,
     response: NavResponse<Option<RenderNavAction>, TitleResponse>,
@@ -69,10 +104,31 @@ impl RenderNavResponse {
             match action {
                 RenderNavAction::PostAction(post_action) => {
                     let txn = Transaction::new(&app.ndb).expect("txn");
-                    let _ = post_action.execute(&app.ndb, &txn, &mut app.pool, &mut app.drafts);
+                    if let Err(err) = post_action.execute(
+                        &app.ndb,
+                        &txn,
+                        &mut app.pool,
+                        &mut app.outbox,
+                        &mut app.drafts,
+                        &app.accounts,
+                        &mut app.remote_signers,
+                    ) {
+                        error!("error posting: {err}");
+                    }
                     app.columns_mut().column_mut(col).router_mut().go_back();
                 }
 
+                RenderNavAction::NoteAction(NoteAction::Bookmark(bookmark_action)) => {
+                    if let Some(account) = app.accounts.selected_or_first_signer() {
+                        let account = account.pubkey;
+                        app.bookmark_edits.request(account, bookmark_action);
+                    }
+                }
+
+                RenderNavAction::NoteAction(NoteAction::Republish(note_id)) => {
+                    app.outbox.retry(note_id, &mut app.pool);
+                }
+
                 RenderNavAction::NoteAction(note_action) => {
                     let txn = Transaction::new(&app.ndb).expect("txn");
 
@@ -83,10 +139,47 @@ impl RenderNavResponse {
                         &mut app.threads,
                         &mut app.profiles,
                         &mut app.note_cache,
-                        &mut app.pool,
+                        &mut app.subscriptions.manager,
                         &txn,
                     );
                 }
+
+                RenderNavAction::ListAction(list_action) => {
+                    if let Some(signer) = app
+                        .accounts
+                        .selected_or_first_signer()
+                        .and_then(Signer::new)
+                    {
+                        let txn = Transaction::new(&app.ndb).expect("txn");
+                        list_action.execute(
+                            &app.ndb,
+                            &txn,
+                            &mut app.pool,
+                            &mut app.outbox,
+                            &mut app.remote_signers,
+                            &signer,
+                        );
+                    }
+                }
+
+                RenderNavAction::EditProfile => {
+                    app.columns_mut()
+                        .column_mut(col)
+                        .router_mut()
+                        .route_to(Route::EditProfile);
+                }
+
+                RenderNavAction::ColumnSettingsChanged => {
+                    col_changed = true;
+                }
+
+                RenderNavAction::ContactAction(contact_action) => {
+                    if let Some(account) = app.accounts.selected_or_first_signer() {
+                        let account = account.pubkey;
+                        app.contact_edits
+                            .request(&mut app.subscriptions, account, contact_action);
+                    }
+                }
             }
         }
 
@@ -107,7 +200,7 @@ impl RenderNavResponse {
                     &app.ndb,
                     &mut app.note_cache,
                     &mut app.threads,
-                    &mut app.pool,
+                    &mut app.subscriptions.manager,
                     root_id,
                 );
             }
@@ -118,10 +211,15 @@ impl RenderNavResponse {
                     &app.ndb,
                     &mut app.note_cache,
                     &mut app.profiles,
-                    &mut app.pool,
+                    &mut app.subscriptions.manager,
                     pubkey.bytes(),
                 );
             }
+
+            if let Some(Route::Search) = r {
+                let col_id = app.columns().get_column_id_at_index(col);
+                app.searches.remove(col_id, &mut app.subscriptions);
+            }
             col_changed = true;
         } else if let Some(NavAction::Navigated) = self.response.action {
             let cur_router = app.columns_mut().column_mut(col).router_mut();
@@ -174,16 +272,43 @@ pub fn render_nav(col: usize, app: &mut Damus, ui: &mut egui::Ui) -> RenderNavRe
                 &mut app.columns,
                 &mut app.drafts,
                 &mut app.img_cache,
+                &mut app.nip05,
                 &mut app.unknown_ids,
                 &mut app.note_cache,
                 &mut app.threads,
+                &mut app.missing_notes,
                 &mut app.profiles,
+                &mut app.profile_stats,
+                &app.wot,
                 &mut app.accounts,
+                &app.contact_edits,
                 *tlr,
                 col,
                 app.textmode,
                 ui,
             ),
+            Route::Accounts(AccountsRoute::CreateAccount) => {
+                let draft = app
+                    .view_state
+                    .create_account
+                    .get_or_insert_with(Default::default);
+                if CreateAccountView::new(draft).ui(ui) {
+                    if let Some(draft) = app.view_state.create_account.take() {
+                        let account = draft.into_account();
+                        account.publish(&app.ndb, &mut app.pool, &mut app.outbox);
+
+                        let action =
+                            add_and_select_account(&mut app.accounts, account.keypair.to_keypair());
+                        let txn = Transaction::new(&app.ndb).expect("txn");
+                        action.process_action(&mut app.unknown_ids, &app.ndb, &txn);
+
+                        let router = app.columns_mut().column_mut(col).router_mut();
+                        router.remove_previous_route();
+                        router.go_back();
+                    }
+                }
+                None
+            }
             Route::Accounts(amr) => {
                 let action = render_accounts_route(
                     ui,
@@ -193,19 +318,130 @@ pub fn render_nav(col: usize, app: &mut Damus, ui: &mut egui::Ui) -> RenderNavRe
                     &mut app.img_cache,
                     &mut app.accounts,
                     &mut app.view_state.login,
+                    &app.proxy.http,
                     *amr,
                 );
+                match app.view_state.login.take_remote_login() {
+                    Some(RemoteLogin::Bunker(uri)) => app.remote_signers.login(&uri),
+                    Some(RemoteLogin::NostrConnect) => {
+                        let uri = app.remote_signers.listen();
+                        app.view_state.login.set_nostrconnect_uri(uri);
+                    }
+                    None => {}
+                }
                 let txn = Transaction::new(&app.ndb).expect("txn");
                 action.process_action(&mut app.unknown_ids, &app.ndb, &txn);
                 None
             }
             Route::Relays => {
                 let manager = RelayPoolManager::new(app.pool_mut());
-                RelayView::new(manager).ui(ui);
+                let route = match RelayView::new(manager).show(ui)? {
+                    RelayViewAction::AddRelay => Route::AddRelay,
+                    RelayViewAction::Network => Route::Network,
+                    RelayViewAction::Proxy => Route::ProxySettings,
+                    RelayViewAction::OpenRelay(url) => Route::RelayDetail(url),
+                };
+                app.columns_mut()
+                    .column_mut(col)
+                    .router_mut()
+                    .route_to(route);
+                None
+            }
+            Route::RelayDetail(url) => {
+                let relay = app
+                    .pool
+                    .relays
+                    .iter()
+                    .find(|r| &r.relay.url == url)
+                    .map(|r| &r.relay);
+                RelayDetailView::new(relay).ui(ui);
+                None
+            }
+            Route::Network => {
+                NetworkView::new(&app.pool).ui(ui);
+                None
+            }
+            Route::ProxySettings => {
+                let draft = app
+                    .view_state
+                    .proxy_settings
+                    .get_or_insert_with(|| ProxySettingsDraft::new(&app.proxy.settings));
+                let settings = ProxySettingsView::new(draft, app.proxy.error()).ui(ui)?;
+
+                let ctx = ui.ctx().clone();
+                app.proxy
+                    .apply(settings, &mut app.pool, move || ctx.request_repaint());
+                app.img_cache.http = app.proxy.http.clone();
+                app.view_state.proxy_settings = None;
+                app.columns_mut().column_mut(col).router_mut().go_back();
+                None
+            }
+            Route::MediaSettings => {
+                let account = if let Some(account) = app.accounts.get_selected_account() {
+                    account.pubkey
+                } else {
+                    ui.label("Add an account to change its media settings");
+                    return None;
+                };
+
+                let mut visibility = app.media.settings.visibility(&account);
+                if MediaSettingsView::new(&mut visibility).ui(ui) {
+                    app.media.set_visibility(&account, visibility);
+                }
+                None
+            }
+            Route::EditProfile => {
+                let account = app.accounts.selected_or_first_signer()?;
+                let pubkey = account.pubkey;
+                let subs = &mut app.subscriptions;
+                let draft = app
+                    .view_state
+                    .edit_profile
+                    .get_or_insert_with(|| EditProfileDraft::new(subs, pubkey));
+                if draft.pubkey != pubkey {
+                    *draft = EditProfileDraft::new(subs, pubkey);
+                }
+                draft.poll(&app.ndb);
+
+                if !EditProfileView::new(draft, account, &mut app.img_cache, &app.proxy.http).ui(ui)
+                {
+                    return None;
+                }
+
+                if let Some(signer) = Signer::new(account) {
+                    let txn = Transaction::new(&app.ndb).expect("txn");
+                    let current = metadata::latest_metadata(&app.ndb, &txn, &pubkey);
+                    match metadata::metadata_note(current.as_ref(), &draft.edits(), &signer) {
+                        Ok(note) => {
+                            if let Err(err) = signer.publish(
+                                note,
+                                &app.ndb,
+                                &mut app.pool,
+                                &mut app.outbox,
+                                &mut app.remote_signers,
+                            ) {
+                                error!("error publishing profile: {err}");
+                            }
+                        }
+                        Err(err) => error!("error building profile: {err}"),
+                    }
+                }
+
+                app.view_state.edit_profile = None;
+                app.columns_mut().column_mut(col).router_mut().go_back();
+                None
+            }
+            Route::AddRelay => {
+                let url =
+                    AddRelayView::new(&mut app.view_state.add_relay, &app.pool, &app.proxy.http)
+                        .ui(ui)?;
+                RelayPoolManager::new(app.pool_mut()).add_relay(ui.ctx(), url);
+                app.view_state.add_relay.clear();
+                app.columns_mut().column_mut(col).router_mut().go_back();
                 None
             }
             Route::ComposeNote => {
-                let kp = app.accounts.selected_or_first_nsec()?;
+                let kp = &app.accounts.selected_or_first_signer()?.pubkey;
                 let draft = app.drafts.compose_mut();
 
                 let txn = nostrdb::Transaction::new(&app.ndb).expect("txn");
@@ -215,6 +451,7 @@ pub fn render_nav(col: usize, app: &mut Damus, ui: &mut egui::Ui) -> RenderNavRe
                     PostType::New,
                     &mut app.img_cache,
                     &mut app.note_cache,
+                    &mut app.nip05,
                     kp,
                 )
                 .ui(&txn, ui);
@@ -231,6 +468,22 @@ pub fn render_nav(col: usize, app: &mut Damus, ui: &mut egui::Ui) -> RenderNavRe
                 SupportView::new(&mut app.support).show(ui);
                 None
             }
+
+            Route::Search => render_search_route(ui, app, col),
+
+            Route::NotificationSettings => {
+                let changed = NotificationSettingsView::new(
+                    &mut app.desktop_notifications.settings,
+                    &app.columns,
+                    &app.ndb,
+                )
+                .ui(ui);
+
+                if changed {
+                    app.desktop_notifications.save_settings();
+                }
+                None
+            }
         });
 
     RenderNavResponse::new(col, nav_response)
@@ -248,6 +501,12 @@ fn unsubscribe_timeline(ndb: &Ndb, timeline: &Timeline) {
             );
         }
     }
+
+    if let Some(list_sub) = timeline.list_subscription {
+        if let Err(e) = ndb.unsubscribe(list_sub) {
+            error!("list unsubscribe error: {}", e);
+        }
+    }
 }
 
 fn title_bar(
@@ -0,0 +1,91 @@
+use std::net::{IpAddr, UdpSocket};
+use std::time::{Duration, Instant, SystemTime};
+use tracing::info;
+
+/// How often we check for network changes
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// If the wall clock moved this much further than our monotonic clock, we
+/// were probably asleep
+const SLEEP_THRESHOLD: Duration = Duration::from_secs(30);
+
+/// Notices when our network has likely changed, so we can reconnect to
+/// relays right away instead of waiting for dead connections to time out.
+///
+/// There's no portable way to get network change events, so we look for
+/// two things: the local address we'd use to reach the internet changing
+/// (new wifi network, vpn, cable unplugged), and waking up from sleep.
+pub struct NetworkMonitor {
+    last_check: Instant,
+    last_check_wall: SystemTime,
+    local_addr: Option<IpAddr>,
+}
+
+impl Default for NetworkMonitor {
+    fn default() -> Self {
+        NetworkMonitor {
+            last_check: Instant::now(),
+            last_check_wall: SystemTime::now(),
+            local_addr: local_addr(),
+        }
+    }
+}
+
+impl NetworkMonitor {
+    /// Returns true if the network changed since we last checked
+    pub fn poll(&mut self) -> bool {
+        let elapsed = self.last_check.elapsed();
+        if elapsed < CHECK_INTERVAL {
+            return false;
+        }
+
+        let wall_elapsed = self.last_check_wall.elapsed().unwrap_or_default();
+        self.last_check = Instant::now();
+        self.last_check_wall = SystemTime::now();
+
+        let woke_up = is_wakeup(elapsed, wall_elapsed);
+        if woke_up {
+            info!("woke up from sleep");
+        }
+
+        let addr = local_addr();
+        let addr_changed = addr != self.local_addr;
+        if addr_changed {
+            info!(
+                "local address changed from {:?} to {:?}",
+                self.local_addr, addr
+            );
+            self.local_addr = addr;
+        }
+
+        woke_up || addr_changed
+    }
+}
+
+fn is_wakeup(elapsed: Duration, wall_elapsed: Duration) -> bool {
+    wall_elapsed.saturating_sub(elapsed) > SLEEP_THRESHOLD
+}
+
+/// The local address our default route would use. Connecting a udp socket
+/// doesn't send anything, it just picks a route.
+fn local_addr() -> Option<IpAddr> {
+    let socket = UdpSocket::bind(("0.0.0.0", 0)).ok()?;
+    socket.connect(("1.1.1.1", 80)).ok()?;
+    socket.local_addr().ok().map(|addr| addr.ip())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_wakeup() {
+        let secs = Duration::from_secs;
+        assert!(!is_wakeup(secs(5), secs(5)));
+        assert!(!is_wakeup(secs(5), secs(6)));
+        assert!(is_wakeup(secs(5), secs(3600)));
+
+        // the wall clock going backwards isn't a wakeup
+        assert!(!is_wakeup(secs(5), secs(0)));
+    }
+}
@@ -0,0 +1,333 @@
+//! Verifying the NIP-05 addresses people put in their profiles, so we can
+//! show who they are and tell impersonators apart.
+//!
+//! The ui asks the [`Nip05Verifier`] for an address's status while
+//! drawing, and it looks it up in the background, a few at a time. Results
+//! are cached on disk so we don't hit everyone's servers again on each
+//! start.
+
+use crate::key_parsing::{parse_nip05, perform_nip05_lookup};
+use crate::proxy::Http;
+use crate::storage::{write_file, DataPath, DataPathType, Directory};
+use crate::{Error, Result};
+use enostr::Pubkey;
+use poll_promise::Promise;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::{debug, error};
+
+const FILE_NAME: &str = "nip05.json";
+
+/// How long a lookup that got an answer is good for, in seconds
+const VERIFIED_TTL: u64 = 60 * 60 * 24;
+
+/// How long until we retry an address whose server we couldn't reach
+const FAILED_TTL: u64 = 60 * 60;
+
+/// How many lookups may run at once
+const MAX_IN_FLIGHT: usize = 4;
+
+/// How long we wait between starting lookups
+const FETCH_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Nip05Status {
+    /// The address points back to the pubkey
+    Verified,
+
+    /// The address points to someone else, or its server doesn't list
+    /// the name
+    Mismatch,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+struct Verification {
+    address: String,
+
+    /// None when we couldn't get an answer from the address's server
+    status: Option<Nip05Status>,
+
+    /// When we checked, in unix seconds
+    checked_at: u64,
+}
+
+impl Verification {
+    fn is_fresh(&self, now: u64) -> bool {
+        let ttl = if self.status.is_some() {
+            VERIFIED_TTL
+        } else {
+            FAILED_TTL
+        };
+        now.saturating_sub(self.checked_at) < ttl
+    }
+
+    fn is_for(&self, address: &str) -> bool {
+        normalize(&self.address) == normalize(address)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedVerification {
+    pubkey: Pubkey,
+    #[serde(flatten)]
+    verification: Verification,
+}
+
+/// How addresses are shown: `_@domain` is just the domain
+pub fn display_address(address: &str) -> &str {
+    let address = address.trim();
+    address.strip_prefix("_@").unwrap_or(address)
+}
+
+/// What we know about everyone's nip05 addresses. Runs the lookups the ui
+/// asks for, a few at a time.
+#[derive(Default)]
+pub struct Nip05Verifier {
+    known: HashMap<Pubkey, Verification>,
+    /// Addresses the ui asked about since the last poll
+    wanted: Vec<(Pubkey, String)>,
+    queue: VecDeque<(Pubkey, String)>,
+    in_flight: Vec<(Pubkey, String, Promise<Result<Pubkey>>)>,
+    last_started: Option<Instant>,
+    cache: Option<Directory>,
+}
+
+impl Nip05Verifier {
+    pub fn new(path: &DataPath) -> Self {
+        let cache = Directory::new(path.path(DataPathType::Cache).join("nip05"));
+
+        let known = load_cached(&cache).unwrap_or_default();
+        debug!("loaded {} cached nip05 verifications", known.len());
+
+        Nip05Verifier {
+            known,
+            cache: Some(cache),
+            ..Default::default()
+        }
+    }
+
+    /// The status of a profile's nip05 address, if we've checked it. Asks
+    /// for a lookup when we haven't, or when our answer is too old.
+    pub fn status(&mut self, pk: &[u8; 32], address: &str) -> Option<Nip05Status> {
+        // nothing to look up
+        if parse_nip05(address).is_err() {
+            return None;
+        }
+
+        let pk = Pubkey::new(*pk);
+        let (status, fresh) = match self.known.get(&pk) {
+            Some(verification) if verification.is_for(address) => {
+                (verification.status, verification.is_fresh(unix_now()))
+            }
+            _ => (None, false),
+        };
+
+        if !fresh && !self.wanted.iter().any(|(wanted, _)| *wanted == pk) {
+            self.wanted.push((pk, address.trim().to_owned()));
+        }
+
+        status
+    }
+
+    /// Start lookups the ui asked for and record the finished ones
+    pub fn poll(&mut self, ctx: &egui::Context, http: &Http) {
+        for (pk, address) in std::mem::take(&mut self.wanted) {
+            let queued = self.queue.iter().any(|(queued, _)| *queued == pk)
+                || self.in_flight.iter().any(|(busy, _, _)| *busy == pk);
+            if !queued {
+                self.queue.push_back((pk, address));
+            }
+        }
+
+        let mut finished = false;
+        let mut i = 0;
+        while i < self.in_flight.len() {
+            if self.in_flight[i].2.ready().is_none() {
+                i += 1;
+                continue;
+            }
+
+            let (pk, address, promise) = self.in_flight.remove(i);
+            let status = match promise.block_and_take() {
+                Ok(found) => Some(check_status(&pk, found.as_ref())),
+                Err(err) => {
+                    debug!("couldn't look up nip05 {address}: {err}");
+                    None
+                }
+            };
+
+            self.known.insert(
+                pk,
+                Verification {
+                    address,
+                    status,
+                    checked_at: unix_now(),
+                },
+            );
+            finished = true;
+        }
+
+        let now = Instant::now();
+        let can_start = self
+            .last_started
+            .map_or(true, |last| now.duration_since(last) >= FETCH_INTERVAL);
+        if can_start && self.in_flight.len() < MAX_IN_FLIGHT {
+            if let Some((pk, address)) = self.queue.pop_front() {
+                let promise = perform_nip05_lookup(http, &address);
+                self.in_flight.push((pk, address, promise));
+                self.last_started = Some(now);
+            }
+        }
+
+        if finished {
+            self.save_cached();
+            ctx.request_repaint();
+        }
+
+        // lookups don't wake us up when they finish
+        if !self.queue.is_empty() || !self.in_flight.is_empty() {
+            ctx.request_repaint_after(FETCH_INTERVAL);
+        }
+    }
+
+    fn save_cached(&self) {
+        let cache = if let Some(cache) = &self.cache {
+            cache
+        } else {
+            return;
+        };
+
+        let now = unix_now();
+        let cached: Vec<CachedVerification> = self
+            .known
+            .iter()
+            .filter(|(_, verification)| verification.is_fresh(now))
+            .map(|(pubkey, verification)| CachedVerification {
+                pubkey: *pubkey,
+                verification: verification.clone(),
+            })
+            .collect();
+
+        let result = serde_json::to_string(&cached)
+            .map_err(|e| Error::Generic(e.to_string()))
+            .and_then(|json| write_file(&cache.file_path, FILE_NAME.to_owned(), &json));
+
+        if let Err(e) = result {
+            error!("could not cache nip05 verifications: {e}");
+        }
+    }
+}
+
+fn load_cached(cache: &Directory) -> Option<HashMap<Pubkey, Verification>> {
+    let json = cache.get_file(FILE_NAME.to_owned()).ok()?;
+    let cached: Vec<CachedVerification> = serde_json::from_str(&json).ok()?;
+
+    let now = unix_now();
+    Some(
+        cached
+            .into_iter()
+            .filter(|cached| cached.verification.is_fresh(now))
+            .map(|cached| (cached.pubkey, cached.verification))
+            .collect(),
+    )
+}
+
+/// `found` is None when the server answered without the name in it
+fn check_status(pk: &Pubkey, found: Option<&Pubkey>) -> Nip05Status {
+    if found == Some(pk) {
+        Nip05Status::Verified
+    } else {
+        Nip05Status::Mismatch
+    }
+}
+
+/// Names are case insensitive, and so are domains
+fn normalize(address: &str) -> String {
+    address.trim().to_lowercase()
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn verification(status: Option<Nip05Status>, checked_at: u64) -> Verification {
+        Verification {
+            address: "Jb55@jb55.com".to_owned(),
+            status,
+            checked_at,
+        }
+    }
+
+    #[test]
+    fn test_ttl() {
+        let now = 1_000_000;
+        let verified = verification(Some(Nip05Status::Verified), now - FAILED_TTL - 1);
+        assert!(verified.is_fresh(now));
+        assert!(!verification(Some(Nip05Status::Mismatch), now - VERIFIED_TTL).is_fresh(now));
+
+        let failed = verification(None, now - FAILED_TTL - 1);
+        assert!(!failed.is_fresh(now));
+        assert!(verification(None, now - 10).is_fresh(now));
+    }
+
+    #[test]
+    fn test_address_changes_need_a_new_lookup() {
+        let verification = verification(Some(Nip05Status::Verified), 0);
+        assert!(verification.is_for(" jb55@JB55.com"));
+        assert!(!verification.is_for("jb55@damus.io"));
+    }
+
+    #[test]
+    fn test_check_status() {
+        let pk = Pubkey::new([7; 32]);
+        assert_eq!(check_status(&pk, Some(&pk)), Nip05Status::Verified);
+        assert_eq!(
+            check_status(&pk, Some(&Pubkey::new([8; 32]))),
+            Nip05Status::Mismatch
+        );
+
+        // a name missing from nostr.json doesn't verify
+        assert_eq!(check_status(&pk, None), Nip05Status::Mismatch);
+    }
+
+    #[test]
+    fn test_display_address() {
+        assert_eq!(display_address("_@jb55.com"), "jb55.com");
+        assert_eq!(display_address(" jb55@jb55.com "), "jb55@jb55.com");
+    }
+
+    #[test]
+    fn test_cache_roundtrip() {
+        let pubkey = Pubkey::new([7; 32]);
+        let cached = vec![CachedVerification {
+            pubkey,
+            verification: verification(Some(Nip05Status::Mismatch), 42),
+        }];
+
+        let json = serde_json::to_string(&cached).unwrap();
+        let decoded: Vec<CachedVerification> = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded[0].pubkey, pubkey);
+        assert_eq!(decoded[0].verification, cached[0].verification);
+    }
+
+    #[test]
+    fn test_only_valid_addresses_are_looked_up() {
+        let mut verifier = Nip05Verifier::default();
+        let pk = [7; 32];
+
+        assert_eq!(verifier.status(&pk, "me@"), None);
+        assert_eq!(verifier.status(&pk, "not an address"), None);
+        assert!(verifier.wanted.is_empty());
+
+        assert_eq!(verifier.status(&pk, "jb55@jb55.com"), None);
+        assert_eq!(verifier.wanted.len(), 1);
+    }
+}
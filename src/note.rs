@@ -1,4 +1,5 @@
 use crate::notecache::NoteCache;
+use enostr::{Filter, NoteAddress};
 use nostrdb::{Ndb, Note, NoteKey, QueryResult, Transaction};
 use std::cmp::Ordering;
 
@@ -71,3 +72,25 @@ pub fn root_note_id_from_selected_id<'a>(
         .root()
         .map_or_else(|| selected_note_id, |nr| nr.id)
 }
+
+/// The filter for the versions of an addressable event
+pub fn address_filter(address: &NoteAddress) -> Filter {
+    Filter::new()
+        .authors([address.pubkey.bytes()])
+        .kinds([address.kind as u64])
+        .tags([address.identifier.clone()], 'd')
+        .build()
+}
+
+/// The newest version of an addressable event that we have
+pub fn find_addressable<'a>(
+    ndb: &Ndb,
+    txn: &'a Transaction,
+    address: &NoteAddress,
+) -> Option<Note<'a>> {
+    ndb.query(txn, &[address_filter(address)], 10)
+        .ok()?
+        .into_iter()
+        .map(|r| r.note)
+        .max_by_key(|note| note.created_at())
+}
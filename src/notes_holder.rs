@@ -1,12 +1,13 @@
 use std::collections::HashMap;
 
-use enostr::{Filter, RelayPool};
+use enostr::Filter;
 use nostrdb::{Ndb, Transaction};
 use tracing::{debug, info, warn};
 
 use crate::{
     actionbar::NotesHolderResult, multi_subscriber::MultiSubscriber, note::NoteRef,
-    notecache::NoteCache, timeline::TimelineTab, unknowns::NoteRefsUnkIdAction, Error, Result,
+    notecache::NoteCache, subscription_manager::SubscriptionManager, timeline::TimelineTab,
+    unknowns::NoteRefsUnkIdAction, Error, Result,
 };
 
 pub struct NotesHolderStorage<M: NotesHolder> {
@@ -154,7 +155,7 @@ pub trait NotesHolder {
         ndb: &Ndb,
         note_cache: &mut NoteCache,
         notes_holder_storage: &mut NotesHolderStorage<M>,
-        pool: &mut RelayPool,
+        subs: &mut SubscriptionManager,
         id: &[u8; 32],
     ) {
         let notes_holder = notes_holder_storage
@@ -162,7 +163,7 @@ pub trait NotesHolder {
             .get_ptr();
 
         if let Some(multi_subscriber) = notes_holder.get_multi_subscriber() {
-            multi_subscriber.unsubscribe(ndb, pool);
+            multi_subscriber.unsubscribe(ndb, subs);
         }
     }
 
@@ -170,7 +171,7 @@ pub trait NotesHolder {
         ndb: &Ndb,
         note_cache: &mut NoteCache,
         txn: &Transaction,
-        pool: &mut RelayPool,
+        subs: &mut SubscriptionManager,
         storage: &mut NotesHolderStorage<M>,
         id: &[u8; 32],
     ) -> Option<NotesHolderResult> {
@@ -207,7 +208,7 @@ pub trait NotesHolder {
             holder.get_multi_subscriber().unwrap()
         };
 
-        multi_subscriber.subscribe(ndb, pool);
+        multi_subscriber.subscribe(ndb, subs);
 
         result
     }
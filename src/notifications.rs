@@ -0,0 +1,230 @@
+use crate::note::NoteRef;
+use enostr::{NoteId, Pubkey};
+use nostrdb::{Ndb, Note, NoteKey, Tag, Transaction};
+use std::collections::HashMap;
+
+/// Mentions, reposts, reactions, follows and zaps
+pub const NOTIFICATION_KINDS: [u64; 5] = [1, 3, 6, 7, 9735];
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum NotificationKind {
+    Mention,
+    Follow,
+    Repost,
+    Reaction,
+    Zap,
+}
+
+impl NotificationKind {
+    pub fn from_note_kind(kind: u32) -> Option<Self> {
+        match kind {
+            1 => Some(NotificationKind::Mention),
+            3 => Some(NotificationKind::Follow),
+            6 => Some(NotificationKind::Repost),
+            7 => Some(NotificationKind::Reaction),
+            9735 => Some(NotificationKind::Zap),
+            _ => None,
+        }
+    }
+}
+
+/// Notifications about the same thing, like everyone who liked one of our
+/// notes. Mentions are never grouped.
+#[derive(Debug, Clone)]
+pub struct NotificationGroup {
+    pub kind: NotificationKind,
+
+    /// The note this group is about. None for follows and profile zaps.
+    pub target: Option<NoteId>,
+
+    /// The newest notification in the group
+    pub newest: NoteKey,
+
+    /// Who did it, newest first
+    pub authors: Vec<Pubkey>,
+
+    /// Whether every reaction in the group is a plain like
+    pub all_likes: bool,
+
+    /// The total amount zapped
+    pub zap_msats: u64,
+}
+
+impl NotificationGroup {
+    fn new(kind: NotificationKind, target: Option<NoteId>, newest: NoteKey) -> Self {
+        NotificationGroup {
+            kind,
+            target,
+            newest,
+            authors: vec![],
+            all_likes: true,
+            zap_msats: 0,
+        }
+    }
+}
+
+/// Group notifications, which are expected to be newest first. Groups are
+/// ordered by their newest notification.
+pub fn group_notifications(
+    ndb: &Ndb,
+    txn: &Transaction,
+    notes: &[NoteRef],
+) -> Vec<NotificationGroup> {
+    let mut groups: Vec<NotificationGroup> = vec![];
+    let mut index: HashMap<(NotificationKind, Option<NoteId>), usize> = HashMap::new();
+
+    for note_ref in notes {
+        let note = if let Ok(note) = ndb.get_note_by_key(txn, note_ref.key) {
+            note
+        } else {
+            continue;
+        };
+
+        let kind = if let Some(kind) = NotificationKind::from_note_kind(note.kind()) {
+            kind
+        } else {
+            continue;
+        };
+
+        if kind == NotificationKind::Mention {
+            groups.push(NotificationGroup::new(
+                kind,
+                Some(NoteId::new(*note.id())),
+                note_ref.key,
+            ));
+            continue;
+        }
+
+        let target = notification_target(&note, kind);
+        let author = notification_author(&note, kind);
+
+        let ind = *index.entry((kind, target)).or_insert_with(|| {
+            groups.push(NotificationGroup::new(kind, target, note_ref.key));
+            groups.len() - 1
+        });
+        let group = &mut groups[ind];
+
+        if !group.authors.contains(&author) {
+            group.authors.push(author);
+        }
+
+        match kind {
+            NotificationKind::Reaction => {
+                let content = note.content();
+                if !(content.is_empty() || content == "+") {
+                    group.all_likes = false;
+                }
+            }
+            NotificationKind::Zap => group.zap_msats += zap_msats(&note).unwrap_or(0),
+            _ => {}
+        }
+    }
+
+    groups
+}
+
+/// The note a notification is about. Mentions are about themselves, follows
+/// and profile zaps aren't about a note.
+pub fn notification_target(note: &Note, kind: NotificationKind) -> Option<NoteId> {
+    match kind {
+        NotificationKind::Mention => Some(NoteId::new(*note.id())),
+        NotificationKind::Follow => None,
+        NotificationKind::Repost | NotificationKind::Zap => first_e_tag(note),
+        NotificationKind::Reaction => last_e_tag(note),
+    }
+}
+
+/// Who a notification is from
+pub fn notification_author(note: &Note, kind: NotificationKind) -> Pubkey {
+    match kind {
+        NotificationKind::Zap => zap_sender(note).unwrap_or_else(|| Pubkey::new(*note.pubkey())),
+        _ => Pubkey::new(*note.pubkey()),
+    }
+}
+
+fn tag_id(tag: &Tag) -> Option<NoteId> {
+    if tag.count() < 2 || tag.get(0).and_then(|t| t.variant().str()) != Some("e") {
+        return None;
+    }
+
+    tag.get(1)
+        .and_then(|id| id.variant().id())
+        .map(|id| NoteId::new(*id))
+}
+
+fn first_e_tag(note: &Note) -> Option<NoteId> {
+    note.tags().iter().find_map(|tag| tag_id(&tag))
+}
+
+/// NIP-25: the last e tag is the note being reacted to
+fn last_e_tag(note: &Note) -> Option<NoteId> {
+    note.tags().iter().filter_map(|tag| tag_id(&tag)).last()
+}
+
+fn tag_value<'a>(note: &Note<'a>, name: &str) -> Option<&'a str> {
+    note.tags().iter().find_map(|tag| {
+        if tag.count() >= 2 && tag.get(0).and_then(|t| t.variant().str()) == Some(name) {
+            tag.get(1).and_then(|v| v.variant().str())
+        } else {
+            None
+        }
+    })
+}
+
+/// Zap receipts are published by the recipient's lightning service, the
+/// person who zapped is the author of the zap request in the description.
+fn zap_sender(receipt: &Note) -> Option<Pubkey> {
+    let request: serde_json::Value =
+        serde_json::from_str(tag_value(receipt, "description")?).ok()?;
+    Pubkey::from_hex(request.get("pubkey")?.as_str()?).ok()
+}
+
+pub fn zap_msats(receipt: &Note) -> Option<u64> {
+    bolt11_msats(tag_value(receipt, "bolt11")?)
+}
+
+/// The amount of a bolt11 invoice in millisats, from its human readable
+/// part. ie. lnbc2500u1... is 2500 micro-bitcoin.
+pub fn bolt11_msats(invoice: &str) -> Option<u64> {
+    let invoice = invoice.to_lowercase();
+    // bech32 data never contains a 1, so the last one is the separator
+    let hrp = &invoice[..invoice.rfind('1')?];
+    let amount = hrp
+        .strip_prefix("ln")?
+        .trim_start_matches(|c: char| c.is_ascii_alphabetic());
+
+    let (digits, multiplier) = match amount.char_indices().last()? {
+        (_, c) if c.is_ascii_digit() => (amount, None),
+        (i, c) => (&amount[..i], Some(c)),
+    };
+
+    let value: u64 = digits.parse().ok()?;
+    match multiplier {
+        None => value.checked_mul(100_000_000_000),
+        Some('m') => value.checked_mul(100_000_000),
+        Some('u') => value.checked_mul(100_000),
+        Some('n') => value.checked_mul(100),
+        Some('p') => Some(value / 10),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bolt11_msats() {
+        assert_eq!(bolt11_msats("lnbc2500u1pvjluezpp5qqq"), Some(250_000_000));
+        assert_eq!(bolt11_msats("lnbc10n1pjq"), Some(1_000));
+        assert_eq!(bolt11_msats("lnbc1m1pjq"), Some(100_000_000));
+        assert_eq!(bolt11_msats("lnbcrt50u1pjq"), Some(5_000_000));
+        assert_eq!(bolt11_msats("LNBC2500U1PVJ"), Some(250_000_000));
+    }
+
+    #[test]
+    fn test_bolt11_without_amount() {
+        assert_eq!(bolt11_msats("lnbc1pvjluezpp5qqq"), None);
+        assert_eq!(bolt11_msats("not an invoice"), None);
+    }
+}
@@ -0,0 +1,141 @@
+use enostr::{RelayPool, RelayStatus};
+use std::time::{Duration, Instant};
+use tracing::info;
+
+/// How long we go without any connected relay before we consider
+/// ourselves offline
+const AUTO_OFFLINE_AFTER: Duration = Duration::from_secs(10);
+
+/// How often we look for local copies of lists that timelines are
+/// waiting on while offline
+const LOCAL_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Whether we're working from nostrdb alone. We're offline when asked to
+/// be (`--offline`, or from the side panel), or when none of our relays
+/// have been reachable for a while.
+pub struct OfflineMode {
+    /// We were asked to stay offline, so we don't connect to relays
+    forced: bool,
+
+    /// None of our relays are reachable
+    relays_down: bool,
+
+    /// When we last had a connected relay, if we don't have one now
+    no_relays_since: Option<Instant>,
+
+    last_local_check: Instant,
+}
+
+impl OfflineMode {
+    pub fn new(forced: bool) -> Self {
+        OfflineMode {
+            forced,
+            relays_down: false,
+            no_relays_since: None,
+            last_local_check: Instant::now(),
+        }
+    }
+
+    pub fn is_offline(&self) -> bool {
+        self.forced || self.relays_down
+    }
+
+    /// Whether we're offline because we were asked to be, not because
+    /// our relays are down
+    pub fn is_forced(&self) -> bool {
+        self.forced
+    }
+
+    pub fn set_forced(
+        &mut self,
+        forced: bool,
+        pool: &mut RelayPool,
+        wakeup: impl Fn() + Send + Sync + Clone + 'static,
+    ) {
+        info!(
+            "{} offline mode",
+            if forced { "entering" } else { "leaving" }
+        );
+        self.forced = forced;
+        pool.set_offline(forced, wakeup);
+    }
+
+    /// Check whether our relays are reachable. Returns true if we just
+    /// went offline.
+    pub fn update(&mut self, pool: &RelayPool) -> bool {
+        let connected = pool
+            .relays
+            .iter()
+            .any(|r| matches!(r.relay.status, RelayStatus::Connected));
+
+        self.update_at(connected, Instant::now())
+    }
+
+    fn update_at(&mut self, connected: bool, now: Instant) -> bool {
+        let was_offline = self.is_offline();
+
+        if connected {
+            self.no_relays_since = None;
+            self.relays_down = false;
+        } else {
+            let since = *self.no_relays_since.get_or_insert(now);
+            self.relays_down = now.duration_since(since) >= AUTO_OFFLINE_AFTER;
+        }
+
+        if was_offline != self.is_offline() {
+            info!(
+                "{}",
+                if self.is_offline() {
+                    "no relays reachable, working offline"
+                } else {
+                    "relays reachable again, back online"
+                }
+            );
+        }
+
+        !was_offline && self.is_offline()
+    }
+
+    /// Whether it's time to look for local lists again. Only while offline.
+    pub fn should_check_local(&mut self) -> bool {
+        if !self.is_offline() || self.last_local_check.elapsed() < LOCAL_CHECK_INTERVAL {
+            return false;
+        }
+
+        self.last_local_check = Instant::now();
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_goes_offline_when_relays_stay_down() {
+        let mut offline = OfflineMode::new(false);
+        let start = Instant::now();
+
+        assert!(!offline.update_at(false, start));
+        assert!(!offline.is_offline());
+
+        assert!(offline.update_at(false, start + AUTO_OFFLINE_AFTER));
+        assert!(offline.is_offline());
+        assert!(!offline.is_forced());
+
+        // already offline
+        assert!(!offline.update_at(false, start + AUTO_OFFLINE_AFTER * 2));
+
+        assert!(!offline.update_at(true, start + AUTO_OFFLINE_AFTER * 3));
+        assert!(!offline.is_offline());
+    }
+
+    #[test]
+    fn test_forced_offline_ignores_relays() {
+        let mut offline = OfflineMode::new(true);
+        assert!(offline.is_offline());
+
+        offline.update_at(true, Instant::now());
+        assert!(offline.is_offline());
+    }
+}
@@ -0,0 +1,569 @@
+use crate::storage::{write_file, DataPath, DataPathType, Directory};
+
+use enostr::{ClientMessage, CommandResult, NoteId, RelayPool, RelayStatus};
+use nostrdb::{Ndb, Note};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{debug, error, info, warn};
+
+static FILE_NAME: &str = "outbox.json";
+
+/// How long we wait for a relay's OK before sending again
+const OK_TIMEOUT: u64 = 30;
+
+/// How many times we try a relay before giving up on it
+const MAX_ATTEMPTS: u32 = 5;
+
+/// Delivered events are dropped from the outbox after this long
+const KEEP_DELIVERED: u64 = 60 * 60 * 24 * 7;
+
+/// We don't keep more than this many delivered or given up events around
+const MAX_ENTRIES: usize = 500;
+
+/// How often we look for things to (re)send
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Where an event is at with a single relay
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Delivery {
+    /// Waiting for the relay to be connected
+    Pending,
+
+    /// Sent, waiting for an OK
+    Sent { at: u64, attempts: u32 },
+
+    /// The relay stored it
+    Accepted,
+
+    /// The relay refused it for a reason that might go away, like rate
+    /// limiting. We'll try again later.
+    Retrying {
+        after: u64,
+        attempts: u32,
+        reason: String,
+    },
+
+    /// The relay refused it and there's no point in trying again
+    Rejected(String),
+}
+
+impl Delivery {
+    fn attempts(&self) -> u32 {
+        match self {
+            Delivery::Sent { attempts, .. } | Delivery::Retrying { attempts, .. } => *attempts,
+            _ => 0,
+        }
+    }
+
+    fn should_send(&self, now: u64) -> bool {
+        match self {
+            Delivery::Pending => true,
+            Delivery::Sent { at, .. } => now >= at + OK_TIMEOUT,
+            Delivery::Retrying { after, .. } => now >= *after,
+            Delivery::Accepted | Delivery::Rejected(_) => false,
+        }
+    }
+}
+
+/// How an event we published is doing across our relays
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct DeliveryStatus {
+    pub accepted: usize,
+    pub rejected: usize,
+    pub total: usize,
+}
+
+impl DeliveryStatus {
+    pub fn is_delivered(&self) -> bool {
+        self.accepted == self.total
+    }
+
+    /// Some relays refused this event, or we gave up on them
+    pub fn can_retry(&self) -> bool {
+        self.rejected > 0
+    }
+
+    /// Every relay either stored this event or we gave up on it
+    pub fn is_finished(&self) -> bool {
+        self.accepted + self.rejected == self.total
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct OutboxEntry {
+    /// hex note id
+    id: String,
+
+    /// The signed event json
+    event: String,
+
+    /// When we queued it
+    queued_at: u64,
+
+    relays: BTreeMap<String, Delivery>,
+}
+
+impl OutboxEntry {
+    fn status(&self) -> DeliveryStatus {
+        let mut status = DeliveryStatus {
+            accepted: 0,
+            rejected: 0,
+            total: self.relays.len(),
+        };
+
+        for delivery in self.relays.values() {
+            match delivery {
+                Delivery::Accepted => status.accepted += 1,
+                Delivery::Rejected(_) => status.rejected += 1,
+                _ => {}
+            }
+        }
+
+        status
+    }
+}
+
+/// Signed events waiting to be delivered to our relays. The outbox is saved
+/// to disk, so nothing we publish is lost if our relays are down or we quit
+/// before they answered.
+pub struct Outbox {
+    directory: Option<Directory>,
+    entries: Vec<OutboxEntry>,
+    last_poll: Instant,
+
+    /// Whether delivery statuses changed since we last published them to
+    /// the ui
+    changed: bool,
+
+    /// Whether there are changes we haven't saved yet. Relays can answer
+    /// many times a second, so we save at most once per poll.
+    dirty: bool,
+}
+
+impl Default for Outbox {
+    fn default() -> Self {
+        Outbox {
+            directory: None,
+            entries: vec![],
+            last_poll: Instant::now(),
+            changed: true,
+            dirty: false,
+        }
+    }
+}
+
+impl Drop for Outbox {
+    fn drop(&mut self) {
+        self.save_if_dirty();
+    }
+}
+
+impl Outbox {
+    pub fn new(path: &DataPath) -> Self {
+        let directory = Directory::new(path.path(DataPathType::Outbox));
+
+        let entries = match directory.get_file(FILE_NAME.to_owned()) {
+            Ok(json) => serde_json::from_str(&json).unwrap_or_else(|err| {
+                error!("could not parse outbox, starting with an empty one: {err}");
+                vec![]
+            }),
+            Err(_) => vec![],
+        };
+
+        Outbox {
+            directory: Some(directory),
+            entries,
+            last_poll: Instant::now(),
+            changed: true,
+            dirty: false,
+        }
+    }
+
+    /// Queue a signed note for delivery to all of our relays. It's
+    /// processed locally right away so it shows up in our timelines.
+    pub fn publish(&mut self, ndb: &Ndb, pool: &mut RelayPool, note: &Note) {
+        let event = match note.json() {
+            Ok(json) => json,
+            Err(err) => {
+                error!("error serializing note for the outbox: {err}");
+                return;
+            }
+        };
+
+        self.publish_event(ndb, pool, hex::encode(note.id()), event);
+    }
+
+    /// Queue signed event json, ie. from a remote signer
+    pub fn publish_event(&mut self, ndb: &Ndb, pool: &mut RelayPool, id: String, event: String) {
+        if let Err(err) = ndb.process_client_event(&format!("[\"EVENT\",{}]", event)) {
+            error!("error processing our own note locally: {err}");
+        }
+
+        info!("queued {} in the outbox", id);
+        self.entries.push(OutboxEntry {
+            id,
+            event,
+            queued_at: now(),
+            relays: BTreeMap::new(),
+        });
+
+        // new events are saved right away, so they survive a crash
+        self.send_pending(pool);
+        self.dirty = true;
+        self.save_if_dirty();
+    }
+
+    /// Send everything that's due to our connected relays
+    pub fn poll(&mut self, pool: &mut RelayPool) {
+        if self.last_poll.elapsed() < POLL_INTERVAL {
+            return;
+        }
+        self.last_poll = Instant::now();
+
+        if self.send_pending(pool) {
+            self.dirty = true;
+        }
+        self.save_if_dirty();
+    }
+
+    /// A relay answered an event we sent
+    pub fn handle_ok(&mut self, relay: &str, result: &CommandResult) {
+        let entry = if let Some(entry) = self.entries.iter_mut().find(|e| e.id == result.event_id) {
+            entry
+        } else {
+            return;
+        };
+
+        let delivery = if let Some(delivery) = entry.relays.get_mut(relay) {
+            delivery
+        } else {
+            return;
+        };
+
+        *delivery = next_delivery(delivery.attempts(), result, now());
+        debug!("{} delivery to {}: {:?}", result.event_id, relay, delivery);
+
+        self.changed = true;
+        self.dirty = true;
+    }
+
+    /// Try relays that gave up on an event again
+    pub fn retry(&mut self, note_id: &NoteId, pool: &mut RelayPool) {
+        let id = note_id.hex();
+        let entry = if let Some(entry) = self.entries.iter_mut().find(|e| e.id == id) {
+            entry
+        } else {
+            warn!("tried to retry {id}, which isn't in the outbox");
+            return;
+        };
+
+        for delivery in entry.relays.values_mut() {
+            if *delivery != Delivery::Accepted {
+                *delivery = Delivery::Pending;
+            }
+        }
+
+        self.changed = true;
+        self.send_pending(pool);
+        self.dirty = true;
+    }
+
+    /// Make delivery statuses available to note views, which don't have
+    /// access to the outbox
+    pub fn publish_statuses(&mut self, ctx: &egui::Context) {
+        if !self.changed {
+            return;
+        }
+        self.changed = false;
+
+        let statuses: HashMap<[u8; 32], DeliveryStatus> = self
+            .entries
+            .iter()
+            .filter_map(|entry| {
+                let id: [u8; 32] = hex::decode(&entry.id).ok()?.try_into().ok()?;
+                Some((id, entry.status()))
+            })
+            .collect();
+
+        ctx.data_mut(|d| d.insert_temp(statuses_id(), Arc::new(statuses)));
+    }
+
+    /// Returns true if anything changed
+    fn send_pending(&mut self, pool: &mut RelayPool) -> bool {
+        let now = now();
+        let mut changed = false;
+
+        for entry in &mut self.entries {
+            let mut event_msg = None;
+
+            for pool_relay in &mut pool.relays {
+                let relay = &mut pool_relay.relay;
+                let delivery = entry
+                    .relays
+                    .entry(relay.url.clone())
+                    .or_insert(Delivery::Pending);
+
+                if !matches!(relay.status, RelayStatus::Connected) || !delivery.should_send(now) {
+                    continue;
+                }
+
+                let attempts = delivery.attempts() + 1;
+                if attempts > MAX_ATTEMPTS {
+                    *delivery = Delivery::Rejected("no response".to_owned());
+                    changed = true;
+                    continue;
+                }
+
+                let msg = event_msg.get_or_insert_with(|| {
+                    ClientMessage::raw(format!("[\"EVENT\",{}]", entry.event))
+                });
+                relay.send(msg);
+
+                *delivery = Delivery::Sent { at: now, attempts };
+                changed = true;
+            }
+        }
+
+        if changed {
+            self.changed = true;
+            self.prune(now);
+        }
+
+        changed
+    }
+
+    fn prune(&mut self, now: u64) {
+        self.entries.retain(|entry| {
+            !(entry.status().is_delivered() && now > entry.queued_at + KEEP_DELIVERED)
+        });
+
+        // oldest first, and never events that are still on their way
+        let mut extra = self.entries.len().saturating_sub(MAX_ENTRIES);
+        self.entries.retain(|entry| {
+            if extra > 0 && entry.status().is_finished() {
+                extra -= 1;
+                false
+            } else {
+                true
+            }
+        });
+    }
+
+    fn save_if_dirty(&mut self) {
+        if self.dirty {
+            self.dirty = false;
+            self.save();
+        }
+    }
+
+    fn save(&self) {
+        let directory = if let Some(directory) = &self.directory {
+            directory
+        } else {
+            return;
+        };
+
+        match serde_json::to_string(&self.entries) {
+            Ok(json) => {
+                if let Err(err) = write_file(&directory.file_path, FILE_NAME.to_owned(), &json) {
+                    error!("error saving outbox: {err}");
+                }
+            }
+            Err(err) => error!("error serializing outbox: {err}"),
+        }
+    }
+}
+
+/// What a relay's OK means for our delivery to it. See NIP-01 for the
+/// machine readable prefixes.
+fn next_delivery(attempts: u32, result: &CommandResult, now: u64) -> Delivery {
+    // relays answer true for duplicates too, but some don't
+    if result.status || result.prefix() == Some("duplicate") {
+        return Delivery::Accepted;
+    }
+
+    match result.prefix() {
+        Some("rate-limited") | Some("error") if attempts < MAX_ATTEMPTS => Delivery::Retrying {
+            // back off a bit more each time
+            after: now + 10 * 2u64.pow(attempts),
+            attempts,
+            reason: result.message.clone(),
+        },
+        _ => Delivery::Rejected(result.message.clone()),
+    }
+}
+
+fn statuses_id() -> egui::Id {
+    egui::Id::new("outbox_delivery_statuses")
+}
+
+/// The delivery status of one of our notes, if it went through the outbox
+pub fn delivery_status(ctx: &egui::Context, note_id: &[u8; 32]) -> Option<DeliveryStatus> {
+    ctx.data(|d| {
+        d.get_temp::<Arc<HashMap<[u8; 32], DeliveryStatus>>>(statuses_id())
+            .and_then(|statuses| statuses.get(note_id).copied())
+    })
+}
+
+fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ok(status: bool, message: &str) -> CommandResult<'static> {
+        CommandResult {
+            event_id: "b1a649ebe8b435ec71d3784793f3bbf4b93e64e17568a741aecd4c7ddeafce30",
+            status,
+            message: message.to_owned(),
+        }
+    }
+
+    #[test]
+    fn test_accepted() {
+        assert_eq!(next_delivery(1, &ok(true, ""), 0), Delivery::Accepted);
+        assert_eq!(
+            next_delivery(1, &ok(false, "duplicate: already have it"), 0),
+            Delivery::Accepted
+        );
+    }
+
+    #[test]
+    fn test_transient_failures_retry() {
+        let delivery = next_delivery(1, &ok(false, "rate-limited: slow down"), 100);
+        assert!(matches!(
+            delivery,
+            Delivery::Retrying {
+                after: 120,
+                attempts: 1,
+                ..
+            }
+        ));
+        assert!(!delivery.should_send(119));
+        assert!(delivery.should_send(120));
+
+        // we give up eventually
+        assert!(matches!(
+            next_delivery(MAX_ATTEMPTS, &ok(false, "error: db down"), 100),
+            Delivery::Rejected(_)
+        ));
+    }
+
+    #[test]
+    fn test_permanent_failures() {
+        assert_eq!(
+            next_delivery(1, &ok(false, "blocked: you are banned"), 0),
+            Delivery::Rejected("blocked: you are banned".to_owned())
+        );
+        assert!(matches!(
+            next_delivery(1, &ok(false, "no prefix"), 0),
+            Delivery::Rejected(_)
+        ));
+    }
+
+    #[test]
+    fn test_oks_save_on_poll() {
+        let mut outbox = Outbox::default();
+        let result = ok(true, "");
+        let mut relays = BTreeMap::new();
+        relays.insert("wss://a".to_owned(), Delivery::Sent { at: 0, attempts: 1 });
+        outbox.entries.push(OutboxEntry {
+            id: result.event_id.to_owned(),
+            event: "{}".to_owned(),
+            queued_at: now(),
+            relays,
+        });
+
+        outbox.handle_ok("wss://a", &result);
+        assert!(outbox.dirty);
+        assert_eq!(outbox.entries[0].relays["wss://a"], Delivery::Accepted);
+
+        // too soon after the last poll, nothing is saved yet
+        let mut pool = RelayPool::new();
+        outbox.poll(&mut pool);
+        assert!(outbox.dirty);
+
+        outbox.last_poll = Instant::now() - POLL_INTERVAL;
+        outbox.poll(&mut pool);
+        assert!(!outbox.dirty);
+    }
+
+    #[test]
+    fn test_prune_keeps_undelivered() {
+        let now = now();
+        let entry = |id: usize, delivery: Delivery| {
+            let mut relays = BTreeMap::new();
+            relays.insert("wss://a".to_owned(), delivery);
+            OutboxEntry {
+                id: id.to_string(),
+                event: "{}".to_owned(),
+                queued_at: now,
+                relays,
+            }
+        };
+
+        let mut outbox = Outbox::default();
+        outbox.entries.push(entry(0, Delivery::Pending));
+        outbox.entries.push(entry(
+            1,
+            Delivery::Sent {
+                at: now,
+                attempts: 1,
+            },
+        ));
+        outbox
+            .entries
+            .push(entry(2, Delivery::Rejected("blocked".to_owned())));
+        for id in 3..(MAX_ENTRIES + 3) {
+            outbox.entries.push(entry(id, Delivery::Accepted));
+        }
+
+        outbox.prune(now);
+        assert_eq!(outbox.entries.len(), MAX_ENTRIES);
+        assert_eq!(outbox.entries[0].id, "0");
+        assert_eq!(outbox.entries[1].id, "1");
+        // the oldest finished ones went first, the one we gave up on too
+        assert_eq!(outbox.entries[2].id, "5");
+
+        // past the limit with nothing finished, we keep everything
+        let mut outbox = Outbox::default();
+        for id in 0..(MAX_ENTRIES + 1) {
+            outbox.entries.push(entry(id, Delivery::Pending));
+        }
+        outbox.prune(now);
+        assert_eq!(outbox.entries.len(), MAX_ENTRIES + 1);
+    }
+
+    #[test]
+    fn test_status() {
+        let mut relays = BTreeMap::new();
+        relays.insert("wss://a".to_owned(), Delivery::Accepted);
+        relays.insert("wss://b".to_owned(), Delivery::Accepted);
+        relays.insert(
+            "wss://c".to_owned(),
+            Delivery::Rejected("blocked".to_owned()),
+        );
+        relays.insert("wss://d".to_owned(), Delivery::Pending);
+
+        let entry = OutboxEntry {
+            id: "00".to_owned(),
+            event: "{}".to_owned(),
+            queued_at: 0,
+            relays,
+        };
+
+        let status = entry.status();
+        assert_eq!(status.accepted, 2);
+        assert_eq!(status.total, 4);
+        assert!(status.can_retry());
+        assert!(!status.is_delivered());
+        assert!(!status.is_finished());
+    }
+}
@@ -1,28 +1,33 @@
-use enostr::FullKeypair;
+use crate::signer::{SignedNote, Signer};
+use crate::Result;
+use enostr::Pubkey;
 use nostrdb::{Note, NoteBuilder, NoteReply};
 use std::collections::HashSet;
 
 pub struct NewPost {
     pub content: String,
-    pub account: FullKeypair,
+    pub account: Pubkey,
+
+    /// NIP-36: readers see this reason and have to choose to see the
+    /// post. It can be empty.
+    pub content_warning: Option<String>,
 }
 
 impl NewPost {
-    pub fn new(content: String, account: FullKeypair) -> Self {
-        NewPost { content, account }
+    pub fn new(content: String, account: Pubkey) -> Self {
+        NewPost {
+            content,
+            account,
+            content_warning: None,
+        }
     }
 
-    pub fn to_note(&self, seckey: &[u8; 32]) -> Note {
-        NoteBuilder::new()
-            .kind(1)
-            .content(&self.content)
-            .sign(seckey)
-            .build()
-            .expect("note should be ok")
+    pub fn to_note(&self, signer: &Signer) -> Result<SignedNote> {
+        signer.sign(self.content_warning_tag(NoteBuilder::new().kind(1).content(&self.content)))
     }
 
-    pub fn to_reply(&self, seckey: &[u8; 32], replying_to: &Note) -> Note {
-        let builder = NoteBuilder::new().kind(1).content(&self.content);
+    pub fn to_reply(&self, signer: &Signer, replying_to: &Note) -> Result<SignedNote> {
+        let builder = self.content_warning_tag(NoteBuilder::new().kind(1).content(&self.content));
 
         let nip10 = NoteReply::new(replying_to.tags());
 
@@ -38,7 +43,6 @@ impl NewPost {
                 .tag_str(&hex::encode(replying_to.id()))
                 .tag_str("")
                 .tag_str("reply")
-                .sign(seckey)
         } else {
             // we're replying to a post that isn't in a thread,
             // just add a single reply-to-root tag
@@ -48,7 +52,6 @@ impl NewPost {
                 .tag_str(&hex::encode(replying_to.id()))
                 .tag_str("")
                 .tag_str("root")
-                .sign(seckey)
         };
 
         let mut seen_p: HashSet<&[u8; 32]> = HashSet::new();
@@ -84,20 +87,17 @@ impl NewPost {
             builder = builder.start_tag().tag_str("p").tag_str(&hex::encode(id));
         }
 
-        builder
-            .sign(seckey)
-            .build()
-            .expect("expected build to work")
+        signer.sign(builder)
     }
 
-    pub fn to_quote(&self, seckey: &[u8; 32], quoting: &Note) -> Note {
+    pub fn to_quote(&self, signer: &Signer, quoting: &Note) -> Result<SignedNote> {
         let new_content = format!(
             "{}\nnostr:{}",
             self.content,
             enostr::NoteId::new(*quoting.id()).to_bech().unwrap()
         );
 
-        NoteBuilder::new()
+        let builder = NoteBuilder::new()
             .kind(1)
             .content(&new_content)
             .start_tag()
@@ -105,9 +105,55 @@ impl NewPost {
             .tag_str(&hex::encode(quoting.id()))
             .start_tag()
             .tag_str("p")
-            .tag_str(&hex::encode(quoting.pubkey()))
-            .sign(seckey)
-            .build()
-            .expect("expected build to work")
+            .tag_str(&hex::encode(quoting.pubkey()));
+
+        signer.sign(self.content_warning_tag(builder))
+    }
+
+    fn content_warning_tag<'b>(&self, builder: NoteBuilder<'b>) -> NoteBuilder<'b> {
+        let reason = if let Some(reason) = &self.content_warning {
+            reason.trim()
+        } else {
+            return builder;
+        };
+
+        let builder = builder.start_tag().tag_str("content-warning");
+        if reason.is_empty() {
+            builder
+        } else {
+            builder.tag_str(reason)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lists::tag_str;
+    use crate::test_utils::{local_note, with_signer};
+    use enostr::FullKeypair;
+
+    fn signed(post: &NewPost, kp: &FullKeypair) -> Note<'static> {
+        with_signer(kp, |signer| local_note(post.to_note(signer).unwrap()))
+    }
+
+    #[test]
+    fn test_content_warning_tag() {
+        let kp = FullKeypair::generate();
+        let mut post = NewPost::new("hello".to_owned(), kp.pubkey);
+
+        let note = signed(&post, &kp);
+        assert_eq!(note.tags().count(), 0);
+
+        post.content_warning = Some(" spoilers ".to_owned());
+        let note = signed(&post, &kp);
+        let tag = note.tags().iter().next().unwrap();
+        assert_eq!(tag_str(&tag, 0), Some("content-warning"));
+        assert_eq!(tag_str(&tag, 1), Some("spoilers"));
+
+        post.content_warning = Some("".to_owned());
+        let note = signed(&post, &kp);
+        let tag = note.tags().iter().next().unwrap();
+        assert_eq!(tag.count(), 1);
     }
 }
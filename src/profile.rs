@@ -8,6 +8,8 @@ use crate::{
     notecache::NoteCache,
     notes_holder::NotesHolder,
     timeline::{copy_notes_into_timeline, PubkeySource, Timeline, TimelineKind},
+    unknowns::NoteRefsUnkIdAction,
+    Error, Result,
 };
 
 pub enum DisplayName<'a> {
@@ -51,6 +53,10 @@ pub fn get_profile_name<'a>(record: &'a ProfileRecord) -> Option<DisplayName<'a>
 pub struct Profile {
     pub timeline: Timeline,
     pub multi_subscriber: Option<MultiSubscriber>,
+
+    /// Whether we looked for the notes and profiles our initial notes
+    /// refer to
+    pub checked_unknowns: bool,
 }
 
 impl Profile {
@@ -70,14 +76,62 @@ impl Profile {
         Profile {
             timeline,
             multi_subscriber: None,
+            checked_unknowns: false,
         }
     }
 
+    /// One filter per kind of tab: notes (also used for the replies and
+    /// media tabs), the reactions they sent and the zaps they received
     fn filters_raw(pk: &[u8; 32]) -> Vec<FilterBuilder> {
-        vec![Filter::new()
-            .authors([pk])
-            .kinds([1])
-            .limit(filter::default_limit())]
+        vec![
+            Filter::new()
+                .authors([pk])
+                .kinds([1])
+                .limit(filter::default_limit()),
+            Filter::new()
+                .authors([pk])
+                .kinds([7])
+                .limit(filter::default_limit()),
+            Filter::new()
+                .kinds([9735])
+                .pubkeys([pk])
+                .limit(filter::default_limit()),
+        ]
+    }
+
+    /// Poll for new notes and insert them into the tabs they belong to
+    pub fn poll_notes_into_views(
+        &mut self,
+        txn: &Transaction,
+        ndb: &Ndb,
+        note_cache: &mut NoteCache,
+    ) -> Result<NoteRefsUnkIdAction> {
+        let multi_subscriber = self
+            .multi_subscriber
+            .as_mut()
+            .ok_or(Error::no_active_sub())?;
+        let note_refs = multi_subscriber.poll_for_notes(ndb, txn)?;
+        if note_refs.is_empty() {
+            return Ok(NoteRefsUnkIdAction::no_action());
+        }
+
+        let reversed = false;
+        for view in &mut self.timeline.views {
+            let filter = view.filter.filter();
+            let refs: Vec<NoteRef> = note_refs
+                .iter()
+                .filter(|nr| {
+                    ndb.get_note_by_key(txn, nr.key).map_or(false, |note| {
+                        filter(note_cache.cached_note_or_insert(nr.key, &note), &note)
+                    })
+                })
+                .copied()
+                .collect();
+
+            view.insert(&refs, reversed);
+        }
+
+        Ok(NoteRefsUnkIdAction::new(note_refs))
     }
 }
 
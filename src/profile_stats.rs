@@ -0,0 +1,460 @@
+//! Following and follower counts, mutual follows and pinned notes for the
+//! profile view.
+//!
+//! Following counts come from the profile's contact list. Nostrdb only
+//! knows the followers whose contact lists we happen to have, so we also
+//! ask relays that support NIP-45 to COUNT them and show the largest
+//! number anyone gave us.
+
+use crate::{
+    contacts::latest_contact_list,
+    lists::{count_tags, tag_str},
+    subscription_manager::SubLifetime,
+    subscriptions::{self, SubKind, Subscriptions},
+};
+
+use enostr::{Filter, NoteId, Pubkey, RelayPool, RelayStatus};
+use nostrdb::{Ndb, Note, Subscription, Transaction};
+use std::collections::{hash_map::Entry, HashMap, HashSet};
+use std::time::{Duration, Instant};
+use tracing::{debug, error};
+
+/// NIP-45: event counts
+const NIP45: u32 = 45;
+
+/// How long following counts and mutuals are good for, since they mean
+/// looking up contact lists
+const LOCAL_REFRESH: Duration = Duration::from_secs(5);
+
+/// How many follower contact lists we look at in nostrdb when we start
+/// showing a profile
+const MAX_LOCAL_FOLLOWERS: i32 = 10000;
+
+/// How long we hold on to a profile's stats after it was last shown
+const FORGET_AFTER: Duration = Duration::from_secs(60);
+
+/// How many mutual follows we show by name
+const MUTUALS_SHOWN: usize = 2;
+
+/// What we know about a profile's follows
+#[derive(Debug, Clone, Default)]
+pub struct ProfileCounts {
+    /// None until we have their contact list
+    pub following: Option<usize>,
+    pub followers: u64,
+
+    /// People the selected account follows who follow this profile
+    pub mutuals: Vec<Pubkey>,
+}
+
+struct LocalCounts {
+    account: Option<Pubkey>,
+    counts: ProfileCounts,
+    updated: Instant,
+}
+
+/// What we know about a profile we're showing
+struct Stats {
+    last_shown: Instant,
+
+    /// The relays we asked to count its followers
+    asked: HashSet<String>,
+
+    /// The biggest follower count relays gave us
+    remote_followers: u64,
+
+    /// Followers whose contact lists are in nostrdb
+    followers: HashSet<Pubkey>,
+
+    /// Watches nostrdb for more of them
+    followers_sub: Option<Subscription>,
+
+    local: Option<LocalCounts>,
+
+    /// Pinned notes we asked relays for, so we only ask once
+    pinned_wanted: HashSet<NoteId>,
+}
+
+impl Stats {
+    fn new(now: Instant) -> Self {
+        Stats {
+            last_shown: now,
+            asked: HashSet::new(),
+            remote_followers: 0,
+            followers: HashSet::new(),
+            followers_sub: None,
+            local: None,
+            pinned_wanted: HashSet::new(),
+        }
+    }
+
+    /// Pick up followers nostrdb got since we last looked. Returns true
+    /// if there were any.
+    fn update_followers(&mut self, ndb: &Ndb, txn: &Transaction, pk: &Pubkey) -> bool {
+        let sub = if let Some(sub) = self.followers_sub {
+            sub
+        } else {
+            // subscribe before we look, so we don't miss any in between
+            let filter = followers_filter(pk.bytes()).build();
+            match ndb.subscribe(&[filter.clone()]) {
+                Ok(sub) => self.followers_sub = Some(sub),
+                Err(err) => error!("error watching {pk}'s followers: {err}"),
+            }
+
+            if let Ok(results) = ndb.query(txn, &[filter], MAX_LOCAL_FOLLOWERS) {
+                self.followers
+                    .extend(results.into_iter().map(|r| Pubkey::new(*r.note.pubkey())));
+            }
+            return true;
+        };
+
+        let before = self.followers.len();
+        for key in ndb.poll_for_notes(sub, 100) {
+            if let Ok(note) = ndb.get_note_by_key(txn, key) {
+                self.followers.insert(Pubkey::new(*note.pubkey()));
+            }
+        }
+        self.followers.len() != before
+    }
+}
+
+#[derive(Default)]
+pub struct ProfileStats {
+    /// Profiles the ui showed, that we haven't fetched lists for yet
+    wanted: HashSet<Pubkey>,
+
+    profiles: HashMap<Pubkey, Stats>,
+
+    /// COUNT subscription ids and who they are counting
+    counting: HashMap<String, Pubkey>,
+}
+
+impl ProfileStats {
+    /// Counts for a profile we're showing, relative to the selected
+    /// account. Asks relays for anything we don't have yet.
+    pub fn counts(
+        &mut self,
+        ndb: &Ndb,
+        txn: &Transaction,
+        pk: &Pubkey,
+        account: Option<&Pubkey>,
+    ) -> ProfileCounts {
+        let now = Instant::now();
+        let stats = self.shown(pk, now);
+
+        let new_followers = stats.update_followers(ndb, txn, pk);
+        let stale = new_followers
+            || stats.local.as_ref().map_or(true, |local| {
+                local.account.as_ref() != account
+                    || now.duration_since(local.updated) > LOCAL_REFRESH
+            });
+
+        if stale {
+            stats.local = Some(LocalCounts {
+                account: account.copied(),
+                counts: local_counts(ndb, txn, pk, account, &stats.followers),
+                updated: now,
+            });
+        }
+
+        let mut counts = stats
+            .local
+            .as_ref()
+            .map(|local| local.counts.clone())
+            .unwrap_or_default();
+        counts.followers = counts.followers.max(stats.remote_followers);
+        counts
+    }
+
+    /// Whether to look up a pinned note we don't have. Only true the
+    /// first time, so we don't keep asking for notes nobody has.
+    pub fn want_pinned(&mut self, pk: &Pubkey, id: &NoteId) -> bool {
+        self.shown(pk, Instant::now()).pinned_wanted.insert(*id)
+    }
+
+    fn shown(&mut self, pk: &Pubkey, now: Instant) -> &mut Stats {
+        let stats = match self.profiles.entry(*pk) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                self.wanted.insert(*pk);
+                entry.insert(Stats::new(now))
+            }
+        };
+        stats.last_shown = now;
+        stats
+    }
+
+    /// Fetch the contact and pinned lists of profiles we started showing,
+    /// ask relays to count their followers, and forget about profiles we
+    /// stopped showing
+    pub fn poll(&mut self, ndb: &Ndb, subs: &mut Subscriptions, pool: &RelayPool) {
+        self.forget(ndb, Instant::now());
+
+        // relays that never answered get asked again
+        self.counting
+            .retain(|subid, _| subs.manager.is_counting(subid));
+
+        for pk in self.wanted.drain() {
+            let subid = subscriptions::new_sub_id();
+            subs.subs.insert(subid.clone(), SubKind::OneShot);
+            subs.manager
+                .subscribe(subid, profile_lists_filters(&pk), SubLifetime::OneShot);
+        }
+
+        // relays we connect to later get asked too
+        for (pk, stats) in &mut self.profiles {
+            for pool_relay in &pool.relays {
+                let relay = &pool_relay.relay;
+
+                if !matches!(relay.status, RelayStatus::Connected)
+                    || !relay.supports_nip(NIP45)
+                    || !stats.asked.insert(relay.url.clone())
+                {
+                    continue;
+                }
+
+                let subid = subscriptions::new_sub_id();
+                debug!("asking {} to count {}'s followers", relay.url, pk);
+                subs.manager.count_to(
+                    &relay.url,
+                    subid.clone(),
+                    vec![followers_filter(pk.bytes()).build()],
+                );
+                self.counting.insert(subid, *pk);
+            }
+        }
+    }
+
+    fn forget(&mut self, ndb: &Ndb, now: Instant) {
+        let forgotten: Vec<Pubkey> = self
+            .profiles
+            .iter()
+            .filter(|(_, stats)| now.duration_since(stats.last_shown) > FORGET_AFTER)
+            .map(|(pk, _)| *pk)
+            .collect();
+
+        for pk in &forgotten {
+            let stats = if let Some(stats) = self.profiles.remove(pk) {
+                stats
+            } else {
+                continue;
+            };
+
+            if let Some(sub) = stats.followers_sub {
+                if let Err(err) = ndb.unsubscribe(sub) {
+                    error!("error unsubscribing from {pk}'s followers: {err}");
+                }
+            }
+            self.wanted.remove(pk);
+        }
+
+        if !forgotten.is_empty() {
+            self.counting.retain(|_, pk| !forgotten.contains(pk));
+        }
+    }
+
+    /// A relay answered one of our COUNT requests
+    pub fn handle_count(&mut self, subid: &str, count: u64) {
+        let pk = if let Some(pk) = self.counting.remove(subid) {
+            pk
+        } else {
+            return;
+        };
+
+        if let Some(stats) = self.profiles.get_mut(&pk) {
+            stats.remote_followers = stats.remote_followers.max(count);
+        }
+    }
+}
+
+/// The contact list for the following count and the NIP-51 pinned notes
+fn profile_lists_filters(pk: &Pubkey) -> Vec<Filter> {
+    vec![
+        crate::contacts::contact_list_filter(pk.bytes()),
+        pinned_filter(pk.bytes()),
+    ]
+}
+
+fn pinned_filter(pk: &[u8; 32]) -> Filter {
+    Filter::new().authors([pk]).kinds([10001]).limit(1).build()
+}
+
+fn followers_filter(pk: &[u8; 32]) -> nostrdb::FilterBuilder {
+    Filter::new().kinds([3]).pubkeys([pk])
+}
+
+fn local_counts(
+    ndb: &Ndb,
+    txn: &Transaction,
+    pk: &Pubkey,
+    account: Option<&Pubkey>,
+    followers: &HashSet<Pubkey>,
+) -> ProfileCounts {
+    let following = latest_contact_list(ndb, txn, pk).map(|list| count_tags(&list, "p"));
+
+    let mutuals = account
+        .filter(|account| *account != pk)
+        .and_then(|account| latest_contact_list(ndb, txn, account))
+        .map(|list| mutual_follows(&list, followers))
+        .unwrap_or_default();
+
+    ProfileCounts {
+        following,
+        followers: followers.len() as u64,
+        mutuals,
+    }
+}
+
+/// The people on a contact list who are also followers, in contact list
+/// order
+fn mutual_follows(contact_list: &Note, followers: &HashSet<Pubkey>) -> Vec<Pubkey> {
+    let mut seen = HashSet::new();
+    contact_list
+        .tags()
+        .iter()
+        .filter(|tag| tag.count() >= 2 && tag_str(tag, 0) == Some("p"))
+        .filter_map(|tag| tag.get(1).and_then(|s| s.variant().id()))
+        .map(|id| Pubkey::new(*id))
+        .filter(|pk| followers.contains(pk) && seen.insert(*pk))
+        .collect()
+}
+
+/// "Followed by Alice", "Followed by Alice, Bob and 3 others you follow"
+pub fn mutuals_summary(names: &[String], total: usize) -> Option<String> {
+    let shown = &names[..names.len().min(MUTUALS_SHOWN)];
+    let others = total.saturating_sub(shown.len());
+
+    Some(match (shown, others) {
+        ([], _) => return None,
+        ([one], 0) => format!("Followed by {one}"),
+        ([one, two], 0) => format!("Followed by {one} and {two}"),
+        (names, 1) => format!("Followed by {} and 1 other you follow", names.join(", ")),
+        (names, n) => format!("Followed by {} and {n} others you follow", names.join(", ")),
+    })
+}
+
+/// The names we show in a mutual follows summary
+pub fn mutuals_shown(mutuals: &[Pubkey]) -> &[Pubkey] {
+    &mutuals[..mutuals.len().min(MUTUALS_SHOWN)]
+}
+
+/// The notes on a profile's NIP-51 pinned notes list, in list order
+pub fn pinned_note_ids(ndb: &Ndb, txn: &Transaction, pk: &Pubkey) -> Vec<NoteId> {
+    let list = ndb
+        .query(txn, &[pinned_filter(pk.bytes())], 10)
+        .ok()
+        .and_then(|results| {
+            results
+                .into_iter()
+                .map(|r| r.note)
+                .max_by_key(|note| note.created_at())
+        });
+
+    list.map_or_else(Vec::new, |list| {
+        list.tags()
+            .iter()
+            .filter(|tag| tag.count() >= 2 && tag_str(tag, 0) == Some("e"))
+            .filter_map(|tag| tag.get(1).and_then(|s| s.variant().id()))
+            .map(|id| NoteId::new(*id))
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use enostr::FullKeypair;
+    use nostrdb::NoteBuilder;
+
+    #[test]
+    fn test_mutual_follows() {
+        let kp = FullKeypair::generate();
+        let alice = FullKeypair::generate().pubkey;
+        let bob = FullKeypair::generate().pubkey;
+        let carol = FullKeypair::generate().pubkey;
+
+        let mut builder = NoteBuilder::new().kind(3).content("");
+        for pk in [&carol, &alice, &bob, &alice] {
+            builder = builder.start_tag().tag_str("p").tag_str(&pk.hex());
+        }
+        let list = builder
+            .sign(&kp.secret_key.to_secret_bytes())
+            .build()
+            .unwrap();
+
+        let followers: HashSet<Pubkey> = [alice, bob].into_iter().collect();
+        assert_eq!(mutual_follows(&list, &followers), vec![alice, bob]);
+    }
+
+    #[test]
+    fn test_mutuals_summary() {
+        let names = |n: &[&str]| n.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(mutuals_summary(&[], 0), None);
+        assert_eq!(
+            mutuals_summary(&names(&["alice"]), 1).unwrap(),
+            "Followed by alice"
+        );
+        assert_eq!(
+            mutuals_summary(&names(&["alice", "bob"]), 2).unwrap(),
+            "Followed by alice and bob"
+        );
+        assert_eq!(
+            mutuals_summary(&names(&["alice", "bob"]), 3).unwrap(),
+            "Followed by alice, bob and 1 other you follow"
+        );
+        assert_eq!(
+            mutuals_summary(&names(&["alice", "bob"]), 12).unwrap(),
+            "Followed by alice, bob and 10 others you follow"
+        );
+    }
+
+    #[test]
+    fn test_handle_count_keeps_largest() {
+        let mut stats = ProfileStats::default();
+        let pk = FullKeypair::generate().pubkey;
+        stats.profiles.insert(pk, Stats::new(Instant::now()));
+        stats.counting.insert("a".to_owned(), pk);
+        stats.counting.insert("b".to_owned(), pk);
+
+        stats.handle_count("a", 120);
+        stats.handle_count("b", 80);
+        stats.handle_count("unknown", 500);
+
+        assert_eq!(stats.profiles[&pk].remote_followers, 120);
+        assert!(stats.counting.is_empty());
+    }
+
+    #[test]
+    fn test_pinned_notes_wanted_once() {
+        let mut stats = ProfileStats::default();
+        let pk = FullKeypair::generate().pubkey;
+        let id = NoteId::new([1; 32]);
+
+        assert!(stats.want_pinned(&pk, &id));
+        assert!(!stats.want_pinned(&pk, &id));
+        assert!(stats.wanted.contains(&pk));
+    }
+
+    #[test]
+    fn test_forgets_profiles_no_longer_shown() {
+        let dir = tempfile::TempDir::new().expect("tmp dir");
+        let ndb = Ndb::new(dir.path().to_str().unwrap(), &nostrdb::Config::new()).expect("ndb");
+
+        let mut stats = ProfileStats::default();
+        let gone = FullKeypair::generate().pubkey;
+        let shown = FullKeypair::generate().pubkey;
+        let now = Instant::now();
+        stats.shown(&gone, now);
+        stats.shown(&shown, now + FORGET_AFTER);
+        stats.counting.insert("a".to_owned(), gone);
+        stats.counting.insert("b".to_owned(), shown);
+
+        stats.forget(&ndb, now + FORGET_AFTER + Duration::from_secs(1));
+
+        assert!(!stats.profiles.contains_key(&gone));
+        assert!(!stats.wanted.contains(&gone));
+        assert!(stats.profiles.contains_key(&shown));
+        assert_eq!(stats.counting.len(), 1);
+    }
+}
@@ -0,0 +1,252 @@
+use crate::storage::{write_file, DataPath, DataPathType, Directory};
+use crate::{Error, Result};
+use enostr::{Proxy, RelayPool};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+
+const FILE_NAME: &str = "proxy.json";
+
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProxySettings {
+    /// ie. `socks5h://127.0.0.1:9050` for Tor
+    pub url: Option<String>,
+
+    /// Only connect to .onion relays while the proxy is on
+    pub block_clearnet: bool,
+}
+
+impl ProxySettings {
+    /// The parsed proxy, if one is set. An invalid proxy is an error: we
+    /// never fall back to connecting directly.
+    pub fn proxy(&self) -> enostr::Result<Option<Proxy>> {
+        match self.url.as_deref().filter(|url| !url.trim().is_empty()) {
+            Some(url) => Proxy::parse(url).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+/// How our http requests get out. Clones share the same connection pool,
+/// so the parts of the app that make requests keep a copy, and get a new
+/// one when the proxy changes.
+#[derive(Clone)]
+pub enum Http {
+    Direct(reqwest::Client),
+    Proxied(reqwest::Client),
+
+    /// Our proxy is broken, so we refuse to make requests rather than
+    /// make them in the clear
+    Refused(String),
+}
+
+impl Default for Http {
+    fn default() -> Self {
+        Http::Direct(reqwest::Client::new())
+    }
+}
+
+impl Http {
+    pub fn new(settings: &ProxySettings) -> Self {
+        let proxy = match settings.proxy() {
+            Ok(Some(proxy)) => proxy,
+            Ok(None) => return Http::default(),
+            Err(err) => return Http::Refused(err.to_string()),
+        };
+
+        let client = reqwest::Proxy::all(proxy.url())
+            .and_then(|proxy| reqwest::Client::builder().proxy(proxy).build());
+        match client {
+            Ok(client) => Http::Proxied(client),
+            Err(err) => Http::Refused(format!("couldn't set up the proxy: {err}")),
+        }
+    }
+
+    /// A reqwest client that goes through our proxy
+    pub fn client(&self) -> Result<reqwest::Client> {
+        match self {
+            Http::Direct(client) | Http::Proxied(client) => Ok(client.clone()),
+            Http::Refused(err) => Err(Error::Generic(format!(
+                "not connecting while the proxy is broken: {err}"
+            ))),
+        }
+    }
+
+    /// Drop-in for `ehttp::fetch` that honours our proxy. ehttp can't use
+    /// a proxy, so proxied requests are made with reqwest instead.
+    pub fn fetch(
+        &self,
+        request: ehttp::Request,
+        on_done: impl 'static + Send + FnOnce(ehttp::Result<ehttp::Response>),
+    ) {
+        match self {
+            Http::Direct(_) => ehttp::fetch(request, on_done),
+            Http::Proxied(client) => {
+                let client = client.clone();
+                tokio::spawn(async move { on_done(fetch_proxied(client, request).await) });
+            }
+            Http::Refused(err) => on_done(Err(format!(
+                "not connecting while the proxy is broken: {err}"
+            ))),
+        }
+    }
+}
+
+/// Our proxy settings, and where they're saved. All network traffic
+/// (relays, images, NIP-05 and NIP-11 lookups) goes through the proxy
+/// once one is set.
+pub struct ProxyManager {
+    pub settings: ProxySettings,
+    pub http: Http,
+    directory: Option<Directory>,
+}
+
+impl ProxyManager {
+    /// Load the saved settings. A proxy given on the command line takes
+    /// precedence over the saved one.
+    pub fn new(path: &DataPath, arg_proxy: Option<String>) -> Self {
+        let directory = Directory::new(path.path(DataPathType::Setting));
+        let mut settings = load_settings(&directory);
+        if arg_proxy.is_some() {
+            settings.url = arg_proxy;
+        }
+
+        ProxyManager {
+            http: Http::new(&settings),
+            settings,
+            directory: Some(directory),
+        }
+    }
+
+    pub fn disabled() -> Self {
+        ProxyManager {
+            settings: ProxySettings::default(),
+            http: Http::default(),
+            directory: None,
+        }
+    }
+
+    /// Why we aren't connecting to anything, if the proxy is broken
+    pub fn error(&self) -> Option<String> {
+        self.settings.proxy().err().map(|err| err.to_string())
+    }
+
+    /// Start using new settings: save them and reconnect our relays
+    /// through the new proxy. Callers hand the new [`ProxyManager::http`]
+    /// to whatever keeps a copy.
+    pub fn apply(
+        &mut self,
+        settings: ProxySettings,
+        pool: &mut RelayPool,
+        wakeup: impl Fn() + Send + Sync + Clone + 'static,
+    ) {
+        self.http = Http::new(&settings);
+        self.settings = settings;
+        self.save_settings();
+        self.setup_pool(pool, wakeup);
+    }
+
+    /// Point the pool at our proxy. Call this before adding relays, so we
+    /// never connect to them directly.
+    pub fn setup_pool(
+        &self,
+        pool: &mut RelayPool,
+        wakeup: impl Fn() + Send + Sync + Clone + 'static,
+    ) {
+        let proxy = self.settings.proxy();
+        match &proxy {
+            Ok(Some(proxy)) => info!("using proxy {}", proxy.url()),
+            Ok(None) => info!("not using a proxy"),
+            Err(err) => error!("not connecting to anything, the proxy is broken: {err}"),
+        }
+
+        pool.set_proxy(proxy, self.settings.block_clearnet, wakeup);
+    }
+
+    fn save_settings(&self) {
+        let directory = if let Some(directory) = &self.directory {
+            directory
+        } else {
+            return;
+        };
+
+        match serde_json::to_string(&self.settings) {
+            Ok(json) => {
+                if let Err(err) = write_file(&directory.file_path, FILE_NAME.to_owned(), &json) {
+                    error!("error saving proxy settings: {err}");
+                }
+            }
+            Err(err) => error!("error serializing proxy settings: {err}"),
+        }
+    }
+}
+
+fn load_settings(directory: &Directory) -> ProxySettings {
+    match directory.get_file(FILE_NAME.to_owned()) {
+        Ok(json) => serde_json::from_str(&json).unwrap_or_else(|err| {
+            error!("invalid proxy settings, using defaults: {err}");
+            ProxySettings::default()
+        }),
+        Err(_) => ProxySettings::default(),
+    }
+}
+
+async fn fetch_proxied(
+    client: reqwest::Client,
+    request: ehttp::Request,
+) -> ehttp::Result<ehttp::Response> {
+    let method =
+        reqwest::Method::from_bytes(request.method.as_bytes()).map_err(|e| e.to_string())?;
+
+    let mut builder = client.request(method, &request.url).body(request.body);
+    for (key, value) in &request.headers {
+        builder = builder.header(key, value);
+    }
+
+    let response = builder.send().await.map_err(|e| e.to_string())?;
+    let status = response.status();
+    let url = response.url().to_string();
+    let headers = response
+        .headers()
+        .iter()
+        .map(|(key, value)| {
+            (
+                key.to_string(),
+                value.to_str().unwrap_or_default().to_owned(),
+            )
+        })
+        .collect();
+    let bytes = response.bytes().await.map_err(|e| e.to_string())?.to_vec();
+
+    Ok(ehttp::Response {
+        url,
+        ok: status.is_success(),
+        status: status.as_u16(),
+        status_text: status.canonical_reason().unwrap_or_default().to_owned(),
+        bytes,
+        headers,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_broken_proxy_refuses_requests() {
+        let settings = ProxySettings {
+            url: Some("http://127.0.0.1:9050".to_owned()),
+            block_clearnet: false,
+        };
+        assert!(settings.proxy().is_err());
+
+        let http = Http::new(&settings);
+        assert!(matches!(http, Http::Refused(_)));
+        assert!(http.client().is_err());
+
+        assert!(matches!(
+            Http::new(&ProxySettings::default()),
+            Http::Direct(_)
+        ));
+    }
+}
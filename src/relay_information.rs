@@ -0,0 +1,181 @@
+use crate::proxy::Http;
+use crate::storage::{write_file, DataPath, DataPathType, Directory};
+use crate::{Error, Result};
+use enostr::{RelayInformation, RelayPool};
+use poll_promise::Promise;
+use std::collections::{HashMap, HashSet};
+use tracing::{debug, error};
+
+/// Fetches NIP-11 relay information documents for the relays in our pool
+/// and attaches them to their relays once they arrive.
+///
+/// Documents are cached on disk, so we know a relay's limits as soon as
+/// we connect to it. We still fetch a fresh copy once per session.
+#[derive(Default)]
+pub struct RelayInformationFetcher {
+    pending: HashMap<String, Promise<Result<RelayInformation>>>,
+    requested: HashSet<String>,
+    cache: Option<Directory>,
+}
+
+impl RelayInformationFetcher {
+    pub fn new(path: &DataPath) -> Self {
+        let cache = Directory::new(path.path(DataPathType::Cache).join("relay_info"));
+
+        RelayInformationFetcher {
+            cache: Some(cache),
+            ..Default::default()
+        }
+    }
+
+    /// Start fetches for relays we haven't asked yet and attach any
+    /// finished documents. Returns the urls of relays that just got their
+    /// information document, so callers can send them anything that was
+    /// waiting on it (ie. NIP-50 searches).
+    pub fn poll(&mut self, ctx: &egui::Context, http: &Http, pool: &mut RelayPool) -> Vec<String> {
+        let mut updated = vec![];
+
+        for pool_relay in &mut pool.relays {
+            let relay = &mut pool_relay.relay;
+
+            if !self.requested.contains(&relay.url) {
+                self.requested.insert(relay.url.clone());
+
+                if relay.info.is_none() {
+                    if let Some(info) = self.load_cached(&relay.url) {
+                        debug!("using cached relay information for {}", relay.url);
+                        relay.info = Some(info);
+                        updated.push(relay.url.clone());
+                    }
+                }
+
+                self.pending.insert(
+                    relay.url.clone(),
+                    fetch_relay_information(ctx, http, &relay.url),
+                );
+                continue;
+            }
+
+            let promise = if let Some(promise) = self.pending.remove(&relay.url) {
+                promise
+            } else {
+                continue;
+            };
+
+            match promise.try_take() {
+                Ok(Ok(info)) => {
+                    debug!("got relay information for {}: {:?}", relay.url, info);
+                    self.save_cached(&relay.url, &info);
+                    if relay.info.as_ref() != Some(&info) {
+                        relay.info = Some(info);
+                        updated.push(relay.url.clone());
+                    }
+                }
+                Ok(Err(err)) => {
+                    error!("error fetching relay information for {}: {err}", relay.url);
+                }
+                Err(promise) => {
+                    self.pending.insert(relay.url.clone(), promise);
+                }
+            }
+        }
+
+        updated
+    }
+
+    fn load_cached(&self, relay_url: &str) -> Option<RelayInformation> {
+        let json = self
+            .cache
+            .as_ref()?
+            .get_file(cache_file_name(relay_url))
+            .ok()?;
+        RelayInformation::from_json(&json).ok()
+    }
+
+    fn save_cached(&self, relay_url: &str, info: &RelayInformation) {
+        let cache = if let Some(cache) = &self.cache {
+            cache
+        } else {
+            return;
+        };
+
+        let result = serde_json::to_string(info)
+            .map_err(|e| Error::Generic(e.to_string()))
+            .and_then(|json| write_file(&cache.file_path, cache_file_name(relay_url), &json));
+
+        if let Err(e) = result {
+            error!("could not cache relay information for {relay_url}: {e}");
+        }
+    }
+}
+
+/// A file name for a relay url, ie. `wss___relay_damus_io.json`
+fn cache_file_name(relay_url: &str) -> String {
+    let name: String = relay_url
+        .trim_end_matches('/')
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    format!("{name}.json")
+}
+
+pub fn fetch_relay_information(
+    ctx: &egui::Context,
+    http: &Http,
+    relay_url: &str,
+) -> Promise<Result<RelayInformation>> {
+    let (sender, promise) = Promise::new();
+
+    let url = if let Some(url) = RelayInformation::http_url(relay_url) {
+        url
+    } else {
+        sender.send(Err(Error::Generic(format!(
+            "can't fetch relay information for {relay_url}"
+        ))));
+        return promise;
+    };
+
+    let mut request = ehttp::Request::get(url);
+    request
+        .headers
+        .insert("Accept".to_owned(), "application/nostr+json".to_owned());
+
+    let ctx = ctx.clone();
+    http.fetch(request, move |response| {
+        let info = response.map_err(Error::Generic).and_then(|resp| {
+            if !resp.ok {
+                return Err(Error::Generic(format!(
+                    "relay information request failed: {} {}",
+                    resp.status, resp.status_text
+                )));
+            }
+
+            let json = std::str::from_utf8(&resp.bytes)
+                .map_err(|e| Error::Generic(format!("relay information not utf8: {e}")))?;
+
+            Ok(RelayInformation::from_json(json)?)
+        });
+
+        sender.send(info);
+        ctx.request_repaint();
+    });
+
+    promise
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_file_name() {
+        assert_eq!(
+            cache_file_name("wss://relay.damus.io/"),
+            "wss___relay_damus_io.json"
+        );
+        assert_eq!(
+            cache_file_name("ws://localhost:8080"),
+            "ws___localhost_8080.json"
+        );
+    }
+}
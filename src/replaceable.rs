@@ -0,0 +1,224 @@
+//! Replaceable notes (kind 0 metadata, kind 3 contact lists) replace each
+//! other as a whole, so an edit made to a stale copy wipes out changes made
+//! from other clients since. Before we edit one of ours we ask our relays
+//! for the newest copy, and we only edit once nostrdb has a copy at least
+//! as new as anything they sent us.
+
+use crate::{
+    subscription_manager::SubLifetime,
+    subscriptions::{self, SubKind, Subscriptions},
+};
+
+use enostr::{Filter, Pubkey};
+use nostrdb::{Ndb, Note, Transaction};
+use serde_json::Value;
+use std::time::{Duration, Instant};
+
+/// How long we give relays to send us the newest copy
+const FETCH_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// How long nostrdb gets to catch up with a copy a relay sent us before
+/// we give up on it
+const INGEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub fn newest_filter(pk: &[u8; 32], kind: u64) -> Filter {
+    Filter::new().authors([pk]).kinds([kind]).limit(1).build()
+}
+
+/// The newest copy of a pubkey's replaceable note of this kind in nostrdb
+pub fn latest_note<'a>(
+    ndb: &Ndb,
+    txn: &'a Transaction,
+    pk: &Pubkey,
+    kind: u64,
+) -> Option<Note<'a>> {
+    ndb.query(txn, &[newest_filter(pk.bytes(), kind)], 10)
+        .ok()?
+        .into_iter()
+        .map(|r| r.note)
+        .max_by_key(|note| note.created_at())
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum FetchError {
+    /// None of the relays that are up finished sending us their copies in
+    /// time. Relays that are down don't hold us up.
+    Unreachable,
+
+    /// Relays sent us a newer copy than the one nostrdb has
+    Stale,
+}
+
+/// Fetching the newest copy of a replaceable note from our relays
+pub struct NewestFetch {
+    pub subid: String,
+    pk: Pubkey,
+    kind: u64,
+
+    /// The newest created_at of the copies relays sent us
+    newest_remote: Option<u64>,
+
+    requested: Instant,
+
+    /// When every relay finished sending us their copy
+    fetched: Option<Instant>,
+}
+
+impl NewestFetch {
+    /// Ask our relays for the newest copy. `sub_kind` is how the app
+    /// routes the subscription's EOSE back to us.
+    pub fn request(subs: &mut Subscriptions, sub_kind: SubKind, pk: Pubkey, kind: u64) -> Self {
+        let subid = subscriptions::new_sub_id();
+        subs.subs.insert(subid.clone(), sub_kind);
+        subs.manager.subscribe(
+            subid.clone(),
+            vec![newest_filter(pk.bytes(), kind)],
+            SubLifetime::OneShot,
+        );
+
+        NewestFetch {
+            subid,
+            pk,
+            kind,
+            newest_remote: None,
+            requested: Instant::now(),
+            fetched: None,
+        }
+    }
+
+    /// Keep track of how new the copies relays are sending us are, so we
+    /// don't edit an older one
+    pub fn handle_event(&mut self, subid: &str, msg: &str) {
+        if subid != self.subid {
+            return;
+        }
+
+        let created_at = serde_json::from_str::<Value>(msg)
+            .ok()
+            .and_then(|msg| msg.get(2)?.get("created_at")?.as_u64());
+
+        if let Some(created_at) = created_at {
+            self.newest_remote = Some(self.newest_remote.unwrap_or(0).max(created_at));
+        }
+    }
+
+    /// Every relay is done sending us their copy
+    pub fn fetched(&mut self, subid: &str) {
+        if subid == self.subid {
+            self.fetched = Some(Instant::now());
+        }
+    }
+
+    /// None while we're still waiting, otherwise whether nostrdb's copy is
+    /// the newest one
+    pub fn poll(&self, ndb: &Ndb, now: Instant) -> Option<Result<(), FetchError>> {
+        if let Some(fetched) = self.fetched {
+            let txn = Transaction::new(ndb).expect("txn");
+            let local = latest_note(ndb, &txn, &self.pk, self.kind).map(|n| n.created_at());
+
+            if is_local_current(local, self.newest_remote) {
+                Some(Ok(()))
+            } else if now.duration_since(fetched) > INGEST_TIMEOUT {
+                Some(Err(FetchError::Stale))
+            } else {
+                None
+            }
+        } else if now.duration_since(self.requested) > FETCH_TIMEOUT {
+            Some(Err(FetchError::Unreachable))
+        } else {
+            None
+        }
+    }
+}
+
+/// Can we edit our local copy? Only if it's at least as new as the
+/// newest copy relays sent us.
+fn is_local_current(local: Option<u64>, remote: Option<u64>) -> bool {
+    match (local, remote) {
+        (_, None) => true,
+        (None, Some(_)) => false,
+        (Some(local), Some(remote)) => local >= remote,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::subscription_manager::{RelayTarget, SubscriptionTransport};
+    use enostr::{ClientMessage, FullKeypair};
+
+    #[test]
+    fn test_refuse_stale_local_copy() {
+        assert!(is_local_current(Some(10), None));
+        assert!(is_local_current(None, None));
+        assert!(is_local_current(Some(10), Some(10)));
+        assert!(!is_local_current(Some(9), Some(10)));
+        assert!(!is_local_current(None, Some(10)));
+    }
+
+    #[test]
+    fn test_handle_event_tracks_newest() {
+        let mut subs = Subscriptions::default();
+        let pk = FullKeypair::generate().pubkey;
+        let mut fetch = NewestFetch::request(&mut subs, SubKind::ContactList, pk, 3);
+        let subid = fetch.subid.clone();
+
+        fetch.handle_event(
+            &subid,
+            &format!(r#"["EVENT","{subid}",{{"created_at":20}}]"#),
+        );
+        fetch.handle_event(
+            &subid,
+            &format!(r#"["EVENT","{subid}",{{"created_at":10}}]"#),
+        );
+        fetch.handle_event("other", r#"["EVENT","other",{"created_at":30}]"#);
+
+        assert_eq!(fetch.newest_remote, Some(20));
+    }
+
+    /// A single relay that remembers the REQ ids we send it
+    struct OneRelay(Vec<String>);
+
+    impl SubscriptionTransport for OneRelay {
+        fn relays(&self) -> Vec<RelayTarget> {
+            vec![RelayTarget {
+                url: "wss://a".to_owned(),
+                connected: true,
+                max_filters: None,
+                max_subscriptions: None,
+            }]
+        }
+
+        fn send_to(&mut self, _relay_url: &str, msg: &ClientMessage) {
+            if let ClientMessage::Req { sub_id, .. } = msg {
+                self.0.push(sub_id.clone());
+            }
+        }
+    }
+
+    #[test]
+    fn test_handle_event_from_coalesced_req() {
+        let mut subs = Subscriptions::default();
+        let pk = FullKeypair::generate().pubkey;
+        let mut fetch = NewestFetch::request(&mut subs, SubKind::ContactList, pk, 3);
+        subs.manager.subscribe(
+            "other".to_owned(),
+            vec![Filter::new().kinds([0]).build()],
+            SubLifetime::OneShot,
+        );
+
+        let mut relay = OneRelay(vec![]);
+        subs.manager.poll(&mut relay);
+        // both one-shots went out as one REQ with an id of its own
+        assert_eq!(relay.0.len(), 1);
+        let wire_id = &relay.0[0];
+        assert_ne!(wire_id, &fetch.subid);
+
+        let msg = format!(r#"["EVENT","{wire_id}",{{"created_at":20}}]"#);
+        for subid in subs.manager.members("wss://a", wire_id) {
+            fetch.handle_event(&subid, &msg);
+        }
+
+        assert_eq!(fetch.newest_remote, Some(20));
+    }
+}
@@ -9,19 +9,31 @@ use crate::{
     timeline::{TimelineId, TimelineRoute},
     ui::{
         add_column::AddColumnRoute,
+        article::get_article_title_string,
         profile::preview::{get_note_users_displayname_string, get_profile_displayname_string},
     },
 };
 
 /// App routing. These describe different places you can go inside Notedeck.
-#[derive(Clone, Copy, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
 pub enum Route {
     Timeline(TimelineRoute),
     Accounts(AccountsRoute),
     Relays,
+    /// The relay in the relay pool with this url
+    RelayDetail(String),
+    AddRelay,
+    Network,
+    ProxySettings,
+    /// The selected account's media settings
+    MediaSettings,
+    /// The selected account's profile
+    EditProfile,
     ComposeNote,
     AddColumn(AddColumnRoute),
     Support,
+    Search,
+    NotificationSettings,
 }
 
 #[derive(Clone)]
@@ -69,6 +81,10 @@ impl Route {
         Route::Timeline(TimelineRoute::Quote(quoting))
     }
 
+    pub fn article(article: NoteId) -> Self {
+        Route::Timeline(TimelineRoute::Article(article))
+    }
+
     pub fn accounts() -> Self {
         Route::Accounts(AccountsRoute::Accounts)
     }
@@ -77,6 +93,10 @@ impl Route {
         Route::Accounts(AccountsRoute::AddAccount)
     }
 
+    pub fn create_account() -> Self {
+        Route::Accounts(AccountsRoute::CreateAccount)
+    }
+
     pub fn get_titled_route(&self, columns: &Columns, ndb: &Ndb) -> TitledRoute {
         let title = match self {
             Route::Timeline(tlr) => match tlr {
@@ -98,13 +118,21 @@ impl Route {
                 TimelineRoute::Profile(pubkey) => {
                     format!("{}'s Profile", get_profile_displayname_string(ndb, pubkey))
                 }
+                TimelineRoute::Article(id) => get_article_title_string(ndb, id),
             },
 
             Route::Relays => "Relays".to_owned(),
+            Route::RelayDetail(_) => "Relay".to_owned(),
+            Route::AddRelay => "Add Relay".to_owned(),
+            Route::Network => "Network".to_owned(),
+            Route::ProxySettings => "Proxy".to_owned(),
+            Route::MediaSettings => "Media".to_owned(),
+            Route::EditProfile => "Edit Profile".to_owned(),
 
             Route::Accounts(amr) => match amr {
                 AccountsRoute::Accounts => "Accounts".to_owned(),
                 AccountsRoute::AddAccount => "Add Account".to_owned(),
+                AccountsRoute::CreateAccount => "Create Account".to_owned(),
             },
             Route::ComposeNote => "Compose Note".to_owned(),
             Route::AddColumn(c) => match c {
@@ -114,13 +142,16 @@ impl Route {
                     "Add External Notifications Column".to_owned()
                 }
                 AddColumnRoute::Hashtag => "Add Hashtag Column".to_owned(),
+                AddColumnRoute::Lists => "Add List Column".to_owned(),
             },
             Route::Support => "Damus Support".to_owned(),
+            Route::Search => "Search".to_owned(),
+            Route::NotificationSettings => "Notification Settings".to_owned(),
         };
 
         TitledRoute {
             title,
-            route: *self,
+            route: self.clone(),
         }
     }
 }
@@ -172,6 +203,14 @@ impl<R: Clone> Router<R> {
         self.routes.get(self.routes.len() - 2).cloned()
     }
 
+    /// Forget the route under the top one, so going back skips over it
+    pub fn remove_previous_route(&mut self) {
+        let num_routes = self.routes.len();
+        if num_routes > 2 {
+            self.routes.remove(num_routes - 2);
+        }
+    }
+
     /// Pop a route, should only be called on a NavRespose::Returned reseponse
     pub fn pop(&mut self) -> Option<R> {
         if self.routes.len() == 1 {
@@ -214,18 +253,28 @@ impl fmt::Display for Route {
                 TimelineRoute::Profile(_id) => write!(f, "Profile"),
                 TimelineRoute::Reply(_id) => write!(f, "Reply"),
                 TimelineRoute::Quote(_id) => write!(f, "Quote"),
+                TimelineRoute::Article(_id) => write!(f, "Article"),
             },
 
             Route::Relays => write!(f, "Relays"),
+            Route::RelayDetail(_) => write!(f, "Relay"),
+            Route::AddRelay => write!(f, "Add Relay"),
+            Route::Network => write!(f, "Network"),
+            Route::ProxySettings => write!(f, "Proxy"),
+            Route::MediaSettings => write!(f, "Media"),
+            Route::EditProfile => write!(f, "Edit Profile"),
 
             Route::Accounts(amr) => match amr {
                 AccountsRoute::Accounts => write!(f, "Accounts"),
                 AccountsRoute::AddAccount => write!(f, "Add Account"),
+                AccountsRoute::CreateAccount => write!(f, "Create Account"),
             },
             Route::ComposeNote => write!(f, "Compose Note"),
 
             Route::AddColumn(_) => write!(f, "Add Column"),
             Route::Support => write!(f, "Support"),
+            Route::Search => write!(f, "Search"),
+            Route::NotificationSettings => write!(f, "Notification Settings"),
         }
     }
 }
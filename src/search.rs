@@ -0,0 +1,265 @@
+use crate::{
+    filter,
+    note::NoteRef,
+    subscription_manager::SubLifetime,
+    subscriptions::{self, SubKind, Subscriptions},
+    timeline::{TimelineTab, ViewFilter},
+    Result,
+};
+
+use enostr::{Filter, Pubkey, RelayPool, RelayStatus};
+use nostrdb::{Ndb, Note, NoteKey, Transaction};
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+use tracing::{debug, error, info};
+
+/// NIP-50: search capability
+pub const NIP50: u32 = 50;
+
+/// How many profiles we show at the top of search results
+const PROFILE_RESULTS_LIMIT: u32 = 10;
+
+/// nostrdb ingests remote results in the background, so we keep
+/// re-running the local query for a little while after sending a search
+/// or getting an EOSE back from a search relay.
+const REFRESH_WINDOW: Duration = Duration::from_secs(3);
+const REFRESH_INTERVAL: Duration = Duration::from_millis(500);
+
+pub fn search_filter(query: &str) -> Filter {
+    Filter::new()
+        .search(query)
+        .kinds([1])
+        .limit(filter::default_limit())
+        .build()
+}
+
+/// Local subscriptions can't evaluate fulltext filters, so we double
+/// check notes coming in on a search timeline against the query
+pub fn note_matches_query(query: &str, note: &Note) -> bool {
+    let content = note.content().to_lowercase();
+    query
+        .split_whitespace()
+        .all(|term| content.contains(&term.to_lowercase()))
+}
+
+/// The state of the search view in a column. Local results come from
+/// nostrdb's fulltext index, remote results are requested from relays
+/// that advertise NIP-50 in their information document. Remote notes
+/// end up in nostrdb, so both are merged by simply re-running the local
+/// query.
+pub struct Search {
+    /// The text in the search box
+    pub query: String,
+
+    /// The query the current results are for
+    searched: String,
+    notes: TimelineTab,
+    profiles: Vec<Pubkey>,
+    seen: HashSet<NoteKey>,
+
+    /// relay url -> subscription id of in-flight NIP-50 requests
+    remote: HashMap<String, String>,
+    searched_relays: HashSet<String>,
+
+    refresh_until: Option<Instant>,
+    last_refresh: Instant,
+}
+
+impl Default for Search {
+    fn default() -> Self {
+        Search {
+            query: String::new(),
+            searched: String::new(),
+            notes: TimelineTab::new(ViewFilter::NotesAndReplies),
+            profiles: vec![],
+            seen: HashSet::new(),
+            remote: HashMap::new(),
+            searched_relays: HashSet::new(),
+            refresh_until: None,
+            last_refresh: Instant::now(),
+        }
+    }
+}
+
+impl Search {
+    pub fn searched(&self) -> &str {
+        &self.searched
+    }
+
+    pub fn notes(&self) -> &TimelineTab {
+        &self.notes
+    }
+
+    pub fn profiles(&self) -> &[Pubkey] {
+        &self.profiles
+    }
+
+    /// Number of relays we're still waiting on
+    pub fn pending_relays(&self) -> usize {
+        self.remote.len()
+    }
+
+    /// Start a new search with the current query, throwing away the old
+    /// results and any in-flight remote requests
+    pub fn submit(&mut self, ndb: &Ndb, subs: &mut Subscriptions, pool: &mut RelayPool) {
+        let query = self.query.trim().to_owned();
+        self.close(subs);
+
+        self.notes = TimelineTab::new(ViewFilter::NotesAndReplies);
+        self.profiles.clear();
+        self.seen.clear();
+        self.searched_relays.clear();
+        self.searched = query;
+
+        if self.searched.is_empty() {
+            return;
+        }
+
+        info!("searching for '{}'", self.searched);
+
+        if let Err(err) = self.query_local(ndb) {
+            error!("local search failed: {err}");
+        }
+
+        self.send_remote(subs, pool);
+        self.refresh_until = Some(Instant::now() + REFRESH_WINDOW);
+    }
+
+    /// Called every frame while the search view is open. Sends the search
+    /// to relays we just learned support NIP-50, and picks up any remote
+    /// results that made it into nostrdb.
+    pub fn poll(&mut self, ndb: &Ndb, subs: &mut Subscriptions, pool: &mut RelayPool) {
+        if self.searched.is_empty() {
+            return;
+        }
+
+        self.send_remote(subs, pool);
+
+        let refresh_until = if let Some(refresh_until) = self.refresh_until {
+            refresh_until
+        } else {
+            return;
+        };
+
+        let now = Instant::now();
+        if now > refresh_until {
+            self.refresh_until = None;
+        }
+
+        if now - self.last_refresh < REFRESH_INTERVAL {
+            return;
+        }
+
+        if let Err(err) = self.query_local(ndb) {
+            error!("local search refresh failed: {err}");
+        }
+    }
+
+    /// A search relay finished sending us results. Returns false if the
+    /// subscription wasn't ours.
+    fn remote_eose(&mut self, subid: &str) -> bool {
+        let before = self.remote.len();
+        self.remote.retain(|_relay, id| id != subid);
+        if self.remote.len() == before {
+            return false;
+        }
+
+        self.refresh_until = Some(Instant::now() + REFRESH_WINDOW);
+        true
+    }
+
+    /// Close any in-flight remote requests
+    pub fn close(&mut self, subs: &mut Subscriptions) {
+        for (_relay_url, subid) in self.remote.drain() {
+            subs.subs.remove(&subid);
+            subs.manager.unsubscribe(&subid);
+        }
+        self.refresh_until = None;
+    }
+
+    fn send_remote(&mut self, subs: &mut Subscriptions, pool: &mut RelayPool) {
+        for pool_relay in &mut pool.relays {
+            let relay = &mut pool_relay.relay;
+
+            if !matches!(relay.status, RelayStatus::Connected) || !relay.supports_nip(NIP50) {
+                continue;
+            }
+
+            // we only search each relay once per query
+            if !self.searched_relays.insert(relay.url.clone()) {
+                continue;
+            }
+
+            let subid = subscriptions::new_sub_id();
+            subs.subs.insert(subid.clone(), SubKind::Search);
+            self.remote.insert(relay.url.clone(), subid.clone());
+
+            debug!("sending search '{}' to {}", self.searched, relay.url);
+            let filter = search_filter(&self.searched).limit_mut(filter::default_remote_limit());
+            subs.manager
+                .subscribe_to(&relay.url, subid, vec![filter], SubLifetime::OneShot);
+        }
+    }
+
+    fn query_local(&mut self, ndb: &Ndb) -> Result<()> {
+        self.last_refresh = Instant::now();
+
+        let txn = Transaction::new(ndb)?;
+        let filter = search_filter(&self.searched);
+        let lim = filter.limit().unwrap_or(filter::default_limit()) as i32;
+
+        let mut new_refs: Vec<NoteRef> = ndb
+            .query(&txn, &[filter], lim)?
+            .into_iter()
+            .map(NoteRef::from_query_result)
+            .filter(|nr| self.seen.insert(nr.key))
+            .collect();
+
+        if !new_refs.is_empty() {
+            debug!(
+                "{} new results for search '{}'",
+                new_refs.len(),
+                self.searched
+            );
+            new_refs.sort();
+            self.notes.insert(&new_refs, false);
+        }
+
+        self.profiles = ndb
+            .search_profile(&txn, &self.searched, PROFILE_RESULTS_LIMIT)?
+            .into_iter()
+            .map(|pk| Pubkey::new(*pk))
+            .collect();
+
+        Ok(())
+    }
+}
+
+/// Search state for each column that has the search view open, keyed by
+/// column id
+#[derive(Default)]
+pub struct Searches {
+    searches: HashMap<u32, Search>,
+}
+
+impl Searches {
+    pub fn search_mut(&mut self, col_id: u32) -> &mut Search {
+        self.searches.entry(col_id).or_default()
+    }
+
+    /// The column left the search view, stop searching
+    pub fn remove(&mut self, col_id: u32, subs: &mut Subscriptions) {
+        if let Some(mut search) = self.searches.remove(&col_id) {
+            search.close(subs);
+        }
+    }
+
+    /// A search request finished on a relay
+    pub fn remote_eose(&mut self, subid: &str) {
+        for search in self.searches.values_mut() {
+            if search.remote_eose(subid) {
+                return;
+            }
+        }
+    }
+}
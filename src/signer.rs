@@ -0,0 +1,934 @@
+use crate::{outbox::Outbox, subscriptions, Error, Result};
+
+use enostr::{
+    BunkerUri, ClientMessage, FullKeypair, Keypair, Nip46Request, NostrConnectUri, Pubkey,
+    RelayEvent, RelayMessage, RelayPool, RelayStatus, RemoteSigner, SecretKey,
+};
+use nostrdb::{Ndb, Note, NoteBuilder};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tracing::{debug, error, info, warn};
+
+/// How long we wait for a remote signer to answer. Signers may ask the
+/// user to approve requests on another device, so this is generous.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Where we ask nostrconnect signers to reach us
+pub const NOSTRCONNECT_RELAY: &str = "wss://relay.nsec.app";
+
+/// Signs notes for one of our accounts
+pub enum Signer<'a> {
+    /// We have the account's secret key
+    Local {
+        pubkey: &'a Pubkey,
+        seckey: [u8; 32],
+    },
+
+    /// The account's key is held by a NIP-46 remote signer
+    Remote {
+        pubkey: &'a Pubkey,
+        signer: &'a RemoteSigner,
+    },
+}
+
+/// A note that's ready to publish, or that still needs a remote signature
+pub enum SignedNote {
+    Local(Note<'static>),
+
+    /// The unsigned event json, to send to a remote signer
+    Remote(String),
+}
+
+impl<'a> Signer<'a> {
+    /// None for accounts we can't sign for, ie. pubkey-only logins
+    pub fn new(account: &'a Keypair) -> Option<Self> {
+        if let Some(secret_key) = &account.secret_key {
+            Some(Signer::Local {
+                pubkey: &account.pubkey,
+                seckey: secret_key.to_secret_bytes(),
+            })
+        } else {
+            account.remote_signer.as_ref().map(|signer| Signer::Remote {
+                pubkey: &account.pubkey,
+                signer,
+            })
+        }
+    }
+
+    pub fn pubkey(&self) -> &'a Pubkey {
+        match self {
+            Signer::Local { pubkey, .. } | Signer::Remote { pubkey, .. } => *pubkey,
+        }
+    }
+
+    pub fn sign<'b>(&'b self, builder: NoteBuilder<'b>) -> Result<SignedNote> {
+        match self {
+            Signer::Local { seckey, .. } => builder
+                .sign(seckey)
+                .build()
+                .map(SignedNote::Local)
+                .ok_or_else(|| Error::Generic("failed to build note".to_owned())),
+
+            Signer::Remote { pubkey, .. } => {
+                let note = builder
+                    .build()
+                    .ok_or_else(|| Error::Generic("failed to build note".to_owned()))?;
+                Ok(SignedNote::Remote(unsigned_json(&note, pubkey)?))
+            }
+        }
+    }
+
+    /// Encrypt something only this account can read, ie. the private
+    /// entries of its lists. Remote signers do this for us, so this is
+    /// None until they answer: ask again later.
+    pub fn encrypt_to_self(
+        &self,
+        remote_signers: &mut RemoteSigners,
+        content: &str,
+    ) -> Result<Option<String>> {
+        match self {
+            Signer::Local { pubkey, seckey } => Ok(Some(
+                full_keypair(pubkey, seckey)?
+                    .to_filled()
+                    .encrypt_to_self(content)?,
+            )),
+            Signer::Remote { pubkey, signer } => {
+                remote_signers.crypt(pubkey, signer, RequestKind::Nip44Encrypt, content)
+            }
+        }
+    }
+
+    /// Decrypt something this account encrypted to itself. Like
+    /// [`Signer::encrypt_to_self`], this is None until a remote signer
+    /// answers.
+    pub fn decrypt_from_self(
+        &self,
+        remote_signers: &mut RemoteSigners,
+        payload: &str,
+    ) -> Result<Option<String>> {
+        match self {
+            Signer::Local { pubkey, seckey } => Ok(Some(
+                full_keypair(pubkey, seckey)?
+                    .to_filled()
+                    .decrypt_from_self(payload)?,
+            )),
+            Signer::Remote { pubkey, signer } => {
+                remote_signers.crypt(pubkey, signer, RequestKind::Nip44Decrypt, payload)
+            }
+        }
+    }
+
+    /// Queue a note in the outbox. Notes for remote signers are sent to
+    /// them first, and queued once they come back signed: then we return
+    /// the id of the request, which [`SignerEvent::Signed`] answers.
+    pub fn publish(
+        &self,
+        note: SignedNote,
+        ndb: &Ndb,
+        pool: &mut RelayPool,
+        outbox: &mut Outbox,
+        remote_signers: &mut RemoteSigners,
+    ) -> Result<Option<String>> {
+        match (note, self) {
+            (SignedNote::Local(note), _) => {
+                outbox.publish(ndb, pool, &note);
+                Ok(None)
+            }
+            (SignedNote::Remote(unsigned), Signer::Remote { pubkey, signer }) => {
+                remote_signers.sign(pubkey, signer, unsigned).map(Some)
+            }
+            (SignedNote::Remote(_), Signer::Local { .. }) => Err(Error::Generic(
+                "got an unsigned note for an account we have the key for".to_owned(),
+            )),
+        }
+    }
+}
+
+fn full_keypair(pubkey: &Pubkey, seckey: &[u8; 32]) -> Result<FullKeypair> {
+    let secret_key = SecretKey::from_slice(seckey).map_err(|e| Error::Generic(e.to_string()))?;
+    Ok(FullKeypair::new(*pubkey, secret_key))
+}
+
+/// The json a remote signer expects: the note without its id and
+/// signature, which the signer fills in
+fn unsigned_json(note: &Note, pubkey: &Pubkey) -> Result<String> {
+    let json = note
+        .json()
+        .map_err(|e| Error::Generic(format!("error serializing note: {e}")))?;
+    let mut event: Value =
+        serde_json::from_str(&json).map_err(|e| Error::Generic(e.to_string()))?;
+
+    if let Some(event) = event.as_object_mut() {
+        event.remove("id");
+        event.remove("sig");
+        event.insert("pubkey".to_owned(), Value::String(pubkey.hex()));
+    }
+
+    Ok(event.to_string())
+}
+
+/// Things that happened with our remote signers since we last checked
+#[derive(Debug)]
+pub enum SignerEvent {
+    /// A remote signer login finished, this is the new account
+    LoggedIn(Keypair),
+
+    LoginFailed(String),
+
+    /// The signer wants the user to approve us at this url before it
+    /// answers
+    NeedsApproval(String),
+
+    /// A note we asked to be signed came back, ready for the outbox
+    Signed {
+        request: String,
+        id: String,
+        event: String,
+    },
+
+    SignFailed {
+        request: String,
+        err: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+enum RequestKind {
+    Connect,
+    GetPublicKey,
+    SignEvent,
+
+    /// Encrypt something to the account itself
+    Nip44Encrypt,
+
+    /// Decrypt something the account encrypted to itself
+    Nip44Decrypt,
+}
+
+impl RequestKind {
+    fn method(&self) -> &'static str {
+        match self {
+            RequestKind::Connect => "connect",
+            RequestKind::GetPublicKey => "get_public_key",
+            RequestKind::SignEvent => "sign_event",
+            RequestKind::Nip44Encrypt => "nip44_encrypt",
+            RequestKind::Nip44Decrypt => "nip44_decrypt",
+        }
+    }
+}
+
+struct PendingRequest {
+    kind: RequestKind,
+
+    /// None while we're waiting for a relay to send it to. Requests only
+    /// time out once they're sent, so they survive going offline.
+    sent_at: Option<Instant>,
+}
+
+enum SessionState {
+    /// Waiting for a signer to accept our nostrconnect uri
+    Listening(NostrConnectUri),
+
+    /// Logging in, we don't know the account yet
+    LoggingIn(RemoteSigner),
+
+    /// Signing for an account
+    Ready {
+        account: Pubkey,
+        signer: RemoteSigner,
+    },
+
+    /// The login failed or was replaced, this session goes away
+    Done,
+}
+
+/// A conversation with one remote signer, over its own relays
+struct Session {
+    state: SessionState,
+    relays: Vec<String>,
+    pool: Option<RelayPool>,
+    subid: String,
+
+    /// Requests waiting for a connected relay, by request id
+    outgoing: Vec<(String, String)>,
+
+    pending: HashMap<String, PendingRequest>,
+
+    /// Answers to our encrypt and decrypt requests, by request id. See
+    /// [`RemoteSigners::crypt`].
+    crypted: Vec<(String, std::result::Result<String, String>)>,
+}
+
+impl Session {
+    fn new(state: SessionState, relays: Vec<String>) -> Self {
+        Session {
+            state,
+            relays,
+            pool: None,
+            subid: subscriptions::new_sub_id(),
+            outgoing: vec![],
+            pending: HashMap::new(),
+            crypted: vec![],
+        }
+    }
+
+    /// The pubkey the signer sends its answers to
+    fn client(&self) -> Option<&Pubkey> {
+        match &self.state {
+            SessionState::Listening(connect) => Some(&connect.client.pubkey),
+            SessionState::LoggingIn(signer) | SessionState::Ready { signer, .. } => {
+                Some(&signer.client.pubkey)
+            }
+            SessionState::Done => None,
+        }
+    }
+
+    fn signer(&self) -> Option<&RemoteSigner> {
+        match &self.state {
+            SessionState::LoggingIn(signer) | SessionState::Ready { signer, .. } => Some(signer),
+            SessionState::Listening(_) | SessionState::Done => None,
+        }
+    }
+
+    /// Queue a request, returning its id
+    fn request(&mut self, kind: RequestKind, params: Vec<String>) -> Result<String> {
+        let signer = self
+            .signer()
+            .ok_or_else(|| Error::Generic("not connected to a signer".to_owned()))?;
+
+        let request = Nip46Request {
+            id: subscriptions::new_sub_id(),
+            method: kind.method().to_owned(),
+            params,
+        };
+        let note = signer.request_note(&request)?;
+        let json = note
+            .json()
+            .map_err(|e| Error::Generic(format!("error serializing nip46 request: {e}")))?;
+
+        debug!("queueing nip46 {} request {}", request.method, request.id);
+        self.outgoing
+            .push((request.id.clone(), format!("[\"EVENT\",{json}]")));
+        self.pending.insert(
+            request.id.clone(),
+            PendingRequest {
+                kind,
+                sent_at: None,
+            },
+        );
+
+        Ok(request.id)
+    }
+
+    fn poll(
+        &mut self,
+        main: &RelayPool,
+        wakeup: impl Fn() + Send + Sync + Clone + 'static,
+    ) -> Vec<SignerEvent> {
+        let client = if let Some(client) = self.client() {
+            *client
+        } else {
+            return vec![];
+        };
+
+        let subid = self.subid.clone();
+        let pool = sync_pool(&mut self.pool, &self.relays, main, wakeup);
+
+        let mut events = vec![];
+        while let Some(ev) = pool.try_recv() {
+            let ev = ev.into_owned();
+            match (&ev.event).into() {
+                RelayEvent::Opened => {
+                    let is_reconnect = pool
+                        .relays
+                        .iter()
+                        .find(|r| r.relay.url == ev.relay)
+                        .map_or(false, |r| r.relay.is_reconnect());
+
+                    if !is_reconnect {
+                        let filter = RemoteSigner::filter(&client);
+                        pool.send_to(&ClientMessage::req(subid.clone(), vec![filter]), &ev.relay);
+                    }
+                }
+                RelayEvent::Message(RelayMessage::Event(sid, msg)) if sid == subid => {
+                    events.push(msg.to_owned());
+                }
+                RelayEvent::Error(err) => warn!("remote signer relay {}: {err}", ev.relay),
+                _ => {}
+            }
+        }
+
+        let connected = pool
+            .relays
+            .iter()
+            .any(|r| matches!(r.relay.status, RelayStatus::Connected));
+        if connected {
+            for (id, msg) in self.outgoing.drain(..) {
+                pool.send(&ClientMessage::raw(msg));
+                if let Some(req) = self.pending.get_mut(&id) {
+                    req.sent_at = Some(Instant::now());
+                }
+            }
+        }
+
+        let mut results: Vec<SignerEvent> = events
+            .iter()
+            .filter_map(|msg| {
+                let msg: Vec<Value> = serde_json::from_str(msg).ok()?;
+                self.handle_event(&msg.get(2)?.to_string())
+            })
+            .collect();
+
+        results.extend(self.expire_requests());
+        results
+    }
+
+    fn expire_requests(&mut self) -> Vec<SignerEvent> {
+        let expired: Vec<String> = self
+            .pending
+            .iter()
+            .filter(|(_, req)| {
+                req.sent_at
+                    .map_or(false, |sent_at| sent_at.elapsed() > REQUEST_TIMEOUT)
+            })
+            .map(|(id, _)| id.clone())
+            .collect();
+
+        let mut events = vec![];
+        for id in expired {
+            let req = if let Some(req) = self.pending.remove(&id) {
+                req
+            } else {
+                continue;
+            };
+
+            let msg = format!(
+                "the remote signer didn't answer our {} request",
+                req.kind.method()
+            );
+            events.extend(self.fail(id, req.kind, msg));
+        }
+
+        events
+    }
+
+    /// A request failed or timed out. A failed login ends the session.
+    fn fail(&mut self, request: String, kind: RequestKind, err: String) -> Option<SignerEvent> {
+        match kind {
+            RequestKind::Connect | RequestKind::GetPublicKey => {
+                self.state = SessionState::Done;
+                Some(SignerEvent::LoginFailed(err))
+            }
+            RequestKind::SignEvent => Some(SignerEvent::SignFailed { request, err }),
+            RequestKind::Nip44Encrypt | RequestKind::Nip44Decrypt => {
+                self.crypted.push((request, Err(err)));
+                None
+            }
+        }
+    }
+
+    fn handle_event(&mut self, event: &str) -> Option<SignerEvent> {
+        if let SessionState::Listening(connect) = &self.state {
+            match connect.read_connect(event) {
+                Ok(signer) => {
+                    info!("nostrconnect signer {} connected", signer.remote_pubkey);
+                    self.state = SessionState::LoggingIn(signer);
+                    return self.request_or_fail(RequestKind::GetPublicKey, vec![]);
+                }
+                Err(err) => {
+                    debug!("ignoring nostrconnect message: {err}");
+                    return None;
+                }
+            }
+        }
+
+        let response = match self.signer()?.read_response(event) {
+            Ok(response) => response,
+            Err(err) => {
+                debug!("ignoring nip46 message: {err}");
+                return None;
+            }
+        };
+
+        let request = self.pending.remove(&response.id)?;
+
+        if response.result.as_deref() == Some("auth_url") {
+            // the signer wants the user to approve us at a url first, and
+            // will answer this request once they have
+            let url = response.error.unwrap_or_default();
+            warn!("remote signer asks for approval at {url}");
+            self.pending.insert(response.id, request);
+            return Some(SignerEvent::NeedsApproval(url));
+        }
+
+        if let Some(err) = response.error.filter(|err| !err.is_empty()) {
+            let msg = format!("remote signer refused {}: {err}", request.kind.method());
+            return self.fail(response.id, request.kind, msg);
+        }
+
+        let result = response.result.unwrap_or_default();
+        match request.kind {
+            RequestKind::Connect => self.request_or_fail(RequestKind::GetPublicKey, vec![]),
+
+            RequestKind::GetPublicKey => {
+                let account = match Pubkey::from_hex(&result) {
+                    Ok(account) => account,
+                    Err(_) => {
+                        self.state = SessionState::Done;
+                        return Some(SignerEvent::LoginFailed(
+                            "remote signer sent an invalid pubkey".to_owned(),
+                        ));
+                    }
+                };
+
+                let signer = self.signer()?.clone();
+                info!("logged in as {account} with remote signer");
+                self.state = SessionState::Ready {
+                    account,
+                    signer: signer.clone(),
+                };
+                Some(SignerEvent::LoggedIn(Keypair::with_remote_signer(
+                    account, signer,
+                )))
+            }
+
+            RequestKind::SignEvent => {
+                let account = if let SessionState::Ready { account, .. } = &self.state {
+                    account
+                } else {
+                    return None;
+                };
+
+                Some(match enostr::check_signed(account, &result) {
+                    Ok(id) => SignerEvent::Signed {
+                        request: response.id,
+                        id,
+                        event: result,
+                    },
+                    Err(err) => SignerEvent::SignFailed {
+                        request: response.id,
+                        err: format!("remote signer sent back a bad note: {err}"),
+                    },
+                })
+            }
+
+            RequestKind::Nip44Encrypt | RequestKind::Nip44Decrypt => {
+                self.crypted.push((response.id, Ok(result)));
+                None
+            }
+        }
+    }
+
+    fn request_or_fail(&mut self, kind: RequestKind, params: Vec<String>) -> Option<SignerEvent> {
+        let err = self.request(kind, params).err()?;
+        self.state = SessionState::Done;
+        Some(SignerEvent::LoginFailed(err.to_string()))
+    }
+}
+
+/// Keep a session's relays in step with the main pool's proxy and offline
+/// settings, connecting to them the first time
+fn sync_pool<'a>(
+    pool: &'a mut Option<RelayPool>,
+    relays: &[String],
+    main: &RelayPool,
+    wakeup: impl Fn() + Send + Sync + Clone + 'static,
+) -> &'a mut RelayPool {
+    let pool = pool.get_or_insert_with(RelayPool::new);
+    pool.set_proxy(main.proxy_setting(), main.blocks_clearnet(), wakeup.clone());
+    pool.set_offline(main.is_offline(), wakeup.clone());
+
+    for url in relays {
+        if let Err(err) = pool.add_url(url.clone(), wakeup.clone()) {
+            error!("can't reach remote signer relay {url}: {err}");
+        }
+    }
+
+    pool.keepalive_ping(wakeup);
+    pool
+}
+
+/// Something we asked a remote signer to encrypt or decrypt
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+struct Crypt {
+    account: Pubkey,
+    kind: RequestKind,
+    input: String,
+}
+
+enum CryptState {
+    /// Waiting on the answer to this request id
+    Waiting(String),
+    Done(String),
+    Failed(String),
+}
+
+/// Our conversations with remote signers: ones signing for our accounts,
+/// and logins in progress
+#[derive(Default)]
+pub struct RemoteSigners {
+    sessions: Vec<Session>,
+
+    /// What signers encrypted and decrypted for us, so we only ask once
+    crypts: HashMap<Crypt, CryptState>,
+}
+
+impl RemoteSigners {
+    /// Log in with a bunker uri. The new account arrives as
+    /// [`SignerEvent::LoggedIn`].
+    pub fn login(&mut self, uri: &BunkerUri) {
+        self.cancel_logins();
+
+        let signer = RemoteSigner::from_bunker_uri(uri);
+        let mut params = vec![signer.remote_pubkey.hex()];
+        if let Some(secret) = &uri.secret {
+            params.push(secret.clone());
+        }
+
+        let mut session = Session::new(SessionState::LoggingIn(signer), uri.relays.clone());
+        if let Err(err) = session.request(RequestKind::Connect, params) {
+            error!("error starting remote signer login: {err}");
+            return;
+        }
+
+        self.sessions.push(session);
+    }
+
+    /// Wait for a signer to connect to us, returning the nostrconnect uri
+    /// to give it
+    pub fn listen(&mut self) -> String {
+        self.cancel_logins();
+
+        let connect = NostrConnectUri::new(
+            vec![NOSTRCONNECT_RELAY.to_owned()],
+            subscriptions::new_sub_id(),
+        );
+        let uri = connect.uri("notedeck");
+        let relays = connect.relays.clone();
+
+        self.sessions
+            .push(Session::new(SessionState::Listening(connect), relays));
+        uri
+    }
+
+    pub fn is_logging_in(&self) -> bool {
+        self.sessions.iter().any(|s| {
+            matches!(
+                s.state,
+                SessionState::Listening(_) | SessionState::LoggingIn(_)
+            )
+        })
+    }
+
+    /// Stop any login in progress
+    pub fn cancel_logins(&mut self) {
+        self.sessions
+            .retain(|s| matches!(s.state, SessionState::Ready { .. }));
+    }
+
+    /// Ask an account's signer to sign a note, returning the request id
+    fn sign(
+        &mut self,
+        account: &Pubkey,
+        signer: &RemoteSigner,
+        unsigned: String,
+    ) -> Result<String> {
+        self.session_for(account, signer)
+            .request(RequestKind::SignEvent, vec![unsigned])
+    }
+
+    /// Ask an account's signer to NIP-44 encrypt something to the account,
+    /// or decrypt something it encrypted to itself. None until the signer
+    /// answers: the answer comes back from a later call with the same
+    /// input. A failure is only returned once, so asking again retries.
+    fn crypt(
+        &mut self,
+        account: &Pubkey,
+        signer: &RemoteSigner,
+        kind: RequestKind,
+        input: &str,
+    ) -> Result<Option<String>> {
+        let crypt = Crypt {
+            account: *account,
+            kind,
+            input: input.to_owned(),
+        };
+
+        match self.crypts.get(&crypt) {
+            Some(CryptState::Waiting(_)) => return Ok(None),
+            Some(CryptState::Done(output)) => return Ok(Some(output.clone())),
+            Some(CryptState::Failed(err)) => {
+                let err = Error::Generic(err.clone());
+                self.crypts.remove(&crypt);
+                return Err(err);
+            }
+            None => {}
+        }
+
+        let request = self
+            .session_for(account, signer)
+            .request(kind, vec![account.hex(), input.to_owned()])?;
+        self.crypts.insert(crypt, CryptState::Waiting(request));
+        Ok(None)
+    }
+
+    /// The session signing for an account, started if we don't have one
+    fn session_for(&mut self, account: &Pubkey, signer: &RemoteSigner) -> &mut Session {
+        let index = if let Some(index) = self.sessions.iter().position(|s| {
+            matches!(&s.state, SessionState::Ready { account: a, signer: sg } if a == account && sg == signer)
+        }) {
+            index
+        } else {
+            let state = SessionState::Ready {
+                account: *account,
+                signer: signer.clone(),
+            };
+            self.sessions
+                .push(Session::new(state, signer.relays.clone()));
+            self.sessions.len() - 1
+        };
+
+        &mut self.sessions[index]
+    }
+
+    /// File the encrypt and decrypt answers our sessions got
+    fn take_crypted(&mut self) {
+        for session in &mut self.sessions {
+            for (request, answer) in session.crypted.drain(..) {
+                let state = self.crypts.values_mut().find(
+                    |state| matches!(state, CryptState::Waiting(waiting) if *waiting == request),
+                );
+
+                if let Some(state) = state {
+                    *state = match answer {
+                        Ok(output) => CryptState::Done(output),
+                        Err(err) => {
+                            error!("{err}");
+                            CryptState::Failed(err)
+                        }
+                    };
+                }
+            }
+        }
+    }
+
+    pub fn poll(
+        &mut self,
+        main: &RelayPool,
+        wakeup: impl Fn() + Send + Sync + Clone + 'static,
+    ) -> Vec<SignerEvent> {
+        let events = self
+            .sessions
+            .iter_mut()
+            .flat_map(|session| session.poll(main, wakeup.clone()))
+            .collect();
+        self.take_crypted();
+
+        self.sessions
+            .retain(|s| !matches!(s.state, SessionState::Done));
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use enostr::{nip46, FullKeypair, Nip46Response};
+
+    /// A stand-in for a bunker, answering the requests a session queued
+    fn answer(
+        session: &mut Session,
+        bunker: &FullKeypair,
+        result: impl Fn(&Nip46Request) -> String,
+    ) -> Vec<SignerEvent> {
+        let client = *session.client().unwrap();
+        let outgoing: Vec<String> = session.outgoing.drain(..).map(|(_, msg)| msg).collect();
+
+        outgoing
+            .iter()
+            .filter_map(|msg| {
+                let msg: Vec<Value> = serde_json::from_str(msg).unwrap();
+                let note = msg[1].to_string();
+                let content = msg[1]["content"].as_str().unwrap();
+                assert!(note.contains(&client.hex()));
+
+                let json = nip46::decrypt(bunker, &client, content).unwrap();
+                let request: Nip46Request = serde_json::from_str(&json).unwrap();
+                let response = Nip46Response {
+                    id: request.id.clone(),
+                    result: Some(result(&request)),
+                    error: None,
+                };
+
+                let content =
+                    nip46::encrypt(bunker, &client, &serde_json::to_string(&response).unwrap())
+                        .unwrap();
+                let reply = nip46::request_note(bunker, &client, &content)
+                    .unwrap()
+                    .json()
+                    .unwrap();
+                session.handle_event(&reply)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_bunker_login_and_sign() {
+        let bunker = FullKeypair::generate();
+        let user = FullKeypair::generate();
+        let uri = BunkerUri::parse(&format!(
+            "bunker://{}?relay=wss://relay.example.com&secret=abc",
+            bunker.pubkey.hex()
+        ))
+        .unwrap();
+
+        let mut signers = RemoteSigners::default();
+        signers.login(&uri);
+        assert!(signers.is_logging_in());
+        let session = &mut signers.sessions[0];
+
+        // connect, then get_public_key
+        let events = answer(session, &bunker, |req| {
+            assert_eq!(req.method, "connect");
+            assert_eq!(req.params, vec![bunker.pubkey.hex(), "abc".to_owned()]);
+            "ack".to_owned()
+        });
+        assert!(events.is_empty());
+
+        let events = answer(session, &bunker, |req| {
+            assert_eq!(req.method, "get_public_key");
+            user.pubkey.hex()
+        });
+        let account = match &events[..] {
+            [SignerEvent::LoggedIn(account)] => account.clone(),
+            other => panic!("expected a login, got {other:?}"),
+        };
+        assert_eq!(account.pubkey, user.pubkey);
+        assert!(!signers.is_logging_in());
+
+        // now sign something
+        let signer = Signer::new(&account).unwrap();
+        let unsigned = match signer
+            .sign(NoteBuilder::new().kind(1).content("hello"))
+            .unwrap()
+        {
+            SignedNote::Remote(unsigned) => unsigned,
+            SignedNote::Local(_) => panic!("expected a remote signature"),
+        };
+        let remote = account.remote_signer.as_ref().unwrap();
+        let request = signers.sign(&account.pubkey, remote, unsigned).unwrap();
+        assert_eq!(signers.sessions.len(), 1);
+
+        let user_seckey = user.secret_key.to_secret_bytes();
+        let events = answer(&mut signers.sessions[0], &bunker, |req| {
+            assert_eq!(req.method, "sign_event");
+            let unsigned: Value = serde_json::from_str(&req.params[0]).unwrap();
+            assert_eq!(unsigned["pubkey"], user.pubkey.hex());
+            assert_eq!(unsigned["content"], "hello");
+
+            NoteBuilder::new()
+                .kind(1)
+                .content("hello")
+                .sign(&user_seckey)
+                .build()
+                .unwrap()
+                .json()
+                .unwrap()
+        });
+
+        match &events[..] {
+            [SignerEvent::Signed {
+                request: signed,
+                event,
+                ..
+            }] => {
+                assert_eq!(signed, &request);
+                assert!(event.contains("hello"));
+            }
+            other => panic!("expected a signed note, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_remote_encrypt_to_self() {
+        let bunker = FullKeypair::generate();
+        let user = FullKeypair::generate();
+        let uri = BunkerUri::parse(&format!(
+            "bunker://{}?relay=wss://relay.example.com",
+            bunker.pubkey.hex()
+        ))
+        .unwrap();
+        let account = Keypair::with_remote_signer(user.pubkey, RemoteSigner::from_bunker_uri(&uri));
+        let signer = Signer::new(&account).unwrap();
+
+        let mut signers = RemoteSigners::default();
+        assert_eq!(
+            signer.encrypt_to_self(&mut signers, "secret").unwrap(),
+            None
+        );
+        // we only ask once
+        assert_eq!(
+            signer.encrypt_to_self(&mut signers, "secret").unwrap(),
+            None
+        );
+        assert_eq!(signers.sessions[0].pending.len(), 1);
+
+        let events = answer(&mut signers.sessions[0], &bunker, |req| {
+            assert_eq!(req.method, "nip44_encrypt");
+            assert_eq!(req.params, vec![user.pubkey.hex(), "secret".to_owned()]);
+            "payload".to_owned()
+        });
+        assert!(events.is_empty());
+        signers.take_crypted();
+
+        assert_eq!(
+            signer.encrypt_to_self(&mut signers, "secret").unwrap(),
+            Some("payload".to_owned())
+        );
+
+        // a decrypt the signer never answers fails once, then is asked again
+        assert_eq!(
+            signer.decrypt_from_self(&mut signers, "payload").unwrap(),
+            None
+        );
+        let session = &mut signers.sessions[0];
+        for req in session.pending.values_mut() {
+            req.sent_at = Some(Instant::now() - REQUEST_TIMEOUT - Duration::from_secs(1));
+        }
+        assert!(session.expire_requests().is_empty());
+        signers.take_crypted();
+
+        assert!(signer.decrypt_from_self(&mut signers, "payload").is_err());
+        assert_eq!(
+            signer.decrypt_from_self(&mut signers, "payload").unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_requests_only_expire_once_sent() {
+        let bunker = FullKeypair::generate();
+        let uri = BunkerUri::parse(&format!(
+            "bunker://{}?relay=wss://relay.example.com",
+            bunker.pubkey.hex()
+        ))
+        .unwrap();
+
+        let mut signers = RemoteSigners::default();
+        signers.login(&uri);
+        let session = &mut signers.sessions[0];
+
+        // still waiting for a relay, ie. we're offline
+        assert!(session.expire_requests().is_empty());
+        assert_eq!(session.pending.len(), 1);
+
+        for req in session.pending.values_mut() {
+            req.sent_at = Some(Instant::now() - REQUEST_TIMEOUT - Duration::from_secs(1));
+        }
+        match &session.expire_requests()[..] {
+            [SignerEvent::LoginFailed(_)] => {}
+            other => panic!("expected the login to time out, got {other:?}"),
+        }
+    }
+}
@@ -31,6 +31,7 @@ pub enum DataPathType {
     SelectedKey,
     Db,
     Cache,
+    Outbox,
 }
 
 impl DataPath {
@@ -42,6 +43,7 @@ impl DataPath {
             DataPathType::SelectedKey => PathBuf::from("storage").join("selected_account"),
             DataPathType::Db => PathBuf::from("db"),
             DataPathType::Cache => PathBuf::from("cache"),
+            DataPathType::Outbox => PathBuf::from("storage").join("outbox"),
         }
     }
 
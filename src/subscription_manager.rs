@@ -0,0 +1,970 @@
+use enostr::{ClientMessage, Filter, RelayPool, RelayStatus};
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+use tracing::{debug, error, warn};
+
+use crate::subscriptions::new_sub_id;
+
+/// How many filters we put in one coalesced REQ, unless the relay's
+/// information document says otherwise
+const DEFAULT_MAX_FILTERS: usize = 10;
+
+/// How many REQs we keep open on a relay at once, unless its information
+/// document says otherwise
+const DEFAULT_MAX_SUBSCRIPTIONS: usize = 20;
+
+/// How many REQs we send to a relay per `REQ_RATE_WINDOW`
+const MAX_REQS_PER_WINDOW: usize = 10;
+const REQ_RATE_WINDOW: Duration = Duration::from_secs(1);
+
+/// How long a relay gets to answer a COUNT before we give up on it
+const COUNT_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubLifetime {
+    /// Closed on a relay as soon as it sends EOSE
+    OneShot,
+
+    /// Stays open for live updates until it is unsubscribed
+    Persistent,
+}
+
+/// A relay we can send subscriptions to, and the limits it told us about
+#[derive(Debug, Clone)]
+pub struct RelayTarget {
+    pub url: String,
+    pub connected: bool,
+    pub max_filters: Option<usize>,
+    pub max_subscriptions: Option<usize>,
+}
+
+impl RelayTarget {
+    /// How many REQs the relay sends a REQ with this many filters out
+    /// as, since it splits them to fit its `max_filters`
+    fn req_count(&self, num_filters: usize) -> usize {
+        match self.max_filters.filter(|max| *max > 0) {
+            Some(max) => num_filters.div_ceil(max).max(1),
+            None => 1,
+        }
+    }
+}
+
+/// Where subscriptions get sent. This is the [`RelayPool`], except in
+/// tests.
+pub trait SubscriptionTransport {
+    fn relays(&self) -> Vec<RelayTarget>;
+    fn send_to(&mut self, relay_url: &str, msg: &ClientMessage);
+}
+
+impl SubscriptionTransport for RelayPool {
+    fn relays(&self) -> Vec<RelayTarget> {
+        self.relays
+            .iter()
+            .map(|pool_relay| {
+                let relay = &pool_relay.relay;
+                RelayTarget {
+                    url: relay.url.clone(),
+                    connected: matches!(relay.status, RelayStatus::Connected),
+                    max_filters: relay
+                        .limitation()
+                        .and_then(|l| l.max_filters)
+                        .map(|n| n as usize),
+                    max_subscriptions: relay
+                        .limitation()
+                        .and_then(|l| l.max_subscriptions)
+                        .map(|n| n as usize),
+                }
+            })
+            .collect()
+    }
+
+    fn send_to(&mut self, relay_url: &str, msg: &ClientMessage) {
+        RelayPool::send_to(self, msg, relay_url)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum RelaySubState {
+    Queued,
+    /// Sent as part of the REQ with this subscription id
+    Open(String),
+    Done,
+}
+
+struct ManagedSub {
+    filters: Vec<Filter>,
+    lifetime: SubLifetime,
+    /// The only relay this goes to. If None, it goes to every relay.
+    relay: Option<String>,
+    /// How many callers share this subscription
+    refs: u32,
+    /// Set for subscriptions shared by everyone asking for the same filters
+    shared_key: Option<String>,
+    relays: HashMap<String, RelaySubState>,
+}
+
+impl ManagedSub {
+    fn is_finished(&self) -> bool {
+        self.lifetime == SubLifetime::OneShot
+            && !self.relays.is_empty()
+            && self
+                .relays
+                .values()
+                .all(|state| *state == RelaySubState::Done)
+    }
+}
+
+/// A NIP-45 COUNT for a single relay
+struct PendingCount {
+    relay: String,
+    filters: Vec<Filter>,
+    /// None until the relay has room for it
+    sent: Option<Instant>,
+}
+
+#[derive(Default)]
+struct RelayQueue {
+    queue: VecDeque<String>,
+    /// REQs open on this relay and the subscriptions in each of them
+    open: HashMap<String, Vec<String>>,
+    /// How many REQs each open REQ takes up on the relay
+    open_reqs: HashMap<String, usize>,
+    /// When we sent our most recent REQs and COUNTs, for rate limiting
+    sent: VecDeque<Instant>,
+}
+
+impl RelayQueue {
+    /// Whether we can send the relay another request without going over
+    /// its rate limit
+    fn has_room(&mut self, now: Instant) -> bool {
+        while self
+            .sent
+            .front()
+            .map_or(false, |at| now.duration_since(*at) >= REQ_RATE_WINDOW)
+        {
+            self.sent.pop_front();
+        }
+
+        self.sent.len() < MAX_REQS_PER_WINDOW
+    }
+}
+
+/// Sits between the things that want remote data and the [`RelayPool`].
+/// Subscriptions are queued per relay and sent at a rate the relay won't
+/// complain about, and held back while the relay has as many open as it
+/// allows. One-shot subscriptions are coalesced into shared REQs and
+/// closed on EOSE, or when a relay goes down, and identical shared
+/// subscriptions are ref-counted. COUNTs share the rate limit and are
+/// dropped when a relay doesn't answer.
+#[derive(Default)]
+pub struct SubscriptionManager {
+    subs: HashMap<String, ManagedSub>,
+    shared: HashMap<String, String>,
+    relays: HashMap<String, RelayQueue>,
+    counts: HashMap<String, PendingCount>,
+    /// One-shot subscriptions that finished because their relays went
+    /// down, rather than on an EOSE
+    finished: Vec<String>,
+}
+
+impl SubscriptionManager {
+    /// Subscribe on every relay
+    pub fn subscribe(&mut self, subid: String, filters: Vec<Filter>, lifetime: SubLifetime) {
+        self.insert(subid, filters, lifetime, None, None);
+    }
+
+    /// Subscribe on a single relay
+    pub fn subscribe_to(
+        &mut self,
+        relay_url: &str,
+        subid: String,
+        filters: Vec<Filter>,
+        lifetime: SubLifetime,
+    ) {
+        self.insert(subid, filters, lifetime, Some(relay_url.to_owned()), None);
+    }
+
+    /// Subscribe on every relay, sharing the subscription with anyone else
+    /// asking for exactly the same filters. Returns the subscription id to
+    /// unsubscribe with.
+    pub fn subscribe_shared(&mut self, filters: Vec<Filter>, lifetime: SubLifetime) -> String {
+        let key = shared_key(&filters);
+
+        if let Some(subid) = self.shared.get(&key) {
+            if let Some(sub) = self.subs.get_mut(subid) {
+                sub.refs += 1;
+                debug!("sharing subscription {subid}, {} refs", sub.refs);
+                return subid.clone();
+            }
+        }
+
+        let subid = new_sub_id();
+        self.shared.insert(key.clone(), subid.clone());
+        self.insert(subid.clone(), filters, lifetime, None, Some(key));
+        subid
+    }
+
+    fn insert(
+        &mut self,
+        subid: String,
+        filters: Vec<Filter>,
+        lifetime: SubLifetime,
+        relay: Option<String>,
+        shared_key: Option<String>,
+    ) {
+        if self.subs.contains_key(&subid) {
+            warn!("replacing subscription {subid}");
+            self.remove(&subid);
+        }
+
+        self.subs.insert(
+            subid,
+            ManagedSub {
+                filters,
+                lifetime,
+                relay,
+                refs: 1,
+                shared_key,
+                relays: HashMap::new(),
+            },
+        );
+    }
+
+    /// Drop a reference to a subscription, closing it on our relays once
+    /// nobody is using it
+    pub fn unsubscribe(&mut self, subid: &str) {
+        let sub = if let Some(sub) = self.subs.get_mut(subid) {
+            sub
+        } else {
+            return;
+        };
+
+        sub.refs = sub.refs.saturating_sub(1);
+        if sub.refs == 0 {
+            self.remove(subid);
+        }
+    }
+
+    /// Whether a subscription still has work to do on some relay
+    pub fn is_active(&self, subid: &str) -> bool {
+        self.subs.contains_key(subid)
+    }
+
+    /// One-shot subscriptions that are done because the relays they were
+    /// waiting on went down. Callers should treat these like the last
+    /// EOSE.
+    pub fn take_finished(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.finished)
+    }
+
+    /// Ask a single relay to count the events matching some filters
+    pub fn count_to(&mut self, relay_url: &str, subid: String, filters: Vec<Filter>) {
+        self.counts.insert(
+            subid,
+            PendingCount {
+                relay: relay_url.to_owned(),
+                filters,
+                sent: None,
+            },
+        );
+    }
+
+    /// A relay answered a COUNT. Returns false if it isn't one we're
+    /// still waiting on.
+    pub fn handle_count(&mut self, relay_url: &str, subid: &str) -> bool {
+        match self.counts.get(subid) {
+            Some(count) if count.relay == relay_url => {
+                self.counts.remove(subid);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether we're still waiting on a relay to answer a COUNT
+    pub fn is_counting(&self, subid: &str) -> bool {
+        self.counts.contains_key(subid)
+    }
+
+    fn remove(&mut self, subid: &str) {
+        let sub = if let Some(sub) = self.subs.remove(subid) {
+            sub
+        } else {
+            return;
+        };
+
+        if let Some(key) = &sub.shared_key {
+            self.shared.remove(key);
+        }
+
+        for (relay_url, state) in sub.relays {
+            let queue = if let Some(queue) = self.relays.get_mut(&relay_url) {
+                queue
+            } else {
+                continue;
+            };
+
+            match state {
+                RelaySubState::Queued => queue.queue.retain(|id| id != subid),
+                RelaySubState::Open(wire_id) => {
+                    if let Some(members) = queue.open.get_mut(&wire_id) {
+                        members.retain(|id| id != subid);
+                    }
+                }
+                RelaySubState::Done => {}
+            }
+        }
+    }
+
+    /// The subscriptions a REQ we sent to a relay is carrying. Every note
+    /// a relay sends us goes through here, since coalesced REQs have an id
+    /// of their own.
+    pub fn members(&self, relay_url: &str, wire_id: &str) -> Vec<String> {
+        self.relays
+            .get(relay_url)
+            .and_then(|queue| queue.open.get(wire_id))
+            .cloned()
+            // not one of ours
+            .unwrap_or_else(|| vec![wire_id.to_owned()])
+    }
+
+    /// A relay sent EOSE for one of our REQs. One-shot subscriptions in
+    /// it are done on that relay. Returns the subscriptions that were part
+    /// of the REQ, so callers can do whatever they were waiting for.
+    pub fn handle_eose(
+        &mut self,
+        transport: &mut impl SubscriptionTransport,
+        relay_url: &str,
+        wire_id: &str,
+    ) -> Vec<String> {
+        let members = self
+            .relays
+            .get(relay_url)
+            .and_then(|queue| queue.open.get(wire_id))
+            .cloned();
+
+        let members = if let Some(members) = members {
+            members
+        } else {
+            // not one of ours
+            return vec![wire_id.to_owned()];
+        };
+
+        let one_shot = members.iter().all(|subid| {
+            self.subs
+                .get(subid)
+                .map_or(true, |sub| sub.lifetime == SubLifetime::OneShot)
+        });
+
+        if one_shot {
+            for subid in &members {
+                let finished = if let Some(sub) = self.subs.get_mut(subid) {
+                    sub.relays.insert(relay_url.to_owned(), RelaySubState::Done);
+                    sub.is_finished()
+                } else {
+                    false
+                };
+
+                if finished {
+                    self.remove(subid);
+                }
+            }
+
+            self.close(transport, relay_url, wire_id);
+        }
+
+        members
+    }
+
+    fn close(
+        &mut self,
+        transport: &mut impl SubscriptionTransport,
+        relay_url: &str,
+        wire_id: &str,
+    ) {
+        if let Some(queue) = self.relays.get_mut(relay_url) {
+            queue.open.remove(wire_id);
+            queue.open_reqs.remove(wire_id);
+        }
+        transport.send_to(relay_url, &ClientMessage::close(wire_id.to_owned()));
+    }
+
+    pub fn poll(&mut self, transport: &mut impl SubscriptionTransport) {
+        self.poll_at(transport, Instant::now());
+    }
+
+    /// Queue subscriptions for relays they haven't been sent to, close
+    /// REQs nobody is using anymore, and send what the relays have room
+    /// for
+    fn poll_at(&mut self, transport: &mut impl SubscriptionTransport, now: Instant) {
+        let targets = transport.relays();
+
+        // forget relays that were removed from the pool
+        self.relays
+            .retain(|url, _| targets.iter().any(|target| &target.url == url));
+
+        // and COUNTs for them, or that they never answered
+        self.counts.retain(|_, count| {
+            targets.iter().any(|target| target.url == count.relay)
+                && count
+                    .sent
+                    .map_or(true, |sent| now.duration_since(sent) < COUNT_TIMEOUT)
+        });
+
+        self.give_up_on_unreachable(&targets);
+        self.assign_relays(&targets);
+
+        for target in &targets {
+            self.close_unused(transport, &target.url);
+
+            if target.connected {
+                self.send_queued(transport, target, now);
+                self.send_counts(transport, &target.url, now);
+            }
+        }
+    }
+
+    /// One-shot subscriptions don't wait on relays that went down or were
+    /// removed from the pool. They count as answered there.
+    fn give_up_on_unreachable(&mut self, targets: &[RelayTarget]) {
+        let mut finished = vec![];
+
+        for (subid, sub) in &mut self.subs {
+            if sub.lifetime != SubLifetime::OneShot {
+                continue;
+            }
+
+            for (relay_url, state) in &mut sub.relays {
+                let connected = targets
+                    .iter()
+                    .any(|target| &target.url == relay_url && target.connected);
+                if connected || *state == RelaySubState::Done {
+                    continue;
+                }
+
+                debug!("{relay_url} is down, not waiting on it for {subid}");
+                if let Some(queue) = self.relays.get_mut(relay_url) {
+                    match &*state {
+                        RelaySubState::Queued => queue.queue.retain(|id| id != subid),
+                        RelaySubState::Open(wire_id) => {
+                            // closed once nobody else is in it, so the
+                            // relay doesn't send it again on reconnect
+                            if let Some(members) = queue.open.get_mut(wire_id) {
+                                members.retain(|id| id != subid);
+                            }
+                        }
+                        RelaySubState::Done => {}
+                    }
+                }
+                *state = RelaySubState::Done;
+            }
+
+            if sub.is_finished() {
+                finished.push(subid.clone());
+            }
+        }
+
+        for subid in finished {
+            self.remove(&subid);
+            self.finished.push(subid);
+        }
+    }
+
+    fn assign_relays(&mut self, targets: &[RelayTarget]) {
+        for (subid, sub) in &mut self.subs {
+            // one-shot requests only go to the relays that were up when
+            // they were made
+            if sub.lifetime == SubLifetime::OneShot && !sub.relays.is_empty() {
+                continue;
+            }
+
+            for target in targets {
+                if sub.relays.contains_key(&target.url) {
+                    continue;
+                }
+
+                if sub.lifetime == SubLifetime::OneShot && !target.connected {
+                    continue;
+                }
+
+                if let Some(relay) = &sub.relay {
+                    if relay != &target.url {
+                        continue;
+                    }
+                }
+
+                sub.relays.insert(target.url.clone(), RelaySubState::Queued);
+                self.relays
+                    .entry(target.url.clone())
+                    .or_default()
+                    .queue
+                    .push_back(subid.clone());
+            }
+        }
+    }
+
+    fn close_unused(&mut self, transport: &mut impl SubscriptionTransport, relay_url: &str) {
+        let unused: Vec<String> = if let Some(queue) = self.relays.get(relay_url) {
+            queue
+                .open
+                .iter()
+                .filter(|(_, members)| members.is_empty())
+                .map(|(wire_id, _)| wire_id.clone())
+                .collect()
+        } else {
+            return;
+        };
+
+        for wire_id in unused {
+            self.close(transport, relay_url, &wire_id);
+        }
+    }
+
+    fn send_queued(
+        &mut self,
+        transport: &mut impl SubscriptionTransport,
+        target: &RelayTarget,
+        now: Instant,
+    ) {
+        let max_filters = target.max_filters.unwrap_or(DEFAULT_MAX_FILTERS).max(1);
+        let max_subscriptions = target
+            .max_subscriptions
+            .unwrap_or(DEFAULT_MAX_SUBSCRIPTIONS);
+
+        loop {
+            let queue = if let Some(queue) = self.relays.get_mut(&target.url) {
+                queue
+            } else {
+                return;
+            };
+
+            if queue.queue.is_empty() || !queue.has_room(now) {
+                return;
+            }
+
+            let open: usize = queue.open_reqs.values().sum();
+            if open >= max_subscriptions {
+                return;
+            }
+
+            let (members, filters) = next_req(&mut queue.queue, &self.subs, max_filters);
+            if members.is_empty() {
+                continue;
+            }
+
+            let reqs = target.req_count(filters.len());
+            if open > 0 && open + reqs > max_subscriptions {
+                // wait for more of them to close. One that takes up more
+                // than the whole limit still goes out on its own.
+                for subid in members.into_iter().rev() {
+                    queue.queue.push_front(subid);
+                }
+                return;
+            }
+
+            let wire_id = if members.len() == 1 {
+                members[0].clone()
+            } else {
+                new_sub_id()
+            };
+
+            debug!(
+                "sending REQ {} to {} for {} subscriptions",
+                wire_id,
+                target.url,
+                members.len()
+            );
+
+            queue.sent.push_back(now);
+            queue.open.insert(wire_id.clone(), members.clone());
+            queue.open_reqs.insert(wire_id.clone(), reqs);
+
+            for subid in &members {
+                if let Some(sub) = self.subs.get_mut(subid) {
+                    sub.relays
+                        .insert(target.url.clone(), RelaySubState::Open(wire_id.clone()));
+                }
+            }
+
+            transport.send_to(&target.url, &ClientMessage::req(wire_id, filters));
+        }
+    }
+
+    /// Send the COUNTs waiting on a relay, once its REQs have gone out
+    fn send_counts(
+        &mut self,
+        transport: &mut impl SubscriptionTransport,
+        relay_url: &str,
+        now: Instant,
+    ) {
+        let queue = self.relays.entry(relay_url.to_owned()).or_default();
+
+        for (subid, count) in &mut self.counts {
+            if count.relay != relay_url || count.sent.is_some() {
+                continue;
+            }
+
+            if !queue.has_room(now) {
+                return;
+            }
+
+            debug!("sending COUNT {subid} to {relay_url}");
+            queue.sent.push_back(now);
+            count.sent = Some(now);
+            transport.send_to(
+                relay_url,
+                &ClientMessage::count(subid.clone(), count.filters.clone()),
+            );
+        }
+    }
+}
+
+/// Take the next REQ off a relay's queue. Persistent subscriptions go out
+/// on their own. One-shot subscriptions are coalesced with any other
+/// queued one-shot subscriptions, up to `max_filters` filters.
+fn next_req(
+    queue: &mut VecDeque<String>,
+    subs: &HashMap<String, ManagedSub>,
+    max_filters: usize,
+) -> (Vec<String>, Vec<Filter>) {
+    let first = if let Some(first) = queue.pop_front() {
+        first
+    } else {
+        return (vec![], vec![]);
+    };
+
+    let sub = if let Some(sub) = subs.get(&first) {
+        sub
+    } else {
+        error!("queued subscription {first} is gone");
+        return (vec![], vec![]);
+    };
+
+    let mut filters = sub.filters.clone();
+    let mut members = vec![first];
+
+    if sub.lifetime == SubLifetime::Persistent {
+        return (members, filters);
+    }
+
+    let mut i = 0;
+    while i < queue.len() {
+        match subs.get(&queue[i]) {
+            None => {
+                queue.remove(i);
+            }
+            Some(sub)
+                if sub.lifetime == SubLifetime::OneShot
+                    && filters.len() + sub.filters.len() <= max_filters =>
+            {
+                filters.extend(sub.filters.iter().cloned());
+                if let Some(subid) = queue.remove(i) {
+                    members.push(subid);
+                }
+            }
+            Some(_) => i += 1,
+        }
+    }
+
+    (members, filters)
+}
+
+fn shared_key(filters: &[Filter]) -> String {
+    filters
+        .iter()
+        .filter_map(|filter| filter.json().ok())
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A scripted stand-in for our relays that records what we send
+    struct FakeRelays {
+        targets: Vec<RelayTarget>,
+        sent: Vec<(String, String)>,
+    }
+
+    impl FakeRelays {
+        fn new(urls: &[&str]) -> Self {
+            FakeRelays {
+                targets: urls
+                    .iter()
+                    .map(|url| RelayTarget {
+                        url: url.to_string(),
+                        connected: true,
+                        max_filters: None,
+                        max_subscriptions: None,
+                    })
+                    .collect(),
+                sent: vec![],
+            }
+        }
+
+        fn take(&mut self) -> Vec<(String, String)> {
+            std::mem::take(&mut self.sent)
+        }
+
+        fn reqs(sent: &[(String, String)]) -> usize {
+            sent.iter()
+                .filter(|(_, msg)| msg.starts_with("[\"REQ\""))
+                .count()
+        }
+
+        fn closes(sent: &[(String, String)]) -> usize {
+            sent.iter()
+                .filter(|(_, msg)| msg.starts_with("[\"CLOSE\""))
+                .count()
+        }
+    }
+
+    impl SubscriptionTransport for FakeRelays {
+        fn relays(&self) -> Vec<RelayTarget> {
+            self.targets.clone()
+        }
+
+        fn send_to(&mut self, relay_url: &str, msg: &ClientMessage) {
+            self.sent
+                .push((relay_url.to_owned(), msg.to_json().unwrap()));
+        }
+    }
+
+    fn filter(kind: u64) -> Vec<Filter> {
+        vec![Filter::new().kinds([kind]).build()]
+    }
+
+    #[test]
+    fn test_coalesces_one_shot_subscriptions() {
+        let mut relays = FakeRelays::new(&["wss://a"]);
+        let mut manager = SubscriptionManager::default();
+
+        manager.subscribe("one".to_owned(), filter(0), SubLifetime::OneShot);
+        manager.subscribe("two".to_owned(), filter(1), SubLifetime::OneShot);
+        manager.subscribe("live".to_owned(), filter(2), SubLifetime::Persistent);
+        manager.poll(&mut relays);
+
+        let sent = relays.take();
+        // the two one-shots share a REQ, the persistent one gets its own
+        assert_eq!(FakeRelays::reqs(&sent), 2);
+    }
+
+    #[test]
+    fn test_closes_one_shot_on_eose() {
+        let mut relays = FakeRelays::new(&["wss://a", "wss://b"]);
+        let mut manager = SubscriptionManager::default();
+
+        manager.subscribe("once".to_owned(), filter(0), SubLifetime::OneShot);
+        manager.poll(&mut relays);
+        assert_eq!(FakeRelays::reqs(&relays.take()), 2);
+
+        let done = manager.handle_eose(&mut relays, "wss://a", "once");
+        assert_eq!(done, vec!["once".to_owned()]);
+        assert_eq!(FakeRelays::closes(&relays.take()), 1);
+
+        // still waiting on the other relay
+        assert!(manager.is_active("once"));
+        manager.handle_eose(&mut relays, "wss://b", "once");
+        assert!(!manager.is_active("once"));
+    }
+
+    #[test]
+    fn test_persistent_stays_open_on_eose() {
+        let mut relays = FakeRelays::new(&["wss://a"]);
+        let mut manager = SubscriptionManager::default();
+
+        manager.subscribe("live".to_owned(), filter(1), SubLifetime::Persistent);
+        manager.poll(&mut relays);
+        relays.take();
+
+        manager.handle_eose(&mut relays, "wss://a", "live");
+        assert_eq!(FakeRelays::closes(&relays.take()), 0);
+        assert!(manager.is_active("live"));
+    }
+
+    #[test]
+    fn test_rate_limits_reqs() {
+        let mut relays = FakeRelays::new(&["wss://a"]);
+        let mut manager = SubscriptionManager::default();
+
+        for i in 0..(MAX_REQS_PER_WINDOW as u64 + 5) {
+            manager.subscribe(format!("live{i}"), filter(i), SubLifetime::Persistent);
+        }
+
+        let now = Instant::now();
+        manager.poll_at(&mut relays, now);
+        assert_eq!(FakeRelays::reqs(&relays.take()), MAX_REQS_PER_WINDOW);
+
+        manager.poll_at(&mut relays, now + REQ_RATE_WINDOW);
+        assert_eq!(FakeRelays::reqs(&relays.take()), 5);
+    }
+
+    #[test]
+    fn test_holds_back_past_relay_limit() {
+        let mut relays = FakeRelays::new(&["wss://a"]);
+        relays.targets[0].max_filters = Some(2);
+        relays.targets[0].max_subscriptions = Some(2);
+        let mut manager = SubscriptionManager::default();
+
+        // the relay splits this one into two REQs, which is all it allows
+        let filters = (0..3).flat_map(filter).collect();
+        manager.subscribe("split".to_owned(), filters, SubLifetime::Persistent);
+        manager.subscribe("live".to_owned(), filter(3), SubLifetime::Persistent);
+        manager.poll(&mut relays);
+        assert_eq!(FakeRelays::reqs(&relays.take()), 1);
+
+        // closing it makes room
+        manager.unsubscribe("split");
+        manager.poll(&mut relays);
+        let sent = relays.take();
+        assert_eq!(FakeRelays::closes(&sent), 1);
+        assert_eq!(FakeRelays::reqs(&sent), 1);
+
+        // without an information document we still hold back
+        relays.targets[0].max_filters = None;
+        relays.targets[0].max_subscriptions = None;
+        let mut now = Instant::now();
+        for i in 0..(DEFAULT_MAX_SUBSCRIPTIONS as u64 + 2) {
+            manager.subscribe(format!("live{i}"), filter(i), SubLifetime::Persistent);
+        }
+        let mut reqs = 0;
+        for _ in 0..4 {
+            manager.poll_at(&mut relays, now);
+            reqs += FakeRelays::reqs(&relays.take());
+            now += REQ_RATE_WINDOW;
+        }
+        // "live" is still open
+        assert_eq!(reqs, DEFAULT_MAX_SUBSCRIPTIONS - 1);
+    }
+
+    #[test]
+    fn test_coalesced_members() {
+        let mut relays = FakeRelays::new(&["wss://a"]);
+        let mut manager = SubscriptionManager::default();
+
+        manager.subscribe("one".to_owned(), filter(0), SubLifetime::OneShot);
+        manager.subscribe("two".to_owned(), filter(1), SubLifetime::OneShot);
+        manager.poll(&mut relays);
+
+        let sent = relays.take();
+        let wire_id = sent[0].1.split('"').nth(3).unwrap().to_owned();
+        assert_eq!(manager.members("wss://a", &wire_id), ["one", "two"]);
+        assert_eq!(manager.members("wss://b", &wire_id), [wire_id.clone()]);
+        assert_eq!(manager.members("wss://a", "other"), ["other"]);
+
+        assert_eq!(
+            manager.handle_eose(&mut relays, "wss://a", &wire_id),
+            ["one", "two"]
+        );
+        assert!(!manager.is_active("one"));
+    }
+
+    #[test]
+    fn test_shared_subscriptions_are_ref_counted() {
+        let mut relays = FakeRelays::new(&["wss://a"]);
+        let mut manager = SubscriptionManager::default();
+
+        let first = manager.subscribe_shared(filter(0), SubLifetime::Persistent);
+        let second = manager.subscribe_shared(filter(0), SubLifetime::Persistent);
+        assert_eq!(first, second);
+
+        manager.poll(&mut relays);
+        assert_eq!(FakeRelays::reqs(&relays.take()), 1);
+
+        manager.unsubscribe(&first);
+        manager.poll(&mut relays);
+        assert_eq!(FakeRelays::closes(&relays.take()), 0);
+
+        manager.unsubscribe(&second);
+        manager.poll(&mut relays);
+        assert_eq!(FakeRelays::closes(&relays.take()), 1);
+    }
+
+    #[test]
+    fn test_counts_time_out() {
+        let mut relays = FakeRelays::new(&["wss://a", "wss://b"]);
+        let mut manager = SubscriptionManager::default();
+
+        manager.count_to("wss://a", "answered".to_owned(), filter(3));
+        manager.count_to("wss://b", "ignored".to_owned(), filter(3));
+
+        let now = Instant::now();
+        manager.poll_at(&mut relays, now);
+        let sent = relays.take();
+        assert_eq!(sent.len(), 2);
+        assert!(sent.iter().all(|(_, msg)| msg.starts_with("[\"COUNT\"")));
+
+        // only the relay we asked can answer
+        assert!(!manager.handle_count("wss://b", "answered"));
+        assert!(manager.handle_count("wss://a", "answered"));
+        assert!(!manager.is_counting("answered"));
+
+        manager.poll_at(&mut relays, now + COUNT_TIMEOUT);
+        assert!(!manager.is_counting("ignored"));
+        assert!(relays.take().is_empty());
+    }
+
+    #[test]
+    fn test_waits_for_connection() {
+        let mut relays = FakeRelays::new(&["wss://a"]);
+        relays.targets[0].connected = false;
+        let mut manager = SubscriptionManager::default();
+
+        manager.subscribe("live".to_owned(), filter(1), SubLifetime::Persistent);
+        manager.poll(&mut relays);
+        assert!(relays.take().is_empty());
+
+        relays.targets[0].connected = true;
+        manager.poll(&mut relays);
+        assert_eq!(FakeRelays::reqs(&relays.take()), 1);
+    }
+
+    #[test]
+    fn test_one_shot_skips_disconnected_relays() {
+        let mut relays = FakeRelays::new(&["wss://a", "ws://localhost:8080"]);
+        relays.targets[1].connected = false;
+        let mut manager = SubscriptionManager::default();
+
+        manager.subscribe("once".to_owned(), filter(0), SubLifetime::OneShot);
+        manager.poll(&mut relays);
+        let sent = relays.take();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].0, "wss://a");
+
+        // the relay that's down doesn't hold it open
+        manager.handle_eose(&mut relays, "wss://a", "once");
+        assert!(!manager.is_active("once"));
+
+        // and doesn't get it when it comes back
+        relays.targets[1].connected = true;
+        manager.poll(&mut relays);
+        assert!(relays.take().is_empty());
+        assert!(manager.take_finished().is_empty());
+    }
+
+    #[test]
+    fn test_one_shot_finishes_when_relay_goes_down() {
+        let mut relays = FakeRelays::new(&["wss://a", "wss://b"]);
+        let mut manager = SubscriptionManager::default();
+
+        manager.subscribe("once".to_owned(), filter(0), SubLifetime::OneShot);
+        manager.poll(&mut relays);
+        relays.take();
+
+        manager.handle_eose(&mut relays, "wss://a", "once");
+        relays.take();
+        assert!(manager.is_active("once"));
+
+        relays.targets[1].connected = false;
+        manager.poll(&mut relays);
+        assert!(!manager.is_active("once"));
+        assert_eq!(manager.take_finished(), ["once"]);
+        assert!(manager.take_finished().is_empty());
+
+        // the REQ is closed so it isn't replayed on reconnect
+        let sent = relays.take();
+        assert_eq!(FakeRelays::closes(&sent), 1);
+        assert_eq!(sent[0].0, "wss://b");
+    }
+}
@@ -1,3 +1,4 @@
+use crate::subscription_manager::SubscriptionManager;
 use crate::timeline::{TimelineId, TimelineKind};
 use std::collections::HashMap;
 use uuid::Uuid;
@@ -13,18 +14,36 @@ pub enum SubKind {
 
     Timeline(TimelineKind),
 
-    /// We are fetching a contact list so that we can use it for our follows
-    /// Filter.
-    // TODO: generalize this to any list?
-    FetchingContactList(TimelineId),
+    /// A NIP-50 search request from the search view. These are closed
+    /// after EOSE, and let the search know it can pick up the results.
+    Search,
+
+    /// We are fetching a contact list or NIP-51 list so that we can use
+    /// it for our timeline filter.
+    FetchingList(TimelineId),
+
+    /// Fetching one of our contact lists before we edit it. See
+    /// [`crate::contacts::ContactEdits`]
+    ContactList,
+
+    /// Fetching our profile before we edit it. See
+    /// [`crate::ui::profile::edit::EditProfileDraft`]
+    Metadata,
+
+    /// Fetching a note a thread needs. See
+    /// [`crate::missing_notes::MissingNotes`]
+    FetchingNote,
 }
 
 /// Subscriptions that need to be tracked at various stages. Sometimes we
 /// need to do A, then B, then C. Tracking requests at various stages by
 /// mapping uuid subids to explicit states happens here.
+///
+/// The REQs themselves go out through the [`SubscriptionManager`].
 #[derive(Default)]
 pub struct Subscriptions {
     pub subs: HashMap<String, SubKind>,
+    pub manager: SubscriptionManager,
 }
 
 pub fn new_sub_id() -> String {
@@ -1,3 +1,6 @@
+use crate::signer::{SignedNote, Signer};
+use enostr::FullKeypair;
+use nostrdb::Note;
 use poll_promise::Promise;
 use std::thread;
 use std::time::Duration;
@@ -34,3 +37,18 @@ macro_rules! promise_assert {
         $assertion_closure!(*result, $expected);
     };
 }
+
+/// Run `f` with a signer that has the key of `kp`, for tests of code that
+/// signs notes
+pub fn with_signer<R>(kp: &FullKeypair, f: impl FnOnce(&Signer) -> R) -> R {
+    let account = kp.to_filled().to_full().to_keypair();
+    f(&Signer::new(&account).expect("local signer"))
+}
+
+/// The note a signer with a local key signed
+pub fn local_note(note: SignedNote) -> Note<'static> {
+    match note {
+        SignedNote::Local(note) => note,
+        SignedNote::Remote(_) => panic!("expected a local signature"),
+    }
+}
@@ -5,12 +5,70 @@ use crate::{
     notes_holder::NotesHolder,
     timeline::{TimelineTab, ViewFilter},
 };
-use nostrdb::{Filter, FilterBuilder, Ndb, Transaction};
+use egui_virtual_list::VirtualList;
+use enostr::NoteId;
+use nostrdb::{Filter, FilterBuilder, Ndb, NoteKey, Transaction};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
 
 #[derive(Default)]
 pub struct Thread {
     view: TimelineTab,
     pub multi_subscriber: Option<MultiSubscriber>,
+
+    /// Branches the user expanded or collapsed
+    toggled: HashSet<NoteId>,
+
+    /// The rows we last laid out, until a note comes in or a branch is
+    /// toggled
+    rows: Option<CachedRows>,
+
+    /// Lays out the rows rather than the view's notes, so it's separate
+    /// from the view's list
+    pub list: Rc<RefCell<VirtualList>>,
+
+    /// The selected note we last scrolled to
+    pub scrolled_to: Option<NoteId>,
+}
+
+struct CachedRows {
+    root: NoteId,
+    selected: NoteId,
+    /// Notes also come in through the view, so we check how many there
+    /// were too
+    notes: usize,
+    rows: Vec<ThreadRow>,
+}
+
+/// A note in a thread and the note it replies to
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct ThreadEntry {
+    pub key: NoteKey,
+    pub id: NoteId,
+    pub parent: Option<NoteId>,
+    pub created_at: u64,
+}
+
+/// A line in the threaded view, in the order they are shown
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ThreadRow {
+    Note {
+        key: NoteKey,
+        id: NoteId,
+        depth: usize,
+        /// How many notes are below this one
+        replies: usize,
+        collapsed: bool,
+    },
+
+    /// A note we're still fetching, with the replies to it we already have
+    Pending {
+        id: NoteId,
+        depth: usize,
+        replies: usize,
+        collapsed: bool,
+    },
 }
 
 impl Thread {
@@ -22,9 +80,17 @@ impl Thread {
         let mut view = TimelineTab::new_with_capacity(ViewFilter::NotesAndReplies, cap);
         view.notes = notes;
 
+        let mut list = VirtualList::new();
+        list.hide_on_resize(None);
+        list.over_scan(1000.0);
+
         Thread {
             view,
             multi_subscriber: None,
+            toggled: HashSet::new(),
+            rows: None,
+            list: Rc::new(RefCell::new(list)),
+            scrolled_to: None,
         }
     }
 
@@ -36,6 +102,90 @@ impl Thread {
         &mut self.view
     }
 
+    /// Add a note the thread's filters don't catch, like a parent that
+    /// doesn't tag the root
+    pub fn insert_note(&mut self, note_ref: NoteRef) {
+        if !self
+            .view
+            .notes
+            .iter()
+            .any(|known| known.key == note_ref.key)
+        {
+            self.view.insert(&[note_ref], false);
+            self.rows = None;
+        }
+    }
+
+    /// Expand a collapsed branch, or collapse an expanded one
+    pub fn toggle(&mut self, id: NoteId) {
+        if !self.toggled.remove(&id) {
+            self.toggled.insert(id);
+        }
+        self.rows = None;
+    }
+
+    /// The thread's notes as a reply tree, with collapsed branches left
+    /// out
+    pub fn rows(
+        &mut self,
+        txn: &Transaction,
+        ndb: &Ndb,
+        note_cache: &mut NoteCache,
+        root: &NoteId,
+        selected: &NoteId,
+    ) -> &[ThreadRow] {
+        let notes = self.view.notes.len();
+        let fresh = self.rows.as_ref().is_some_and(|cached| {
+            cached.root == *root && cached.selected == *selected && cached.notes == notes
+        });
+
+        if !fresh {
+            let rows = self.layout(txn, ndb, note_cache, root, selected);
+            self.list.borrow_mut().reset();
+            self.rows = Some(CachedRows {
+                root: *root,
+                selected: *selected,
+                notes,
+                rows,
+            });
+        }
+
+        self.rows.as_ref().map_or(&[], |cached| &cached.rows)
+    }
+
+    fn layout(
+        &self,
+        txn: &Transaction,
+        ndb: &Ndb,
+        note_cache: &mut NoteCache,
+        root: &NoteId,
+        selected: &NoteId,
+    ) -> Vec<ThreadRow> {
+        let entries: Vec<ThreadEntry> = self
+            .view
+            .notes
+            .iter()
+            .filter_map(|note_ref| {
+                let note = ndb.get_note_by_key(txn, note_ref.key).ok()?;
+                let cached_note = note_cache.cached_note_or_insert(note_ref.key, &note);
+                let note_reply = cached_note.reply.borrow(note.tags());
+                let parent = note_reply
+                    .reply()
+                    .or_else(|| note_reply.root())
+                    .map(|parent| NoteId::new(*parent.id));
+
+                Some(ThreadEntry {
+                    key: note_ref.key,
+                    id: NoteId::new(*note.id()),
+                    parent,
+                    created_at: note_ref.created_at,
+                })
+            })
+            .collect();
+
+        thread_rows(root, selected, &entries, &self.toggled)
+    }
+
     fn filters_raw(root: &[u8; 32]) -> Vec<FilterBuilder> {
         vec![
             nostrdb::Filter::new().kinds([1]).event(root),
@@ -58,6 +208,196 @@ impl Thread {
     }
 }
 
+/// Lay out a thread's notes as a reply tree. Replies are in the order
+/// they were posted, below the note they reply to. Replies to notes we
+/// don't have yet go below a placeholder for that note, which starts
+/// collapsed unless the selected note is in there.
+pub fn thread_rows(
+    root: &NoteId,
+    selected: &NoteId,
+    entries: &[ThreadEntry],
+    toggled: &HashSet<NoteId>,
+) -> Vec<ThreadRow> {
+    let mut tree = ThreadTree::new(entries, toggled);
+
+    // the notes the selected note replies to, all the way up
+    let mut parent = Some(*selected);
+    while let Some(id) = parent {
+        if !tree.selected_path.insert(id) {
+            break;
+        }
+        parent = tree.entry(&id).and_then(|entry| entry.parent);
+    }
+
+    let mut rows = vec![];
+    if let Some(root_entry) = tree.entry(root) {
+        tree.emit_note(root_entry, 0, &mut rows);
+    } else if tree.children.contains_key(root) {
+        tree.emit_pending(root, 0, &mut rows);
+    }
+
+    // notes without a parent, which should only ever be the root
+    for entry in entries {
+        if entry.parent.is_none() && entry.id != *root {
+            tree.emit_note(entry, 0, &mut rows);
+        }
+    }
+
+    // replies to notes we don't have go below the root
+    let mut missing: Vec<(&ThreadEntry, NoteId)> = tree
+        .children
+        .iter()
+        .filter(|(parent, _)| *parent != root && tree.entry(parent).is_none())
+        .map(|(parent, children)| (&entries[children[0]], *parent))
+        .collect();
+    missing.sort_by_key(|(first_reply, _)| (first_reply.created_at, first_reply.key));
+
+    let depth = if rows.is_empty() { 0 } else { 1 };
+    for (_, parent) in missing {
+        tree.emit_pending(&parent, depth, &mut rows);
+    }
+
+    rows
+}
+
+struct ThreadTree<'a> {
+    entries: &'a [ThreadEntry],
+    toggled: &'a HashSet<NoteId>,
+    by_id: HashMap<NoteId, usize>,
+    /// Replies to each note, oldest first
+    children: HashMap<NoteId, Vec<usize>>,
+    /// How many notes are below each note
+    reply_counts: HashMap<NoteId, usize>,
+    selected_path: HashSet<NoteId>,
+    /// Notes we've shown, so that a reply loop can't hang us
+    emitted: HashSet<NoteId>,
+}
+
+impl<'a> ThreadTree<'a> {
+    fn new(entries: &'a [ThreadEntry], toggled: &'a HashSet<NoteId>) -> Self {
+        let mut by_id = HashMap::new();
+        let mut children: HashMap<NoteId, Vec<usize>> = HashMap::new();
+
+        for (i, entry) in entries.iter().enumerate() {
+            by_id.insert(entry.id, i);
+            if let Some(parent) = entry.parent.filter(|parent| *parent != entry.id) {
+                children.entry(parent).or_default().push(i);
+            }
+        }
+
+        for replies in children.values_mut() {
+            replies.sort_by_key(|i| (entries[*i].created_at, entries[*i].key));
+        }
+
+        let reply_counts = count_replies(entries, &children);
+
+        ThreadTree {
+            entries,
+            toggled,
+            by_id,
+            children,
+            reply_counts,
+            selected_path: HashSet::new(),
+            emitted: HashSet::new(),
+        }
+    }
+
+    fn entry(&self, id: &NoteId) -> Option<&'a ThreadEntry> {
+        self.by_id.get(id).map(|i| &self.entries[*i])
+    }
+
+    fn replies(&self, id: &NoteId) -> usize {
+        self.reply_counts.get(id).copied().unwrap_or(0)
+    }
+
+    fn is_collapsed(&self, id: &NoteId, collapsed_by_default: bool) -> bool {
+        collapsed_by_default != self.toggled.contains(id)
+    }
+
+    fn emit_note(&mut self, entry: &ThreadEntry, depth: usize, rows: &mut Vec<ThreadRow>) {
+        if !self.emitted.insert(entry.id) {
+            return;
+        }
+
+        let collapsed = self.is_collapsed(&entry.id, false);
+        rows.push(ThreadRow::Note {
+            key: entry.key,
+            id: entry.id,
+            depth,
+            replies: self.replies(&entry.id),
+            collapsed,
+        });
+
+        if !collapsed {
+            self.emit_replies(&entry.id, depth + 1, rows);
+        }
+    }
+
+    fn emit_pending(&mut self, id: &NoteId, depth: usize, rows: &mut Vec<ThreadRow>) {
+        if !self.emitted.insert(*id) {
+            return;
+        }
+
+        let collapsed = self.is_collapsed(id, !self.selected_path.contains(id));
+        rows.push(ThreadRow::Pending {
+            id: *id,
+            depth,
+            replies: self.replies(id),
+            collapsed,
+        });
+
+        if !collapsed {
+            self.emit_replies(id, depth + 1, rows);
+        }
+    }
+
+    fn emit_replies(&mut self, id: &NoteId, depth: usize, rows: &mut Vec<ThreadRow>) {
+        let replies = self.children.get(id).cloned().unwrap_or_default();
+        for i in replies {
+            let entries = self.entries;
+            self.emit_note(&entries[i], depth, rows);
+        }
+    }
+}
+
+/// Count the notes below each note in one walk of the tree, counting
+/// replies before the notes they reply to
+fn count_replies(
+    entries: &[ThreadEntry],
+    children: &HashMap<NoteId, Vec<usize>>,
+) -> HashMap<NoteId, usize> {
+    let mut counts: HashMap<NoteId, usize> = HashMap::with_capacity(children.len());
+    // notes we're below, so that a reply loop can't hang us
+    let mut walking: HashSet<NoteId> = HashSet::new();
+
+    for start in children.keys() {
+        let mut stack = vec![(*start, false)];
+        while let Some((id, replies_counted)) = stack.pop() {
+            if replies_counted {
+                let count = children
+                    .get(&id)
+                    .into_iter()
+                    .flatten()
+                    .map(|i| 1 + counts.get(&entries[*i].id).copied().unwrap_or(0))
+                    .sum();
+                counts.insert(id, count);
+                continue;
+            }
+
+            if counts.contains_key(&id) || !walking.insert(id) {
+                continue;
+            }
+
+            stack.push((id, true));
+            for i in children.get(&id).into_iter().flatten() {
+                stack.push((entries[*i].id, false));
+            }
+        }
+    }
+
+    counts
+}
+
 impl NotesHolder for Thread {
     fn get_multi_subscriber(&mut self) -> Option<&mut MultiSubscriber> {
         self.multi_subscriber.as_mut()
@@ -90,3 +430,131 @@ impl NotesHolder for Thread {
         self.multi_subscriber = Some(subscriber);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(n: u8, parent: Option<u8>) -> ThreadEntry {
+        ThreadEntry {
+            key: NoteKey::new(n as u64),
+            id: NoteId::new([n; 32]),
+            parent: parent.map(|p| NoteId::new([p; 32])),
+            created_at: n as u64,
+        }
+    }
+
+    fn summary(rows: &[ThreadRow]) -> Vec<(u8, usize, usize, bool)> {
+        rows.iter()
+            .map(|row| match row {
+                ThreadRow::Note {
+                    id,
+                    depth,
+                    replies,
+                    collapsed,
+                    ..
+                }
+                | ThreadRow::Pending {
+                    id,
+                    depth,
+                    replies,
+                    collapsed,
+                } => (id.bytes()[0], *depth, *replies, *collapsed),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_reply_tree() {
+        let root = NoteId::new([1; 32]);
+        // out of order, like they come from the timeline
+        let entries = [
+            entry(4, Some(2)),
+            entry(3, Some(1)),
+            entry(2, Some(1)),
+            entry(1, None),
+            entry(5, Some(4)),
+        ];
+
+        let rows = thread_rows(&root, &root, &entries, &HashSet::new());
+        assert_eq!(
+            summary(&rows),
+            vec![
+                (1, 0, 4, false),
+                (2, 1, 2, false),
+                (4, 2, 1, false),
+                (5, 3, 0, false),
+                (3, 1, 0, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_collapsed_branch() {
+        let root = NoteId::new([1; 32]);
+        let entries = [
+            entry(1, None),
+            entry(2, Some(1)),
+            entry(3, Some(2)),
+            entry(4, Some(1)),
+        ];
+        let toggled: HashSet<NoteId> = [NoteId::new([2; 32])].into_iter().collect();
+
+        let rows = thread_rows(&root, &root, &entries, &toggled);
+        assert_eq!(
+            summary(&rows),
+            vec![(1, 0, 3, false), (2, 1, 1, true), (4, 1, 0, false)]
+        );
+    }
+
+    #[test]
+    fn test_replies_to_missing_notes() {
+        let root = NoteId::new([1; 32]);
+        // 9 is still being fetched
+        let entries = [entry(1, None), entry(2, Some(9)), entry(3, Some(2))];
+
+        let rows = thread_rows(&root, &root, &entries, &HashSet::new());
+        assert_eq!(summary(&rows), vec![(1, 0, 0, false), (9, 1, 2, true)]);
+
+        // unless we're looking at one of them
+        let selected = NoteId::new([3; 32]);
+        let rows = thread_rows(&root, &selected, &entries, &HashSet::new());
+        assert_eq!(
+            summary(&rows),
+            vec![
+                (1, 0, 0, false),
+                (9, 1, 2, false),
+                (2, 2, 1, false),
+                (3, 3, 0, false)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reply_counts_in_long_chain() {
+        // each note replies to the one before it
+        let entries: Vec<ThreadEntry> = (1..=200u8)
+            .map(|n| entry(n, if n == 1 { None } else { Some(n - 1) }))
+            .collect();
+        let mut children: HashMap<NoteId, Vec<usize>> = HashMap::new();
+        for (i, entry) in entries.iter().enumerate() {
+            if let Some(parent) = entry.parent {
+                children.entry(parent).or_default().push(i);
+            }
+        }
+
+        let counts = count_replies(&entries, &children);
+        assert_eq!(counts[&NoteId::new([1; 32])], 199);
+        assert_eq!(counts[&NoteId::new([150; 32])], 50);
+        assert_eq!(counts.get(&NoteId::new([200; 32])), None);
+    }
+
+    #[test]
+    fn test_reply_loops_terminate() {
+        let root = NoteId::new([1; 32]);
+        let entries = [entry(1, None), entry(2, Some(3)), entry(3, Some(2))];
+
+        let rows = thread_rows(&root, &root, &entries, &HashSet::new());
+        assert_eq!(rows.len(), 1);
+    }
+}
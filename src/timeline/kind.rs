@@ -1,10 +1,15 @@
+use crate::article;
 use crate::error::{Error, FilterError};
 use crate::filter;
 use crate::filter::FilterState;
-use crate::timeline::Timeline;
+use crate::lists;
+use crate::notifications;
+use crate::search;
+use crate::timeline::{Timeline, ViewFilter};
 use crate::ui::profile::preview::get_profile_displayname_string;
+use crate::Result;
 use enostr::{Filter, Pubkey};
-use nostrdb::{Ndb, Transaction};
+use nostrdb::{Ndb, Note, Transaction};
 use serde::{Deserialize, Serialize};
 use std::fmt::Display;
 use tracing::{error, warn};
@@ -15,9 +20,102 @@ pub enum PubkeySource {
     DeckAuthor,
 }
 
+impl PubkeySource {
+    pub fn to_pubkey<'a>(&'a self, default_user: Option<&'a [u8; 32]>) -> Option<&'a [u8; 32]> {
+        match self {
+            PubkeySource::DeckAuthor => default_user,
+            PubkeySource::Explicit(pk) => Some(pk.bytes()),
+        }
+    }
+}
+
+/// Lists that can be used as the source of a timeline. Besides contact
+/// lists, these are the NIP-51 lists we know how to turn into filters.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ListKind {
     Contact(PubkeySource),
+
+    /// kind 30000 follow set, identified by its d tag
+    FollowSet(PubkeySource, String),
+
+    /// kind 10003 bookmarks
+    Bookmarks(PubkeySource),
+
+    /// kind 10001 pinned notes
+    Pinned(PubkeySource),
+
+    /// kind 10015 interests
+    Interests(PubkeySource),
+}
+
+impl ListKind {
+    pub fn pubkey_source(&self) -> &PubkeySource {
+        match self {
+            ListKind::Contact(pk_src) => pk_src,
+            ListKind::FollowSet(pk_src, _) => pk_src,
+            ListKind::Bookmarks(pk_src) => pk_src,
+            ListKind::Pinned(pk_src) => pk_src,
+            ListKind::Interests(pk_src) => pk_src,
+        }
+    }
+
+    /// The kind of the list note
+    pub fn kind(&self) -> u64 {
+        match self {
+            ListKind::Contact(_) => 3,
+            ListKind::FollowSet(_, _) => 30000,
+            ListKind::Bookmarks(_) => 10003,
+            ListKind::Pinned(_) => 10001,
+            ListKind::Interests(_) => 10015,
+        }
+    }
+
+    /// The d tag of parameterized replaceable lists
+    pub fn identifier(&self) -> Option<&str> {
+        match self {
+            ListKind::FollowSet(_, d) => Some(d),
+            _ => None,
+        }
+    }
+
+    /// The filter for fetching the list note itself
+    pub fn list_filter(&self, pk: &[u8; 32]) -> Filter {
+        let filter = Filter::new().authors([pk]).kinds([self.kind()]);
+
+        let filter = if let Some(d) = self.identifier() {
+            filter.tags([d.to_owned()], 'd')
+        } else {
+            filter
+        };
+
+        filter.limit(1).build()
+    }
+
+    /// Build the timeline filter from the list note
+    pub fn timeline_filter(&self, list: &Note) -> Result<Vec<Filter>> {
+        // bookmark timelines follow the list itself, and may only have
+        // private entries
+        if let ListKind::Bookmarks(_) = self {
+            return Ok(vec![self.list_filter(list.pubkey())]);
+        }
+
+        let tags = filter::filter_from_tags(list)?;
+
+        let filter = match self {
+            ListKind::Contact(_) | ListKind::FollowSet(_, _) | ListKind::Interests(_) => {
+                tags.into_follow_filter()
+            }
+            ListKind::Bookmarks(_) | ListKind::Pinned(_) => {
+                tags.into_ids_filter(filter::default_limit())
+            }
+        };
+
+        if filter.is_empty() {
+            Err(Error::empty_list())
+        } else {
+            Ok(filter)
+        }
+    }
 }
 
 ///
@@ -42,17 +140,29 @@ pub enum TimelineKind {
     Generic,
 
     Hashtag(String),
+
+    /// NIP-50 fulltext search
+    Search(String),
+
+    /// NIP-23 long-form articles
+    Articles,
 }
 
 impl Display for TimelineKind {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             TimelineKind::List(ListKind::Contact(_src)) => f.write_str("Contacts"),
+            TimelineKind::List(ListKind::FollowSet(_src, _)) => f.write_str("Follow Set"),
+            TimelineKind::List(ListKind::Bookmarks(_src)) => f.write_str("Bookmarks"),
+            TimelineKind::List(ListKind::Pinned(_src)) => f.write_str("Pinned"),
+            TimelineKind::List(ListKind::Interests(_src)) => f.write_str("Interests"),
             TimelineKind::Generic => f.write_str("Timeline"),
             TimelineKind::Notifications(_) => f.write_str("Notifications"),
             TimelineKind::Profile(_) => f.write_str("Profile"),
             TimelineKind::Universe => f.write_str("Universe"),
             TimelineKind::Hashtag(_) => f.write_str("Hashtag"),
+            TimelineKind::Search(_) => f.write_str("Search"),
+            TimelineKind::Articles => f.write_str("Articles"),
         }
     }
 }
@@ -78,6 +188,46 @@ impl TimelineKind {
         TimelineKind::Notifications(pk)
     }
 
+    pub fn is_search(&self) -> bool {
+        matches!(self, TimelineKind::Search(_))
+    }
+
+    /// Columns anyone can end up in, which is where the spam is
+    pub fn is_open_feed(&self) -> bool {
+        matches!(
+            self,
+            TimelineKind::Universe
+                | TimelineKind::Hashtag(_)
+                | TimelineKind::Search(_)
+                | TimelineKind::Articles
+        )
+    }
+
+    /// The tabs a timeline of this kind has
+    pub fn view_filters(&self) -> Vec<ViewFilter> {
+        match self {
+            TimelineKind::Notifications(_) => vec![
+                ViewFilter::All,
+                ViewFilter::Mentions,
+                ViewFilter::Reactions,
+                ViewFilter::Zaps,
+            ],
+            TimelineKind::Profile(_) => vec![
+                ViewFilter::Notes,
+                ViewFilter::NotesAndReplies,
+                ViewFilter::Media,
+                ViewFilter::Likes,
+                ViewFilter::Zaps,
+            ],
+            TimelineKind::Articles => vec![ViewFilter::All],
+            _ => vec![ViewFilter::Notes, ViewFilter::NotesAndReplies],
+        }
+    }
+
+    pub fn is_bookmarks(&self) -> bool {
+        matches!(self, TimelineKind::List(ListKind::Bookmarks(_)))
+    }
+
     pub fn into_timeline(self, ndb: &Ndb, default_user: Option<&[u8; 32]>) -> Option<Timeline> {
         match self {
             TimelineKind::Universe => Some(Timeline::new(
@@ -119,7 +269,7 @@ impl TimelineKind {
 
                 let notifications_filter = Filter::new()
                     .pubkeys([pk])
-                    .kinds([1])
+                    .kinds(notifications::NOTIFICATION_KINDS)
                     .limit(crate::filter::default_limit())
                     .build();
 
@@ -142,30 +292,61 @@ impl TimelineKind {
                 ))
             }
 
-            TimelineKind::List(ListKind::Contact(pk_src)) => {
-                let pk = match &pk_src {
-                    PubkeySource::DeckAuthor => default_user?,
-                    PubkeySource::Explicit(pk) => pk.bytes(),
-                };
+            TimelineKind::Articles => Some(Timeline::new(
+                TimelineKind::Articles,
+                FilterState::ready(vec![Filter::new()
+                    .kinds([article::KIND_ARTICLE as u64])
+                    .limit(filter::default_limit())
+                    .build()]),
+            )),
 
-                let contact_filter = Filter::new().authors([pk]).kinds([3]).limit(1).build();
+            TimelineKind::Search(query) => {
+                let filter = search::search_filter(&query);
+
+                Some(Timeline::new(
+                    TimelineKind::Search(query),
+                    FilterState::ready(vec![filter]),
+                ))
+            }
+
+            // Bookmark timelines follow the list itself and are built from
+            // it in saved order. See bookmarks::BookmarkTimelines
+            TimelineKind::List(ListKind::Bookmarks(pk_src)) => {
+                let list_kind = ListKind::Bookmarks(pk_src);
+                let pk = list_kind.pubkey_source().to_pubkey(default_user)?;
+                let list_filter = list_kind.list_filter(pk);
+
+                Some(Timeline::new(
+                    TimelineKind::List(list_kind),
+                    FilterState::ready(vec![list_filter]),
+                ))
+            }
+
+            TimelineKind::List(list_kind) => {
+                let pk = list_kind.pubkey_source().to_pubkey(default_user)?;
+                let list_filter = list_kind.list_filter(pk);
 
                 let txn = Transaction::new(ndb).expect("txn");
-                let results = ndb
-                    .query(&txn, &[contact_filter.clone()], 1)
-                    .expect("contact query failed?");
+                let results = match ndb.query(&txn, &[list_filter.clone()], 1) {
+                    Ok(results) => results,
+                    Err(err) => {
+                        error!("error querying list for {list_kind:?}: {err}");
+                        vec![]
+                    }
+                };
 
                 if results.is_empty() {
                     return Some(Timeline::new(
-                        TimelineKind::contact_list(pk_src),
-                        FilterState::needs_remote(vec![contact_filter.clone()]),
+                        TimelineKind::List(list_kind),
+                        FilterState::needs_remote(vec![list_filter]),
                     ));
                 }
 
-                match Timeline::contact_list(&results[0].note, pk_src.clone()) {
-                    Err(Error::Filter(FilterError::EmptyContactList)) => Some(Timeline::new(
-                        TimelineKind::contact_list(pk_src),
-                        FilterState::needs_remote(vec![contact_filter]),
+                match Timeline::from_list(&results[0].note, list_kind.clone()) {
+                    Err(Error::Filter(FilterError::EmptyContactList))
+                    | Err(Error::Filter(FilterError::EmptyList)) => Some(Timeline::new(
+                        TimelineKind::List(list_kind),
+                        FilterState::needs_remote(vec![list_filter]),
                     )),
                     Err(e) => {
                         error!("Unexpected error: {e}");
@@ -186,6 +367,40 @@ impl TimelineKind {
                     }
                     PubkeySource::DeckAuthor => "Contacts".to_owned(),
                 },
+                ListKind::FollowSet(pubkey_source, d) => {
+                    let title = match pubkey_source {
+                        PubkeySource::Explicit(pubkey) => lists::list_title(ndb, pubkey, list_kind),
+                        PubkeySource::DeckAuthor => None,
+                    };
+                    title.unwrap_or_else(|| d.to_owned())
+                }
+                ListKind::Bookmarks(pubkey_source) => match pubkey_source {
+                    PubkeySource::Explicit(pubkey) => {
+                        format!(
+                            "{}'s Bookmarks",
+                            get_profile_displayname_string(ndb, pubkey)
+                        )
+                    }
+                    PubkeySource::DeckAuthor => "Bookmarks".to_owned(),
+                },
+                ListKind::Pinned(pubkey_source) => match pubkey_source {
+                    PubkeySource::Explicit(pubkey) => {
+                        format!(
+                            "{}'s Pinned Notes",
+                            get_profile_displayname_string(ndb, pubkey)
+                        )
+                    }
+                    PubkeySource::DeckAuthor => "Pinned Notes".to_owned(),
+                },
+                ListKind::Interests(pubkey_source) => match pubkey_source {
+                    PubkeySource::Explicit(pubkey) => {
+                        format!(
+                            "{}'s Interests",
+                            get_profile_displayname_string(ndb, pubkey)
+                        )
+                    }
+                    PubkeySource::DeckAuthor => "Interests".to_owned(),
+                },
             },
             TimelineKind::Notifications(pubkey_source) => match pubkey_source {
                 PubkeySource::DeckAuthor => "Notifications".to_owned(),
@@ -203,6 +418,8 @@ impl TimelineKind {
             TimelineKind::Universe => "Universe".to_owned(),
             TimelineKind::Generic => "Custom Filter".to_owned(),
             TimelineKind::Hashtag(hashtag) => format!("#{}", hashtag),
+            TimelineKind::Search(query) => format!("Search: \"{}\"", query),
+            TimelineKind::Articles => "Articles".to_owned(),
         }
     }
 }
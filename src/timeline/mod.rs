@@ -4,8 +4,11 @@ use crate::{
     filter::{self, FilterState, FilterStates},
     note::NoteRef,
     notecache::{CachedNote, NoteCache},
+    search,
+    subscription_manager::SubLifetime,
     subscriptions::{self, SubKind, Subscriptions},
     unknowns::UnknownIds,
+    wot::{TrustFilter, WebOfTrust},
     Result,
 };
 
@@ -13,7 +16,7 @@ use std::fmt;
 use std::sync::atomic::{AtomicU32, Ordering};
 
 use egui_virtual_list::VirtualList;
-use enostr::{Relay, RelayPool};
+use enostr::{Relay, RelayPool, RelayStatus};
 use nostrdb::{Filter, Ndb, Note, Subscription, Transaction};
 use serde::{Deserialize, Serialize};
 use std::cell::RefCell;
@@ -25,7 +28,7 @@ use tracing::{debug, error, info, warn};
 pub mod kind;
 pub mod route;
 
-pub use kind::{PubkeySource, TimelineKind};
+pub use kind::{ListKind, PubkeySource, TimelineKind};
 pub use route::TimelineRoute;
 
 #[derive(Debug, Hash, Copy, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
@@ -49,6 +52,16 @@ pub enum ViewFilter {
 
     #[default]
     NotesAndReplies,
+
+    /// Profile tabs
+    Media,
+    Likes,
+
+    /// Notification tabs
+    All,
+    Mentions,
+    Reactions,
+    Zaps,
 }
 
 impl ViewFilter {
@@ -56,32 +69,149 @@ impl ViewFilter {
         match self {
             ViewFilter::Notes => "Notes",
             ViewFilter::NotesAndReplies => "Notes & Replies",
+            ViewFilter::Media => "Media",
+            ViewFilter::Likes => "Likes",
+            ViewFilter::All => "All",
+            ViewFilter::Mentions => "Mentions",
+            ViewFilter::Reactions => "Reactions",
+            ViewFilter::Zaps => "Zaps",
         }
     }
 
-    pub fn index(&self) -> usize {
-        match self {
-            ViewFilter::Notes => 0,
-            ViewFilter::NotesAndReplies => 1,
-        }
+    /// Is this the view that shows the most of the timeline? Reactions
+    /// and zaps have their own tabs, so it may not be every note.
+    pub fn is_unfiltered(&self) -> bool {
+        matches!(self, ViewFilter::NotesAndReplies | ViewFilter::All)
+    }
+
+    /// Reactions and zaps end up in profile timelines for their own tabs,
+    /// but they aren't notes
+    fn is_note(note: &Note) -> bool {
+        !matches!(note.kind(), 7 | 9735)
     }
 
     pub fn filter_notes(cache: &CachedNote, note: &Note) -> bool {
-        !cache.reply.borrow(note.tags()).is_reply()
+        ViewFilter::is_note(note) && !cache.reply.borrow(note.tags()).is_reply()
+    }
+
+    fn filter_notes_and_replies(_cache: &CachedNote, note: &Note) -> bool {
+        ViewFilter::is_note(note)
+    }
+
+    fn filter_media(_cache: &CachedNote, note: &Note) -> bool {
+        note.kind() == 1 && note.content().split_whitespace().any(is_media_url)
+    }
+
+    fn filter_likes(_cache: &CachedNote, note: &Note) -> bool {
+        note.kind() == 7
     }
 
     fn identity(_cache: &CachedNote, _note: &Note) -> bool {
         true
     }
 
+    fn filter_mentions(_cache: &CachedNote, note: &Note) -> bool {
+        note.kind() == 1
+    }
+
+    fn filter_reactions(_cache: &CachedNote, note: &Note) -> bool {
+        note.kind() == 7
+    }
+
+    fn filter_zaps(_cache: &CachedNote, note: &Note) -> bool {
+        note.kind() == 9735
+    }
+
+    /// Lay out a tab's notes for a column's trust filter, with each run of
+    /// notes it hides as a single row that says why. Who we trust changes
+    /// as lists come in, so unlike the other filters this runs when the
+    /// tab is shown rather than when notes come in.
+    pub fn trust_rows(
+        notes: &[NoteRef],
+        txn: &Transaction,
+        ndb: &Ndb,
+        wot: &WebOfTrust,
+        filter: TrustFilter,
+    ) -> Vec<TabRow> {
+        let mut rows: Vec<TabRow> = Vec::with_capacity(notes.len());
+
+        for (i, note_ref) in notes.iter().enumerate() {
+            let reason = ndb
+                .get_note_by_key(txn, note_ref.key)
+                .ok()
+                .and_then(|note| wot.hidden_reason(note.pubkey(), filter));
+
+            let reason = if let Some(reason) = reason {
+                reason
+            } else {
+                rows.push(TabRow::Note(i));
+                continue;
+            };
+
+            if let Some(TabRow::Hidden { count, reasons }) = rows.last_mut() {
+                *count += 1;
+                if !reasons.contains(&reason) {
+                    reasons.push(reason);
+                }
+            } else {
+                rows.push(TabRow::Hidden {
+                    count: 1,
+                    reasons: vec![reason],
+                });
+            }
+        }
+
+        rows
+    }
+
     pub fn filter(&self) -> fn(&CachedNote, &Note) -> bool {
         match self {
             ViewFilter::Notes => ViewFilter::filter_notes,
-            ViewFilter::NotesAndReplies => ViewFilter::identity,
+            ViewFilter::NotesAndReplies => ViewFilter::filter_notes_and_replies,
+            ViewFilter::Media => ViewFilter::filter_media,
+            ViewFilter::Likes => ViewFilter::filter_likes,
+            ViewFilter::All => ViewFilter::identity,
+            ViewFilter::Mentions => ViewFilter::filter_mentions,
+            ViewFilter::Reactions => ViewFilter::filter_reactions,
+            ViewFilter::Zaps => ViewFilter::filter_zaps,
         }
     }
 }
 
+/// Links to images and videos, the kind of thing that shows up in a
+/// profile's media tab
+fn is_media_url(word: &str) -> bool {
+    const MEDIA_EXTENSIONS: [&str; 8] = ["png", "jpg", "jpeg", "gif", "webp", "mp4", "mov", "webm"];
+
+    let word = word.to_lowercase();
+    if !word.starts_with("https://") && !word.starts_with("http://") {
+        return false;
+    }
+
+    let path = word.split(['?', '#']).next().unwrap_or_default();
+    path.rsplit_once('.')
+        .map_or(false, |(_, ext)| MEDIA_EXTENSIONS.contains(&ext))
+}
+
+/// A row of a tab with a trust filter
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TabRow {
+    /// The note at this index in the tab
+    Note(usize),
+
+    /// Notes in a row that the trust filter hides, and why
+    Hidden { count: usize, reasons: Vec<String> },
+}
+
+#[derive(Debug)]
+struct TrustRows {
+    filter: TrustFilter,
+    /// The web of trust generation the rows were laid out with
+    generation: u64,
+    notes: usize,
+    rows: Vec<TabRow>,
+}
+
 /// A timeline view is a filtered view of notes in a timeline. Two standard views
 /// are "Notes" and "Notes & Replies". A timeline is associated with a Filter,
 /// but a TimelineTab is a further filtered view of this Filter that can't
@@ -92,6 +222,7 @@ pub struct TimelineTab {
     pub selection: i32,
     pub filter: ViewFilter,
     pub list: Rc<RefCell<VirtualList>>,
+    trust_rows: Option<TrustRows>,
 }
 
 impl TimelineTab {
@@ -112,9 +243,55 @@ impl TimelineTab {
             selection,
             filter,
             list,
+            trust_rows: None,
         }
     }
 
+    /// Lay the tab out again for a trust filter if notes came in, or who
+    /// we trust changed, since we last did
+    pub fn update_trust_rows(
+        &mut self,
+        txn: &Transaction,
+        ndb: &Ndb,
+        wot: &WebOfTrust,
+        filter: TrustFilter,
+    ) {
+        if filter == TrustFilter::Everyone {
+            if self.trust_rows.take().is_some() {
+                self.list.borrow_mut().reset();
+            }
+            return;
+        }
+
+        let fresh = self.trust_rows.as_ref().is_some_and(|cached| {
+            cached.filter == filter
+                && cached.generation == wot.generation()
+                && cached.notes == self.notes.len()
+        });
+        if fresh {
+            return;
+        }
+
+        let rows = ViewFilter::trust_rows(&self.notes, txn, ndb, wot, filter);
+        if self.trust_rows.as_ref().map(|cached| &cached.rows) != Some(&rows) {
+            self.list.borrow_mut().reset();
+        }
+
+        self.trust_rows = Some(TrustRows {
+            filter,
+            generation: wot.generation(),
+            notes: self.notes.len(),
+            rows,
+        });
+    }
+
+    /// The tab's rows under its column's trust filter, if it has one
+    pub fn trust_rows(&self) -> Option<&[TabRow]> {
+        self.trust_rows
+            .as_ref()
+            .map(|cached| cached.rows.as_slice())
+    }
+
     pub fn insert(&mut self, new_refs: &[NoteRef], reversed: bool) {
         if new_refs.is_empty() {
             return;
@@ -182,27 +359,46 @@ pub struct Timeline {
 
     /// Our nostrdb subscription
     pub subscription: Option<Subscription>,
+
+    /// Watches for new copies of the list a contact list timeline is
+    /// built from, so we can rebuild it
+    pub list_subscription: Option<Subscription>,
+
+    /// The relay subscriptions for the timeline filter
+    pub remote_subids: Vec<String>,
+
+    /// Built from our local copy of its list while we were offline. That
+    /// copy may be stale, so we fetch the list again once relays are back
+    /// and rebuild the timeline if it changed.
+    pub provisional: bool,
+
+    /// Whose notes we show, going by the selected account's web of trust
+    pub trust_filter: TrustFilter,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct SerializableTimeline {
     pub id: TimelineId,
     pub kind: TimelineKind,
+    #[serde(default)]
+    pub trust_filter: TrustFilter,
 }
 
 impl SerializableTimeline {
     pub fn into_timeline(self, ndb: &Ndb, deck_user_pubkey: Option<&[u8; 32]>) -> Option<Timeline> {
-        self.kind.into_timeline(ndb, deck_user_pubkey)
+        let mut timeline = self.kind.into_timeline(ndb, deck_user_pubkey)?;
+        timeline.trust_filter = self.trust_filter;
+        Some(timeline)
     }
 }
 
 impl Timeline {
-    /// Create a timeline from a contact list
-    pub fn contact_list(contact_list: &Note, pk_src: PubkeySource) -> Result<Self> {
-        let filter = filter::filter_from_tags(contact_list)?.into_follow_filter();
+    /// Create a timeline from a contact list or NIP-51 list
+    pub fn from_list(list: &Note, list_kind: ListKind) -> Result<Self> {
+        let filter = list_kind.timeline_filter(list)?;
 
         Ok(Timeline::new(
-            TimelineKind::contact_list(pk_src),
+            TimelineKind::List(list_kind),
             FilterState::ready(filter),
         ))
     }
@@ -221,9 +417,11 @@ impl Timeline {
 
         let filter = FilterStates::new(filter_state);
         let subscription: Option<Subscription> = None;
-        let notes = TimelineTab::new(ViewFilter::Notes);
-        let replies = TimelineTab::new(ViewFilter::NotesAndReplies);
-        let views = vec![notes, replies];
+        let views: Vec<TimelineTab> = kind
+            .view_filters()
+            .into_iter()
+            .map(TimelineTab::new)
+            .collect();
         let selected_view = 0;
         let id = TimelineId::new(UIDS.fetch_add(1, Ordering::Relaxed));
 
@@ -233,7 +431,11 @@ impl Timeline {
             filter,
             views,
             subscription,
+            list_subscription: None,
+            remote_subids: Vec::new(),
+            provisional: false,
             selected_view,
+            trust_filter: TrustFilter::default(),
         }
     }
 
@@ -245,18 +447,33 @@ impl Timeline {
         &mut self.views[self.selected_view as usize]
     }
 
+    pub fn view_filters(&self) -> Vec<ViewFilter> {
+        self.views.iter().map(|v| v.filter).collect()
+    }
+
+    /// All of the notes in the timeline, regardless of the selected view
+    pub fn all_notes(&self) -> &[NoteRef] {
+        self.views
+            .iter()
+            .find(|v| v.filter.is_unfiltered())
+            .map_or(&[], |v| &v.notes)
+    }
+
+    /// The notes in one of our views, or none if we don't have that view
     pub fn notes(&self, view: ViewFilter) -> &[NoteRef] {
-        &self.views[view.index()].notes
+        self.view(view).map_or(&[], |v| &v.notes)
     }
 
-    pub fn view(&self, view: ViewFilter) -> &TimelineTab {
-        &self.views[view.index()]
+    pub fn view(&self, view: ViewFilter) -> Option<&TimelineTab> {
+        self.views.iter().find(|v| v.filter == view)
     }
 
-    pub fn view_mut(&mut self, view: ViewFilter) -> &mut TimelineTab {
-        &mut self.views[view.index()]
+    pub fn view_mut(&mut self, view: ViewFilter) -> Option<&mut TimelineTab> {
+        self.views.iter_mut().find(|v| v.filter == view)
     }
 
+    /// Poll our subscription for new notes and insert them into our views.
+    /// Returns the new notes.
     pub fn poll_notes_into_view(
         timeline_idx: usize,
         mut timelines: Vec<&mut Timeline>,
@@ -264,7 +481,7 @@ impl Timeline {
         txn: &Transaction,
         unknown_ids: &mut UnknownIds,
         note_cache: &mut NoteCache,
-    ) -> Result<()> {
+    ) -> Result<Vec<NoteRef>> {
         let timeline = timelines
             .get_mut(timeline_idx)
             .ok_or(Error::TimelineNotFound)?;
@@ -272,7 +489,7 @@ impl Timeline {
 
         let new_note_ids = ndb.poll_for_notes(sub, 500);
         if new_note_ids.is_empty() {
-            return Ok(());
+            return Ok(vec![]);
         } else {
             debug!("{} new notes! {:?}", new_note_ids.len(), new_note_ids);
         }
@@ -287,6 +504,12 @@ impl Timeline {
                 continue;
             };
 
+            if let TimelineKind::Search(query) = &timeline.kind {
+                if !search::note_matches_query(query, &note) {
+                    continue;
+                }
+            }
+
             UnknownIds::update_from_note(txn, ndb, unknown_ids, note_cache, &note);
 
             let created_at = note.created_at();
@@ -300,43 +523,28 @@ impl Timeline {
         // optimization doesn't make sense in those situations.
         let reversed = false;
 
-        // ViewFilter::NotesAndReplies
-        {
-            let refs: Vec<NoteRef> = new_refs.iter().map(|(_note, nr)| *nr).collect();
-
-            let reversed = false;
-            timeline
-                .view_mut(ViewFilter::NotesAndReplies)
-                .insert(&refs, reversed);
-        }
-
-        //
-        // handle the filtered case (ViewFilter::Notes, no replies)
-        //
-        // TODO(jb55): this is mostly just copied from above, let's just use a loop
-        //             I initially tried this but ran into borrow checker issues
-        {
+        for view in &mut timeline.views {
+            let filter = view.filter.filter();
             let mut filtered_refs = Vec::with_capacity(new_refs.len());
             for (note, nr) in &new_refs {
                 let cached_note = note_cache.cached_note_or_insert(nr.key, note);
 
-                if ViewFilter::filter_notes(cached_note, note) {
+                if filter(cached_note, note) {
                     filtered_refs.push(*nr);
                 }
             }
 
-            timeline
-                .view_mut(ViewFilter::Notes)
-                .insert(&filtered_refs, reversed);
+            view.insert(&filtered_refs, reversed);
         }
 
-        Ok(())
+        Ok(new_refs.into_iter().map(|(_note, nr)| nr).collect())
     }
 
     pub fn as_serializable_timeline(&self) -> SerializableTimeline {
         SerializableTimeline {
             id: self.id,
             kind: self.kind.clone(),
+            trust_filter: self.trust_filter,
         }
     }
 }
@@ -396,7 +604,7 @@ pub fn setup_new_timeline(
     since_optimize: bool,
 ) {
     // if we're ready, setup local subs
-    if is_timeline_ready(ndb, pool, note_cache, timeline) {
+    if is_timeline_ready(ndb, subs, note_cache, timeline) {
         if let Err(err) = setup_timeline_nostrdb_sub(ndb, note_cache, timeline) {
             error!("setup_new_timeline: {err}");
         }
@@ -433,6 +641,37 @@ pub fn send_initial_timeline_filters(
     Some(())
 }
 
+/// We don't know if a relay supports NIP-50 until we have its information
+/// document, which usually shows up after we've connected. Once it does,
+/// send it any search timelines we skipped in send_initial_timeline_filters.
+pub fn send_search_timeline_filters(
+    ndb: &Ndb,
+    since_optimize: bool,
+    columns: &mut Columns,
+    subs: &mut Subscriptions,
+    pool: &mut RelayPool,
+    relay_id: &str,
+) -> Option<()> {
+    let relay = &mut pool
+        .relays
+        .iter_mut()
+        .find(|r| r.relay.url == relay_id)?
+        .relay;
+
+    if !matches!(relay.status, RelayStatus::Connected) || !relay.supports_nip(search::NIP50) {
+        return None;
+    }
+
+    for timeline in columns.timelines_mut() {
+        if timeline.kind.is_search() {
+            info!("Sending search timeline filter to {}", relay_id);
+            send_initial_timeline_filter(ndb, since_optimize, subs, relay, timeline);
+        }
+    }
+
+    Some(())
+}
+
 pub fn send_initial_timeline_filter(
     ndb: &Ndb,
     can_since_optimize: bool,
@@ -440,6 +679,12 @@ pub fn send_initial_timeline_filter(
     relay: &mut Relay,
     timeline: &mut Timeline,
 ) {
+    // relays that don't support search would ignore the search field and
+    // send us everything
+    if timeline.kind.is_search() && !relay.supports_nip(search::NIP50) {
+        return;
+    }
+
     let filter_state = timeline.filter.get(&relay.url);
 
     match filter_state {
@@ -469,7 +714,7 @@ pub fn send_initial_timeline_filter(
                     filter = filter.limit_mut(lim);
                 }
 
-                let notes = timeline.notes(ViewFilter::NotesAndReplies);
+                let notes = timeline.all_notes();
 
                 // Should we since optimize? Not always. For example
                 // if we only have a few notes locally. One way to
@@ -477,7 +722,10 @@ pub fn send_initial_timeline_filter(
                 // and seeing what its limit is. If we have less
                 // notes than the limit, we might want to backfill
                 // older notes
-                if can_since_optimize && filter::should_since_optimize(lim, notes.len()) {
+                if can_since_optimize
+                    && !timeline.kind.is_bookmarks()
+                    && filter::should_since_optimize(lim, notes.len())
+                {
                     filter = filter::since_optimize_filter(filter, notes);
                 } else {
                     warn!("Skipping since optimization for {:?}: number of local notes is less than limit, attempting to backfill.", filter);
@@ -489,25 +737,27 @@ pub fn send_initial_timeline_filter(
             //let sub_id = damus.gen_subid(&SubKind::Initial);
             let sub_id = subscriptions::new_sub_id();
             subs.subs.insert(sub_id.clone(), SubKind::Initial);
+            timeline.remote_subids.push(sub_id.clone());
 
-            relay.subscribe(sub_id, new_filters);
+            subs.manager
+                .subscribe_to(&relay.url, sub_id, new_filters, SubLifetime::Persistent);
         }
 
         // we need some data first
         FilterState::NeedsRemote(filter) => {
-            fetch_contact_list(filter.to_owned(), ndb, subs, relay, timeline)
+            fetch_list(filter.to_owned(), ndb, subs, relay, timeline)
         }
     }
 }
 
-fn fetch_contact_list(
+fn fetch_list(
     filter: Vec<Filter>,
     ndb: &Ndb,
     subs: &mut Subscriptions,
     relay: &mut Relay,
     timeline: &mut Timeline,
 ) {
-    let sub_kind = SubKind::FetchingContactList(timeline.id);
+    let sub_kind = SubKind::FetchingList(timeline.id);
     let sub_id = subscriptions::new_sub_id();
     let local_sub = ndb.subscribe(&filter).expect("sub");
 
@@ -518,8 +768,9 @@ fn fetch_contact_list(
 
     subs.subs.insert(sub_id.clone(), sub_kind);
 
-    info!("fetching contact list from {}", &relay.url);
-    relay.subscribe(sub_id, filter);
+    info!("fetching {} list from {}", timeline.kind, &relay.url);
+    subs.manager
+        .subscribe_to(&relay.url, sub_id, filter, SubLifetime::OneShot);
 }
 
 fn setup_initial_timeline(
@@ -529,6 +780,12 @@ fn setup_initial_timeline(
     filters: &[Filter],
 ) -> Result<()> {
     timeline.subscription = Some(ndb.subscribe(filters)?);
+
+    // bookmark timelines are built from the list, not the query results
+    if timeline.kind.is_bookmarks() {
+        return Ok(());
+    }
+
     let txn = Transaction::new(ndb)?;
     debug!(
         "querying nostrdb sub {:?} {:?}",
@@ -604,13 +861,210 @@ fn setup_timeline_nostrdb_sub(
     Ok(())
 }
 
+/// Build a timeline that's waiting on a list (ie. a contact list) from the
+/// last copy of that list we have in nostrdb. We do this when we're
+/// offline, instead of waiting on relays that aren't there. The timeline
+/// stays provisional until relays tell us whether our copy is the newest.
+/// Returns true if the timeline is ready now.
+pub fn setup_timeline_from_local_list(
+    ndb: &Ndb,
+    note_cache: &mut NoteCache,
+    subs: &mut Subscriptions,
+    timeline: &mut Timeline,
+) -> bool {
+    if timeline.filter.get_any_ready().is_some() {
+        return false;
+    }
+
+    let list_filter = if let FilterState::NeedsRemote(filter) = &timeline.filter.initial_state {
+        filter.clone()
+    } else {
+        return false;
+    };
+
+    let filter = {
+        let txn = Transaction::new(ndb).expect("txn");
+        let results = match ndb.query(&txn, &list_filter, 1) {
+            Ok(results) => results,
+            Err(err) => {
+                error!("error querying local list for {}: {err}", timeline.kind);
+                return false;
+            }
+        };
+
+        let note = if let Some(result) = results.first() {
+            &result.note
+        } else {
+            return false;
+        };
+
+        if let TimelineKind::List(list_kind) = &timeline.kind {
+            list_kind.timeline_filter(note)
+        } else {
+            filter::filter_from_tags(note).map(|f| f.into_follow_filter())
+        }
+    };
+
+    let filter = match filter {
+        Ok(filter) => filter,
+        Err(err) => {
+            debug!("can't build {} from our local list: {err}", timeline.kind);
+            return false;
+        }
+    };
+
+    info!("building {} from our local copy of its list", timeline.kind);
+    if let Err(err) = setup_initial_timeline(ndb, timeline, note_cache, &filter) {
+        error!("setup_timeline_from_local_list: {err}");
+        return false;
+    }
+
+    // once we're ready everywhere, relays get sent the timeline filter
+    // directly when they come back
+    timeline.filter.initial_state = FilterState::ready(filter);
+
+    // the manager holds this until relays are back. A newer copy gets the
+    // timeline rebuilt in refresh_list_timelines.
+    let subid = subscriptions::new_sub_id();
+    subs.subs.insert(subid.clone(), SubKind::OneShot);
+    subs.manager
+        .subscribe(subid, list_filter, SubLifetime::OneShot);
+    timeline.provisional = true;
+
+    true
+}
+
+/// Contact list timelines follow their list, and so do timelines we built
+/// from a possibly stale local copy of their list. When a newer copy of it
+/// shows up in nostrdb, ie. after we follow someone, rebuild the timeline
+/// from it.
+pub fn refresh_list_timelines(
+    ndb: &Ndb,
+    note_cache: &mut NoteCache,
+    subs: &mut Subscriptions,
+    pool: &mut RelayPool,
+    columns: &mut Columns,
+    default_user: Option<&[u8; 32]>,
+    since_optimize: bool,
+) {
+    for timeline in columns.timelines_mut() {
+        let list_kind = match &timeline.kind {
+            TimelineKind::List(list_kind @ ListKind::Contact(_)) => list_kind.clone(),
+            TimelineKind::List(list_kind) if timeline.provisional => list_kind.clone(),
+            _ => continue,
+        };
+
+        // still waiting on the first copy, is_timeline_ready handles that
+        if timeline.filter.get_any_ready().is_none() {
+            continue;
+        }
+
+        let list_filter = if let Some(pk) = list_kind.pubkey_source().to_pubkey(default_user) {
+            list_kind.list_filter(pk)
+        } else {
+            continue;
+        };
+
+        let list_sub = if let Some(sub) = timeline.list_subscription {
+            sub
+        } else {
+            match ndb.subscribe(&[list_filter.clone()]) {
+                Ok(sub) => *timeline.list_subscription.insert(sub),
+                Err(err) => {
+                    error!("error watching list for {}: {err}", timeline.kind);
+                    continue;
+                }
+            }
+        };
+
+        let new_notes = ndb.poll_for_notes(list_sub, 10);
+        if new_notes.is_empty() {
+            continue;
+        }
+
+        let filter = {
+            let txn = Transaction::new(ndb).expect("txn");
+            let results = match ndb.query(&txn, &[list_filter], 1) {
+                Ok(results) => results,
+                Err(err) => {
+                    error!("error querying list for {}: {err}", timeline.kind);
+                    continue;
+                }
+            };
+
+            // an older copy from a slow relay doesn't change anything
+            match results.first() {
+                Some(newest) if new_notes.contains(&newest.note_key) => {
+                    list_kind.timeline_filter(&newest.note)
+                }
+                _ => continue,
+            }
+        };
+
+        match filter {
+            Ok(filter) => {
+                info!("{} changed, rebuilding it", timeline.kind);
+                rebuild_timeline(
+                    ndb,
+                    note_cache,
+                    subs,
+                    pool,
+                    timeline,
+                    filter,
+                    since_optimize,
+                );
+
+                // only contact lists keep following their list
+                if !matches!(list_kind, ListKind::Contact(_)) {
+                    if let Some(sub) = timeline.list_subscription.take() {
+                        if let Err(err) = ndb.unsubscribe(sub) {
+                            error!("error unsubscribing from {}'s list: {err}", timeline.kind);
+                        }
+                    }
+                }
+            }
+            Err(err) => error!("can't rebuild {}: {err}", timeline.kind),
+        }
+    }
+}
+
+/// Start a timeline over with a new filter
+fn rebuild_timeline(
+    ndb: &Ndb,
+    note_cache: &mut NoteCache,
+    subs: &mut Subscriptions,
+    pool: &mut RelayPool,
+    timeline: &mut Timeline,
+    filter: Vec<Filter>,
+    since_optimize: bool,
+) {
+    if let Some(sub) = timeline.subscription.take() {
+        if let Err(err) = ndb.unsubscribe(sub) {
+            error!("error unsubscribing from {}: {err}", timeline.kind);
+        }
+    }
+
+    for subid in timeline.remote_subids.drain(..) {
+        subs.manager.unsubscribe(&subid);
+        subs.subs.remove(&subid);
+    }
+
+    for view in &mut timeline.views {
+        *view = TimelineTab::new(view.filter);
+    }
+
+    timeline.filter = FilterStates::new(FilterState::ready(filter));
+    timeline.provisional = false;
+    setup_new_timeline(timeline, ndb, subs, pool, note_cache, since_optimize);
+}
+
 /// Check our timeline filter and see if we have any filter data ready.
 /// Our timelines may require additional data before it is functional. For
 /// example, when we have to fetch a contact list before we do the actual
 /// following list query.
 pub fn is_timeline_ready(
     ndb: &Ndb,
-    pool: &mut RelayPool,
+    subs: &mut Subscriptions,
     note_cache: &mut NoteCache,
     timeline: &mut Timeline,
 ) -> bool {
@@ -637,17 +1091,20 @@ pub fn is_timeline_ready(
         return false;
     }
 
-    info!("notes found for contact timeline after GotRemote!");
+    info!("notes found for list timeline after GotRemote!");
 
     let note_key = res[0];
 
     let filter = {
         let txn = Transaction::new(ndb).expect("txn");
         let note = ndb.get_note_by_key(&txn, note_key).expect("note");
-        filter::filter_from_tags(&note).map(|f| f.into_follow_filter())
+        if let TimelineKind::List(list_kind) = &timeline.kind {
+            list_kind.timeline_filter(&note)
+        } else {
+            filter::filter_from_tags(&note).map(|f| f.into_follow_filter())
+        }
     };
 
-    // TODO: into_follow_filter is hardcoded to contact lists, let's generalize
     match filter {
         Err(Error::Filter(e)) => {
             error!("got broken when building filter {e}");
@@ -666,7 +1123,7 @@ pub fn is_timeline_ready(
         Ok(filter) => {
             // we just switched to the ready state, we should send initial
             // queries and setup the local subscription
-            info!("Found contact list! Setting up local and remote contact list query");
+            info!("Found list! Setting up local and remote list query");
             setup_initial_timeline(ndb, timeline, note_cache, &filter).expect("setup init");
             timeline
                 .filter
@@ -675,8 +1132,78 @@ pub fn is_timeline_ready(
             //let ck = &timeline.kind;
             //let subid = damus.gen_subid(&SubKind::Column(ck.clone()));
             let subid = subscriptions::new_sub_id();
-            pool.subscribe(subid, filter);
+            // so rebuild_timeline closes it
+            timeline.remote_subids.push(subid.clone());
+            subs.manager
+                .subscribe(subid, filter, SubLifetime::Persistent);
             true
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use enostr::FullKeypair;
+    use nostrdb::NoteBuilder;
+
+    fn note(kind: u32, content: &str, reply: bool) -> Note<'static> {
+        let kp = FullKeypair::generate();
+        let mut builder = NoteBuilder::new().kind(kind).content(content);
+        if reply {
+            builder = builder
+                .start_tag()
+                .tag_str("e")
+                .tag_str(&hex::encode([1; 32]))
+                .tag_str("")
+                .tag_str("root");
+        }
+        builder
+            .sign(&kp.secret_key.to_secret_bytes())
+            .build()
+            .unwrap()
+    }
+
+    /// Which of these notes a tab shows
+    fn shown(filter: ViewFilter, notes: &[Note]) -> Vec<bool> {
+        notes
+            .iter()
+            .map(|note| filter.filter()(&CachedNote::new(note), note))
+            .collect()
+    }
+
+    #[test]
+    fn test_tab_filters() {
+        let notes = [
+            note(1, "hello", false),
+            note(1, "a reply", true),
+            note(1, "look https://example.com/cat.JPG?size=large", false),
+            note(6, "", false),
+            note(7, "+", false),
+            note(9735, "", false),
+        ];
+
+        let t = true;
+        let f = false;
+        assert_eq!(shown(ViewFilter::Notes, &notes), [t, f, t, t, f, f]);
+        assert_eq!(
+            shown(ViewFilter::NotesAndReplies, &notes),
+            [t, t, t, t, f, f]
+        );
+        assert_eq!(shown(ViewFilter::Media, &notes), [f, f, t, f, f, f]);
+        assert_eq!(shown(ViewFilter::Likes, &notes), [f, f, f, f, t, f]);
+        assert_eq!(shown(ViewFilter::All, &notes), [t, t, t, t, t, t]);
+        assert_eq!(shown(ViewFilter::Mentions, &notes), [t, t, t, f, f, f]);
+        assert_eq!(shown(ViewFilter::Reactions, &notes), [f, f, f, f, t, f]);
+        assert_eq!(shown(ViewFilter::Zaps, &notes), [f, f, f, f, f, t]);
+    }
+
+    #[test]
+    fn test_is_media_url() {
+        assert!(is_media_url("https://example.com/a.png"));
+        assert!(is_media_url("http://example.com/a.mp4#t=10"));
+        assert!(!is_media_url("https://example.com/a.png.html"));
+        assert!(!is_media_url("example.com/a.png"));
+        assert!(!is_media_url("https://example.com/"));
+    }
+}
@@ -1,12 +1,17 @@
 use crate::{
     accounts::Accounts,
     column::Columns,
+    contacts::ContactEdits,
     draft::Drafts,
     imgcache::ImageCache,
+    missing_notes::MissingNotes,
     nav::RenderNavAction,
+    nip05::Nip05Verifier,
     notecache::NoteCache,
     notes_holder::NotesHolderStorage,
     profile::Profile,
+    profile_stats::ProfileStats,
+    route::Route,
     thread::Thread,
     timeline::{TimelineId, TimelineKind},
     ui::{
@@ -15,6 +20,7 @@ use crate::{
         profile::ProfileView,
     },
     unknowns::UnknownIds,
+    wot::WebOfTrust,
 };
 
 use enostr::{NoteId, Pubkey};
@@ -27,6 +33,7 @@ pub enum TimelineRoute {
     Profile(Pubkey),
     Reply(NoteId),
     Quote(NoteId),
+    Article(NoteId),
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -35,11 +42,16 @@ pub fn render_timeline_route(
     columns: &mut Columns,
     drafts: &mut Drafts,
     img_cache: &mut ImageCache,
+    nip05: &mut Nip05Verifier,
     unknown_ids: &mut UnknownIds,
     note_cache: &mut NoteCache,
     threads: &mut NotesHolderStorage<Thread>,
+    missing_notes: &mut MissingNotes,
     profiles: &mut NotesHolderStorage<Profile>,
+    profile_stats: &mut ProfileStats,
+    wot: &WebOfTrust,
     accounts: &mut Accounts,
+    contact_edits: &ContactEdits,
     route: TimelineRoute,
     col: usize,
     textmode: bool,
@@ -47,6 +59,38 @@ pub fn render_timeline_route(
 ) -> Option<RenderNavAction> {
     match route {
         TimelineRoute::Timeline(timeline_id) => {
+            let is_notifications = columns
+                .find_timeline(timeline_id)
+                .map_or(false, |tl| tl.kind.is_notifications());
+
+            if is_notifications {
+                ui.horizontal(|ui| {
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui.small_button("🔔 Desktop notifications").clicked() {
+                            columns
+                                .column_mut(col)
+                                .router_mut()
+                                .route_to(Route::NotificationSettings);
+                        }
+                    });
+                });
+            }
+
+            let mut trust_filter_changed = false;
+            if let Some(timeline) = columns.find_timeline_mut(timeline_id) {
+                if timeline.kind.is_open_feed() {
+                    ui.horizontal(|ui| {
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            trust_filter_changed = ui::timeline::trust_filter_ui(
+                                ui,
+                                egui::Id::new(("trust_filter", timeline_id)),
+                                &mut timeline.trust_filter,
+                            );
+                        });
+                    });
+                }
+            }
+
             let note_options = {
                 let is_universe = if let Some(timeline) = columns.find_timeline(timeline_id) {
                     timeline.kind == TimelineKind::Universe
@@ -65,11 +109,15 @@ pub fn render_timeline_route(
                 ndb,
                 note_cache,
                 img_cache,
+                nip05,
+                wot,
                 note_options,
             )
             .ui(ui);
 
-            note_action.map(RenderNavAction::NoteAction)
+            note_action
+                .map(RenderNavAction::NoteAction)
+                .or(trust_filter_changed.then_some(RenderNavAction::ColumnSettingsChanged))
         }
 
         TimelineRoute::Thread(id) => ui::ThreadView::new(
@@ -77,7 +125,9 @@ pub fn render_timeline_route(
             ndb,
             note_cache,
             unknown_ids,
+            missing_notes,
             img_cache,
+            nip05,
             id.bytes(),
             textmode,
         )
@@ -101,13 +151,13 @@ pub fn render_timeline_route(
             };
 
             let id = egui::Id::new(("post", col, note.key().unwrap()));
-            let poster = accounts.selected_or_first_nsec()?;
+            let poster = &accounts.selected_or_first_signer()?.pubkey;
 
             let action = {
                 let draft = drafts.reply_mut(note.id());
 
                 let response = egui::ScrollArea::vertical().show(ui, |ui| {
-                    ui::PostReplyView::new(ndb, poster, draft, note_cache, img_cache, &note)
+                    ui::PostReplyView::new(ndb, poster, draft, note_cache, img_cache, nip05, &note)
                         .id_source(id)
                         .show(ui)
                 });
@@ -118,9 +168,20 @@ pub fn render_timeline_route(
             action.map(Into::into)
         }
 
-        TimelineRoute::Profile(pubkey) => {
-            render_profile_route(&pubkey, ndb, profiles, img_cache, note_cache, col, ui)
-        }
+        TimelineRoute::Profile(pubkey) => render_profile_route(
+            &pubkey,
+            ndb,
+            profiles,
+            profile_stats,
+            img_cache,
+            nip05,
+            note_cache,
+            unknown_ids,
+            accounts,
+            contact_edits,
+            col,
+            ui,
+        ),
 
         TimelineRoute::Quote(id) => {
             let txn = Transaction::new(ndb).expect("txn");
@@ -134,17 +195,24 @@ pub fn render_timeline_route(
 
             let id = egui::Id::new(("post", col, note.key().unwrap()));
 
-            let poster = accounts.selected_or_first_nsec()?;
+            let poster = &accounts.selected_or_first_signer()?.pubkey;
             let draft = drafts.quote_mut(note.id());
 
             let response = egui::ScrollArea::vertical().show(ui, |ui| {
-                QuoteRepostView::new(ndb, poster, note_cache, img_cache, draft, &note)
+                QuoteRepostView::new(ndb, poster, note_cache, img_cache, nip05, draft, &note)
                     .id_source(id)
                     .show(ui)
             });
 
             response.inner.action.map(Into::into)
         }
+
+        TimelineRoute::Article(id) => {
+            ui::ArticleView::new(ndb, img_cache, nip05, missing_notes, &id)
+                .id_source(egui::Id::new(("articlescroll", col)))
+                .ui(ui)
+                .map(Into::into)
+        }
     }
 }
 
@@ -153,21 +221,30 @@ pub fn render_profile_route(
     pubkey: &Pubkey,
     ndb: &Ndb,
     profiles: &mut NotesHolderStorage<Profile>,
+    profile_stats: &mut ProfileStats,
     img_cache: &mut ImageCache,
+    nip05: &mut Nip05Verifier,
     note_cache: &mut NoteCache,
+    unknown_ids: &mut UnknownIds,
+    accounts: &Accounts,
+    contact_edits: &ContactEdits,
     col: usize,
     ui: &mut egui::Ui,
 ) -> Option<RenderNavAction> {
-    let note_action = ProfileView::new(
+    ProfileView::new(
         pubkey,
         col,
         profiles,
+        profile_stats,
         ndb,
         note_cache,
         img_cache,
+        nip05,
+        unknown_ids,
         NoteOptions::default(),
     )
-    .ui(ui);
-
-    note_action.map(RenderNavAction::NoteAction)
+    .list_owner(accounts.selected_or_first_signer().map(|kp| &kp.pubkey))
+    .account(accounts.get_selected_account().map(|kp| &kp.pubkey))
+    .contact_edits(contact_edits)
+    .ui(ui)
 }
@@ -1,6 +1,7 @@
 use crate::app_style::NotedeckTextStyle;
 use crate::key_parsing::AcquireKeyError;
 use crate::login_manager::AcquireKeyState;
+use crate::proxy::Http;
 use crate::ui::{Preview, PreviewConfig, View};
 use egui::TextEdit;
 use egui::{Align, Button, Color32, Frame, InnerResponse, Margin, RichText, Vec2};
@@ -8,6 +9,7 @@ use enostr::Keypair;
 
 pub struct AccountLoginView<'a> {
     manager: &'a mut AcquireKeyState,
+    http: &'a Http,
 }
 
 pub enum AccountLoginResponse {
@@ -16,8 +18,11 @@ pub enum AccountLoginResponse {
 }
 
 impl<'a> AccountLoginView<'a> {
-    pub fn new(state: &'a mut AcquireKeyState) -> Self {
-        AccountLoginView { manager: state }
+    pub fn new(state: &'a mut AcquireKeyState, http: &'a Http) -> Self {
+        AccountLoginView {
+            manager: state,
+            http,
+        }
     }
 
     pub fn ui(&mut self, ui: &mut egui::Ui) -> InnerResponse<Option<AccountLoginResponse>> {
@@ -43,10 +48,12 @@ impl<'a> AccountLoginView<'a> {
                 self.loading_and_error(ui);
 
                 if ui.add(login_button()).clicked() {
-                    self.manager.apply_acquire();
+                    self.manager.apply_acquire(self.http);
                 }
             });
 
+            self.nostrconnect(ui);
+
             ui.horizontal(|ui| {
                 ui.label(
                     RichText::new("New to Nostr?")
@@ -73,6 +80,29 @@ impl<'a> AccountLoginView<'a> {
         None
     }
 
+    fn nostrconnect(&mut self, ui: &mut egui::Ui) {
+        ui.add_space(8.0);
+
+        if let Some(uri) = self.manager.nostrconnect_uri() {
+            ui.label(
+                RichText::new("Paste this into your signer app:")
+                    .text_style(NotedeckTextStyle::Body.text_style()),
+            );
+            ui.horizontal(|ui| {
+                ui.add(egui::Label::new(RichText::new(uri).monospace()).truncate());
+                if ui.button("Copy").clicked() {
+                    ui.ctx().copy_text(uri.to_owned());
+                }
+            });
+        } else if !self.manager.is_awaiting_network()
+            && ui
+                .add(Button::new(RichText::new("Use a signer app")).frame(false))
+                .clicked()
+        {
+            self.manager.start_nostrconnect();
+        }
+    }
+
     fn loading_and_error(&mut self, ui: &mut egui::Ui) {
         ui.add_space(8.0);
 
@@ -96,7 +126,7 @@ fn show_error(ui: &mut egui::Ui, err: &AcquireKeyError) {
             AcquireKeyError::InvalidKey => {
                 egui::Label::new(RichText::new("Invalid key.").color(ui.visuals().error_fg_color))
             }
-            AcquireKeyError::Nip05Failed(e) => {
+            AcquireKeyError::Nip05Failed(e) | AcquireKeyError::RemoteSignerFailed(e) => {
                 egui::Label::new(RichText::new(e).color(ui.visuals().error_fg_color))
             }
         };
@@ -130,7 +160,7 @@ fn login_textedit(manager: &mut AcquireKeyState) -> TextEdit {
     manager.get_acquire_textedit(|text| {
         egui::TextEdit::singleline(text)
             .hint_text(
-                RichText::new("Enter your public key (npub), nostr address (e.g. vrod@damus.io), or private key (nsec) here...")
+                RichText::new("Enter your public key (npub), nostr address (e.g. vrod@damus.io), private key (nsec), or bunker:// uri here...")
                     .text_style(NotedeckTextStyle::Body.text_style()),
             )
             .vertical_align(Align::Center)
@@ -144,11 +174,12 @@ mod preview {
 
     pub struct AccountLoginPreview {
         manager: AcquireKeyState,
+        http: Http,
     }
 
     impl View for AccountLoginPreview {
         fn ui(&mut self, ui: &mut egui::Ui) {
-            AccountLoginView::new(&mut self.manager).ui(ui);
+            AccountLoginView::new(&mut self.manager, &self.http).ui(ui);
         }
     }
 
@@ -158,7 +189,10 @@ mod preview {
         fn preview(cfg: PreviewConfig) -> Self::Prev {
             let _ = cfg;
             let manager = AcquireKeyState::new();
-            AccountLoginPreview { manager }
+            AccountLoginPreview {
+                manager,
+                http: Http::default(),
+            }
         }
     }
 }
@@ -22,6 +22,8 @@ pub enum AccountsViewResponse {
     SelectAccount(usize),
     RemoveAccount(usize),
     RouteToLogin,
+    /// What media the selected account sees in notes
+    RouteToMediaSettings,
 }
 
 #[derive(Debug)]
@@ -119,6 +121,8 @@ impl<'a> AccountsView<'a> {
             |ui| {
                 if ui.add(add_account_button()).clicked() {
                     Some(AccountsViewResponse::RouteToLogin)
+                } else if ui.add(media_settings_button()).clicked() {
+                    Some(AccountsViewResponse::RouteToMediaSettings)
                 } else {
                     None
                 }
@@ -183,6 +187,10 @@ fn add_account_button() -> Button<'static> {
     .frame(false)
 }
 
+fn media_settings_button() -> Button<'static> {
+    Button::new("Media").min_size(Vec2::new(0.0, 32.0))
+}
+
 fn sign_out_button(ui: &egui::Ui) -> egui::Button<'static> {
     let img_data = egui::include_image!("../../assets/icons/signout_icon_4x.png");
     let img = Image::new(img_data).fit_to_exact_size(Vec2::new(16.0, 16.0));
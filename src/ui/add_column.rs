@@ -6,13 +6,15 @@ use egui::{
     pos2, vec2, Align, Color32, FontId, Id, ImageSource, Margin, Pos2, Rect, RichText, Separator,
     Ui, Vec2,
 };
-use nostrdb::Ndb;
+use nostrdb::{Ndb, Transaction};
 use tracing::error;
 
 use crate::{
     app_style::{get_font_size, NotedeckTextStyle},
+    lists,
     login_manager::AcquireKeyState,
-    timeline::{PubkeySource, Timeline, TimelineKind},
+    proxy::Http,
+    timeline::{ListKind, PubkeySource, Timeline, TimelineKind},
     ui::anim::ICON_EXPANSION_MULTIPLE,
     user_account::UserAccount,
     Damus,
@@ -25,6 +27,7 @@ pub enum AddColumnResponse {
     UndecidedNotification,
     ExternalNotification,
     Hashtag,
+    Lists,
 }
 
 pub enum NotificationColumnType {
@@ -41,6 +44,9 @@ enum AddColumnOption {
     Home(PubkeySource),
     UndecidedHashtag,
     Hashtag(String),
+    UndecidedList,
+    List(ListKind),
+    Articles,
 }
 
 #[derive(Clone, Copy, Eq, PartialEq, Debug, Serialize, Deserialize)]
@@ -49,6 +55,7 @@ pub enum AddColumnRoute {
     UndecidedNotification,
     ExternalNotification,
     Hashtag,
+    Lists,
 }
 
 impl AddColumnOption {
@@ -77,6 +84,13 @@ impl AddColumnOption {
             AddColumnOption::Hashtag(hashtag) => TimelineKind::Hashtag(hashtag)
                 .into_timeline(ndb, None)
                 .map(AddColumnResponse::Timeline),
+            AddColumnOption::UndecidedList => Some(AddColumnResponse::Lists),
+            AddColumnOption::List(list_kind) => TimelineKind::List(list_kind)
+                .into_timeline(ndb, cur_account.map(|a| a.pubkey.bytes()))
+                .map(AddColumnResponse::Timeline),
+            AddColumnOption::Articles => TimelineKind::Articles
+                .into_timeline(ndb, None)
+                .map(AddColumnResponse::Timeline),
         }
     }
 }
@@ -85,6 +99,7 @@ pub struct AddColumnView<'a> {
     key_state_map: &'a mut HashMap<Id, AcquireKeyState>,
     ndb: &'a Ndb,
     cur_account: Option<&'a UserAccount>,
+    http: &'a Http,
 }
 
 impl<'a> AddColumnView<'a> {
@@ -92,11 +107,13 @@ impl<'a> AddColumnView<'a> {
         key_state_map: &'a mut HashMap<Id, AcquireKeyState>,
         ndb: &'a Ndb,
         cur_account: Option<&'a UserAccount>,
+        http: &'a Http,
     ) -> Self {
         Self {
             key_state_map,
             ndb,
             cur_account,
+            http,
         }
     }
 
@@ -148,7 +165,7 @@ impl<'a> AddColumnView<'a> {
             ui.add(text_edit);
 
             if ui.button("Add").clicked() {
-                key_state.apply_acquire();
+                key_state.apply_acquire(self.http);
             }
 
             if key_state.is_awaiting_network() {
@@ -174,7 +191,57 @@ impl<'a> AddColumnView<'a> {
         .inner
     }
 
-    fn column_option_ui(&mut self, ui: &mut Ui, data: ColumnOptionData) -> egui::Response {
+    fn lists_ui(&mut self, ui: &mut Ui) -> Option<AddColumnResponse> {
+        let acc = self.cur_account?;
+        let txn = Transaction::new(self.ndb).expect("txn");
+        let lists = lists::account_lists(self.ndb, &txn, &acc.pubkey);
+
+        if lists.is_empty() {
+            padding(16.0, ui, |ui| {
+                ui.label("Looking for your lists...");
+                ui.spinner();
+            });
+            return None;
+        }
+
+        let mut selected_option: Option<AddColumnResponse> = None;
+        for list in &lists {
+            let (description, icon) = match &list.list_kind {
+                ListKind::FollowSet(_, _) => (
+                    format!("Notes from the {} people in this follow set", list.size),
+                    egui::include_image!("../../assets/icons/home_icon_dark_4x.png"),
+                ),
+                // bookmarks have their own column option
+                ListKind::Bookmarks(_) => continue,
+                ListKind::Pinned(_) => (
+                    format!("Your {} pinned notes", list.size),
+                    egui::include_image!("../../assets/icons/select_icon_3x.png"),
+                ),
+                ListKind::Interests(_) | ListKind::Contact(_) => (
+                    format!("Notes from your {} interests", list.size),
+                    egui::include_image!("../../assets/icons/universe_icon_dark_4x.png"),
+                ),
+            };
+
+            let data = ColumnOptionData {
+                title: &list.title,
+                description: &description,
+                icon,
+                option: AddColumnOption::List(list.list_kind.clone()),
+            };
+
+            let option = data.option.clone();
+            if self.column_option_ui(ui, data).clicked() {
+                selected_option = option.take_as_response(self.ndb, self.cur_account);
+            }
+
+            ui.add(Separator::default().spacing(0.0));
+        }
+
+        selected_option
+    }
+
+    fn column_option_ui(&mut self, ui: &mut Ui, data: ColumnOptionData<'_>) -> egui::Response {
         let icon_padding = 8.0;
         let min_icon_width = 32.0;
         let height_padding = 12.0;
@@ -275,7 +342,7 @@ impl<'a> AddColumnView<'a> {
         helper.take_animation_response()
     }
 
-    fn get_base_options(&self) -> Vec<ColumnOptionData> {
+    fn get_base_options(&self) -> Vec<ColumnOptionData<'static>> {
         let mut vec = Vec::new();
         vec.push(ColumnOptionData {
             title: "Universe",
@@ -306,11 +373,34 @@ impl<'a> AddColumnView<'a> {
             icon: egui::include_image!("../../assets/icons/notifications_icon_dark_4x.png"),
             option: AddColumnOption::UndecidedHashtag,
         });
+        vec.push(ColumnOptionData {
+            title: "Articles",
+            description: "Read long-form posts from across nostr",
+            icon: egui::include_image!("../../assets/icons/universe_icon_dark_4x.png"),
+            option: AddColumnOption::Articles,
+        });
+
+        if let Some(acc) = self.cur_account {
+            vec.push(ColumnOptionData {
+                title: "Bookmarks",
+                description: "Notes you've saved for later",
+                icon: egui::include_image!("../../assets/icons/select_icon_3x.png"),
+                option: AddColumnOption::List(ListKind::Bookmarks(PubkeySource::Explicit(
+                    acc.pubkey,
+                ))),
+            });
+            vec.push(ColumnOptionData {
+                title: "Lists",
+                description: "Follow sets, pinned notes and interests",
+                icon: egui::include_image!("../../assets/icons/home_icon_dark_4x.png"),
+                option: AddColumnOption::UndecidedList,
+            });
+        }
 
         vec
     }
 
-    fn get_notifications_options(&self) -> Vec<ColumnOptionData> {
+    fn get_notifications_options(&self) -> Vec<ColumnOptionData<'static>> {
         let mut vec = Vec::new();
 
         if let Some(acc) = self.cur_account {
@@ -339,9 +429,9 @@ impl<'a> AddColumnView<'a> {
     }
 }
 
-struct ColumnOptionData {
-    title: &'static str,
-    description: &'static str,
+struct ColumnOptionData<'a> {
+    title: &'a str,
+    description: &'a str,
     icon: ImageSource<'static>,
     option: AddColumnOption,
 }
@@ -356,12 +446,14 @@ pub fn render_add_column_routes(
         &mut app.view_state.id_state_map,
         &app.ndb,
         app.accounts.get_selected_account(),
+        &app.proxy.http,
     );
     let resp = match route {
         AddColumnRoute::Base => add_column_view.ui(ui),
         AddColumnRoute::UndecidedNotification => add_column_view.notifications_ui(ui),
         AddColumnRoute::ExternalNotification => add_column_view.external_notification_ui(ui),
         AddColumnRoute::Hashtag => hashtag_ui(ui, &app.ndb, &mut app.view_state.id_string_map),
+        AddColumnRoute::Lists => add_column_view.lists_ui(ui),
     };
 
     if let Some(resp) = resp {
@@ -393,6 +485,16 @@ pub fn render_add_column_routes(
                     .router_mut()
                     .route_to(crate::route::Route::AddColumn(AddColumnRoute::Hashtag));
             }
+            AddColumnResponse::Lists => {
+                if let Some(acc) = app.accounts.get_selected_account() {
+                    lists::fetch_lists(&mut app.subscriptions, acc.pubkey.bytes());
+                }
+
+                app.columns_mut()
+                    .column_mut(col)
+                    .router_mut()
+                    .route_to(crate::route::Route::AddColumn(AddColumnRoute::Lists));
+            }
         };
     }
 }
@@ -455,6 +557,7 @@ mod preview {
                 &mut self.app.view_state.id_state_map,
                 &self.app.ndb,
                 self.app.accounts.get_selected_account(),
+                &self.app.proxy.http,
             )
             .ui(ui);
         }
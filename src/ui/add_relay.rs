@@ -0,0 +1,172 @@
+use egui::{Align, Margin, RichText, Vec2};
+use enostr::{RelayInformation, RelayPool};
+use poll_promise::Promise;
+use url::Url;
+
+use crate::{
+    app_style::NotedeckTextStyle, proxy::Http, relay_information::fetch_relay_information, ui,
+    Result,
+};
+
+use super::relay_detail::relay_information_ui;
+
+/// What's been typed into the add relay form, and the information
+/// document of the relay it points to
+#[derive(Default)]
+pub struct AddRelayState {
+    pub url: String,
+    preview: Option<RelayPreview>,
+}
+
+struct RelayPreview {
+    url: String,
+    info: Promise<Result<RelayInformation>>,
+}
+
+impl AddRelayState {
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// A form for adding a relay. The url is validated as you type, and once
+/// it's valid we fetch the relay's information document so you can see
+/// what you're connecting to.
+pub struct AddRelayView<'a> {
+    state: &'a mut AddRelayState,
+    pool: &'a RelayPool,
+    http: &'a Http,
+}
+
+impl<'a> AddRelayView<'a> {
+    pub fn new(state: &'a mut AddRelayState, pool: &'a RelayPool, http: &'a Http) -> Self {
+        AddRelayView { state, pool, http }
+    }
+
+    /// Returns the url of the relay to add, once the user confirms it
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> Option<String> {
+        ui::padding(16.0, ui, |ui| {
+            ui.spacing_mut().item_spacing.y = 8.0;
+
+            let text_edit = egui::TextEdit::singleline(&mut self.state.url)
+                .hint_text(
+                    RichText::new("wss://relay.example.com")
+                        .text_style(NotedeckTextStyle::Body.text_style()),
+                )
+                .vertical_align(Align::Center)
+                .desired_width(f32::INFINITY)
+                .min_size(Vec2::new(0.0, 40.0))
+                .margin(Margin::same(12.0));
+            let resp = ui.add(text_edit);
+
+            let url = match validate_relay_url(&self.state.url) {
+                Ok(url) => url,
+                Err(err) => {
+                    if !self.state.url.is_empty() {
+                        ui.colored_label(ui.visuals().error_fg_color, err);
+                    }
+                    return None;
+                }
+            };
+
+            // ie. an onion relay without a proxy
+            if let Err(err) = self.pool.check_url(&url) {
+                ui.colored_label(ui.visuals().error_fg_color, err.to_string());
+                return None;
+            }
+
+            let submitted = resp.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+
+            // only look the relay up once they've stopped typing
+            let is_previewed = self.state.preview.as_ref().map(|p| &p.url) == Some(&url);
+            if !is_previewed && !resp.has_focus() {
+                self.state.preview = Some(RelayPreview {
+                    url: url.clone(),
+                    info: fetch_relay_information(ui.ctx(), self.http, &url),
+                });
+            }
+
+            let add_clicked = ui.button("Add relay").clicked();
+
+            match &self.state.preview {
+                Some(preview) if preview.url == url => preview_ui(ui, preview),
+                _ => {
+                    ui.weak("Click away from the address to look up this relay");
+                }
+            }
+
+            (add_clicked || submitted).then_some(url)
+        })
+        .inner
+    }
+}
+
+fn preview_ui(ui: &mut egui::Ui, preview: &RelayPreview) {
+    match preview.info.ready() {
+        None => {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.weak("Looking up relay...");
+            });
+        }
+        Some(Err(err)) => {
+            ui.weak(format!(
+                "Couldn't get this relay's information document: {err}"
+            ));
+        }
+        Some(Ok(info)) => {
+            ui.separator();
+            relay_information_ui(ui, info);
+        }
+    }
+}
+
+/// Check that a url is something we can connect to as a relay. If no
+/// scheme is given we assume `wss://`.
+fn validate_relay_url(input: &str) -> std::result::Result<String, String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Err("Enter a relay url".to_owned());
+    }
+
+    let input = if input.contains("://") {
+        input.to_owned()
+    } else {
+        format!("wss://{input}")
+    };
+
+    let url = Url::parse(&input).map_err(|e| format!("Invalid url: {e}"))?;
+
+    if url.scheme() != "wss" && url.scheme() != "ws" {
+        return Err("Relay urls start with wss:// or ws://".to_owned());
+    }
+
+    match url.host_str() {
+        Some(host) if !host.is_empty() => Ok(url.to_string()),
+        _ => Err("Relay url is missing a host".to_owned()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_relay_url() {
+        assert_eq!(
+            validate_relay_url("wss://relay.damus.io").as_deref(),
+            Ok("wss://relay.damus.io/")
+        );
+        assert_eq!(
+            validate_relay_url(" nos.lol ").as_deref(),
+            Ok("wss://nos.lol/")
+        );
+        assert_eq!(
+            validate_relay_url("ws://localhost:8080").as_deref(),
+            Ok("ws://localhost:8080/")
+        );
+        assert!(validate_relay_url("https://relay.damus.io").is_err());
+        assert!(validate_relay_url("").is_err());
+        assert!(validate_relay_url("wss://").is_err());
+    }
+}
@@ -0,0 +1,245 @@
+use crate::{
+    actionbar::NoteAction,
+    app_style::NotedeckTextStyle,
+    article::{self, ArticleInfo},
+    colors,
+    imgcache::ImageCache,
+    missing_notes::{FetchState, MissingNotes},
+    nip05::Nip05Verifier,
+    ui::{
+        self,
+        markdown::Markdown,
+        note::contents::content_image,
+        profile::preview::{get_display_name_as_string, get_profile_url},
+    },
+};
+use egui::{RichText, Sense};
+use enostr::{NoteId, Pubkey};
+use nostrdb::{Ndb, Note, Transaction};
+
+/// How much of a summary fits on a card
+const CARD_SUMMARY_CHARS: usize = 280;
+
+const CARD_IMAGE_HEIGHT: f32 = 140.0;
+const COVER_HEIGHT: f32 = 240.0;
+
+/// Reads a long-form article
+pub struct ArticleView<'a> {
+    ndb: &'a Ndb,
+    img_cache: &'a mut ImageCache,
+    nip05: &'a mut Nip05Verifier,
+    missing_notes: &'a mut MissingNotes,
+    note_id: &'a NoteId,
+    id_source: egui::Id,
+}
+
+impl<'a> ArticleView<'a> {
+    pub fn new(
+        ndb: &'a Ndb,
+        img_cache: &'a mut ImageCache,
+        nip05: &'a mut Nip05Verifier,
+        missing_notes: &'a mut MissingNotes,
+        note_id: &'a NoteId,
+    ) -> Self {
+        ArticleView {
+            ndb,
+            img_cache,
+            nip05,
+            missing_notes,
+            note_id,
+            id_source: egui::Id::new("articlescroll"),
+        }
+    }
+
+    pub fn id_source(mut self, id: egui::Id) -> Self {
+        self.id_source = id;
+        self
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> Option<NoteAction> {
+        let txn = Transaction::new(self.ndb).expect("txn");
+
+        let note = if let Ok(note) = self.ndb.get_note_by_id(&txn, self.note_id.bytes()) {
+            note
+        } else {
+            self.missing_article_ui(ui);
+            return None;
+        };
+
+        egui::ScrollArea::vertical()
+            .id_source(self.id_source)
+            .auto_shrink([false, false])
+            .show(ui, |ui| {
+                ui::padding(16.0, ui, |ui| self.article_ui(ui, &txn, &note)).inner
+            })
+            .inner
+    }
+
+    fn article_ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        txn: &Transaction,
+        note: &Note,
+    ) -> Option<NoteAction> {
+        let info = ArticleInfo::new(note);
+        let blur_media = self.img_cache.media_blur.should_blur(note.pubkey());
+        let mut action = None;
+
+        if let Some(image) = info.image.filter(|_| !blur_media) {
+            let width = ui.available_width();
+            content_image(ui, self.img_cache, image, width, COVER_HEIGHT);
+        }
+
+        ui.add(
+            egui::Label::new(
+                RichText::new(info.title_or_untitled())
+                    .text_style(NotedeckTextStyle::Heading.text_style())
+                    .strong(),
+            )
+            .wrap(),
+        );
+
+        if author_ui(ui, self.ndb, self.img_cache, txn, note).clicked() {
+            action = Some(NoteAction::OpenProfile(Pubkey::new(*note.pubkey())));
+        }
+
+        ui.label(RichText::new(article_byline(&info, note)).color(colors::GRAY_SECONDARY));
+
+        if let Some(summary) = info.summary {
+            ui.add(egui::Label::new(RichText::new(summary).italics()).wrap());
+        }
+
+        ui.separator();
+
+        let body = Markdown::new(self.ndb, self.img_cache, self.nip05, txn, note.content())
+            .blur_media(blur_media)
+            .show(ui);
+
+        action.or(body)
+    }
+
+    fn missing_article_ui(&mut self, ui: &mut egui::Ui) {
+        self.missing_notes.want(self.note_id, &[]);
+
+        ui::padding(16.0, ui, |ui| {
+            ui.vertical_centered(|ui| {
+                if self.missing_notes.state(self.note_id) == Some(FetchState::NotFound) {
+                    ui.heading("Article not found");
+                    ui.add_space(8.0);
+                    if ui.button("Try again").clicked() {
+                        self.missing_notes.retry(self.note_id);
+                    }
+                } else {
+                    ui.spinner();
+                    ui.label(
+                        RichText::new("Looking for this article...").color(colors::GRAY_SECONDARY),
+                    );
+                }
+            });
+        });
+    }
+}
+
+/// An article in a timeline or mentioned in a note: its cover, title,
+/// summary and reading time. Click it to read the article.
+pub fn article_card(
+    ui: &mut egui::Ui,
+    ndb: &Ndb,
+    img_cache: &mut ImageCache,
+    txn: &Transaction,
+    note: &Note,
+    show_author: bool,
+) -> egui::Response {
+    let info = ArticleInfo::new(note);
+
+    let resp = egui::Frame::none()
+        .fill(ui.visuals().faint_bg_color)
+        .inner_margin(egui::Margin::same(8.0))
+        .outer_margin(egui::Margin::symmetric(0.0, 4.0))
+        .rounding(egui::Rounding::same(8.0))
+        .stroke(ui.visuals().noninteractive().bg_stroke)
+        .show(ui, |ui| {
+            ui.set_width(ui.available_width());
+
+            let blur_media = img_cache.media_blur.should_blur(note.pubkey());
+            if let Some(image) = info.image.filter(|_| !blur_media) {
+                let width = ui.available_width();
+                content_image(ui, img_cache, image, width, CARD_IMAGE_HEIGHT);
+            }
+
+            if show_author {
+                author_ui(ui, ndb, img_cache, txn, note);
+            }
+
+            ui.add(
+                egui::Label::new(
+                    RichText::new(info.title_or_untitled())
+                        .text_style(NotedeckTextStyle::Heading3.text_style())
+                        .strong(),
+                )
+                .wrap(),
+            );
+
+            if let Some(summary) = info.summary {
+                ui.add(egui::Label::new(RichText::new(abbreviated_summary(summary)).weak()).wrap());
+            }
+
+            ui.label(
+                RichText::new(article_byline(&info, note))
+                    .text_style(NotedeckTextStyle::Small.text_style())
+                    .color(colors::GRAY_SECONDARY),
+            );
+        })
+        .response;
+
+    resp.interact(Sense::click())
+        .on_hover_cursor(egui::CursorIcon::PointingHand)
+}
+
+fn author_ui(
+    ui: &mut egui::Ui,
+    ndb: &Ndb,
+    img_cache: &mut ImageCache,
+    txn: &Transaction,
+    note: &Note,
+) -> egui::Response {
+    let profile = ndb.get_profile_by_pubkey(txn, note.pubkey()).ok();
+
+    ui.horizontal(|ui| {
+        ui.add(
+            ui::ProfilePic::new(img_cache, get_profile_url(profile.as_ref()))
+                .size(ui::ProfilePic::small_size()),
+        );
+        ui.label(RichText::new(get_display_name_as_string(profile.as_ref())).strong());
+    })
+    .response
+    .interact(Sense::click())
+}
+
+/// When it was published and how long it takes to read
+fn article_byline(info: &ArticleInfo, note: &Note) -> String {
+    let minutes = article::reading_time(note.content());
+    let published = info.published_at.unwrap_or_else(|| note.created_at());
+
+    match chrono::DateTime::from_timestamp(published as i64, 0) {
+        Some(date) => format!("{} · {} min read", date.format("%b %-d, %Y"), minutes),
+        None => format!("{} min read", minutes),
+    }
+}
+
+fn abbreviated_summary(summary: &str) -> String {
+    if summary.chars().count() <= CARD_SUMMARY_CHARS {
+        summary.to_owned()
+    } else {
+        let cut: String = summary.chars().take(CARD_SUMMARY_CHARS).collect();
+        format!("{}…", cut.trim_end())
+    }
+}
+
+pub fn get_article_title_string(ndb: &Ndb, id: &NoteId) -> String {
+    let txn = Transaction::new(ndb).expect("Transaction should have worked");
+    match ndb.get_note_by_id(&txn, id.bytes()) {
+        Ok(note) => ArticleInfo::new(&note).title_or_untitled().to_owned(),
+        Err(_) => "Article".to_owned(),
+    }
+}
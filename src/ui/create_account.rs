@@ -0,0 +1,179 @@
+use egui::{Align, Button, Color32, Margin, RichText, Vec2};
+use enostr::{FullKeypair, Pubkey};
+
+use crate::{
+    accounts::{suggested_follows, NewAccount, ProfileFields},
+    app_style::NotedeckTextStyle,
+    ui,
+};
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum CreateAccountStep {
+    /// Show the new secret key and make sure it gets saved somewhere
+    Backup,
+    Profile,
+    Follows,
+}
+
+/// An account that's being created, before it's added and published
+pub struct CreateAccountDraft {
+    step: CreateAccountStep,
+    keypair: FullKeypair,
+    backed_up: bool,
+    profile: ProfileFields,
+    follows: Vec<(&'static str, Pubkey, bool)>,
+}
+
+impl Default for CreateAccountDraft {
+    fn default() -> Self {
+        CreateAccountDraft {
+            step: CreateAccountStep::Backup,
+            keypair: FullKeypair::generate(),
+            backed_up: false,
+            profile: ProfileFields::default(),
+            follows: suggested_follows()
+                .into_iter()
+                .map(|(name, pk)| (name, pk, false))
+                .collect(),
+        }
+    }
+}
+
+impl CreateAccountDraft {
+    pub fn into_account(self) -> NewAccount {
+        NewAccount {
+            keypair: self.keypair,
+            profile: self.profile,
+            follows: self
+                .follows
+                .into_iter()
+                .filter(|(_, _, follow)| *follow)
+                .map(|(_, pk, _)| pk)
+                .collect(),
+        }
+    }
+}
+
+/// Onboarding for a brand new nostr account: back up the key, fill in a
+/// profile and pick some people to follow
+pub struct CreateAccountView<'a> {
+    draft: &'a mut CreateAccountDraft,
+}
+
+impl<'a> CreateAccountView<'a> {
+    pub fn new(draft: &'a mut CreateAccountDraft) -> Self {
+        CreateAccountView { draft }
+    }
+
+    /// Returns true once the user is done and the account should be created
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> bool {
+        ui::padding(16.0, ui, |ui| {
+            ui.spacing_mut().item_spacing.y = 8.0;
+
+            match self.draft.step {
+                CreateAccountStep::Backup => self.backup_ui(ui),
+                CreateAccountStep::Profile => self.profile_ui(ui),
+                CreateAccountStep::Follows => return self.follows_ui(ui),
+            }
+
+            false
+        })
+        .inner
+    }
+
+    fn backup_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label(step_title("Your secret key"));
+        ui.label("This is the only way to log into your account. Nobody can recover it for you, so save it somewhere safe, like a password manager.");
+
+        if let Some(nsec) = self.draft.keypair.nsec() {
+            ui.horizontal(|ui| {
+                ui.add(egui::Label::new(RichText::new(&nsec).monospace()).truncate());
+                if ui.button("Copy").clicked() {
+                    ui.ctx().copy_text(nsec);
+                }
+            });
+        }
+
+        ui.checkbox(
+            &mut self.draft.backed_up,
+            "I've saved my secret key somewhere safe",
+        );
+
+        if ui
+            .add_enabled(self.draft.backed_up, next_button("Next"))
+            .clicked()
+        {
+            self.draft.step = CreateAccountStep::Profile;
+        }
+    }
+
+    fn profile_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label(step_title("Your profile"));
+
+        let profile = &mut self.draft.profile;
+        ui.add(profile_textedit(&mut profile.name, "Username"));
+        ui.add(profile_textedit(&mut profile.display_name, "Display name"));
+        ui.add(profile_textedit(
+            &mut profile.picture,
+            "Profile picture url",
+        ));
+        ui.add(
+            egui::TextEdit::multiline(&mut profile.about)
+                .hint_text("About you")
+                .desired_width(f32::INFINITY)
+                .margin(Margin::same(12.0)),
+        );
+
+        ui.horizontal(|ui| {
+            if ui.button("Back").clicked() {
+                self.draft.step = CreateAccountStep::Backup;
+            }
+            if ui.add(next_button("Next")).clicked() {
+                self.draft.step = CreateAccountStep::Follows;
+            }
+        });
+    }
+
+    fn follows_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        ui.label(step_title("Follow some people"));
+        ui.label("Pick a few accounts to fill your home timeline. You can skip this.");
+
+        for (name, _pk, follow) in &mut self.draft.follows {
+            ui.checkbox(follow, *name);
+        }
+
+        let mut done = false;
+        ui.horizontal(|ui| {
+            if ui.button("Back").clicked() {
+                self.draft.step = CreateAccountStep::Profile;
+            }
+            done = ui.add(next_button("Create account")).clicked();
+        });
+        done
+    }
+}
+
+fn step_title(title: &str) -> RichText {
+    RichText::new(title)
+        .text_style(NotedeckTextStyle::Heading2.text_style())
+        .strong()
+}
+
+fn next_button(text: &str) -> Button<'static> {
+    Button::new(
+        RichText::new(text)
+            .text_style(NotedeckTextStyle::Body.text_style())
+            .strong(),
+    )
+    .fill(Color32::from_rgb(0xF8, 0x69, 0xB6))
+    .min_size(Vec2::new(120.0, 40.0))
+}
+
+fn profile_textedit<'t>(text: &'t mut String, hint: &str) -> egui::TextEdit<'t> {
+    egui::TextEdit::singleline(text)
+        .hint_text(RichText::new(hint).text_style(NotedeckTextStyle::Body.text_style()))
+        .vertical_align(Align::Center)
+        .desired_width(f32::INFINITY)
+        .min_size(Vec2::new(0.0, 40.0))
+        .margin(Margin::same(12.0))
+}
@@ -0,0 +1,525 @@
+use crate::{
+    actionbar::NoteAction,
+    app_style::NotedeckTextStyle,
+    article, colors,
+    imgcache::ImageCache,
+    nip05::Nip05Verifier,
+    note,
+    ui::{self, note::contents::content_image},
+};
+use egui::{Hyperlink, RichText, Sense};
+use enostr::{Nip19, NoteId};
+use nostrdb::{Ndb, Transaction};
+use pulldown_cmark::{Event, HeadingLevel, Options, Parser, Tag};
+
+const INDENT: f32 = 16.0;
+const IMAGE_HEIGHT: f32 = 360.0;
+
+/// Renders Markdown, ie. the contents of long-form articles. `nostr:`
+/// links and mentions can be clicked.
+pub struct Markdown<'a> {
+    ndb: &'a Ndb,
+    img_cache: &'a mut ImageCache,
+    nip05: &'a mut Nip05Verifier,
+    txn: &'a Transaction,
+    text: &'a str,
+    blur_media: bool,
+}
+
+/// Markdown elements we're in the middle of
+enum Open {
+    Paragraph,
+    Heading(HeadingLevel),
+    Quote,
+    CodeBlock,
+    List(Option<u64>),
+    Item,
+    Emphasis,
+    Strong,
+    Strikethrough,
+    Link(String),
+    Image(String),
+    Other,
+}
+
+/// A piece of a paragraph
+enum Inline {
+    Text {
+        text: String,
+        emphasis: bool,
+        strong: bool,
+        strikethrough: bool,
+        link: Option<String>,
+    },
+    Code(String),
+    Nostr(String),
+    Break,
+}
+
+/// Part of some text: either plain text or a `nostr:` uri
+#[derive(Debug, PartialEq, Eq)]
+pub enum TextSegment<'a> {
+    Text(&'a str),
+    Nostr(&'a str),
+}
+
+impl<'a> Markdown<'a> {
+    pub fn new(
+        ndb: &'a Ndb,
+        img_cache: &'a mut ImageCache,
+        nip05: &'a mut Nip05Verifier,
+        txn: &'a Transaction,
+        text: &'a str,
+    ) -> Self {
+        Markdown {
+            ndb,
+            img_cache,
+            nip05,
+            txn,
+            text,
+            blur_media: false,
+        }
+    }
+
+    /// Show links instead of images
+    pub fn blur_media(mut self, blur_media: bool) -> Self {
+        self.blur_media = blur_media;
+        self
+    }
+
+    pub fn show(mut self, ui: &mut egui::Ui) -> Option<NoteAction> {
+        let mut action = None;
+        let mut open: Vec<Open> = vec![];
+        let mut inlines: Vec<Inline> = vec![];
+        let mut code = String::new();
+        let mut bullet: Option<String> = None;
+
+        ui.spacing_mut().item_spacing.y = 8.0;
+
+        let parser = Parser::new_ext(
+            self.text,
+            Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS,
+        );
+
+        for event in parser {
+            match event {
+                Event::Start(tag) => {
+                    let starts_block = matches!(
+                        tag,
+                        Tag::Paragraph
+                            | Tag::Heading { .. }
+                            | Tag::BlockQuote { .. }
+                            | Tag::CodeBlock(_)
+                            | Tag::List(_)
+                            | Tag::Item
+                    );
+                    if starts_block && !inlines.is_empty() {
+                        action = action.or(self.flush(ui, &open, &mut inlines, &mut bullet));
+                    }
+
+                    let opened = match tag {
+                        Tag::Paragraph => Open::Paragraph,
+                        Tag::Heading { level, .. } => Open::Heading(level),
+                        Tag::BlockQuote { .. } => Open::Quote,
+                        Tag::CodeBlock(_) => {
+                            code.clear();
+                            Open::CodeBlock
+                        }
+                        Tag::List(start) => Open::List(start),
+                        Tag::Item => {
+                            bullet = Some(next_bullet(&mut open));
+                            Open::Item
+                        }
+                        Tag::Emphasis => Open::Emphasis,
+                        Tag::Strong => Open::Strong,
+                        Tag::Strikethrough => Open::Strikethrough,
+                        Tag::Link { dest_url, .. } => Open::Link(dest_url.to_string()),
+                        Tag::Image { dest_url, .. } => Open::Image(dest_url.to_string()),
+                        _ => Open::Other,
+                    };
+                    open.push(opened);
+                }
+
+                Event::End(_) => {
+                    let ends_block = matches!(
+                        open.last(),
+                        Some(
+                            Open::Paragraph
+                                | Open::Heading(_)
+                                | Open::Quote
+                                | Open::List(_)
+                                | Open::Item
+                        )
+                    );
+                    if ends_block {
+                        action = action.or(self.flush(ui, &open, &mut inlines, &mut bullet));
+                    }
+
+                    match open.pop() {
+                        Some(Open::CodeBlock) => code_block_ui(ui, &open, code.trim_end()),
+                        Some(Open::Image(url)) => {
+                            action = action.or(self.flush(ui, &open, &mut inlines, &mut bullet));
+                            self.image_ui(ui, &open, &url);
+                        }
+                        _ => {}
+                    }
+                }
+
+                Event::Text(text) => match open.last() {
+                    Some(Open::CodeBlock) => code.push_str(&text),
+                    // alt text, we show the image instead
+                    Some(Open::Image(_)) => {}
+                    _ => push_text(&open, &mut inlines, &text),
+                },
+
+                Event::Code(text) => inlines.push(Inline::Code(text.to_string())),
+
+                Event::SoftBreak => push_text(&open, &mut inlines, " "),
+
+                Event::HardBreak => inlines.push(Inline::Break),
+
+                Event::Rule => {
+                    action = action.or(self.flush(ui, &open, &mut inlines, &mut bullet));
+                    ui.separator();
+                }
+
+                Event::TaskListMarker(done) => {
+                    bullet = Some(if done { "☑ " } else { "☐ " }.to_owned());
+                }
+
+                Event::Html(html) | Event::InlineHtml(html) => {
+                    push_text(&open, &mut inlines, &html);
+                }
+
+                _ => {}
+            }
+        }
+
+        action.or(self.flush(ui, &open, &mut inlines, &mut bullet))
+    }
+
+    /// Lay out the paragraph we've collected so far
+    fn flush(
+        &mut self,
+        ui: &mut egui::Ui,
+        open: &[Open],
+        inlines: &mut Vec<Inline>,
+        bullet: &mut Option<String>,
+    ) -> Option<NoteAction> {
+        if inlines.is_empty() && bullet.is_none() {
+            return None;
+        }
+
+        let inlines = std::mem::take(inlines);
+        let bullet = bullet.take();
+        let heading = open.iter().rev().find_map(|o| match o {
+            Open::Heading(level) => Some(*level),
+            _ => None,
+        });
+        let quoted = open.iter().any(|o| matches!(o, Open::Quote));
+        let mut action = None;
+
+        ui.horizontal(|ui| {
+            ui.add_space(indent(open));
+            if let Some(bullet) = bullet {
+                ui.label(bullet);
+            }
+
+            ui.horizontal_wrapped(|ui| {
+                ui.spacing_mut().item_spacing.x = 0.0;
+
+                for inline in inlines {
+                    match inline {
+                        Inline::Text {
+                            text,
+                            emphasis,
+                            strong,
+                            strikethrough,
+                            link,
+                        } => {
+                            let mut rich = RichText::new(&text);
+                            if let Some(level) = heading {
+                                rich = rich.text_style(heading_style(level)).strong();
+                            }
+                            if emphasis || quoted {
+                                rich = rich.italics();
+                            }
+                            if strong {
+                                rich = rich.strong();
+                            }
+                            if strikethrough {
+                                rich = rich.strikethrough();
+                            }
+
+                            match link {
+                                Some(url) if url.starts_with("nostr:") => {
+                                    let resp = ui.add(
+                                        egui::Label::new(rich.color(colors::PURPLE))
+                                            .sense(Sense::click()),
+                                    );
+                                    if resp.clicked() {
+                                        action = nostr_action(self.ndb, self.txn, &url);
+                                    }
+                                }
+                                Some(url) => {
+                                    ui.add(Hyperlink::from_label_and_url(
+                                        rich.color(colors::PURPLE),
+                                        url,
+                                    ));
+                                }
+                                None => {
+                                    if quoted {
+                                        rich = rich.weak();
+                                    }
+                                    ui.add(egui::Label::new(rich).selectable(true));
+                                }
+                            }
+                        }
+
+                        Inline::Code(text) => {
+                            ui.code(text);
+                        }
+
+                        Inline::Nostr(uri) => {
+                            if let Some(a) = self.nostr_uri_ui(ui, &uri) {
+                                action = Some(a);
+                            }
+                        }
+
+                        Inline::Break => {
+                            ui.end_row();
+                        }
+                    }
+                }
+            });
+        });
+
+        action
+    }
+
+    /// Mentions show the profile, and everything else can be clicked to
+    /// open it
+    fn nostr_uri_ui(&mut self, ui: &mut egui::Ui, uri: &str) -> Option<NoteAction> {
+        let entity = if let Ok(entity) = Nip19::parse(uri) {
+            entity
+        } else {
+            ui.label(uri);
+            return None;
+        };
+
+        let label = match &entity {
+            Nip19::Pubkey(pubkey) | Nip19::Profile { pubkey, .. } => {
+                let resp = ui.add(ui::Mention::new(
+                    self.ndb,
+                    self.img_cache,
+                    self.nip05,
+                    self.txn,
+                    pubkey.bytes(),
+                ));
+                return resp
+                    .interact(Sense::click())
+                    .clicked()
+                    .then_some(NoteAction::OpenProfile(*pubkey));
+            }
+
+            Nip19::Address(address) => {
+                let title = note::find_addressable(self.ndb, self.txn, address)
+                    .filter(article::is_article)
+                    .map(|note| {
+                        article::ArticleInfo::new(&note)
+                            .title_or_untitled()
+                            .to_owned()
+                    });
+                title.unwrap_or_else(|| abbreviated(uri))
+            }
+
+            Nip19::Relay(relay) => relay.clone(),
+
+            Nip19::Note(_) | Nip19::Event { .. } => abbreviated(uri),
+        };
+
+        let resp = ui.add(
+            egui::Label::new(RichText::new(label).color(colors::PURPLE)).sense(Sense::click()),
+        );
+        if resp.clicked() {
+            nostr_action(self.ndb, self.txn, uri)
+        } else {
+            None
+        }
+    }
+
+    fn image_ui(&mut self, ui: &mut egui::Ui, open: &[Open], url: &str) {
+        ui.horizontal(|ui| {
+            ui.add_space(indent(open));
+            if self.blur_media {
+                ui.add(Hyperlink::from_label_and_url(
+                    RichText::new(url).color(colors::PURPLE),
+                    url,
+                ));
+            } else {
+                let width = ui.available_width();
+                content_image(ui, self.img_cache, url, width, IMAGE_HEIGHT);
+            }
+        });
+    }
+}
+
+/// The bullet or number for the next item of the innermost list
+fn next_bullet(open: &mut [Open]) -> String {
+    let list = open.iter_mut().rev().find_map(|o| match o {
+        Open::List(start) => Some(start),
+        _ => None,
+    });
+
+    match list {
+        Some(Some(n)) => {
+            let bullet = format!("{n}. ");
+            *n += 1;
+            bullet
+        }
+        _ => "• ".to_owned(),
+    }
+}
+
+fn code_block_ui(ui: &mut egui::Ui, open: &[Open], code: &str) {
+    ui.horizontal(|ui| {
+        ui.add_space(indent(open));
+        egui::Frame::none()
+            .fill(ui.visuals().code_bg_color)
+            .inner_margin(egui::Margin::same(8.0))
+            .rounding(egui::Rounding::same(4.0))
+            .show(ui, |ui| {
+                ui.set_width(ui.available_width());
+                egui::ScrollArea::horizontal().show(ui, |ui| {
+                    ui.add(
+                        egui::Label::new(
+                            RichText::new(code)
+                                .text_style(NotedeckTextStyle::Monospace.text_style()),
+                        )
+                        .selectable(true)
+                        .extend(),
+                    );
+                });
+            });
+    });
+}
+
+/// Text goes in with the styles it's in, with `nostr:` uris pulled out
+fn push_text(open: &[Open], inlines: &mut Vec<Inline>, text: &str) {
+    let link = open.iter().rev().find_map(|o| match o {
+        Open::Link(url) => Some(url.clone()),
+        _ => None,
+    });
+
+    // the text of a link is the link
+    if link.is_some() {
+        inlines.push(styled(open, text.to_owned(), link));
+        return;
+    }
+
+    for segment in split_nostr_uris(text) {
+        match segment {
+            TextSegment::Text(text) => inlines.push(styled(open, text.to_owned(), None)),
+            TextSegment::Nostr(uri) => inlines.push(Inline::Nostr(uri.to_owned())),
+        }
+    }
+}
+
+fn styled(open: &[Open], text: String, link: Option<String>) -> Inline {
+    Inline::Text {
+        text,
+        emphasis: open.iter().any(|o| matches!(o, Open::Emphasis)),
+        strong: open.iter().any(|o| matches!(o, Open::Strong)),
+        strikethrough: open.iter().any(|o| matches!(o, Open::Strikethrough)),
+        link,
+    }
+}
+
+/// Split `nostr:` uris out of some text
+pub fn split_nostr_uris(text: &str) -> Vec<TextSegment<'_>> {
+    let mut segments = vec![];
+    let mut rest = text;
+
+    while let Some(start) = rest.find("nostr:") {
+        let uri_len = rest[start + 6..]
+            .find(|c: char| !c.is_ascii_alphanumeric())
+            .unwrap_or(rest.len() - start - 6);
+
+        // a bare `nostr:` isn't a uri
+        if uri_len == 0 {
+            segments.push(TextSegment::Text(&rest[..start + 6]));
+            rest = &rest[start + 6..];
+            continue;
+        }
+
+        if start > 0 {
+            segments.push(TextSegment::Text(&rest[..start]));
+        }
+        segments.push(TextSegment::Nostr(&rest[start..start + 6 + uri_len]));
+        rest = &rest[start + 6 + uri_len..];
+    }
+
+    if !rest.is_empty() {
+        segments.push(TextSegment::Text(rest));
+    }
+
+    segments
+}
+
+/// What clicking a `nostr:` link does
+fn nostr_action(ndb: &Ndb, txn: &Transaction, uri: &str) -> Option<NoteAction> {
+    match Nip19::parse(uri).ok()? {
+        Nip19::Pubkey(pubkey) | Nip19::Profile { pubkey, .. } => {
+            Some(NoteAction::OpenProfile(pubkey))
+        }
+        Nip19::Note(id) | Nip19::Event { id, .. } => Some(NoteAction::OpenThread(id)),
+        Nip19::Address(address) => note::find_addressable(ndb, txn, &address)
+            .map(|note| NoteAction::OpenThread(NoteId::new(*note.id()))),
+        Nip19::Relay(_) => None,
+    }
+}
+
+fn abbreviated(uri: &str) -> String {
+    let bech = uri.strip_prefix("nostr:").unwrap_or(uri);
+    format!("{}…", &bech[..bech.len().min(16)])
+}
+
+/// Nested lists and quotes are indented, top level lists have their bullets
+fn indent(open: &[Open]) -> f32 {
+    let lists = open.iter().filter(|o| matches!(o, Open::List(_))).count();
+    let quotes = open.iter().filter(|o| matches!(o, Open::Quote)).count();
+    (lists.saturating_sub(1) + quotes) as f32 * INDENT
+}
+
+fn heading_style(level: HeadingLevel) -> egui::TextStyle {
+    match level {
+        HeadingLevel::H1 => NotedeckTextStyle::Heading.text_style(),
+        HeadingLevel::H2 => NotedeckTextStyle::Heading2.text_style(),
+        HeadingLevel::H3 => NotedeckTextStyle::Heading3.text_style(),
+        _ => NotedeckTextStyle::Body.text_style(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_nostr_uris() {
+        assert_eq!(
+            split_nostr_uris("hi nostr:npub1abc, and nostr:note1xyz"),
+            vec![
+                TextSegment::Text("hi "),
+                TextSegment::Nostr("nostr:npub1abc"),
+                TextSegment::Text(", and "),
+                TextSegment::Nostr("nostr:note1xyz"),
+            ]
+        );
+
+        assert_eq!(
+            split_nostr_uris("just nostr: text"),
+            vec![TextSegment::Text("just nostr:"), TextSegment::Text(" text")]
+        );
+        assert!(split_nostr_uris("").is_empty());
+    }
+}
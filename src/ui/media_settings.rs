@@ -0,0 +1,31 @@
+use crate::{media_settings::MediaVisibility, ui};
+
+/// What media the selected account sees in notes
+pub struct MediaSettingsView<'a> {
+    visibility: &'a mut MediaVisibility,
+}
+
+impl<'a> MediaSettingsView<'a> {
+    pub fn new(visibility: &'a mut MediaVisibility) -> Self {
+        MediaSettingsView { visibility }
+    }
+
+    /// Returns true if the setting changed and should be saved
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = false;
+
+        ui::padding(16.0, ui, |ui| {
+            ui.spacing_mut().item_spacing.y = 8.0;
+
+            ui.label("Blurred images aren't downloaded until you click them. This only applies to the selected account.");
+
+            for visibility in MediaVisibility::ALL {
+                changed |= ui
+                    .radio_value(self.visibility, visibility, visibility.name())
+                    .changed();
+            }
+        });
+
+        changed
+    }
+}
@@ -1,9 +1,10 @@
-use crate::{colors, imgcache::ImageCache, ui};
+use crate::{colors, imgcache::ImageCache, nip05::Nip05Verifier, ui, ui::nip05::profile_nip05};
 use nostrdb::{Ndb, Transaction};
 
 pub struct Mention<'a> {
     ndb: &'a Ndb,
     img_cache: &'a mut ImageCache,
+    nip05: &'a mut Nip05Verifier,
     txn: &'a Transaction,
     pk: &'a [u8; 32],
     selectable: bool,
@@ -14,6 +15,7 @@ impl<'a> Mention<'a> {
     pub fn new(
         ndb: &'a Ndb,
         img_cache: &'a mut ImageCache,
+        nip05: &'a mut Nip05Verifier,
         txn: &'a Transaction,
         pk: &'a [u8; 32],
     ) -> Self {
@@ -22,6 +24,7 @@ impl<'a> Mention<'a> {
         Mention {
             ndb,
             img_cache,
+            nip05,
             txn,
             pk,
             selectable,
@@ -45,6 +48,7 @@ impl<'a> egui::Widget for Mention<'a> {
         mention_ui(
             self.ndb,
             self.img_cache,
+            self.nip05,
             self.txn,
             self.pk,
             ui,
@@ -54,9 +58,11 @@ impl<'a> egui::Widget for Mention<'a> {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn mention_ui(
     ndb: &Ndb,
     img_cache: &mut ImageCache,
+    nip05: &mut Nip05Verifier,
     txn: &Transaction,
     pk: &[u8; 32],
     ui: &mut egui::Ui,
@@ -81,10 +87,14 @@ fn mention_ui(
                 .selectable(selectable),
         );
 
+        if let Some(address) = profile_nip05(profile.as_ref()) {
+            ui.add(ui::Nip05Badge::new(nip05, pk, address).size(size * 0.75));
+        }
+
         if let Some(rec) = profile.as_ref() {
             resp.on_hover_ui_at_pointer(|ui| {
                 ui.set_max_width(300.0);
-                ui.add(ui::ProfilePreview::new(rec, img_cache));
+                ui.add(ui::ProfilePreview::new(rec, img_cache).verify(nip05, pk));
             });
         }
     })
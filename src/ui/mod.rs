@@ -1,20 +1,35 @@
 pub mod account_login_view;
 pub mod accounts;
 pub mod add_column;
+pub mod add_relay;
 pub mod anim;
+pub mod article;
+pub mod create_account;
+pub mod markdown;
+pub mod media_settings;
 pub mod mention;
+pub mod network;
+pub mod nip05;
 pub mod note;
+pub mod notification_settings;
+pub mod notifications;
 pub mod preview;
 pub mod profile;
+pub mod proxy_settings;
 pub mod relay;
+pub mod relay_detail;
+pub mod search;
 pub mod side_panel;
+pub mod signer_notice;
 pub mod support;
 pub mod thread;
 pub mod timeline;
 pub mod username;
 
 pub use accounts::AccountsView;
+pub use article::ArticleView;
 pub use mention::Mention;
+pub use nip05::Nip05Badge;
 pub use note::{NoteResponse, NoteView, PostReplyView, PostView};
 pub use preview::{Preview, PreviewApp, PreviewConfig};
 pub use profile::{ProfilePic, ProfilePreview};
@@ -0,0 +1,174 @@
+use chrono::{DateTime, Local};
+use egui::{CollapsingHeader, Grid, RichText, ScrollArea};
+use enostr::{ConnectionEventKind, FrameDirection, Relay, RelayPool, RelayStats};
+use std::time::SystemTime;
+
+use crate::{app_style::NotedeckTextStyle, ui};
+
+use super::relay::show_connection_status;
+
+/// Per-relay traffic counters, connection history and a live log of raw
+/// frames, for figuring out which relay is misbehaving
+pub struct NetworkView<'a> {
+    pool: &'a RelayPool,
+}
+
+impl<'a> NetworkView<'a> {
+    pub fn new(pool: &'a RelayPool) -> Self {
+        NetworkView { pool }
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ScrollArea::vertical()
+            .auto_shrink([false, false])
+            .show(ui, |ui| {
+                ui::padding(8.0, ui, |ui| {
+                    for pool_relay in &self.pool.relays {
+                        relay_ui(ui, &pool_relay.relay);
+                    }
+                });
+            });
+    }
+}
+
+fn relay_ui(ui: &mut egui::Ui, relay: &Relay) {
+    let stats = &relay.stats;
+
+    ui.horizontal(|ui| {
+        ui.label(RichText::new(&relay.url).text_style(NotedeckTextStyle::Monospace.text_style()));
+        show_connection_status(ui, &relay.status);
+    });
+
+    ui.push_id(&relay.url, |ui| {
+        counters_ui(ui, stats);
+
+        CollapsingHeader::new(format!("Subscriptions ({})", stats.subscriptions.len()))
+            .show(ui, |ui| subscriptions_ui(ui, stats));
+
+        CollapsingHeader::new(format!(
+            "Connection history ({} connections)",
+            relay.connections
+        ))
+        .show(ui, |ui| connection_history_ui(ui, stats));
+
+        CollapsingHeader::new(format!("Last {} frames", stats.frames.len()))
+            .show(ui, |ui| frames_ui(ui, stats));
+    });
+
+    ui::hline(ui);
+    ui.add_space(8.0);
+}
+
+fn counters_ui(ui: &mut egui::Ui, stats: &RelayStats) {
+    Grid::new("relay_counters")
+        .num_columns(4)
+        .spacing([16.0, 2.0])
+        .show(ui, |ui| {
+            ui.label("In");
+            ui.label(format!(
+                "{} messages, {}",
+                stats.messages_in,
+                format_bytes(stats.bytes_in)
+            ));
+            ui.label("Out");
+            ui.label(format!(
+                "{} messages, {}",
+                stats.messages_out,
+                format_bytes(stats.bytes_out)
+            ));
+            ui.end_row();
+
+            ui.label("Events");
+            ui.label(stats.events.to_string());
+            ui.label("Duplicates");
+            ui.label(stats.duplicates.to_string());
+            ui.end_row();
+
+            ui.label("Notices");
+            ui.label(stats.notices.to_string());
+            ui.label("Errors");
+            ui.label(stats.errors.to_string());
+            ui.end_row();
+        });
+
+    if let Some(notice) = &stats.last_notice {
+        ui.label(RichText::new(format!("Last notice: {notice}")).weak());
+    }
+}
+
+fn subscriptions_ui(ui: &mut egui::Ui, stats: &RelayStats) {
+    let mut subs: Vec<_> = stats.subscriptions.iter().collect();
+    subs.sort_by(|a, b| a.0.cmp(b.0));
+
+    Grid::new("relay_subscriptions")
+        .num_columns(4)
+        .striped(true)
+        .show(ui, |ui| {
+            ui.strong("Subscription");
+            ui.strong("Events");
+            ui.strong("Duplicates");
+            ui.strong("EOSE");
+            ui.end_row();
+
+            for (subid, sub) in subs {
+                ui.monospace(subid);
+                ui.label(sub.events.to_string());
+                ui.label(sub.duplicates.to_string());
+                match sub.eose_latency {
+                    Some(latency) => ui.label(format!("{} ms", latency.as_millis())),
+                    None => ui.weak("waiting"),
+                };
+                ui.end_row();
+            }
+        });
+}
+
+fn connection_history_ui(ui: &mut egui::Ui, stats: &RelayStats) {
+    for event in stats.connection_history.iter().rev() {
+        let what = match &event.kind {
+            ConnectionEventKind::Connecting => "connecting".to_owned(),
+            ConnectionEventKind::Opened => "connected".to_owned(),
+            ConnectionEventKind::Closed => "closed".to_owned(),
+            ConnectionEventKind::Error(err) => format!("error: {err}"),
+        };
+        ui.label(format!("{} {}", format_time(event.at), what));
+    }
+}
+
+fn frames_ui(ui: &mut egui::Ui, stats: &RelayStats) {
+    ScrollArea::vertical()
+        .max_height(300.0)
+        .stick_to_bottom(true)
+        .show(ui, |ui| {
+            for frame in &stats.frames {
+                let arrow = match frame.direction {
+                    FrameDirection::In => "⬇",
+                    FrameDirection::Out => "⬆",
+                };
+                ui.label(
+                    RichText::new(format!(
+                        "{} {} {} {}",
+                        format_time(frame.at),
+                        arrow,
+                        format_bytes(frame.len as u64),
+                        frame.text
+                    ))
+                    .text_style(NotedeckTextStyle::Monospace.text_style()),
+                );
+            }
+        });
+}
+
+fn format_time(at: SystemTime) -> String {
+    DateTime::<Local>::from(at).format("%H:%M:%S").to_string()
+}
+
+fn format_bytes(bytes: u64) -> String {
+    if bytes < 1024 {
+        format!("{bytes} B")
+    } else if bytes < 1024 * 1024 {
+        format!("{:.1} KiB", bytes as f64 / 1024.0)
+    } else {
+        format!("{:.1} MiB", bytes as f64 / (1024.0 * 1024.0))
+    }
+}
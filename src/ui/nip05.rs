@@ -0,0 +1,74 @@
+use crate::colors;
+use crate::nip05::{self, Nip05Status, Nip05Verifier};
+use egui::{RichText, Widget};
+use nostrdb::ProfileRecord;
+
+/// A checkmark next to names whose nip05 address points back at them, or
+/// a warning when it points at someone else
+pub struct Nip05Badge<'a> {
+    verifier: &'a mut Nip05Verifier,
+    pk: &'a [u8; 32],
+    address: &'a str,
+    show_address: bool,
+    size: f32,
+}
+
+impl<'a> Nip05Badge<'a> {
+    pub fn new(verifier: &'a mut Nip05Verifier, pk: &'a [u8; 32], address: &'a str) -> Self {
+        Nip05Badge {
+            verifier,
+            pk,
+            address,
+            show_address: false,
+            size: 12.0,
+        }
+    }
+
+    /// Show the address next to the badge instead of only on hover
+    pub fn show_address(mut self, show_address: bool) -> Self {
+        self.show_address = show_address;
+        self
+    }
+
+    pub fn size(mut self, size: f32) -> Self {
+        self.size = size;
+        self
+    }
+}
+
+impl<'a> Widget for Nip05Badge<'a> {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        let address = nip05::display_address(self.address);
+
+        let (icon, color, hover) = match self.verifier.status(self.pk, self.address) {
+            Some(Nip05Status::Verified) => ("✔", colors::PURPLE, format!("Verified as {address}")),
+            Some(Nip05Status::Mismatch) => (
+                "⚠",
+                ui.visuals().warn_fg_color,
+                format!("{address} doesn't verify for this key. This may be an impersonator."),
+            ),
+            None => {
+                // need any Response so we dont need an Option
+                return ui.allocate_response(egui::Vec2::ZERO, egui::Sense::hover());
+            }
+        };
+
+        let text = if self.show_address {
+            format!("{icon} {address}")
+        } else {
+            icon.to_owned()
+        };
+
+        ui.label(RichText::new(text).color(color).size(self.size))
+            .on_hover_text(hover)
+    }
+}
+
+/// The nip05 address in a profile, if it has one
+pub fn profile_nip05<'a>(profile: Option<&'a ProfileRecord<'a>>) -> Option<&'a str> {
+    profile?
+        .record()
+        .profile()?
+        .nip05()
+        .filter(|address| !address.trim().is_empty())
+}
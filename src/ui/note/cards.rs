@@ -0,0 +1,486 @@
+//! Previews of the notes other notes mention. Each kind we understand gets
+//! its own card, everything else gets a card that can show the raw note.
+
+use crate::{
+    actionbar::NoteAction,
+    app_style::NotedeckTextStyle,
+    article::KIND_ARTICLE,
+    colors,
+    imgcache::ImageCache,
+    lists::tag_str,
+    nip05::Nip05Verifier,
+    note,
+    notecache::NoteCache,
+    notifications::{self, NotificationKind},
+    ui::{
+        self,
+        note::{contents::content_image, NoteResponse},
+        profile::preview::{get_display_name_as_string, get_profile_url},
+    },
+};
+use egui::{Color32, Hyperlink, RichText, Sense};
+use enostr::{NoteAddress, NoteId};
+use nostrdb::{Ndb, Note, Transaction};
+use tracing::error;
+
+pub const KIND_HIGHLIGHT: u32 = 9802;
+pub const KIND_LIVE_EVENT: u32 = 30311;
+
+const LIVE_IMAGE_HEIGHT: f32 = 140.0;
+
+/// How much of a mentioned note we show in reaction and zap cards
+const QUOTED_CHARS: usize = 120;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum PreviewKind {
+    Note,
+    Article,
+    Highlight,
+    Repost,
+    Reaction,
+    Zap,
+    LiveEvent,
+    Unsupported(u32),
+}
+
+impl PreviewKind {
+    pub fn from_note_kind(kind: u32) -> Self {
+        match kind {
+            1 => PreviewKind::Note,
+            6 => PreviewKind::Repost,
+            7 => PreviewKind::Reaction,
+            9735 => PreviewKind::Zap,
+            KIND_HIGHLIGHT => PreviewKind::Highlight,
+            KIND_ARTICLE => PreviewKind::Article,
+            KIND_LIVE_EVENT => PreviewKind::LiveEvent,
+            kind => PreviewKind::Unsupported(kind),
+        }
+    }
+}
+
+/// Render a mentioned note with the card for its kind
+pub fn kind_preview(
+    ui: &mut egui::Ui,
+    ndb: &Ndb,
+    note_cache: &mut NoteCache,
+    img_cache: &mut ImageCache,
+    nip05: &mut Nip05Verifier,
+    txn: &Transaction,
+    note: &Note,
+) -> NoteResponse {
+    match PreviewKind::from_note_kind(note.kind()) {
+        PreviewKind::Note => note_card(ui, ndb, note_cache, img_cache, nip05, note),
+
+        PreviewKind::Article => {
+            let resp = ui::article::article_card(ui, ndb, img_cache, txn, note, true);
+            let action = resp.clicked().then(|| open_note(note));
+            NoteResponse::new(resp).with_action(action)
+        }
+
+        PreviewKind::Highlight => highlight_card(ui, ndb, img_cache, txn, note),
+        PreviewKind::Repost => repost_card(ui, ndb, note_cache, img_cache, nip05, txn, note),
+        PreviewKind::Reaction => reaction_card(ui, ndb, img_cache, txn, note),
+        PreviewKind::Zap => zap_card(ui, ndb, img_cache, txn, note),
+        PreviewKind::LiveEvent => NoteResponse::new(live_event_card(ui, ndb, img_cache, txn, note)),
+        PreviewKind::Unsupported(kind) => NoteResponse::new(unsupported_card(ui, note, kind)),
+    }
+}
+
+/// Render the addressable note an `naddr` points at, if we have it
+pub fn address_preview(
+    ui: &mut egui::Ui,
+    ndb: &Ndb,
+    note_cache: &mut NoteCache,
+    img_cache: &mut ImageCache,
+    nip05: &mut Nip05Verifier,
+    txn: &Transaction,
+    address: &NoteAddress,
+) -> NoteResponse {
+    if let Some(note) = note::find_addressable(ndb, txn, address) {
+        kind_preview(ui, ndb, note_cache, img_cache, nip05, txn, &note)
+    } else {
+        NoteResponse::new(ui.colored_label(
+            colors::PURPLE,
+            format!("@{}:{}", address.kind, address.identifier),
+        ))
+    }
+}
+
+fn card_frame<R>(
+    ui: &mut egui::Ui,
+    add_contents: impl FnOnce(&mut egui::Ui) -> R,
+) -> egui::InnerResponse<R> {
+    egui::Frame::none()
+        .fill(ui.visuals().noninteractive().weak_bg_fill)
+        .inner_margin(egui::Margin::same(8.0))
+        .outer_margin(egui::Margin::symmetric(0.0, 8.0))
+        .rounding(egui::Rounding::same(10.0))
+        .stroke(egui::Stroke::new(
+            1.0,
+            ui.visuals().noninteractive().bg_stroke.color,
+        ))
+        .show(ui, |ui| {
+            ui.set_width(ui.available_width());
+            add_contents(ui)
+        })
+}
+
+fn clickable(resp: egui::Response) -> egui::Response {
+    resp.interact(Sense::click())
+        .on_hover_cursor(egui::CursorIcon::PointingHand)
+}
+
+fn open_note(note: &Note) -> NoteAction {
+    NoteAction::OpenThread(NoteId::new(*note.id()))
+}
+
+fn note_card(
+    ui: &mut egui::Ui,
+    ndb: &Ndb,
+    note_cache: &mut NoteCache,
+    img_cache: &mut ImageCache,
+    nip05: &mut Nip05Verifier,
+    note: &Note,
+) -> NoteResponse {
+    card_frame(ui, |ui| {
+        ui::NoteView::new(ndb, note_cache, img_cache, nip05, note)
+            .actionbar(false)
+            .small_pfp(true)
+            .wide(true)
+            .note_previews(false)
+            .options_button(true)
+            .show(ui)
+    })
+    .inner
+}
+
+/// Who did something, ie. "Alice reposted"
+fn byline(
+    ui: &mut egui::Ui,
+    ndb: &Ndb,
+    img_cache: &mut ImageCache,
+    txn: &Transaction,
+    pubkey: &[u8; 32],
+    doing: &str,
+) {
+    let profile = ndb.get_profile_by_pubkey(txn, pubkey).ok();
+
+    ui.horizontal(|ui| {
+        ui.add(
+            ui::ProfilePic::new(img_cache, get_profile_url(profile.as_ref()))
+                .size(ui::ProfilePic::small_size()),
+        );
+        ui.label(RichText::new(get_display_name_as_string(profile.as_ref())).strong());
+        ui.label(RichText::new(doing).color(colors::GRAY_SECONDARY));
+    });
+}
+
+/// A short excerpt of the note another note is about
+fn quoted_note_ui(ui: &mut egui::Ui, ndb: &Ndb, txn: &Transaction, id: &NoteId) {
+    let text = match ndb.get_note_by_id(txn, id.bytes()) {
+        Ok(note) => excerpt(note.content()),
+        Err(_) => "a note we don't have yet".to_owned(),
+    };
+
+    ui.add(egui::Label::new(RichText::new(text).weak()).wrap());
+}
+
+fn excerpt(content: &str) -> String {
+    let content = content.trim();
+    if content.chars().count() <= QUOTED_CHARS {
+        content.to_owned()
+    } else {
+        let cut: String = content.chars().take(QUOTED_CHARS).collect();
+        format!("{}…", cut.trim_end())
+    }
+}
+
+fn tag_value<'a>(note: &Note<'a>, name: &str) -> Option<&'a str> {
+    note.tags()
+        .iter()
+        .find(|tag| tag.count() >= 2 && tag_str(tag, 0) == Some(name))
+        .and_then(|tag| tag_str(&tag, 1))
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+}
+
+/// A NIP-84 highlight: an excerpt from a note, an article or the web
+fn highlight_card(
+    ui: &mut egui::Ui,
+    ndb: &Ndb,
+    img_cache: &mut ImageCache,
+    txn: &Transaction,
+    note: &Note,
+) -> NoteResponse {
+    // where the highlight is from. web highlights only have a url
+    let source = note.tags().iter().find_map(|tag| match tag_str(&tag, 0)? {
+        "e" => tag
+            .get(1)
+            .and_then(|id| id.variant().id())
+            .map(|id| NoteId::new(*id)),
+        "a" => NoteAddress::from_coordinate(tag_str(&tag, 1)?)
+            .ok()
+            .and_then(|address| note::find_addressable(ndb, txn, &address))
+            .map(|source| NoteId::new(*source.id())),
+        _ => None,
+    });
+    let url = tag_value(note, "r");
+
+    let resp = card_frame(ui, |ui| {
+        byline(ui, ndb, img_cache, txn, note.pubkey(), "highlighted");
+
+        ui.horizontal(|ui| {
+            ui.label(RichText::new("❝").color(colors::PURPLE).size(20.0));
+            ui.add(egui::Label::new(RichText::new(note.content().trim()).italics()).wrap());
+        });
+
+        if let Some(comment) = tag_value(note, "comment") {
+            ui.add(egui::Label::new(comment).wrap());
+        }
+
+        if let Some(url) = url {
+            ui.add(Hyperlink::from_label_and_url(
+                RichText::new(format!("from {url}"))
+                    .text_style(NotedeckTextStyle::Small.text_style())
+                    .color(colors::PURPLE),
+                url,
+            ));
+        }
+    })
+    .response;
+
+    let resp = if source.is_some() {
+        clickable(resp)
+    } else {
+        resp
+    };
+    let action = source
+        .filter(|_| resp.clicked())
+        .map(NoteAction::OpenThread);
+    NoteResponse::new(resp).with_action(action)
+}
+
+fn repost_card(
+    ui: &mut egui::Ui,
+    ndb: &Ndb,
+    note_cache: &mut NoteCache,
+    img_cache: &mut ImageCache,
+    nip05: &mut Nip05Verifier,
+    txn: &Transaction,
+    note: &Note,
+) -> NoteResponse {
+    let reposted = notifications::notification_target(note, NotificationKind::Repost)
+        .and_then(|id| ndb.get_note_by_id(txn, id.bytes()).ok())
+        // a repost of a repost would have us recurse
+        .filter(|reposted| reposted.kind() != 6);
+
+    let inner = card_frame(ui, |ui| {
+        byline(ui, ndb, img_cache, txn, note.pubkey(), "reposted");
+
+        if let Some(reposted) = &reposted {
+            kind_preview(ui, ndb, note_cache, img_cache, nip05, txn, reposted).action
+        } else {
+            ui.weak("a note we don't have yet");
+            None
+        }
+    });
+
+    NoteResponse::new(inner.response).with_action(inner.inner)
+}
+
+fn reaction_card(
+    ui: &mut egui::Ui,
+    ndb: &Ndb,
+    img_cache: &mut ImageCache,
+    txn: &Transaction,
+    note: &Note,
+) -> NoteResponse {
+    let target = notifications::notification_target(note, NotificationKind::Reaction);
+    let reaction = match note.content().trim() {
+        "" | "+" => "❤",
+        "-" => "👎",
+        reaction => reaction,
+    };
+
+    let resp = card_frame(ui, |ui| {
+        byline(
+            ui,
+            ndb,
+            img_cache,
+            txn,
+            note.pubkey(),
+            &format!("reacted {reaction} to"),
+        );
+
+        if let Some(target) = &target {
+            quoted_note_ui(ui, ndb, txn, target);
+        }
+    })
+    .response;
+
+    let resp = clickable(resp);
+    let action = target
+        .filter(|_| resp.clicked())
+        .map(NoteAction::OpenThread);
+    NoteResponse::new(resp).with_action(action)
+}
+
+fn zap_card(
+    ui: &mut egui::Ui,
+    ndb: &Ndb,
+    img_cache: &mut ImageCache,
+    txn: &Transaction,
+    note: &Note,
+) -> NoteResponse {
+    let target = notifications::notification_target(note, NotificationKind::Zap);
+    let sender = notifications::notification_author(note, NotificationKind::Zap);
+    let zapped = match notifications::zap_msats(note) {
+        Some(msats) => format!("⚡ zapped {} sats", msats / 1000),
+        None => "⚡ zapped".to_owned(),
+    };
+
+    let resp = card_frame(ui, |ui| {
+        byline(ui, ndb, img_cache, txn, sender.bytes(), &zapped);
+
+        if let Some(target) = &target {
+            quoted_note_ui(ui, ndb, txn, target);
+        }
+    })
+    .response;
+
+    let resp = clickable(resp);
+    let action = target
+        .filter(|_| resp.clicked())
+        .map(NoteAction::OpenThread);
+    NoteResponse::new(resp).with_action(action)
+}
+
+/// A NIP-53 live stream
+fn live_event_card(
+    ui: &mut egui::Ui,
+    ndb: &Ndb,
+    img_cache: &mut ImageCache,
+    txn: &Transaction,
+    note: &Note,
+) -> egui::Response {
+    let title = tag_value(note, "title").unwrap_or("Untitled stream");
+    let (status, status_color) = match tag_value(note, "status") {
+        Some("live") => ("🔴 LIVE", Color32::RED),
+        Some("ended") => ("Ended", colors::GRAY_SECONDARY),
+        _ => ("Planned", colors::GRAY_SECONDARY),
+    };
+
+    card_frame(ui, |ui| {
+        let blur_media = img_cache.media_blur.should_blur(note.pubkey());
+        if let Some(image) = tag_value(note, "image").filter(|_| !blur_media) {
+            let width = ui.available_width();
+            content_image(ui, img_cache, image, width, LIVE_IMAGE_HEIGHT);
+        }
+
+        byline(ui, ndb, img_cache, txn, note.pubkey(), "is streaming");
+
+        ui.horizontal_wrapped(|ui| {
+            ui.label(RichText::new(status).color(status_color).strong());
+            ui.label(
+                RichText::new(title)
+                    .text_style(NotedeckTextStyle::Heading3.text_style())
+                    .strong(),
+            );
+        });
+
+        if let Some(summary) = tag_value(note, "summary") {
+            ui.add(egui::Label::new(RichText::new(excerpt(summary)).weak()).wrap());
+        }
+
+        if let Some(url) = tag_value(note, "streaming") {
+            ui.add(Hyperlink::from_label_and_url(
+                RichText::new("Watch").color(colors::PURPLE),
+                url,
+            ));
+        }
+    })
+    .response
+}
+
+/// A kind we don't know how to show. NIP-31 asks notes like these to
+/// describe themselves in an alt tag.
+fn unsupported_card(ui: &mut egui::Ui, note: &Note, kind: u32) -> egui::Response {
+    let id = egui::Id::new(("raw_json", note.id()));
+    let open = ui.ctx().data(|d| d.get_temp::<bool>(id)).unwrap_or(false);
+
+    card_frame(ui, |ui| {
+        ui.label(RichText::new(format!("Unsupported kind {kind}")).strong());
+
+        if let Some(alt) = tag_value(note, "alt") {
+            ui.add(egui::Label::new(RichText::new(alt).weak()).wrap());
+        }
+
+        let button = if open {
+            "Hide raw JSON"
+        } else {
+            "Open raw JSON"
+        };
+        if ui.small_button(button).clicked() {
+            ui.ctx().data_mut(|d| d.insert_temp(id, !open));
+        }
+
+        if open {
+            let json = raw_json(note);
+            if ui.small_button("Copy").clicked() {
+                ui.ctx().copy_text(json.clone());
+            }
+            ui.add(
+                egui::Label::new(
+                    RichText::new(json).text_style(NotedeckTextStyle::Monospace.text_style()),
+                )
+                .wrap(),
+            );
+        }
+    })
+    .response
+}
+
+fn raw_json(note: &Note) -> String {
+    let json = match note.json() {
+        Ok(json) => json,
+        Err(err) => {
+            error!("error serializing note: {err}");
+            return String::new();
+        }
+    };
+
+    serde_json::from_str::<serde_json::Value>(&json)
+        .and_then(|value| serde_json::to_string_pretty(&value))
+        .unwrap_or(json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preview_kinds() {
+        assert_eq!(PreviewKind::from_note_kind(1), PreviewKind::Note);
+        assert_eq!(PreviewKind::from_note_kind(6), PreviewKind::Repost);
+        assert_eq!(PreviewKind::from_note_kind(7), PreviewKind::Reaction);
+        assert_eq!(PreviewKind::from_note_kind(9735), PreviewKind::Zap);
+        assert_eq!(PreviewKind::from_note_kind(9802), PreviewKind::Highlight);
+        assert_eq!(PreviewKind::from_note_kind(30023), PreviewKind::Article);
+        assert_eq!(
+            PreviewKind::from_note_kind(30024),
+            PreviewKind::Unsupported(30024)
+        );
+        assert_eq!(PreviewKind::from_note_kind(30311), PreviewKind::LiveEvent);
+        assert_eq!(
+            PreviewKind::from_note_kind(31337),
+            PreviewKind::Unsupported(31337)
+        );
+    }
+
+    #[test]
+    fn test_excerpt() {
+        assert_eq!(excerpt("  short  "), "short");
+        let long = "a".repeat(QUOTED_CHARS + 10);
+        assert_eq!(excerpt(&long).chars().count(), QUOTED_CHARS + 1);
+    }
+}
@@ -1,11 +1,15 @@
 use crate::actionbar::NoteAction;
+use crate::article;
 use crate::images::ImageType;
 use crate::imgcache::ImageCache;
+use crate::lists::tag_str;
+use crate::nip05::Nip05Verifier;
 use crate::notecache::NoteCache;
-use crate::ui::note::{NoteOptions, NoteResponse};
+use crate::ui::note::{cards, NoteOptions, NoteResponse};
 use crate::ui::ProfilePic;
 use crate::{colors, ui};
 use egui::{Color32, Hyperlink, Image, RichText};
+use enostr::{Nip19, NoteAddress, NoteId};
 use nostrdb::{BlockType, Mention, Ndb, Note, NoteKey, Transaction};
 use tracing::warn;
 
@@ -13,6 +17,7 @@ pub struct NoteContents<'a> {
     ndb: &'a Ndb,
     img_cache: &'a mut ImageCache,
     note_cache: &'a mut NoteCache,
+    nip05: &'a mut Nip05Verifier,
     txn: &'a Transaction,
     note: &'a Note<'a>,
     note_key: NoteKey,
@@ -21,10 +26,12 @@ pub struct NoteContents<'a> {
 }
 
 impl<'a> NoteContents<'a> {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         ndb: &'a Ndb,
         img_cache: &'a mut ImageCache,
         note_cache: &'a mut NoteCache,
+        nip05: &'a mut Nip05Verifier,
         txn: &'a Transaction,
         note: &'a Note,
         note_key: NoteKey,
@@ -34,6 +41,7 @@ impl<'a> NoteContents<'a> {
             ndb,
             img_cache,
             note_cache,
+            nip05,
             txn,
             note,
             note_key,
@@ -54,6 +62,7 @@ impl egui::Widget for &mut NoteContents<'_> {
             self.ndb,
             self.img_cache,
             self.note_cache,
+            self.nip05,
             self.txn,
             self.note,
             self.note_key,
@@ -66,11 +75,13 @@ impl egui::Widget for &mut NoteContents<'_> {
 
 /// Render an inline note preview with a border. These are used when
 /// notes are references within a note
+#[allow(clippy::too_many_arguments)]
 pub fn render_note_preview(
     ui: &mut egui::Ui,
     ndb: &Ndb,
     note_cache: &mut NoteCache,
     img_cache: &mut ImageCache,
+    nip05: &mut Nip05Verifier,
     txn: &Transaction,
     id: &[u8; 32],
     _id_str: &str,
@@ -79,15 +90,7 @@ pub fn render_note_preview(
     puffin::profile_function!();
 
     let note = if let Ok(note) = ndb.get_note_by_id(txn, id) {
-        // TODO: support other preview kinds
-        if note.kind() == 1 {
-            note
-        } else {
-            return NoteResponse::new(ui.colored_label(
-                Color32::RED,
-                format!("TODO: can't preview kind {}", note.kind()),
-            ));
-        }
+        note
     } else {
         return NoteResponse::new(ui.colored_label(Color32::RED, "TODO: COULD NOT LOAD"));
         /*
@@ -101,25 +104,7 @@ pub fn render_note_preview(
             */
     };
 
-    egui::Frame::none()
-        .fill(ui.visuals().noninteractive().weak_bg_fill)
-        .inner_margin(egui::Margin::same(8.0))
-        .outer_margin(egui::Margin::symmetric(0.0, 8.0))
-        .rounding(egui::Rounding::same(10.0))
-        .stroke(egui::Stroke::new(
-            1.0,
-            ui.visuals().noninteractive().bg_stroke.color,
-        ))
-        .show(ui, |ui| {
-            ui::NoteView::new(ndb, note_cache, img_cache, &note)
-                .actionbar(false)
-                .small_pfp(true)
-                .wide(true)
-                .note_previews(false)
-                .options_button(true)
-                .show(ui)
-        })
-        .inner
+    cards::kind_preview(ui, ndb, note_cache, img_cache, nip05, txn, &note)
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -128,6 +113,7 @@ fn render_note_contents(
     ndb: &Ndb,
     img_cache: &mut ImageCache,
     note_cache: &mut NoteCache,
+    nip05: &mut Nip05Verifier,
     txn: &Transaction,
     note: &Note,
     note_key: NoteKey,
@@ -136,9 +122,25 @@ fn render_note_contents(
     #[cfg(feature = "profiling")]
     puffin::profile_function!();
 
+    if let Some(reason) = content_warning(note) {
+        let id = egui::Id::new(("content_warning", note_key));
+        if !ui.ctx().data(|d| d.get_temp::<bool>(id)).unwrap_or(false) {
+            return NoteResponse::new(content_warning_ui(ui, id, reason));
+        }
+    }
+
+    if article::is_article(note) {
+        let resp = ui::article::article_card(ui, ndb, img_cache, txn, note, false);
+        let action = resp
+            .clicked()
+            .then(|| NoteAction::OpenThread(NoteId::new(*note.id())));
+        return NoteResponse::new(resp).with_action(action);
+    }
+
     let selectable = options.has_selectable_text();
     let mut images: Vec<String> = vec![];
     let mut inline_note: Option<(&[u8; 32], &str)> = None;
+    let mut inline_address: Option<NoteAddress> = None;
     let hide_media = options.has_hide_media();
 
     let response = ui.horizontal_wrapped(|ui| {
@@ -156,11 +158,17 @@ fn render_note_contents(
             match block.blocktype() {
                 BlockType::MentionBech32 => match block.as_mention().unwrap() {
                     Mention::Profile(profile) => {
-                        ui.add(ui::Mention::new(ndb, img_cache, txn, profile.pubkey()));
+                        ui.add(ui::Mention::new(
+                            ndb,
+                            img_cache,
+                            nip05,
+                            txn,
+                            profile.pubkey(),
+                        ));
                     }
 
                     Mention::Pubkey(npub) => {
-                        ui.add(ui::Mention::new(ndb, img_cache, txn, npub.pubkey()));
+                        ui.add(ui::Mention::new(ndb, img_cache, nip05, txn, npub.pubkey()));
                     }
 
                     Mention::Note(note) if options.has_note_previews() => {
@@ -171,9 +179,21 @@ fn render_note_contents(
                         inline_note = Some((note.id(), block.as_str()));
                     }
 
-                    _ => {
-                        ui.colored_label(colors::PURPLE, format!("@{}", &block.as_str()[4..16]));
-                    }
+                    _ => match Nip19::parse(block.as_str()) {
+                        Ok(Nip19::Address(address)) if options.has_note_previews() => {
+                            inline_address = Some(address);
+                        }
+                        Ok(Nip19::Relay(relay)) => {
+                            ui.colored_label(colors::PURPLE, relay)
+                                .on_hover_text("Relay");
+                        }
+                        _ => {
+                            ui.colored_label(
+                                colors::PURPLE,
+                                format!("@{}", &block.as_str()[4..16]),
+                            );
+                        }
+                    },
                 },
 
                 BlockType::Hashtag => {
@@ -210,7 +230,9 @@ fn render_note_contents(
     });
 
     let note_action = if let Some((id, block_str)) = inline_note {
-        render_note_preview(ui, ndb, note_cache, img_cache, txn, id, block_str).action
+        render_note_preview(ui, ndb, note_cache, img_cache, nip05, txn, id, block_str).action
+    } else if let Some(address) = inline_address {
+        cards::address_preview(ui, ndb, note_cache, img_cache, nip05, txn, &address).action
     } else {
         None
     };
@@ -218,13 +240,80 @@ fn render_note_contents(
     if !images.is_empty() && !options.has_textmode() {
         ui.add_space(2.0);
         let carousel_id = egui::Id::new(("carousel", note.key().expect("expected tx note")));
-        image_carousel(ui, img_cache, images, carousel_id);
+        let revealed_id = carousel_id.with("revealed");
+        let revealed = ui.ctx().data(|d| d.get_temp::<bool>(revealed_id));
+        if revealed.unwrap_or(false) || !options.has_blur_media() {
+            image_carousel(ui, img_cache, images, carousel_id);
+        } else {
+            blurred_media_ui(ui, revealed_id, images.len());
+        }
         ui.add_space(2.0);
     }
 
     NoteResponse::new(response.response).with_action(note_action)
 }
 
+/// The reason from a note's NIP-36 `content-warning` tag, which may be
+/// empty. None if it doesn't have one.
+fn content_warning<'a>(note: &Note<'a>) -> Option<&'a str> {
+    note.tags()
+        .iter()
+        .find(|tag| tag_str(tag, 0) == Some("content-warning"))
+        .map(|tag| tag_str(&tag, 1).unwrap_or("").trim())
+}
+
+/// Stands in for a note's contents until the user chooses to see them
+fn content_warning_ui(ui: &mut egui::Ui, id: egui::Id, reason: &str) -> egui::Response {
+    let text = if reason.is_empty() {
+        "⚠ Content warning".to_owned()
+    } else {
+        format!("⚠ Content warning: {reason}")
+    };
+
+    egui::Frame::none()
+        .fill(ui.visuals().faint_bg_color)
+        .inner_margin(egui::Margin::same(8.0))
+        .rounding(egui::Rounding::same(6.0))
+        .show(ui, |ui| {
+            ui.horizontal_wrapped(|ui| {
+                ui.label(RichText::new(text).color(ui.visuals().warn_fg_color));
+                if ui.button("Show").clicked() {
+                    ui.ctx().data_mut(|d| d.insert_temp(id, true));
+                }
+            });
+        })
+        .response
+}
+
+/// A placeholder for media we haven't fetched yet. Clicking it shows the
+/// media.
+fn blurred_media_ui(ui: &mut egui::Ui, id: egui::Id, count: usize) {
+    let text = if count == 1 {
+        "Image hidden, click to show".to_owned()
+    } else {
+        format!("{count} images hidden, click to show")
+    };
+
+    let size = egui::vec2(ui.available_width(), 120.0);
+    let (rect, resp) = ui.allocate_exact_size(size, egui::Sense::click());
+    let resp = resp.on_hover_cursor(egui::CursorIcon::PointingHand);
+
+    let visuals = ui.style().interact(&resp);
+    ui.painter()
+        .rect_filled(rect, 5.0, ui.visuals().extreme_bg_color);
+    ui.painter().text(
+        rect.center(),
+        egui::Align2::CENTER_CENTER,
+        text,
+        egui::FontId::proportional(14.0),
+        visuals.text_color(),
+    );
+
+    if resp.clicked() {
+        ui.ctx().data_mut(|d| d.insert_temp(id, true));
+    }
+}
+
 fn image_carousel(
     ui: &mut egui::Ui,
     img_cache: &mut ImageCache,
@@ -235,7 +324,6 @@ fn image_carousel(
 
     let height = 360.0;
     let width = ui.available_size().x;
-    let spinsz = if height > width { width } else { height };
 
     ui.add_sized([width, height], |ui: &mut egui::Ui| {
         egui::ScrollArea::horizontal()
@@ -243,55 +331,7 @@ fn image_carousel(
             .show(ui, |ui| {
                 ui.horizontal(|ui| {
                     for image in images {
-                        // If the cache is empty, initiate the fetch
-                        let m_cached_promise = img_cache.map().get(&image);
-                        if m_cached_promise.is_none() {
-                            let res = crate::images::fetch_img(
-                                img_cache,
-                                ui.ctx(),
-                                &image,
-                                ImageType::Content(width.round() as u32, height.round() as u32),
-                            );
-                            img_cache.map_mut().insert(image.to_owned(), res);
-                        }
-
-                        // What is the state of the fetch?
-                        match img_cache.map()[&image].ready() {
-                            // Still waiting
-                            None => {
-                                ui.allocate_space(egui::vec2(spinsz, spinsz));
-                                //ui.add(egui::Spinner::new().size(spinsz));
-                            }
-                            // Failed to fetch image!
-                            Some(Err(_err)) => {
-                                // FIXME - use content-specific error instead
-                                let no_pfp = crate::images::fetch_img(
-                                    img_cache,
-                                    ui.ctx(),
-                                    ProfilePic::no_pfp_url(),
-                                    ImageType::Profile(128),
-                                );
-                                img_cache.map_mut().insert(image.to_owned(), no_pfp);
-                                // spin until next pass
-                                ui.allocate_space(egui::vec2(spinsz, spinsz));
-                                //ui.add(egui::Spinner::new().size(spinsz));
-                            }
-                            // Use the previously resolved image
-                            Some(Ok(img)) => {
-                                let img_resp = ui.add(
-                                    Image::new(img)
-                                        .max_height(height)
-                                        .rounding(5.0)
-                                        .fit_to_original_size(1.0),
-                                );
-                                img_resp.context_menu(|ui| {
-                                    if ui.button("Copy Link").clicked() {
-                                        ui.ctx().copy_text(image);
-                                        ui.close_menu();
-                                    }
-                                });
-                            }
-                        }
+                        content_image(ui, img_cache, &image, width, height);
                     }
                 })
                 .response
@@ -299,3 +339,64 @@ fn image_carousel(
             .inner
     });
 }
+
+/// Fetch and show an image from a note's contents, at most `height` tall
+pub fn content_image(
+    ui: &mut egui::Ui,
+    img_cache: &mut ImageCache,
+    image: &str,
+    width: f32,
+    height: f32,
+) {
+    let spinsz = if height > width { width } else { height };
+
+    // If the cache is empty, initiate the fetch
+    let m_cached_promise = img_cache.map().get(image);
+    if m_cached_promise.is_none() {
+        let res = crate::images::fetch_img(
+            img_cache,
+            ui.ctx(),
+            image,
+            ImageType::Content(width.round() as u32, height.round() as u32),
+        );
+        img_cache.map_mut().insert(image.to_owned(), res);
+    }
+
+    // What is the state of the fetch?
+    match img_cache.map()[image].ready() {
+        // Still waiting
+        None => {
+            ui.allocate_space(egui::vec2(spinsz, spinsz));
+            //ui.add(egui::Spinner::new().size(spinsz));
+        }
+        // Failed to fetch image!
+        Some(Err(_err)) => {
+            // FIXME - use content-specific error instead
+            let no_pfp = crate::images::fetch_img(
+                img_cache,
+                ui.ctx(),
+                ProfilePic::no_pfp_url(),
+                ImageType::Profile(128),
+            );
+            img_cache.map_mut().insert(image.to_owned(), no_pfp);
+            // spin until next pass
+            ui.allocate_space(egui::vec2(spinsz, spinsz));
+            //ui.add(egui::Spinner::new().size(spinsz));
+        }
+        // Use the previously resolved image
+        Some(Ok(img)) => {
+            let img_resp = ui.add(
+                Image::new(img)
+                    .max_height(height)
+                    .rounding(5.0)
+                    .fit_to_original_size(1.0),
+            );
+            img_resp.context_menu(|ui| {
+                if ui.button("Copy Link").clicked() {
+                    ui.ctx().copy_text(image.to_owned());
+                    ui.close_menu();
+                }
+            });
+        }
+    }
+}
@@ -1,4 +1,8 @@
-use crate::colors;
+use crate::{
+    actionbar::NoteAction,
+    bookmarks::{self, BookmarkAction, Bookmarked},
+    colors,
+};
 use egui::{Rect, Vec2};
 use enostr::{NoteId, Pubkey};
 use nostrdb::{Note, NoteKey};
@@ -9,10 +13,17 @@ pub enum NoteContextSelection {
     CopyText,
     CopyPubkey,
     CopyNoteId,
+    Bookmark,
+    BookmarkPrivately,
+    RemoveBookmark,
 }
 
 impl NoteContextSelection {
-    pub fn process(&self, ui: &mut egui::Ui, note: &Note<'_>) {
+    /// Process the selection. Selections that change our account's state
+    /// are returned as a [`NoteAction`] for the nav to handle.
+    pub fn process(&self, ui: &mut egui::Ui, note: &Note<'_>) -> Option<NoteAction> {
+        let note_id = NoteId::new(*note.id());
+
         match self {
             NoteContextSelection::CopyText => {
                 ui.output_mut(|w| {
@@ -28,12 +39,29 @@ impl NoteContextSelection {
             }
             NoteContextSelection::CopyNoteId => {
                 ui.output_mut(|w| {
-                    if let Some(bech) = NoteId::new(*note.id()).to_bech() {
+                    if let Some(bech) = note_id.to_bech() {
                         w.copied_text = bech;
                     }
                 });
             }
+            NoteContextSelection::Bookmark => {
+                return Some(NoteAction::Bookmark(BookmarkAction::Add {
+                    note_id,
+                    private: false,
+                }));
+            }
+            NoteContextSelection::BookmarkPrivately => {
+                return Some(NoteAction::Bookmark(BookmarkAction::Add {
+                    note_id,
+                    private: true,
+                }));
+            }
+            NoteContextSelection::RemoveBookmark => {
+                return Some(NoteAction::Bookmark(BookmarkAction::Remove(note_id)));
+            }
         }
+
+        None
     }
 }
 
@@ -139,6 +167,7 @@ impl NoteContextButton {
     pub fn menu(
         ui: &mut egui::Ui,
         button_response: egui::Response,
+        note_id: &NoteId,
     ) -> Option<NoteContextSelection> {
         let mut context_selection: Option<NoteContextSelection> = None;
 
@@ -156,6 +185,28 @@ impl NoteContextButton {
                 context_selection = Some(NoteContextSelection::CopyNoteId);
                 ui.close_menu();
             }
+
+            // no bookmark options without an account we can bookmark with
+            let bookmarked = if let Some(bookmarked) = bookmarks::bookmarked(ui.ctx(), note_id) {
+                bookmarked
+            } else {
+                return;
+            };
+
+            ui.separator();
+            if bookmarked == Bookmarked::No {
+                if ui.button("Bookmark").clicked() {
+                    context_selection = Some(NoteContextSelection::Bookmark);
+                    ui.close_menu();
+                }
+                if ui.button("Bookmark privately").clicked() {
+                    context_selection = Some(NoteContextSelection::BookmarkPrivately);
+                    ui.close_menu();
+                }
+            } else if ui.button("Remove bookmark").clicked() {
+                context_selection = Some(NoteContextSelection::RemoveBookmark);
+                ui.close_menu();
+            }
         });
 
         context_selection
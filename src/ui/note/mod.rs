@@ -1,3 +1,4 @@
+pub mod cards;
 pub mod contents;
 pub mod context;
 pub mod options;
@@ -17,7 +18,9 @@ use crate::{
     app_style::NotedeckTextStyle,
     colors,
     imgcache::ImageCache,
+    nip05::Nip05Verifier,
     notecache::{CachedNote, NoteCache},
+    outbox,
     ui::{self, View},
 };
 use egui::emath::{pos2, Vec2};
@@ -25,12 +28,14 @@ use egui::{Id, Label, Pos2, Rect, Response, RichText, Sense};
 use enostr::{NoteId, Pubkey};
 use nostrdb::{Ndb, Note, NoteKey, NoteReply, Transaction};
 
+use super::nip05::profile_nip05;
 use super::profile::preview::{get_display_name, one_line_display_name_widget};
 
 pub struct NoteView<'a> {
     ndb: &'a Ndb,
     note_cache: &'a mut NoteCache,
     img_cache: &'a mut ImageCache,
+    nip05: &'a mut Nip05Verifier,
     note: &'a nostrdb::Note<'a>,
     flags: NoteOptions,
 }
@@ -73,6 +78,7 @@ fn reply_desc(
     note_reply: &NoteReply,
     ndb: &Ndb,
     img_cache: &mut ImageCache,
+    nip05: &mut Nip05Verifier,
 ) {
     #[cfg(feature = "profiling")]
     puffin::profile_function!();
@@ -112,7 +118,7 @@ fn reply_desc(
     if note_reply.is_reply_to_root() {
         // We're replying to the root, let's show this
         ui.add(
-            ui::Mention::new(ndb, img_cache, txn, reply_note.pubkey())
+            ui::Mention::new(ndb, img_cache, nip05, txn, reply_note.pubkey())
                 .size(size)
                 .selectable(selectable),
         );
@@ -131,7 +137,7 @@ fn reply_desc(
             if root_note.pubkey() == reply_note.pubkey() {
                 // simply "replying to bob's note" when replying to bob in his thread
                 ui.add(
-                    ui::Mention::new(ndb, img_cache, txn, reply_note.pubkey())
+                    ui::Mention::new(ndb, img_cache, nip05, txn, reply_note.pubkey())
                         .size(size)
                         .selectable(selectable),
                 );
@@ -147,7 +153,7 @@ fn reply_desc(
                 // replying to bob in alice's thread
 
                 ui.add(
-                    ui::Mention::new(ndb, img_cache, txn, reply_note.pubkey())
+                    ui::Mention::new(ndb, img_cache, nip05, txn, reply_note.pubkey())
                         .size(size)
                         .selectable(selectable),
                 );
@@ -156,7 +162,7 @@ fn reply_desc(
                         .selectable(selectable),
                 );
                 ui.add(
-                    ui::Mention::new(ndb, img_cache, txn, root_note.pubkey())
+                    ui::Mention::new(ndb, img_cache, nip05, txn, root_note.pubkey())
                         .size(size)
                         .selectable(selectable),
                 );
@@ -171,7 +177,7 @@ fn reply_desc(
             }
         } else {
             ui.add(
-                ui::Mention::new(ndb, img_cache, txn, reply_note.pubkey())
+                ui::Mention::new(ndb, img_cache, nip05, txn, reply_note.pubkey())
                     .size(size)
                     .selectable(selectable),
             );
@@ -192,6 +198,7 @@ impl<'a> NoteView<'a> {
         ndb: &'a Ndb,
         note_cache: &'a mut NoteCache,
         img_cache: &'a mut ImageCache,
+        nip05: &'a mut Nip05Verifier,
         note: &'a nostrdb::Note<'a>,
     ) -> Self {
         let flags = NoteOptions::actionbar | NoteOptions::note_previews;
@@ -199,6 +206,7 @@ impl<'a> NoteView<'a> {
             ndb,
             note_cache,
             img_cache,
+            nip05,
             note,
             flags,
         }
@@ -290,6 +298,7 @@ impl<'a> NoteView<'a> {
                 self.ndb,
                 self.img_cache,
                 self.note_cache,
+                self.nip05,
                 txn,
                 self.note,
                 note_key,
@@ -342,10 +351,10 @@ impl<'a> NoteView<'a> {
                 ui.put(rect, ui::ProfilePic::new(self.img_cache, pic).size(size))
                     .on_hover_ui_at_pointer(|ui| {
                         ui.set_max_width(300.0);
-                        ui.add(ui::ProfilePreview::new(
-                            profile.as_ref().unwrap(),
-                            self.img_cache,
-                        ));
+                        ui.add(
+                            ui::ProfilePreview::new(profile.as_ref().unwrap(), self.img_cache)
+                                .verify(self.nip05, self.note.pubkey()),
+                        );
                     });
                 resp
             }
@@ -359,6 +368,9 @@ impl<'a> NoteView<'a> {
     }
 
     pub fn show(&mut self, ui: &mut egui::Ui) -> NoteResponse {
+        let blur_media = self.img_cache.media_blur.should_blur(self.note.pubkey());
+        self.options_mut().set_blur_media(blur_media);
+
         if self.options().has_textmode() {
             NoteResponse::new(self.textmode_ui(ui))
         } else {
@@ -380,7 +392,10 @@ impl<'a> NoteView<'a> {
                     if let Ok(rec) = &profile {
                         resp.on_hover_ui_at_pointer(|ui| {
                             ui.set_max_width(300.0);
-                            ui.add(ui::ProfilePreview::new(rec, self.img_cache));
+                            ui.add(
+                                ui::ProfilePreview::new(rec, self.img_cache)
+                                    .verify(self.nip05, self.note.pubkey()),
+                            );
                         });
                     }
                     ui.add_space(4.0);
@@ -390,7 +405,14 @@ impl<'a> NoteView<'a> {
                             .text_style(style.text_style()),
                     );
                 });
-                NoteView::new(self.ndb, self.note_cache, self.img_cache, &note_to_repost).show(ui)
+                NoteView::new(
+                    self.ndb,
+                    self.note_cache,
+                    self.img_cache,
+                    self.nip05,
+                    &note_to_repost,
+                )
+                .show(ui)
             } else {
                 self.show_standard(ui)
             }
@@ -400,6 +422,7 @@ impl<'a> NoteView<'a> {
     fn note_header(
         ui: &mut egui::Ui,
         note_cache: &mut NoteCache,
+        nip05: &mut Nip05Verifier,
         note: &Note,
         profile: &Result<nostrdb::ProfileRecord<'_>, nostrdb::Error>,
         options: NoteOptions,
@@ -410,6 +433,9 @@ impl<'a> NoteView<'a> {
         let inner_response = ui.horizontal(|ui| {
             ui.spacing_mut().item_spacing.x = 2.0;
             ui.add(ui::Username::new(profile.as_ref().ok(), note.pubkey()).abbreviated(20));
+            if let Some(address) = profile_nip05(profile.as_ref().ok()) {
+                ui.add(ui::Nip05Badge::new(nip05, note.pubkey(), address));
+            }
 
             let cached_note = note_cache.cached_note_or_insert_mut(note_key, note);
             render_reltime(ui, cached_note, true);
@@ -422,7 +448,7 @@ impl<'a> NoteView<'a> {
                 };
 
                 let resp = ui.add(NoteContextButton::new(note_key).place_at(context_pos));
-                NoteContextButton::menu(ui, resp.clone())
+                NoteContextButton::menu(ui, resp.clone(), &NoteId::new(*note.id()))
             } else {
                 None
             }
@@ -463,6 +489,7 @@ impl<'a> NoteView<'a> {
                             selected_option = NoteView::note_header(
                                 ui,
                                 self.note_cache,
+                                self.nip05,
                                 self.note,
                                 &profile,
                                 self.options(),
@@ -481,7 +508,7 @@ impl<'a> NoteView<'a> {
 
                     if note_reply.reply().is_some() {
                         ui.horizontal(|ui| {
-                            reply_desc(ui, txn, &note_reply, self.ndb, self.img_cache);
+                            reply_desc(ui, txn, &note_reply, self.ndb, self.img_cache, self.nip05);
                         });
                     }
                 });
@@ -491,6 +518,7 @@ impl<'a> NoteView<'a> {
                 self.ndb,
                 self.img_cache,
                 self.note_cache,
+                self.nip05,
                 txn,
                 self.note,
                 note_key,
@@ -520,6 +548,7 @@ impl<'a> NoteView<'a> {
                     selected_option = NoteView::note_header(
                         ui,
                         self.note_cache,
+                        self.nip05,
                         self.note,
                         &profile,
                         self.options(),
@@ -536,7 +565,7 @@ impl<'a> NoteView<'a> {
                             .borrow(self.note.tags());
 
                         if note_reply.reply().is_some() {
-                            reply_desc(ui, txn, &note_reply, self.ndb, self.img_cache);
+                            reply_desc(ui, txn, &note_reply, self.ndb, self.img_cache, self.nip05);
                         }
                     });
 
@@ -544,6 +573,7 @@ impl<'a> NoteView<'a> {
                         self.ndb,
                         self.img_cache,
                         self.note_cache,
+                        self.nip05,
                         txn,
                         self.note,
                         note_key,
@@ -657,17 +687,44 @@ fn render_note_actionbar(
     ui.horizontal(|ui| {
         let reply_resp = reply_button(ui, note_key);
         let quote_resp = quote_repost_button(ui, note_key);
+        let republish = delivery_status_ui(ui, note_id);
 
         if reply_resp.clicked() {
             Some(NoteAction::Reply(NoteId::new(*note_id)))
         } else if quote_resp.clicked() {
             Some(NoteAction::Quote(NoteId::new(*note_id)))
+        } else if republish {
+            Some(NoteAction::Republish(NoteId::new(*note_id)))
         } else {
             None
         }
     })
 }
 
+/// How many relays have our note, if it's one we published. Returns true
+/// if we should try the relays that refused it again.
+fn delivery_status_ui(ui: &mut egui::Ui, note_id: &[u8; 32]) -> bool {
+    let status = if let Some(status) = outbox::delivery_status(ui.ctx(), note_id) {
+        status
+    } else {
+        return false;
+    };
+
+    let icon = if status.is_delivered() {
+        "✔"
+    } else if status.can_retry() {
+        "⚠"
+    } else {
+        "⏳"
+    };
+    secondary_label(
+        ui,
+        format!("{} {}/{} relays", icon, status.accepted, status.total),
+    );
+
+    status.can_retry() && ui.small_button("Retry").clicked()
+}
+
 fn secondary_label(ui: &mut egui::Ui, s: impl Into<String>) {
     ui.add(Label::new(
         RichText::new(s).size(10.0).color(colors::GRAY_SECONDARY),
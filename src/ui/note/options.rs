@@ -15,6 +15,7 @@ bitflags! {
         const textmode        = 0b0000000001000000;
         const options_button  = 0b0000000010000000;
         const hide_media      = 0b0000000100000000;
+        const blur_media      = 0b0000001000000000;
     }
 }
 
@@ -47,6 +48,7 @@ impl NoteOptions {
     create_setter!(set_wide, wide);
     create_setter!(set_options_button, options_button);
     create_setter!(set_hide_media, hide_media);
+    create_setter!(set_blur_media, blur_media);
 
     pub fn new(is_universe_timeline: bool) -> Self {
         let mut options = NoteOptions::default();
@@ -64,6 +66,12 @@ impl NoteOptions {
         (self & NoteOptions::hide_media) == NoteOptions::hide_media
     }
 
+    /// Media stays blurred until it's clicked
+    #[inline]
+    pub fn has_blur_media(self) -> bool {
+        (self & NoteOptions::blur_media) == NoteOptions::blur_media
+    }
+
     #[inline]
     pub fn has_selectable_text(self) -> bool {
         (self & NoteOptions::selectable_text) == NoteOptions::selectable_text
@@ -1,15 +1,18 @@
+use crate::accounts::Accounts;
 use crate::draft::{Draft, Drafts};
 use crate::imgcache::ImageCache;
+use crate::nip05::Nip05Verifier;
 use crate::notecache::NoteCache;
+use crate::outbox::Outbox;
 use crate::post::NewPost;
+use crate::signer::{RemoteSigners, Signer};
 use crate::ui;
 use crate::ui::{Preview, PreviewConfig, View};
-use crate::Result;
+use crate::{Error, Result};
 use egui::widgets::text_edit::TextEdit;
 use egui::{Frame, Layout};
-use enostr::{FilledKeypair, FullKeypair, NoteId, RelayPool};
+use enostr::{FullKeypair, NoteId, Pubkey, RelayPool};
 use nostrdb::{Config, Ndb, Transaction};
-use tracing::info;
 
 use super::contents::render_note_preview;
 
@@ -19,7 +22,8 @@ pub struct PostView<'a> {
     post_type: PostType,
     img_cache: &'a mut ImageCache,
     note_cache: &'a mut NoteCache,
-    poster: FilledKeypair<'a>,
+    nip05: &'a mut Nip05Verifier,
+    poster: &'a Pubkey,
     id_source: Option<egui::Id>,
 }
 
@@ -40,33 +44,44 @@ impl PostAction {
         PostAction { post_type, post }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn execute(
         &self,
         ndb: &Ndb,
         txn: &Transaction,
         pool: &mut RelayPool,
+        outbox: &mut Outbox,
         drafts: &mut Drafts,
+        accounts: &Accounts,
+        remote_signers: &mut RemoteSigners,
     ) -> Result<()> {
-        let seckey = self.post.account.secret_key.to_secret_bytes();
+        let signer = accounts
+            .find_account(self.post.account.bytes())
+            .and_then(Signer::new)
+            .ok_or_else(|| Error::Generic("can't sign notes for this account".to_owned()))?;
 
         let note = match self.post_type {
-            PostType::New => self.post.to_note(&seckey),
+            PostType::New => self.post.to_note(&signer)?,
 
             PostType::Reply(target) => {
                 let replying_to = ndb.get_note_by_id(txn, target.bytes())?;
-                self.post.to_reply(&seckey, &replying_to)
+                self.post.to_reply(&signer, &replying_to)?
             }
 
             PostType::Quote(target) => {
                 let quoting = ndb.get_note_by_id(txn, target.bytes())?;
-                self.post.to_quote(&seckey, &quoting)
+                self.post.to_quote(&signer, &quoting)?
             }
         };
 
-        let raw_msg = format!("[\"EVENT\",{}]", note.json().unwrap());
-        info!("sending {}", raw_msg);
-        pool.send(&enostr::ClientMessage::raw(raw_msg));
-        drafts.get_from_post_type(&self.post_type).clear();
+        let draft = drafts.get_from_post_type(&self.post_type);
+        match signer.publish(note, ndb, pool, outbox, remote_signers)? {
+            Some(request) => {
+                draft.signing = Some(request);
+                draft.sign_error = None;
+            }
+            None => draft.clear(),
+        }
 
         Ok(())
     }
@@ -84,7 +99,8 @@ impl<'a> PostView<'a> {
         post_type: PostType,
         img_cache: &'a mut ImageCache,
         note_cache: &'a mut NoteCache,
-        poster: FilledKeypair<'a>,
+        nip05: &'a mut Nip05Verifier,
+        poster: &'a Pubkey,
     ) -> Self {
         let id_source: Option<egui::Id> = None;
         PostView {
@@ -92,6 +108,7 @@ impl<'a> PostView<'a> {
             draft,
             img_cache,
             note_cache,
+            nip05,
             poster,
             id_source,
             post_type,
@@ -111,7 +128,7 @@ impl<'a> PostView<'a> {
         // TODO: refactor pfp control to do all of this for us
         let poster_pfp = self
             .ndb
-            .get_profile_by_pubkey(txn, self.poster.pubkey.bytes())
+            .get_profile_by_pubkey(txn, self.poster.bytes())
             .as_ref()
             .ok()
             .and_then(|p| Some(ui::ProfilePic::from_profile(self.img_cache, p)?.size(pfp_size)));
@@ -185,6 +202,18 @@ impl<'a> PostView<'a> {
                 ui.vertical(|ui| {
                     let edit_response = ui.horizontal(|ui| self.editbox(txn, ui)).inner;
 
+                    if let Some(err) = &self.draft.sign_error {
+                        ui.colored_label(ui.visuals().error_fg_color, err);
+                    }
+
+                    if let Some(reason) = &mut self.draft.content_warning {
+                        ui.add(
+                            TextEdit::singleline(reason)
+                                .hint_text("Content warning reason (optional)")
+                                .desired_width(f32::INFINITY),
+                        );
+                    }
+
                     let action = ui
                         .horizontal(|ui| {
                             if let PostType::Quote(id) = self.post_type {
@@ -198,6 +227,7 @@ impl<'a> PostView<'a> {
                                                 self.ndb,
                                                 self.note_cache,
                                                 self.img_cache,
+                                                self.nip05,
                                                 txn,
                                                 id.bytes(),
                                                 "",
@@ -208,19 +238,38 @@ impl<'a> PostView<'a> {
                             }
 
                             ui.with_layout(egui::Layout::right_to_left(egui::Align::BOTTOM), |ui| {
+                                if self.draft.signing.is_some() {
+                                    ui.spinner();
+                                    ui.weak("Waiting for your signer…");
+                                    return None;
+                                }
+
                                 if self.draft.buffer.is_empty() {
                                     // Don't render button if our buffer is empty
                                     return None;
                                 }
 
-                                if ui
+                                let post = ui
                                     .add_sized([91.0, 32.0], egui::Button::new("Post now"))
+                                    .clicked();
+
+                                let has_warning = self.draft.content_warning.is_some();
+                                if ui
+                                    .selectable_label(has_warning, "⚠ CW")
+                                    .on_hover_text("Add a content warning")
                                     .clicked()
                                 {
-                                    let new_post = NewPost::new(
-                                        self.draft.buffer.clone(),
-                                        self.poster.to_full(),
-                                    );
+                                    self.draft.content_warning = if has_warning {
+                                        None
+                                    } else {
+                                        Some(String::new())
+                                    };
+                                }
+
+                                if post {
+                                    let mut new_post =
+                                        NewPost::new(self.draft.buffer.clone(), *self.poster);
+                                    new_post.content_warning = self.draft.content_warning.clone();
                                     Some(PostAction::new(self.post_type.clone(), new_post))
                                 } else {
                                     None
@@ -248,6 +297,7 @@ mod preview {
         ndb: Ndb,
         img_cache: ImageCache,
         note_cache: NoteCache,
+        nip05: Nip05Verifier,
         draft: Draft,
         poster: FullKeypair,
     }
@@ -260,6 +310,7 @@ mod preview {
                 ndb,
                 img_cache: ImageCache::new(".".into()),
                 note_cache: NoteCache::default(),
+                nip05: Nip05Verifier::default(),
                 draft: Draft::new(),
                 poster: FullKeypair::generate(),
             }
@@ -275,7 +326,8 @@ mod preview {
                 PostType::New,
                 &mut self.img_cache,
                 &mut self.note_cache,
-                self.poster.to_filled(),
+                &mut self.nip05,
+                &self.poster.pubkey,
             )
             .ui(&txn, ui);
         }
@@ -1,15 +1,16 @@
-use enostr::{FilledKeypair, NoteId};
+use enostr::{NoteId, Pubkey};
 use nostrdb::Ndb;
 
-use crate::{draft::Draft, imgcache::ImageCache, notecache::NoteCache, ui};
+use crate::{draft::Draft, imgcache::ImageCache, nip05::Nip05Verifier, notecache::NoteCache, ui};
 
 use super::{PostResponse, PostType};
 
 pub struct QuoteRepostView<'a> {
     ndb: &'a Ndb,
-    poster: FilledKeypair<'a>,
+    poster: &'a Pubkey,
     note_cache: &'a mut NoteCache,
     img_cache: &'a mut ImageCache,
+    nip05: &'a mut Nip05Verifier,
     draft: &'a mut Draft,
     quoting_note: &'a nostrdb::Note<'a>,
     id_source: Option<egui::Id>,
@@ -18,9 +19,10 @@ pub struct QuoteRepostView<'a> {
 impl<'a> QuoteRepostView<'a> {
     pub fn new(
         ndb: &'a Ndb,
-        poster: FilledKeypair<'a>,
+        poster: &'a Pubkey,
         note_cache: &'a mut NoteCache,
         img_cache: &'a mut ImageCache,
+        nip05: &'a mut Nip05Verifier,
         draft: &'a mut Draft,
         quoting_note: &'a nostrdb::Note<'a>,
     ) -> Self {
@@ -30,6 +32,7 @@ impl<'a> QuoteRepostView<'a> {
             poster,
             note_cache,
             img_cache,
+            nip05,
             draft,
             quoting_note,
             id_source,
@@ -46,6 +49,7 @@ impl<'a> QuoteRepostView<'a> {
             PostType::Quote(NoteId::new(quoting_note_id.to_owned())),
             self.img_cache,
             self.note_cache,
+            self.nip05,
             self.poster,
         )
         .id_source(id)
@@ -1,16 +1,18 @@
 use crate::draft::Draft;
 use crate::imgcache::ImageCache;
+use crate::nip05::Nip05Verifier;
 use crate::notecache::NoteCache;
 use crate::ui;
 use crate::ui::note::{PostResponse, PostType};
-use enostr::{FilledKeypair, NoteId};
+use enostr::{NoteId, Pubkey};
 use nostrdb::Ndb;
 
 pub struct PostReplyView<'a> {
     ndb: &'a Ndb,
-    poster: FilledKeypair<'a>,
+    poster: &'a Pubkey,
     note_cache: &'a mut NoteCache,
     img_cache: &'a mut ImageCache,
+    nip05: &'a mut Nip05Verifier,
     draft: &'a mut Draft,
     note: &'a nostrdb::Note<'a>,
     id_source: Option<egui::Id>,
@@ -19,10 +21,11 @@ pub struct PostReplyView<'a> {
 impl<'a> PostReplyView<'a> {
     pub fn new(
         ndb: &'a Ndb,
-        poster: FilledKeypair<'a>,
+        poster: &'a Pubkey,
         draft: &'a mut Draft,
         note_cache: &'a mut NoteCache,
         img_cache: &'a mut ImageCache,
+        nip05: &'a mut Nip05Verifier,
         note: &'a nostrdb::Note<'a>,
     ) -> Self {
         let id_source: Option<egui::Id> = None;
@@ -33,6 +36,7 @@ impl<'a> PostReplyView<'a> {
             note,
             note_cache,
             img_cache,
+            nip05,
             id_source,
         }
     }
@@ -64,11 +68,17 @@ impl<'a> PostReplyView<'a> {
             egui::Frame::none()
                 .outer_margin(egui::Margin::same(note_offset))
                 .show(ui, |ui| {
-                    ui::NoteView::new(self.ndb, self.note_cache, self.img_cache, self.note)
-                        .actionbar(false)
-                        .medium_pfp(true)
-                        .options_button(true)
-                        .show(ui);
+                    ui::NoteView::new(
+                        self.ndb,
+                        self.note_cache,
+                        self.img_cache,
+                        self.nip05,
+                        self.note,
+                    )
+                    .actionbar(false)
+                    .medium_pfp(true)
+                    .options_button(true)
+                    .show(ui);
                 });
 
             let id = self.id();
@@ -82,6 +92,7 @@ impl<'a> PostReplyView<'a> {
                     PostType::Reply(NoteId::new(*replying_to)),
                     self.img_cache,
                     self.note_cache,
+                    self.nip05,
                     self.poster,
                 )
                 .id_source(id)
@@ -0,0 +1,124 @@
+use egui::{RichText, ScrollArea};
+use nostrdb::Ndb;
+
+use crate::{
+    app_style::NotedeckTextStyle,
+    column::Columns,
+    desktop_notifications::{DesktopNotificationKind, NotificationSettings, QuietHours},
+    ui,
+};
+
+/// Desktop notification settings: what we notify about, from which
+/// columns, and when not to
+pub struct NotificationSettingsView<'a> {
+    settings: &'a mut NotificationSettings,
+    columns: &'a Columns,
+    ndb: &'a Ndb,
+}
+
+impl<'a> NotificationSettingsView<'a> {
+    pub fn new(settings: &'a mut NotificationSettings, columns: &'a Columns, ndb: &'a Ndb) -> Self {
+        NotificationSettingsView {
+            settings,
+            columns,
+            ndb,
+        }
+    }
+
+    /// Returns true if the settings changed and should be saved
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = false;
+
+        ScrollArea::vertical()
+            .auto_shrink([false, false])
+            .show(ui, |ui| {
+                ui::padding(16.0, ui, |ui| {
+                    ui.spacing_mut().item_spacing.y = 8.0;
+
+                    changed |= ui
+                        .checkbox(
+                            &mut self.settings.enabled,
+                            "Show desktop notifications when notedeck is in the background",
+                        )
+                        .changed();
+
+                    ui.add_enabled_ui(self.settings.enabled, |ui| {
+                        changed |= self.kinds_ui(ui);
+                        changed |= self.columns_ui(ui);
+                        changed |= self.quiet_hours_ui(ui);
+                    });
+                });
+            });
+
+        changed
+    }
+
+    fn kinds_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = false;
+
+        heading(ui, "Notify me about");
+        for kind in DesktopNotificationKind::ALL {
+            changed |= ui
+                .checkbox(self.settings.kind_enabled_mut(kind), kind.name())
+                .changed();
+        }
+
+        changed
+    }
+
+    fn columns_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = false;
+
+        heading(ui, "Columns");
+
+        let notification_kinds: Vec<_> = self
+            .columns
+            .timelines()
+            .into_iter()
+            .map(|tl| &tl.kind)
+            .filter(|kind| kind.is_notifications())
+            .collect();
+
+        if notification_kinds.is_empty() {
+            ui.weak("Add a notifications column to get desktop notifications");
+        }
+
+        for kind in notification_kinds {
+            let mut enabled = !self.settings.is_column_muted(kind);
+            if ui.checkbox(&mut enabled, kind.to_title(self.ndb)).changed() {
+                self.settings.set_column_muted(kind, !enabled);
+                changed = true;
+            }
+        }
+
+        changed
+    }
+
+    fn quiet_hours_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = false;
+
+        heading(ui, "Do not disturb");
+
+        let mut quiet = self.settings.quiet_hours.is_some();
+        if ui.checkbox(&mut quiet, "Quiet hours").changed() {
+            self.settings.quiet_hours = quiet.then(QuietHours::default);
+            changed = true;
+        }
+
+        if let Some(quiet_hours) = &mut self.settings.quiet_hours {
+            changed |= ui
+                .add(egui::Slider::new(&mut quiet_hours.start, 0..=23).text("from"))
+                .changed();
+            changed |= ui
+                .add(egui::Slider::new(&mut quiet_hours.end, 0..=23).text("until"))
+                .changed();
+        }
+
+        changed
+    }
+}
+
+fn heading(ui: &mut egui::Ui, text: &str) {
+    ui.add_space(8.0);
+    ui.label(RichText::new(text).text_style(NotedeckTextStyle::Heading3.text_style()));
+}
@@ -0,0 +1,245 @@
+use crate::{
+    actionbar::NoteAction,
+    imgcache::ImageCache,
+    nip05::Nip05Verifier,
+    notecache::NoteCache,
+    notifications::{group_notifications, NotificationGroup, NotificationKind},
+    timeline::TimelineTab,
+    ui::{
+        self,
+        note::NoteOptions,
+        profile::preview::{get_display_name_as_string, get_profile_url},
+        ProfilePic,
+    },
+};
+use egui::{RichText, Sense};
+use enostr::Pubkey;
+use nostrdb::{Ndb, NoteKey, Transaction};
+use std::sync::Arc;
+
+/// How many profile pictures we show for a group
+const MAX_GROUP_PFPS: usize = 8;
+
+/// How much of the note a group is about we show
+const TARGET_PREVIEW_CHARS: usize = 140;
+
+/// Grouping is done at render time, so we cache the groups until the
+/// notes in the tab change
+#[derive(Clone)]
+struct CachedGroups {
+    len: usize,
+    first: Option<NoteKey>,
+    groups: Arc<Vec<NotificationGroup>>,
+}
+
+/// Renders a notifications tab with reactions, reposts, zaps and follows
+/// grouped by the note they are about
+pub struct NotificationsView<'a> {
+    tab: &'a TimelineTab,
+    view_id: egui::Id,
+    note_options: NoteOptions,
+    txn: &'a Transaction,
+    ndb: &'a Ndb,
+    note_cache: &'a mut NoteCache,
+    img_cache: &'a mut ImageCache,
+    nip05: &'a mut Nip05Verifier,
+}
+
+impl<'a> NotificationsView<'a> {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        tab: &'a TimelineTab,
+        view_id: egui::Id,
+        note_options: NoteOptions,
+        txn: &'a Transaction,
+        ndb: &'a Ndb,
+        note_cache: &'a mut NoteCache,
+        img_cache: &'a mut ImageCache,
+        nip05: &'a mut Nip05Verifier,
+    ) -> Self {
+        NotificationsView {
+            tab,
+            view_id,
+            note_options,
+            txn,
+            ndb,
+            note_cache,
+            img_cache,
+            nip05,
+        }
+    }
+
+    fn groups(&self, ui: &mut egui::Ui) -> Arc<Vec<NotificationGroup>> {
+        let id = self.view_id.with("notification_groups");
+        let len = self.tab.notes.len();
+        let first = self.tab.notes.first().map(|nr| nr.key);
+
+        if let Some(cached) = ui.data(|d| d.get_temp::<CachedGroups>(id)) {
+            if cached.len == len && cached.first == first {
+                return cached.groups;
+            }
+        }
+
+        let groups = Arc::new(group_notifications(self.ndb, self.txn, &self.tab.notes));
+        ui.data_mut(|d| {
+            d.insert_temp(
+                id,
+                CachedGroups {
+                    len,
+                    first,
+                    groups: groups.clone(),
+                },
+            )
+        });
+
+        // groups can change anywhere in the list
+        self.tab.list.borrow_mut().reset();
+
+        groups
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui) -> Option<NoteAction> {
+        let groups = self.groups(ui);
+        let mut action: Option<NoteAction> = None;
+
+        self.tab
+            .list
+            .clone()
+            .borrow_mut()
+            .ui_custom_layout(ui, groups.len(), |ui, index| {
+                ui.spacing_mut().item_spacing.y = 0.0;
+                ui.spacing_mut().item_spacing.x = 4.0;
+
+                let group = &groups[index];
+
+                ui::padding(8.0, ui, |ui| {
+                    let group_action = if group.kind == NotificationKind::Mention {
+                        self.mention_ui(ui, group)
+                    } else {
+                        self.group_ui(ui, group)
+                    };
+
+                    if group_action.is_some() {
+                        action = group_action;
+                    }
+                });
+
+                ui::hline(ui);
+
+                1
+            });
+
+        action
+    }
+
+    fn mention_ui(&mut self, ui: &mut egui::Ui, group: &NotificationGroup) -> Option<NoteAction> {
+        let note = self.ndb.get_note_by_key(self.txn, group.newest).ok()?;
+
+        let resp = ui::NoteView::new(self.ndb, self.note_cache, self.img_cache, self.nip05, &note)
+            .note_options(self.note_options)
+            .show(ui);
+
+        if let Some(context) = resp.context_selection {
+            if let Some(note_action) = context.process(ui, &note) {
+                return Some(note_action);
+            }
+        }
+
+        resp.action
+    }
+
+    fn group_ui(&mut self, ui: &mut egui::Ui, group: &NotificationGroup) -> Option<NoteAction> {
+        let mut action = None;
+
+        ui.horizontal(|ui| {
+            ui.label(RichText::new(group_icon(group.kind)).size(20.0));
+
+            ui.vertical(|ui| {
+                ui.horizontal(|ui| {
+                    for pk in group.authors.iter().take(MAX_GROUP_PFPS) {
+                        let profile = self.ndb.get_profile_by_pubkey(self.txn, pk.bytes()).ok();
+                        let resp = ui
+                            .add(
+                                ProfilePic::new(self.img_cache, get_profile_url(profile.as_ref()))
+                                    .size(ProfilePic::small_size()),
+                            )
+                            .interact(Sense::click())
+                            .on_hover_cursor(egui::CursorIcon::PointingHand);
+
+                        if resp.clicked() {
+                            action = Some(NoteAction::OpenProfile(*pk));
+                        }
+                    }
+                });
+
+                ui.label(format!(
+                    "{} {}",
+                    self.authors_summary(&group.authors),
+                    group_verb(group)
+                ));
+
+                let target = group.target.and_then(|id| {
+                    let note = self.ndb.get_note_by_id(self.txn, id.bytes()).ok()?;
+                    Some((id, note))
+                });
+
+                if let Some((target_id, note)) = target {
+                    let preview: String =
+                        note.content().chars().take(TARGET_PREVIEW_CHARS).collect();
+                    let resp = ui
+                        .add(egui::Label::new(RichText::new(preview).weak()).sense(Sense::click()))
+                        .on_hover_cursor(egui::CursorIcon::PointingHand);
+
+                    if resp.clicked() {
+                        action = Some(NoteAction::OpenThread(target_id));
+                    }
+                }
+            });
+        });
+
+        action
+    }
+
+    /// "Alice", "Alice and Bob" or "Alice and 12 others"
+    fn authors_summary(&self, authors: &[Pubkey]) -> String {
+        let name = |pk: &Pubkey| {
+            let profile = self.ndb.get_profile_by_pubkey(self.txn, pk.bytes()).ok();
+            get_display_name_as_string(profile.as_ref())
+        };
+
+        match authors {
+            [] => "Someone".to_owned(),
+            [one] => name(one),
+            [one, two] => format!("{} and {}", name(one), name(two)),
+            [one, rest @ ..] => format!("{} and {} others", name(one), rest.len()),
+        }
+    }
+}
+
+fn group_icon(kind: NotificationKind) -> &'static str {
+    match kind {
+        NotificationKind::Mention => "💬",
+        NotificationKind::Follow => "👤",
+        NotificationKind::Repost => "🔁",
+        NotificationKind::Reaction => "❤",
+        NotificationKind::Zap => "⚡",
+    }
+}
+
+fn group_verb(group: &NotificationGroup) -> String {
+    match group.kind {
+        NotificationKind::Mention => "mentioned you".to_owned(),
+        NotificationKind::Follow => "followed you".to_owned(),
+        NotificationKind::Repost => "reposted your note".to_owned(),
+        NotificationKind::Reaction if group.all_likes => "liked your note".to_owned(),
+        NotificationKind::Reaction => "reacted to your note".to_owned(),
+        NotificationKind::Zap => {
+            let sats = group.zap_msats / 1000;
+            if group.target.is_some() {
+                format!("zapped your note {} sats", sats)
+            } else {
+                format!("zapped you {} sats", sats)
+            }
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use crate::{
+    actionbar::NoteAction,
+    imgcache::ImageCache,
+    nip05::Nip05Verifier,
+    notecache::NoteCache,
+    notifications::{self, NotificationKind},
+    timeline::TimelineTab,
+    ui::{self, note::NoteOptions, profile::preview::get_display_name_as_string},
+};
+use egui::RichText;
+use nostrdb::{Ndb, NoteKey, Transaction};
+
+/// The likes and zaps tabs of a profile. Reactions and zap receipts don't
+/// say much on their own, so we show the notes they are about.
+pub struct ProfileActivityView<'a> {
+    tab: &'a TimelineTab,
+    note_options: NoteOptions,
+    txn: &'a Transaction,
+    ndb: &'a Ndb,
+    note_cache: &'a mut NoteCache,
+    img_cache: &'a mut ImageCache,
+    nip05: &'a mut Nip05Verifier,
+}
+
+impl<'a> ProfileActivityView<'a> {
+    pub fn new(
+        tab: &'a TimelineTab,
+        note_options: NoteOptions,
+        txn: &'a Transaction,
+        ndb: &'a Ndb,
+        note_cache: &'a mut NoteCache,
+        img_cache: &'a mut ImageCache,
+        nip05: &'a mut Nip05Verifier,
+    ) -> Self {
+        ProfileActivityView {
+            tab,
+            note_options,
+            txn,
+            ndb,
+            note_cache,
+            img_cache,
+            nip05,
+        }
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui) -> Option<NoteAction> {
+        let mut action: Option<NoteAction> = None;
+
+        self.tab.list.clone().borrow_mut().ui_custom_layout(
+            ui,
+            self.tab.notes.len(),
+            |ui, index| {
+                ui.spacing_mut().item_spacing.y = 0.0;
+                ui.spacing_mut().item_spacing.x = 4.0;
+
+                let note_key = self.tab.notes[index].key;
+                ui::padding(8.0, ui, |ui| {
+                    if let Some(row_action) = self.row_ui(ui, note_key) {
+                        action = Some(row_action);
+                    }
+                });
+
+                ui::hline(ui);
+
+                1
+            },
+        );
+
+        action
+    }
+
+    fn row_ui(&mut self, ui: &mut egui::Ui, note_key: NoteKey) -> Option<NoteAction> {
+        let note = self.ndb.get_note_by_key(self.txn, note_key).ok()?;
+        let kind = NotificationKind::from_note_kind(note.kind())?;
+
+        let header = if kind == NotificationKind::Zap {
+            let sender = notifications::notification_author(&note, kind);
+            let profile = self
+                .ndb
+                .get_profile_by_pubkey(self.txn, sender.bytes())
+                .ok();
+            let sats = notifications::zap_msats(&note).unwrap_or(0) / 1000;
+            format!(
+                "⚡ {} zapped {} sats",
+                get_display_name_as_string(profile.as_ref()),
+                sats
+            )
+        } else {
+            reaction_label(note.content())
+        };
+        ui.label(RichText::new(header).weak());
+
+        // zaps to the profile itself aren't about a note
+        let target = notifications::notification_target(&note, kind)?;
+        let target_note = if let Ok(target_note) = self.ndb.get_note_by_id(self.txn, target.bytes())
+        {
+            target_note
+        } else {
+            ui.label(RichText::new("A note we haven't seen yet").weak().italics());
+            return None;
+        };
+
+        let resp = ui::NoteView::new(
+            self.ndb,
+            self.note_cache,
+            self.img_cache,
+            self.nip05,
+            &target_note,
+        )
+        .note_options(self.note_options)
+        .show(ui);
+
+        if let Some(context) = resp.context_selection {
+            if let Some(note_action) = context.process(ui, &target_note) {
+                return Some(note_action);
+            }
+        }
+
+        resp.action
+    }
+}
+
+/// NIP-25: "+" or an empty reaction is a like, "-" a dislike, and anything
+/// else is an emoji reaction
+fn reaction_label(content: &str) -> String {
+    match content.trim() {
+        "" | "+" => "❤ Liked".to_owned(),
+        "-" => "👎 Disliked".to_owned(),
+        emoji => format!("{emoji} Reacted"),
+    }
+}
@@ -0,0 +1,399 @@
+use egui::{Align, Margin, RichText, Vec2, Widget};
+use enostr::{Keypair, Pubkey};
+use nostrdb::{Ndb, Transaction};
+use poll_promise::Promise;
+use serde_json::Value;
+
+use crate::{
+    app_style::NotedeckTextStyle,
+    imgcache::ImageCache,
+    key_parsing::{parse_nip05, perform_nip05_lookup},
+    metadata::{latest_metadata, parse_metadata},
+    proxy::Http,
+    replaceable::{FetchError, NewestFetch},
+    subscriptions::{SubKind, Subscriptions},
+    ui::{self, ProfilePic},
+    upload, DisplayName, Result,
+};
+use std::time::Instant;
+
+use super::preview::{ProfileDisplay, ProfilePreview};
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum UploadTarget {
+    Picture,
+    Banner,
+}
+
+/// Checking that the nip05 address points back at us
+struct Nip05Check {
+    address: String,
+    result: Promise<Result<Option<Pubkey>>>,
+}
+
+/// Our profile as it's being edited, before it's published
+pub struct EditProfileDraft {
+    pub pubkey: Pubkey,
+    pub name: String,
+    pub display_name: String,
+    pub about: String,
+    pub picture: String,
+    pub banner: String,
+    pub website: String,
+    pub nip05: String,
+    pub lud16: String,
+
+    /// Set while we wait for the newest copy of the profile from relays
+    fetch: Option<NewestFetch>,
+    fetch_error: Option<FetchError>,
+
+    nip05_check: Option<Nip05Check>,
+    upload: Option<Promise<Result<String>>>,
+    upload_target: UploadTarget,
+    upload_error: Option<String>,
+}
+
+impl EditProfileDraft {
+    /// Ask our relays for the newest copy of the profile. The fields are
+    /// filled in once it's arrived, see [`EditProfileDraft::poll`].
+    pub fn new(subs: &mut Subscriptions, pubkey: Pubkey) -> Self {
+        EditProfileDraft {
+            pubkey,
+            name: String::new(),
+            display_name: String::new(),
+            about: String::new(),
+            picture: String::new(),
+            banner: String::new(),
+            website: String::new(),
+            nip05: String::new(),
+            lud16: String::new(),
+            fetch: Some(NewestFetch::request(subs, SubKind::Metadata, pubkey, 0)),
+            fetch_error: None,
+            nip05_check: None,
+            upload: None,
+            upload_target: UploadTarget::Picture,
+            upload_error: None,
+        }
+    }
+
+    pub fn handle_event(&mut self, subid: &str, msg: &str) {
+        if let Some(fetch) = &mut self.fetch {
+            fetch.handle_event(subid, msg);
+        }
+    }
+
+    pub fn fetched(&mut self, subid: &str) {
+        if let Some(fetch) = &mut self.fetch {
+            fetch.fetched(subid);
+        }
+    }
+
+    /// Start editing once nostrdb has the newest copy of the profile. If we
+    /// couldn't get it we edit what we have, and warn about it.
+    pub fn poll(&mut self, ndb: &Ndb) {
+        let result = if let Some(fetch) = &self.fetch {
+            fetch.poll(ndb, Instant::now())
+        } else {
+            return;
+        };
+
+        if let Some(result) = result {
+            self.fetch = None;
+            self.fetch_error = result.err();
+            self.load(ndb);
+        }
+    }
+
+    fn load(&mut self, ndb: &Ndb) {
+        let txn = Transaction::new(ndb).expect("txn");
+        let fields = latest_metadata(ndb, &txn, &self.pubkey)
+            .map(|note| parse_metadata(note.content()))
+            .unwrap_or_default();
+        let get = |key: &str| {
+            fields
+                .get(key)
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_owned()
+        };
+
+        self.name = get("name");
+        self.display_name = get("display_name");
+        self.about = get("about");
+        self.picture = get("picture");
+        self.banner = get("banner");
+        self.website = get("website");
+        self.nip05 = get("nip05");
+        self.lud16 = get("lud16");
+    }
+
+    /// The fields we edit, as (metadata key, value)
+    pub fn edits(&self) -> [(&'static str, &str); 8] {
+        [
+            ("name", &self.name),
+            ("display_name", &self.display_name),
+            ("about", &self.about),
+            ("picture", &self.picture),
+            ("banner", &self.banner),
+            ("website", &self.website),
+            ("nip05", &self.nip05),
+            ("lud16", &self.lud16),
+        ]
+    }
+
+    fn display(&self) -> ProfileDisplay<'_> {
+        let non_empty = |s: &str| Some(s.trim()).filter(|s| !s.is_empty());
+
+        let display_name = match (non_empty(&self.display_name), non_empty(&self.name)) {
+            (Some(display_name), Some(username)) => DisplayName::Both {
+                display_name,
+                username,
+            },
+            (Some(name), None) | (None, Some(name)) => DisplayName::One(name),
+            (None, None) => DisplayName::One("??"),
+        };
+
+        ProfileDisplay {
+            display_name,
+            picture: non_empty(&self.picture).unwrap_or(ProfilePic::no_pfp_url()),
+            banner: non_empty(&self.banner),
+            about: non_empty(&self.about),
+            nip05: non_empty(&self.nip05),
+        }
+    }
+}
+
+/// Edit the selected account's profile
+pub struct EditProfileView<'a> {
+    draft: &'a mut EditProfileDraft,
+    account: &'a Keypair,
+    img_cache: &'a mut ImageCache,
+    http: &'a Http,
+}
+
+impl<'a> EditProfileView<'a> {
+    pub fn new(
+        draft: &'a mut EditProfileDraft,
+        account: &'a Keypair,
+        img_cache: &'a mut ImageCache,
+        http: &'a Http,
+    ) -> Self {
+        EditProfileView {
+            draft,
+            account,
+            img_cache,
+            http,
+        }
+    }
+
+    /// Returns true when the user saves the profile
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> bool {
+        if self.draft.fetch.is_some() {
+            ui::padding(16.0, ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label("Fetching your profile…");
+                });
+            });
+            return false;
+        }
+
+        egui::ScrollArea::vertical()
+            .show(ui, |ui| {
+                ProfilePreview::from_display(self.draft.display(), self.img_cache).ui(ui);
+
+                ui::padding(16.0, ui, |ui| {
+                    ui.spacing_mut().item_spacing.y = 8.0;
+
+                    match self.draft.fetch_error {
+                        Some(FetchError::Unreachable) => {
+                            ui.colored_label(
+                                ui.visuals().warn_fg_color,
+                                "Couldn't reach your relays, this may be an older copy of your profile",
+                            );
+                        }
+                        Some(FetchError::Stale) => {
+                            ui.colored_label(
+                                ui.visuals().warn_fg_color,
+                                "Your relays have a newer copy of your profile than this one",
+                            );
+                        }
+                        None => {}
+                    }
+
+                    field(ui, "Username", &mut self.draft.name);
+                    field(ui, "Display name", &mut self.draft.display_name);
+                    ui.label("About");
+                    ui.add(
+                        egui::TextEdit::multiline(&mut self.draft.about)
+                            .desired_width(f32::INFINITY)
+                            .margin(Margin::same(12.0)),
+                    );
+                    field(ui, "Picture url", &mut self.draft.picture);
+                    field(ui, "Banner url", &mut self.draft.banner);
+                    self.upload_ui(ui);
+                    field(ui, "Website", &mut self.draft.website);
+                    self.nip05_ui(ui);
+                    field(ui, "Lightning address", &mut self.draft.lud16);
+
+                    ui.add_space(8.0);
+                    ui.button(
+                        RichText::new("Save").text_style(NotedeckTextStyle::Body.text_style()),
+                    )
+                    .clicked()
+                })
+                .inner
+            })
+            .inner
+    }
+
+    /// The nip05 field, checked against its server once the user is done
+    /// typing it
+    fn nip05_ui(&mut self, ui: &mut egui::Ui) {
+        let resp = field(ui, "Nostr address (NIP-05)", &mut self.draft.nip05);
+
+        let address = self.draft.nip05.trim();
+        let checked = self
+            .draft
+            .nip05_check
+            .as_ref()
+            .map_or(false, |check| check.address == address);
+
+        if address.is_empty() {
+            self.draft.nip05_check = None;
+        } else if !checked && !resp.has_focus() {
+            let result = match parse_nip05(address) {
+                Ok(_) => perform_nip05_lookup(self.http, address),
+                Err(err) => Promise::from_ready(Err(err)),
+            };
+            self.draft.nip05_check = Some(Nip05Check {
+                address: address.to_owned(),
+                result,
+            });
+        }
+
+        let check = if let Some(check) = &self.draft.nip05_check {
+            check
+        } else {
+            return;
+        };
+
+        match check.result.ready() {
+            None => {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label("Checking address…");
+                });
+            }
+            Some(Ok(Some(pk))) if *pk == self.account.pubkey => {
+                ui.colored_label(ui.visuals().hyperlink_color, "✔ This address points to you");
+            }
+            Some(Ok(Some(_))) => {
+                ui.colored_label(
+                    ui.visuals().error_fg_color,
+                    "This address points to a different key",
+                );
+            }
+            Some(Ok(None)) => {
+                ui.colored_label(
+                    ui.visuals().error_fg_color,
+                    "This name isn't listed on the address's server",
+                );
+            }
+            Some(Err(err)) => {
+                ui.colored_label(ui.visuals().error_fg_color, err.to_string());
+            }
+        }
+    }
+
+    /// Images dropped onto the window get uploaded and used as the
+    /// picture or banner
+    fn upload_ui(&mut self, ui: &mut egui::Ui) {
+        let kp = if let Some(kp) = self.account.to_full() {
+            kp
+        } else {
+            ui.label(
+                RichText::new("Uploading images needs this account's key on this device")
+                    .small()
+                    .weak(),
+            );
+            return;
+        };
+
+        ui.horizontal(|ui| {
+            ui.label("Drop an image here to upload it as your");
+            ui.radio_value(
+                &mut self.draft.upload_target,
+                UploadTarget::Picture,
+                "picture",
+            );
+            ui.radio_value(
+                &mut self.draft.upload_target,
+                UploadTarget::Banner,
+                "banner",
+            );
+        });
+
+        if self.draft.upload.is_none() {
+            let dropped = ui.ctx().input(|i| {
+                i.raw
+                    .dropped_files
+                    .iter()
+                    .find_map(|file| file.path.clone())
+            });
+
+            if let Some(path) = dropped {
+                self.draft.upload_error = None;
+                self.draft.upload = Some(upload::upload_file(self.http, &path, kp));
+            }
+        }
+
+        let done = self
+            .draft
+            .upload
+            .as_ref()
+            .and_then(|upload| upload.ready())
+            .map(|result| {
+                result
+                    .as_ref()
+                    .map(String::clone)
+                    .map_err(|e| e.to_string())
+            });
+
+        match done {
+            Some(Ok(url)) => {
+                match self.draft.upload_target {
+                    UploadTarget::Picture => self.draft.picture = url,
+                    UploadTarget::Banner => self.draft.banner = url,
+                }
+                self.draft.upload = None;
+            }
+            Some(Err(err)) => {
+                self.draft.upload_error = Some(err);
+                self.draft.upload = None;
+            }
+            None if self.draft.upload.is_some() => {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label("Uploading…");
+                });
+            }
+            None => {}
+        }
+
+        if let Some(err) = &self.draft.upload_error {
+            ui.colored_label(ui.visuals().error_fg_color, err);
+        }
+    }
+}
+
+fn field(ui: &mut egui::Ui, label: &str, text: &mut String) -> egui::Response {
+    ui.label(label);
+    ui.add(
+        egui::TextEdit::singleline(text)
+            .vertical_align(Align::Center)
+            .desired_width(f32::INFINITY)
+            .min_size(Vec2::new(0.0, 40.0))
+            .margin(Margin::same(12.0)),
+    )
+}
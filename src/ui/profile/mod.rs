@@ -1,52 +1,105 @@
+pub mod activity;
+pub mod edit;
 pub mod picture;
 pub mod preview;
 
-use crate::ui::note::NoteOptions;
-use egui::{ScrollArea, Widget};
+use crate::ui::{self, note::NoteOptions};
+use egui::{RichText, ScrollArea, Widget};
 use enostr::Pubkey;
 use nostrdb::{Ndb, Transaction};
 pub use picture::ProfilePic;
 pub use preview::ProfilePreview;
+use tracing::error;
 
 use crate::{
-    actionbar::NoteAction, imgcache::ImageCache, notecache::NoteCache,
-    notes_holder::NotesHolderStorage, profile::Profile,
+    actionbar::NoteAction,
+    contacts::{self, ContactAction, ContactEdits, EditState},
+    imgcache::ImageCache,
+    lists::{self, ListAction},
+    nav::RenderNavAction,
+    nip05::Nip05Verifier,
+    notecache::NoteCache,
+    notes_holder::NotesHolderStorage,
+    profile::Profile,
+    profile_stats::{self, ProfileCounts, ProfileStats},
+    replaceable::FetchError,
+    timeline::{ListKind, ViewFilter},
+    unknowns::UnknownIds,
 };
 
+use self::activity::ProfileActivityView;
+use self::preview::get_display_name_as_string;
 use super::timeline::{tabs_ui, TimelineTabView};
 
 pub struct ProfileView<'a> {
     pubkey: &'a Pubkey,
     col_id: usize,
     profiles: &'a mut NotesHolderStorage<Profile>,
+    profile_stats: &'a mut ProfileStats,
+    list_owner: Option<&'a Pubkey>,
+    account: Option<&'a Pubkey>,
+    contact_edits: Option<&'a ContactEdits>,
     note_options: NoteOptions,
     ndb: &'a Ndb,
     note_cache: &'a mut NoteCache,
     img_cache: &'a mut ImageCache,
+    nip05: &'a mut Nip05Verifier,
+    unknown_ids: &'a mut UnknownIds,
 }
 
 impl<'a> ProfileView<'a> {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         pubkey: &'a Pubkey,
         col_id: usize,
         profiles: &'a mut NotesHolderStorage<Profile>,
+        profile_stats: &'a mut ProfileStats,
         ndb: &'a Ndb,
         note_cache: &'a mut NoteCache,
         img_cache: &'a mut ImageCache,
+        nip05: &'a mut Nip05Verifier,
+        unknown_ids: &'a mut UnknownIds,
         note_options: NoteOptions,
     ) -> Self {
         ProfileView {
             pubkey,
             col_id,
             profiles,
+            profile_stats,
+            list_owner: None,
+            account: None,
+            contact_edits: None,
             ndb,
             note_cache,
             img_cache,
+            nip05,
+            unknown_ids,
             note_options,
         }
     }
 
-    pub fn ui(&mut self, ui: &mut egui::Ui) -> Option<NoteAction> {
+    /// The selected account, for showing who we follow that follows this
+    /// profile
+    pub fn account(mut self, account: Option<&'a Pubkey>) -> Self {
+        self.account = account;
+        self
+    }
+
+    /// The account that can follow this profile and add it to its lists.
+    /// This account must be able to sign.
+    pub fn list_owner(mut self, list_owner: Option<&'a Pubkey>) -> Self {
+        self.list_owner = list_owner;
+        self
+    }
+
+    /// Follows and unfollows that haven't been applied yet, so we can show
+    /// how they're going
+    pub fn contact_edits(mut self, contact_edits: &'a ContactEdits) -> Self {
+        self.contact_edits = Some(contact_edits);
+        self
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> Option<RenderNavAction> {
         let scroll_id = egui::Id::new(("profile_scroll", self.col_id, self.pubkey));
 
         ScrollArea::vertical()
@@ -54,28 +107,278 @@ impl<'a> ProfileView<'a> {
             .show(ui, |ui| {
                 let txn = Transaction::new(self.ndb).expect("txn");
                 if let Ok(profile) = self.ndb.get_profile_by_pubkey(&txn, self.pubkey.bytes()) {
-                    ProfilePreview::new(&profile, self.img_cache).ui(ui);
+                    ProfilePreview::new(&profile, self.img_cache)
+                        .verify(self.nip05, self.pubkey.bytes())
+                        .ui(ui);
                 }
+
+                let mut list_action = None;
+                let mut contact_action = None;
+                let mut edit_profile = false;
+                if let Some(owner) = self.list_owner {
+                    ui.horizontal(|ui| {
+                        ui.add_space(16.0);
+                        if owner == self.pubkey {
+                            edit_profile = ui.button("Edit profile").clicked();
+                        } else {
+                            contact_action = self.follow_button(ui, &txn, owner);
+                        }
+                        list_action = self.lists_menu(ui, &txn, owner);
+                    });
+                }
+
+                let counts = self
+                    .profile_stats
+                    .counts(self.ndb, &txn, self.pubkey, self.account);
+                self.counts_ui(ui, &txn, &counts);
+
+                let pinned_action = self.pinned_ui(ui, &txn);
+
                 let profile = self
                     .profiles
                     .notes_holder_mutated(self.ndb, self.note_cache, &txn, self.pubkey.bytes())
                     .get_ptr();
 
-                profile.timeline.selected_view = tabs_ui(ui);
+                // reactions and zaps point at notes we may not have
+                if !profile.checked_unknowns {
+                    for view in &profile.timeline.views {
+                        UnknownIds::update_from_note_refs(
+                            &txn,
+                            self.ndb,
+                            self.unknown_ids,
+                            self.note_cache,
+                            &view.notes,
+                        );
+                    }
+                    profile.checked_unknowns = true;
+                }
 
-                let reversed = false;
+                if profile.multi_subscriber.is_some() {
+                    match profile.poll_notes_into_views(&txn, self.ndb, self.note_cache) {
+                        Ok(action) => {
+                            action.process_action(&txn, self.ndb, self.unknown_ids, self.note_cache)
+                        }
+                        Err(err) => error!("{err}"),
+                    }
+                }
 
-                TimelineTabView::new(
-                    profile.timeline.current_view(),
-                    reversed,
-                    self.note_options,
-                    &txn,
-                    self.ndb,
-                    self.note_cache,
-                    self.img_cache,
-                )
-                .show(ui)
+                let view_filters = profile.timeline.view_filters();
+                profile.timeline.selected_view = tabs_ui(ui, &view_filters);
+
+                let note_action = match profile.timeline.current_view().filter {
+                    ViewFilter::Likes | ViewFilter::Zaps => ProfileActivityView::new(
+                        profile.timeline.current_view(),
+                        self.note_options,
+                        &txn,
+                        self.ndb,
+                        self.note_cache,
+                        self.img_cache,
+                        self.nip05,
+                    )
+                    .show(ui),
+
+                    _ => {
+                        let reversed = false;
+                        TimelineTabView::new(
+                            profile.timeline.current_view(),
+                            reversed,
+                            self.note_options,
+                            &txn,
+                            self.ndb,
+                            self.note_cache,
+                            self.img_cache,
+                            self.nip05,
+                        )
+                        .show(ui)
+                    }
+                };
+
+                note_action
+                    .or(pinned_action)
+                    .map(RenderNavAction::NoteAction)
+                    .or(list_action.map(RenderNavAction::ListAction))
+                    .or(contact_action.map(RenderNavAction::ContactAction))
+                    .or(edit_profile.then_some(RenderNavAction::EditProfile))
             })
             .inner
     }
+
+    /// "12 Following  340 Followers", and who we follow that follows them
+    fn counts_ui(&self, ui: &mut egui::Ui, txn: &Transaction, counts: &ProfileCounts) {
+        ui.horizontal(|ui| {
+            ui.add_space(16.0);
+            if let Some(following) = counts.following {
+                ui.label(RichText::new(following.to_string()).strong());
+                ui.label(RichText::new("Following").weak());
+                ui.add_space(8.0);
+            }
+            ui.label(RichText::new(counts.followers.to_string()).strong());
+            ui.label(RichText::new("Followers").weak());
+        });
+
+        let names: Vec<String> = profile_stats::mutuals_shown(&counts.mutuals)
+            .iter()
+            .map(|pk| {
+                let profile = self.ndb.get_profile_by_pubkey(txn, pk.bytes()).ok();
+                get_display_name_as_string(profile.as_ref())
+            })
+            .collect();
+
+        if let Some(summary) = profile_stats::mutuals_summary(&names, counts.mutuals.len()) {
+            ui.horizontal(|ui| {
+                ui.add_space(16.0);
+                ui.label(RichText::new(summary).small().weak());
+            });
+        }
+    }
+
+    /// The notes on their NIP-51 pinned notes list
+    fn pinned_ui(&mut self, ui: &mut egui::Ui, txn: &Transaction) -> Option<NoteAction> {
+        let pinned = profile_stats::pinned_note_ids(self.ndb, txn, self.pubkey);
+        if pinned.is_empty() {
+            return None;
+        }
+
+        let mut action = None;
+        egui::CollapsingHeader::new(format!("📌 Pinned ({})", pinned.len()))
+            .id_source(("profile_pinned", self.col_id, self.pubkey))
+            .default_open(true)
+            .show(ui, |ui| {
+                for id in &pinned {
+                    let note = if let Ok(note) = self.ndb.get_note_by_id(txn, id.bytes()) {
+                        note
+                    } else {
+                        if self.profile_stats.want_pinned(self.pubkey, id) {
+                            self.unknown_ids.add_note_id_if_missing(self.ndb, txn, id);
+                        }
+                        continue;
+                    };
+
+                    let resp = ui::NoteView::new(
+                        self.ndb,
+                        self.note_cache,
+                        self.img_cache,
+                        self.nip05,
+                        &note,
+                    )
+                    .note_options(self.note_options)
+                    .show(ui);
+                    if resp.action.is_some() {
+                        action = resp.action;
+                    }
+                    ui::hline(ui);
+                }
+            });
+
+        action
+    }
+
+    fn follow_button(
+        &self,
+        ui: &mut egui::Ui,
+        txn: &Transaction,
+        owner: &Pubkey,
+    ) -> Option<ContactAction> {
+        match self
+            .contact_edits
+            .and_then(|edits| edits.state(owner, self.pubkey))
+        {
+            Some(EditState::Fetching) => {
+                ui.spinner();
+                return None;
+            }
+
+            Some(EditState::Failed(action, err)) => {
+                let verb = match action {
+                    ContactAction::Follow(_) => "follow",
+                    ContactAction::Unfollow(_) => "unfollow",
+                };
+                let reason = match err {
+                    FetchError::Unreachable => "none of your relays sent your contact list",
+                    FetchError::Stale => "your relays have a newer contact list than ours",
+                };
+                ui.colored_label(
+                    ui.visuals().error_fg_color,
+                    format!("Couldn't {verb}: {reason}"),
+                );
+                return ui.button("Retry").clicked().then_some(action);
+            }
+
+            None => {}
+        }
+
+        if contacts::is_following(self.ndb, txn, owner, self.pubkey) {
+            ui.button("Unfollow")
+                .clicked()
+                .then_some(ContactAction::Unfollow(*self.pubkey))
+        } else {
+            ui.button("Follow")
+                .clicked()
+                .then_some(ContactAction::Follow(*self.pubkey))
+        }
+    }
+
+    /// "Add to list…" menu for adding and removing this profile from the
+    /// owner's follow sets, or creating a new one
+    fn lists_menu(
+        &self,
+        ui: &mut egui::Ui,
+        txn: &Transaction,
+        owner: &Pubkey,
+    ) -> Option<ListAction> {
+        let mut action = None;
+
+        ui.menu_button("Add to list…", |ui| {
+            for list in lists::account_lists(self.ndb, txn, owner) {
+                let identifier = if let ListKind::FollowSet(_, identifier) = list.list_kind {
+                    identifier
+                } else {
+                    continue;
+                };
+
+                let is_member = lists::latest_follow_set(self.ndb, txn, owner, &identifier)
+                    .map_or(false, |note| {
+                        lists::list_has_pubkey(&note, self.pubkey.bytes())
+                    });
+
+                let mut checked = is_member;
+                if ui.checkbox(&mut checked, &list.title).changed() {
+                    let pubkey = *self.pubkey;
+                    action = Some(if checked {
+                        ListAction::AddToFollowSet { identifier, pubkey }
+                    } else {
+                        ListAction::RemoveFromFollowSet { identifier, pubkey }
+                    });
+                }
+            }
+
+            ui.separator();
+
+            let id = ui.id().with(("new_list_title", self.pubkey));
+            let mut title = ui
+                .data_mut(|d| d.get_temp::<String>(id))
+                .unwrap_or_default();
+
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut title)
+                        .hint_text("New list")
+                        .desired_width(120.0),
+                );
+
+                if ui.button("Create").clicked() && !title.trim().is_empty() {
+                    action = Some(ListAction::NewFollowSet {
+                        title: title.trim().to_owned(),
+                        pubkey: *self.pubkey,
+                    });
+                    title.clear();
+                    ui.close_menu();
+                }
+            });
+
+            ui.data_mut(|d| d.insert_temp(id, title));
+        });
+
+        action
+    }
 }
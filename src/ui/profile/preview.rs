@@ -1,7 +1,9 @@
 use crate::app_style::NotedeckTextStyle;
 use crate::imgcache::ImageCache;
+use crate::nip05::Nip05Verifier;
 use crate::storage::{DataPath, DataPathType};
-use crate::ui::ProfilePic;
+use crate::ui::nip05::profile_nip05;
+use crate::ui::{Nip05Badge, ProfilePic};
 use crate::user_account::UserAccount;
 use crate::{colors, images, DisplayName};
 use egui::load::TexturePoll;
@@ -10,33 +12,63 @@ use egui_extras::Size;
 use enostr::NoteId;
 use nostrdb::ProfileRecord;
 
+/// What a [`ProfilePreview`] shows. This lets us preview profiles that
+/// aren't in nostrdb yet, like the one being edited.
+pub struct ProfileDisplay<'a> {
+    pub display_name: DisplayName<'a>,
+    pub picture: &'a str,
+    pub banner: Option<&'a str>,
+    pub about: Option<&'a str>,
+    pub nip05: Option<&'a str>,
+}
+
+impl<'a> ProfileDisplay<'a> {
+    pub fn from_record(profile: &'a ProfileRecord<'a>) -> Self {
+        let record = profile.record().profile();
+        ProfileDisplay {
+            display_name: get_display_name(Some(profile)),
+            picture: get_profile_url(Some(profile)),
+            banner: record.and_then(|p| p.banner()),
+            about: record.and_then(|p| p.about()),
+            nip05: profile_nip05(Some(profile)),
+        }
+    }
+}
+
 pub struct ProfilePreview<'a, 'cache> {
-    profile: &'a ProfileRecord<'a>,
+    profile: ProfileDisplay<'a>,
     cache: &'cache mut ImageCache,
     banner_height: Size,
+    nip05: Option<(&'cache mut Nip05Verifier, &'a [u8; 32])>,
 }
 
 impl<'a, 'cache> ProfilePreview<'a, 'cache> {
     pub fn new(profile: &'a ProfileRecord<'a>, cache: &'cache mut ImageCache) -> Self {
+        ProfilePreview::from_display(ProfileDisplay::from_record(profile), cache)
+    }
+
+    pub fn from_display(profile: ProfileDisplay<'a>, cache: &'cache mut ImageCache) -> Self {
         let banner_height = Size::exact(80.0);
         ProfilePreview {
             profile,
             cache,
             banner_height,
+            nip05: None,
         }
     }
 
+    /// Whose profile this is, so we can check their nip05 address
+    pub fn verify(mut self, verifier: &'cache mut Nip05Verifier, pubkey: &'a [u8; 32]) -> Self {
+        self.nip05 = Some((verifier, pubkey));
+        self
+    }
+
     pub fn banner_height(&mut self, size: Size) {
         self.banner_height = size;
     }
 
-    fn banner_texture(
-        ui: &mut egui::Ui,
-        profile: &ProfileRecord<'_>,
-    ) -> Option<egui::load::SizedTexture> {
+    fn banner_texture(ui: &mut egui::Ui, banner: Option<&str>) -> Option<egui::load::SizedTexture> {
         // TODO: cache banner
-        let banner = profile.record().profile().and_then(|p| p.banner());
-
         if let Some(banner) = banner {
             let texture_load_res =
                 egui::Image::new(banner).load_for_size(ui.ctx(), ui.available_size());
@@ -51,8 +83,8 @@ impl<'a, 'cache> ProfilePreview<'a, 'cache> {
         None
     }
 
-    fn banner(ui: &mut egui::Ui, profile: &ProfileRecord<'_>) -> egui::Response {
-        if let Some(texture) = Self::banner_texture(ui, profile) {
+    fn banner(ui: &mut egui::Ui, banner: Option<&str>) -> egui::Response {
+        if let Some(texture) = Self::banner_texture(ui, banner) {
             images::aspect_fill(
                 ui,
                 Sense::hover(),
@@ -67,12 +99,12 @@ impl<'a, 'cache> ProfilePreview<'a, 'cache> {
 
     fn body(self, ui: &mut egui::Ui) {
         crate::ui::padding(12.0, ui, |ui| {
-            ui.add(ProfilePic::new(self.cache, get_profile_url(Some(self.profile))).size(80.0));
-            ui.add(display_name_widget(
-                get_display_name(Some(self.profile)),
-                false,
-            ));
-            ui.add(about_section_widget(self.profile));
+            ui.add(ProfilePic::new(self.cache, self.profile.picture).size(80.0));
+            ui.add(display_name_widget(self.profile.display_name, false));
+            if let (Some((verifier, pk)), Some(address)) = (self.nip05, self.profile.nip05) {
+                ui.add(Nip05Badge::new(verifier, pk, address).show_address(true));
+            }
+            ui.add(about_section_widget(self.profile.about));
         });
     }
 }
@@ -81,7 +113,7 @@ impl<'a, 'cache> egui::Widget for ProfilePreview<'a, 'cache> {
     fn ui(self, ui: &mut egui::Ui) -> egui::Response {
         ui.vertical(|ui| {
             ui.add_sized([ui.available_size().x, 80.0], |ui: &mut egui::Ui| {
-                ProfilePreview::banner(ui, self.profile)
+                ProfilePreview::banner(ui, self.profile.banner)
             });
 
             self.body(ui);
@@ -251,9 +283,9 @@ pub fn one_line_display_name_widget(
     }
 }
 
-fn about_section_widget<'a>(profile: &'a ProfileRecord<'a>) -> impl egui::Widget + 'a {
-    |ui: &mut egui::Ui| {
-        if let Some(about) = profile.record().profile().and_then(|p| p.about()) {
+fn about_section_widget(about: Option<&str>) -> impl egui::Widget + '_ {
+    move |ui: &mut egui::Ui| {
+        if let Some(about) = about {
             ui.label(about)
         } else {
             // need any Response so we dont need an Option
@@ -262,7 +294,7 @@ fn about_section_widget<'a>(profile: &'a ProfileRecord<'a>) -> impl egui::Widget
     }
 }
 
-fn get_display_name_as_string(profile: Option<&'_ ProfileRecord<'_>>) -> String {
+pub fn get_display_name_as_string(profile: Option<&'_ ProfileRecord<'_>>) -> String {
     let display_name = get_display_name(profile);
     match display_name {
         DisplayName::One(n) => n.to_string(),
@@ -0,0 +1,102 @@
+use egui::{Align, Margin, RichText, Vec2};
+use enostr::Proxy;
+
+use crate::{app_style::NotedeckTextStyle, proxy::ProxySettings, ui};
+
+/// The proxy settings as they're being edited, before they're saved
+pub struct ProxySettingsDraft {
+    pub url: String,
+    pub block_clearnet: bool,
+}
+
+impl ProxySettingsDraft {
+    pub fn new(settings: &ProxySettings) -> Self {
+        ProxySettingsDraft {
+            url: settings.url.clone().unwrap_or_default(),
+            block_clearnet: settings.block_clearnet,
+        }
+    }
+
+    pub fn settings(&self) -> ProxySettings {
+        let url = self.url.trim();
+        ProxySettings {
+            url: (!url.is_empty()).then(|| url.to_owned()),
+            block_clearnet: self.block_clearnet,
+        }
+    }
+}
+
+/// Route relay connections and http requests through a SOCKS5 proxy,
+/// ie. Tor
+pub struct ProxySettingsView<'a> {
+    draft: &'a mut ProxySettingsDraft,
+
+    /// What's wrong with the proxy we're using now, if anything
+    current_error: Option<String>,
+}
+
+impl<'a> ProxySettingsView<'a> {
+    pub fn new(draft: &'a mut ProxySettingsDraft, current_error: Option<String>) -> Self {
+        ProxySettingsView {
+            draft,
+            current_error,
+        }
+    }
+
+    /// Returns the new settings once the user saves them
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> Option<ProxySettings> {
+        ui::padding(16.0, ui, |ui| {
+            ui.spacing_mut().item_spacing.y = 8.0;
+
+            if let Some(err) = &self.current_error {
+                ui.colored_label(
+                    ui.visuals().error_fg_color,
+                    format!("Nothing is connecting until this is fixed: {err}"),
+                );
+            }
+
+            ui.label("Send all relay connections, images and lookups through a SOCKS5 proxy. Leave this empty to connect directly.");
+
+            let text_edit = egui::TextEdit::singleline(&mut self.draft.url)
+                .hint_text(
+                    RichText::new("socks5h://127.0.0.1:9050")
+                        .text_style(NotedeckTextStyle::Body.text_style()),
+                )
+                .vertical_align(Align::Center)
+                .desired_width(f32::INFINITY)
+                .min_size(Vec2::new(0.0, 40.0))
+                .margin(Margin::same(12.0));
+            ui.add(text_edit);
+
+            let url = self.draft.url.trim();
+            let valid = if url.is_empty() {
+                true
+            } else {
+                match Proxy::parse(url) {
+                    Ok(proxy) => {
+                        if !proxy.remote_dns {
+                            ui.weak("socks5:// resolves hostnames locally. Use socks5h:// with Tor so lookups don't leak.");
+                        }
+                        true
+                    }
+                    Err(err) => {
+                        ui.colored_label(ui.visuals().error_fg_color, err.to_string());
+                        false
+                    }
+                }
+            };
+
+            ui.add_enabled_ui(!url.is_empty(), |ui| {
+                ui.checkbox(
+                    &mut self.draft.block_clearnet,
+                    "Only connect to .onion relays",
+                );
+            });
+
+            ui.add_enabled(valid, egui::Button::new("Save"))
+                .clicked()
+                .then(|| self.draft.settings())
+        })
+        .inner
+    }
+}
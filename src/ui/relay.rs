@@ -1,6 +1,6 @@
 use crate::relay_pool_manager::{RelayPoolManager, RelayStatus};
 use crate::ui::{Preview, PreviewConfig, View};
-use egui::{Align, Button, Frame, Layout, Margin, Rgba, RichText, Rounding, Ui, Vec2};
+use egui::{Align, Button, Frame, Layout, Margin, Rgba, RichText, Rounding, Sense, Ui, Vec2};
 
 use crate::app_style::NotedeckTextStyle;
 use enostr::RelayPool;
@@ -9,8 +9,29 @@ pub struct RelayView<'a> {
     manager: RelayPoolManager<'a>,
 }
 
+pub enum RelayViewAction {
+    AddRelay,
+    /// Show connection stats for all relays
+    Network,
+    Proxy,
+    /// Show the details of the relay with this url
+    OpenRelay(String),
+}
+
 impl<'a> View for RelayView<'a> {
     fn ui(&mut self, ui: &mut egui::Ui) {
+        self.show(ui);
+    }
+}
+
+impl<'a> RelayView<'a> {
+    pub fn new(manager: RelayPoolManager<'a>) -> Self {
+        RelayView { manager }
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui) -> Option<RelayViewAction> {
+        let mut action = None;
+
         ui.add_space(24.0);
 
         ui.horizontal(|ui| {
@@ -22,35 +43,53 @@ impl<'a> View for RelayView<'a> {
 
             ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
                 if ui.add(add_relay_button()).clicked() {
-                    // TODO: navigate to 'add relay view'
+                    action = Some(RelayViewAction::AddRelay);
+                };
+
+                if ui.add(network_button()).clicked() {
+                    action = Some(RelayViewAction::Network);
+                };
+
+                if ui.add(proxy_button()).clicked() {
+                    action = Some(RelayViewAction::Proxy);
                 };
             });
         });
 
         ui.add_space(8.0);
 
+        if let Some(err) = self.manager.pool.proxy_error() {
+            ui.colored_label(
+                ui.visuals().error_fg_color,
+                format!("Not connecting to any relays until the proxy is fixed: {err}"),
+            );
+            ui.add_space(8.0);
+        }
+
         egui::ScrollArea::vertical()
             .scroll_bar_visibility(egui::scroll_area::ScrollBarVisibility::AlwaysHidden)
             .auto_shrink([false; 2])
             .show(ui, |ui| {
-                if let Some(indices) = self.show_relays(ui) {
+                let (opened, removed) = self.show_relays(ui);
+                if let Some(url) = opened {
+                    action = Some(RelayViewAction::OpenRelay(url));
+                }
+                if let Some(indices) = removed {
                     self.manager.remove_relays(indices);
                 }
             });
-    }
-}
 
-impl<'a> RelayView<'a> {
-    pub fn new(manager: RelayPoolManager<'a>) -> Self {
-        RelayView { manager }
+        action
     }
 
     pub fn panel(&mut self, ui: &mut egui::Ui) {
         egui::CentralPanel::default().show(ui.ctx(), |ui| self.ui(ui));
     }
 
-    /// Show the current relays, and returns the indices of relays the user requested to delete
-    fn show_relays(&'a self, ui: &mut Ui) -> Option<Vec<usize>> {
+    /// Show the current relays. Returns the url of the relay the user
+    /// clicked on, and the indices of relays the user requested to delete
+    fn show_relays(&'a self, ui: &mut Ui) -> (Option<String>, Option<Vec<usize>>) {
+        let mut opened: Option<String> = None;
         let mut indices_to_remove: Option<Vec<usize>> = None;
         for (index, relay_info) in self.manager.get_relay_infos().iter().enumerate() {
             ui.add_space(8.0);
@@ -70,19 +109,29 @@ impl<'a> RelayView<'a> {
                                                 - get_right_side_width(relay_info.status),
                                         ) // TODO: refactor to dynamically check the size of the 'right to left' portion and set the max width to be the screen width minus padding minus 'right to left' width
                                         .show(ui, |ui| {
-                                            ui.label(
-                                                RichText::new(relay_info.relay_url)
-                                                    .text_style(
-                                                        NotedeckTextStyle::Monospace.text_style(),
+                                            let resp = ui
+                                                .add(
+                                                    egui::Label::new(
+                                                        RichText::new(relay_info.relay_url)
+                                                            .text_style(
+                                                                NotedeckTextStyle::Monospace
+                                                                    .text_style(),
+                                                            )
+                                                            .color(
+                                                                ui.style()
+                                                                    .visuals
+                                                                    .noninteractive()
+                                                                    .fg_stroke
+                                                                    .color,
+                                                            ),
                                                     )
-                                                    .color(
-                                                        ui.style()
-                                                            .visuals
-                                                            .noninteractive()
-                                                            .fg_stroke
-                                                            .color,
-                                                    ),
-                                            );
+                                                    .sense(Sense::click()),
+                                                )
+                                                .on_hover_cursor(egui::CursorIcon::PointingHand);
+
+                                            if resp.clicked() {
+                                                opened = Some(relay_info.relay_url.to_owned());
+                                            }
                                         });
                                 });
                         });
@@ -99,7 +148,7 @@ impl<'a> RelayView<'a> {
             });
         }
 
-        indices_to_remove
+        (opened, indices_to_remove)
     }
 }
 
@@ -115,6 +164,14 @@ fn add_relay_button() -> egui::Button<'static> {
     Button::new("+ Add relay").min_size(Vec2::new(0.0, 32.0))
 }
 
+fn network_button() -> egui::Button<'static> {
+    Button::new("Network").min_size(Vec2::new(0.0, 32.0))
+}
+
+fn proxy_button() -> egui::Button<'static> {
+    Button::new("Proxy").min_size(Vec2::new(0.0, 32.0))
+}
+
 fn delete_button(_dark_mode: bool) -> egui::Button<'static> {
     /*
     let img_data = if dark_mode {
@@ -136,7 +193,7 @@ fn relay_frame(ui: &mut Ui) -> Frame {
         .stroke(ui.style().visuals.noninteractive().bg_stroke)
 }
 
-fn show_connection_status(ui: &mut Ui, status: &RelayStatus) {
+pub fn show_connection_status(ui: &mut Ui, status: &RelayStatus) {
     let fg_color = match status {
         RelayStatus::Connected => ui.visuals().selection.bg_fill,
         RelayStatus::Connecting => ui.visuals().warn_fg_color,
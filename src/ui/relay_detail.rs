@@ -0,0 +1,185 @@
+use egui::{Grid, Hyperlink, RichText, ScrollArea};
+use enostr::{Pubkey, Relay, RelayFee, RelayInformation, RelayLimitation};
+
+use crate::{app_style::NotedeckTextStyle, ui};
+
+use super::relay::show_connection_status;
+
+/// Everything a relay tells us about itself in its NIP-11 information
+/// document
+pub struct RelayDetailView<'a> {
+    relay: Option<&'a Relay>,
+}
+
+impl<'a> RelayDetailView<'a> {
+    pub fn new(relay: Option<&'a Relay>) -> Self {
+        RelayDetailView { relay }
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        let relay = if let Some(relay) = self.relay {
+            relay
+        } else {
+            ui::padding(16.0, ui, |ui| {
+                ui.label("This relay is no longer in your relay list");
+            });
+            return;
+        };
+
+        ScrollArea::vertical()
+            .auto_shrink([false, false])
+            .show(ui, |ui| {
+                ui::padding(16.0, ui, |ui| {
+                    ui.spacing_mut().item_spacing.y = 8.0;
+
+                    ui.horizontal(|ui| {
+                        ui.label(
+                            RichText::new(&relay.url)
+                                .text_style(NotedeckTextStyle::Monospace.text_style()),
+                        );
+                        show_connection_status(ui, &relay.status);
+                    });
+
+                    if let Some(info) = &relay.info {
+                        relay_information_ui(ui, info);
+                    } else {
+                        ui.weak("This relay hasn't told us anything about itself yet");
+                    }
+                });
+            });
+    }
+}
+
+/// The contents of a relay information document. Also used to preview a
+/// relay before adding it.
+pub fn relay_information_ui(ui: &mut egui::Ui, info: &RelayInformation) {
+    if let Some(name) = &info.name {
+        ui.label(RichText::new(name).text_style(NotedeckTextStyle::Heading2.text_style()));
+    }
+
+    if let Some(description) = &info.description {
+        ui.label(description);
+    }
+
+    if info.pubkey.is_some() || info.contact.is_some() {
+        heading(ui, "Operator");
+        if let Some(pubkey) = &info.pubkey {
+            let npub = Pubkey::from_hex(pubkey)
+                .ok()
+                .and_then(|pk| pk.to_bech())
+                .unwrap_or_else(|| pubkey.to_owned());
+            ui.label(RichText::new(npub).text_style(NotedeckTextStyle::Monospace.text_style()));
+        }
+        if let Some(contact) = &info.contact {
+            ui.label(contact);
+        }
+    }
+
+    if let Some(software) = &info.software {
+        heading(ui, "Software");
+        match &info.version {
+            Some(version) => ui.label(format!("{software} {version}")),
+            None => ui.label(software),
+        };
+    }
+
+    if let Some(nips) = &info.supported_nips {
+        heading(ui, "Supported NIPs");
+        let nips: Vec<String> = nips.iter().map(|nip| nip.to_string()).collect();
+        ui.label(nips.join(", "));
+    }
+
+    if let Some(limitation) = &info.limitation {
+        heading(ui, "Limitations");
+        limitation_ui(ui, limitation);
+    }
+
+    payment_ui(ui, info);
+}
+
+fn limitation_ui(ui: &mut egui::Ui, limitation: &RelayLimitation) {
+    let numbers = [
+        ("Max message length", limitation.max_message_length),
+        ("Max subscriptions", limitation.max_subscriptions),
+        ("Max filters per subscription", limitation.max_filters),
+        ("Max notes per filter", limitation.max_limit),
+        ("Max subscription id length", limitation.max_subid_length),
+    ];
+    let flags = [
+        ("Authentication required", limitation.auth_required),
+        ("Payment required", limitation.payment_required),
+    ];
+
+    Grid::new("relay_limitations")
+        .num_columns(2)
+        .spacing([16.0, 4.0])
+        .show(ui, |ui| {
+            for (label, value) in numbers {
+                if let Some(value) = value {
+                    ui.label(label);
+                    ui.label(value.to_string());
+                    ui.end_row();
+                }
+            }
+
+            for (label, value) in flags {
+                if let Some(value) = value {
+                    ui.label(label);
+                    ui.label(if value { "Yes" } else { "No" });
+                    ui.end_row();
+                }
+            }
+        });
+}
+
+fn payment_ui(ui: &mut egui::Ui, info: &RelayInformation) {
+    if info.fees.is_none() && info.payments_url.is_none() {
+        return;
+    }
+
+    heading(ui, "Payment");
+
+    if let Some(fees) = &info.fees {
+        let kinds = [
+            ("Admission", &fees.admission),
+            ("Subscription", &fees.subscription),
+            ("Publication", &fees.publication),
+        ];
+
+        for (label, fees) in kinds {
+            for fee in fees.iter().flatten() {
+                ui.label(format!("{label}: {}", describe_fee(fee)));
+            }
+        }
+    }
+
+    if let Some(url) = &info.payments_url {
+        ui.add(Hyperlink::from_label_and_url("Pay this relay", url));
+    }
+}
+
+/// ie. "5000 sats every 30 days"
+fn describe_fee(fee: &RelayFee) -> String {
+    let mut desc = format!("{} {}", fee.amount, fee.unit);
+
+    if let Some(period) = fee.period {
+        let days = period / (60 * 60 * 24);
+        if days > 0 {
+            desc.push_str(&format!(" every {days} days"));
+        } else {
+            desc.push_str(&format!(" every {period} seconds"));
+        }
+    }
+
+    if let Some(kinds) = &fee.kinds {
+        let kinds: Vec<String> = kinds.iter().map(|k| k.to_string()).collect();
+        desc.push_str(&format!(" for kinds {}", kinds.join(", ")));
+    }
+
+    desc
+}
+
+fn heading(ui: &mut egui::Ui, text: &str) {
+    ui.add_space(8.0);
+    ui.label(RichText::new(text).text_style(NotedeckTextStyle::Heading3.text_style()));
+}
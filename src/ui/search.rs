@@ -0,0 +1,249 @@
+use egui::{Align, Margin, RichText, ScrollArea, Sense, Vec2};
+use enostr::Pubkey;
+use nostrdb::{Ndb, Transaction};
+
+use crate::{
+    actionbar::NoteAction,
+    app_style::NotedeckTextStyle,
+    imgcache::ImageCache,
+    nav::RenderNavAction,
+    nip05::Nip05Verifier,
+    notecache::NoteCache,
+    search::Search,
+    timeline::{self, TimelineKind},
+    ui::{
+        self, note::NoteOptions, profile::preview::SimpleProfilePreview, timeline::TimelineTabView,
+    },
+    Damus,
+};
+
+pub enum SearchAction {
+    /// Run the query in the search box
+    Submit,
+
+    /// Turn the current search into a live column
+    Pin(String),
+
+    Note(NoteAction),
+}
+
+pub struct SearchView<'a> {
+    search: &'a mut Search,
+    col_id: u32,
+    ndb: &'a Ndb,
+    note_cache: &'a mut NoteCache,
+    img_cache: &'a mut ImageCache,
+    nip05: &'a mut Nip05Verifier,
+    note_options: NoteOptions,
+}
+
+impl<'a> SearchView<'a> {
+    pub fn new(
+        search: &'a mut Search,
+        col_id: u32,
+        ndb: &'a Ndb,
+        note_cache: &'a mut NoteCache,
+        img_cache: &'a mut ImageCache,
+        nip05: &'a mut Nip05Verifier,
+        note_options: NoteOptions,
+    ) -> Self {
+        SearchView {
+            search,
+            col_id,
+            ndb,
+            note_cache,
+            img_cache,
+            nip05,
+            note_options,
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> Option<SearchAction> {
+        let mut action = ui::padding(8.0, ui, |ui| self.search_box(ui)).inner;
+
+        if self.search.searched().is_empty() {
+            return action;
+        }
+
+        let scroll_id = egui::Id::new(("search_scroll", self.col_id));
+        let txn = Transaction::new(self.ndb).expect("txn");
+
+        let results_action = ScrollArea::vertical()
+            .id_source(scroll_id)
+            .auto_shrink([false, false])
+            .show(ui, |ui| {
+                let mut action = None;
+
+                if !self.search.profiles().is_empty() {
+                    if let Some(pk) = self.profile_results(ui, &txn) {
+                        action = Some(SearchAction::Note(NoteAction::OpenProfile(pk)));
+                    }
+                }
+
+                ui::padding(8.0, ui, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label(
+                            RichText::new("Notes")
+                                .text_style(NotedeckTextStyle::Heading3.text_style()),
+                        );
+
+                        let pending = self.search.pending_relays();
+                        if pending > 0 {
+                            ui.spinner();
+                            ui.weak(format!(
+                                "searching {} relay{}",
+                                pending,
+                                if pending == 1 { "" } else { "s" }
+                            ));
+                        }
+
+                        ui.with_layout(egui::Layout::right_to_left(Align::Center), |ui| {
+                            if ui.button("Pin as column").clicked() {
+                                action = Some(SearchAction::Pin(self.search.searched().to_owned()));
+                            }
+                        });
+                    });
+                });
+
+                ui::hline(ui);
+
+                if self.search.notes().notes.is_empty() {
+                    ui::padding(8.0, ui, |ui| {
+                        ui.weak("No notes found (yet)");
+                    });
+                }
+
+                let reversed = false;
+                let note_action = TimelineTabView::new(
+                    self.search.notes(),
+                    reversed,
+                    self.note_options,
+                    &txn,
+                    self.ndb,
+                    self.note_cache,
+                    self.img_cache,
+                    self.nip05,
+                )
+                .show(ui);
+
+                if let Some(note_action) = note_action {
+                    action = Some(SearchAction::Note(note_action));
+                }
+
+                action
+            })
+            .inner;
+
+        if results_action.is_some() {
+            action = results_action;
+        }
+
+        action
+    }
+
+    fn search_box(&mut self, ui: &mut egui::Ui) -> Option<SearchAction> {
+        let mut submit = false;
+
+        ui.horizontal(|ui| {
+            let text_edit = egui::TextEdit::singleline(&mut self.search.query)
+                .hint_text(
+                    RichText::new("Search notes and profiles")
+                        .text_style(NotedeckTextStyle::Body.text_style()),
+                )
+                .vertical_align(Align::Center)
+                .desired_width(ui.available_width() - 80.0)
+                .min_size(Vec2::new(0.0, 40.0))
+                .margin(Margin::same(12.0));
+
+            let resp = ui.add(text_edit);
+            if resp.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                submit = true;
+            }
+
+            if ui.button("Search").clicked() {
+                submit = true;
+            }
+        });
+
+        if submit {
+            Some(SearchAction::Submit)
+        } else {
+            None
+        }
+    }
+
+    fn profile_results(&mut self, ui: &mut egui::Ui, txn: &Transaction) -> Option<Pubkey> {
+        let mut clicked = None;
+
+        ui::padding(8.0, ui, |ui| {
+            ui.label(
+                RichText::new("Profiles").text_style(NotedeckTextStyle::Heading3.text_style()),
+            );
+            ui.add_space(4.0);
+
+            for pk in self.search.profiles() {
+                let profile = self.ndb.get_profile_by_pubkey(txn, pk.bytes()).ok();
+                let resp = ui
+                    .add(SimpleProfilePreview::new(profile.as_ref(), self.img_cache))
+                    .interact(Sense::click())
+                    .on_hover_cursor(egui::CursorIcon::PointingHand);
+
+                if resp.clicked() {
+                    clicked = Some(*pk);
+                }
+            }
+        });
+
+        ui::hline(ui);
+
+        clicked
+    }
+}
+
+pub fn render_search_route(
+    ui: &mut egui::Ui,
+    app: &mut Damus,
+    col: usize,
+) -> Option<RenderNavAction> {
+    let col_id = app.columns.get_column_id_at_index(col);
+    let search = app.searches.search_mut(col_id);
+    search.poll(&app.ndb, &mut app.subscriptions, &mut app.pool);
+
+    let mut note_options = NoteOptions::new(false);
+    note_options.set_textmode(app.textmode);
+
+    let action = SearchView::new(
+        search,
+        col_id,
+        &app.ndb,
+        &mut app.note_cache,
+        &mut app.img_cache,
+        &mut app.nip05,
+        note_options,
+    )
+    .ui(ui)?;
+
+    match action {
+        SearchAction::Submit => {
+            search.submit(&app.ndb, &mut app.subscriptions, &mut app.pool);
+            None
+        }
+
+        SearchAction::Pin(query) => {
+            if let Some(mut timeline) = TimelineKind::Search(query).into_timeline(&app.ndb, None) {
+                timeline::setup_new_timeline(
+                    &mut timeline,
+                    &app.ndb,
+                    &mut app.subscriptions,
+                    &mut app.pool,
+                    &mut app.note_cache,
+                    app.since_optimize,
+                );
+                app.columns.add_new_timeline_column(timeline);
+            }
+            None
+        }
+
+        SearchAction::Note(note_action) => Some(note_action.into()),
+    }
+}
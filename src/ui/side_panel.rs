@@ -6,6 +6,7 @@ use crate::{
     colors,
     column::{Column, Columns},
     imgcache::ImageCache,
+    offline::OfflineMode,
     route::Route,
     support::Support,
     user_account::UserAccount,
@@ -20,11 +21,13 @@ use super::{
 
 pub static SIDE_PANEL_WIDTH: f32 = 64.0;
 static ICON_WIDTH: f32 = 40.0;
+const ONLINE_COLOR: Color32 = Color32::from_rgb(0x3C, 0xB3, 0x71);
 
 pub struct DesktopSidePanel<'a> {
     ndb: &'a nostrdb::Ndb,
     img_cache: &'a mut ImageCache,
     selected_account: Option<&'a UserAccount>,
+    offline: &'a OfflineMode,
 }
 
 impl<'a> View for DesktopSidePanel<'a> {
@@ -43,6 +46,9 @@ pub enum SidePanelAction {
     Search,
     ExpandSidePanel,
     Support,
+    /// Go offline, or back online if we were asked to be offline. Handled
+    /// by the app, since it needs the relay pool.
+    ToggleOffline,
 }
 
 pub struct SidePanelResponse {
@@ -61,11 +67,13 @@ impl<'a> DesktopSidePanel<'a> {
         ndb: &'a nostrdb::Ndb,
         img_cache: &'a mut ImageCache,
         selected_account: Option<&'a UserAccount>,
+        offline: &'a OfflineMode,
     ) -> Self {
         Self {
             ndb,
             img_cache,
             selected_account,
+            offline,
         }
     }
 
@@ -117,6 +125,7 @@ impl<'a> DesktopSidePanel<'a> {
                         let settings_resp = ui.add(settings_button(dark_mode));
 
                         let support_resp = ui.add(support_button());
+                        let offline_resp = ui.add(offline_indicator(self.offline));
 
                         let optional_inner = if pfp_resp.clicked() {
                             Some(egui::InnerResponse::new(
@@ -133,6 +142,11 @@ impl<'a> DesktopSidePanel<'a> {
                                 SidePanelAction::Support,
                                 support_resp,
                             ))
+                        } else if offline_resp.clicked() {
+                            Some(egui::InnerResponse::new(
+                                SidePanelAction::ToggleOffline,
+                                offline_resp,
+                            ))
                         } else {
                             None
                         };
@@ -179,7 +193,7 @@ impl<'a> DesktopSidePanel<'a> {
                 if router
                     .routes()
                     .iter()
-                    .any(|&r| r == Route::Accounts(AccountsRoute::Accounts))
+                    .any(|r| *r == Route::Accounts(AccountsRoute::Accounts))
                 {
                     // return if we are already routing to accounts
                     router.go_back();
@@ -188,7 +202,7 @@ impl<'a> DesktopSidePanel<'a> {
                 }
             }
             SidePanelAction::Settings => {
-                if router.routes().iter().any(|&r| r == Route::Relays) {
+                if router.routes().iter().any(|r| *r == Route::Relays) {
                     // return if we are already routing to accounts
                     router.go_back();
                 } else {
@@ -199,7 +213,7 @@ impl<'a> DesktopSidePanel<'a> {
                 if router
                     .routes()
                     .iter()
-                    .any(|&r| matches!(r, Route::AddColumn(_)))
+                    .any(|r| matches!(r, Route::AddColumn(_)))
                 {
                     router.go_back();
                 } else {
@@ -207,28 +221,32 @@ impl<'a> DesktopSidePanel<'a> {
                 }
             }
             SidePanelAction::ComposeNote => {
-                if router.routes().iter().any(|&r| r == Route::ComposeNote) {
+                if router.routes().iter().any(|r| *r == Route::ComposeNote) {
                     router.go_back();
                 } else {
                     router.route_to(Route::ComposeNote);
                 }
             }
             SidePanelAction::Search => {
-                // TODO
-                info!("Clicked search button");
+                if router.routes().iter().any(|r| *r == Route::Search) {
+                    router.go_back();
+                } else {
+                    router.route_to(Route::Search);
+                }
             }
             SidePanelAction::ExpandSidePanel => {
                 // TODO
                 info!("Clicked expand side panel button");
             }
             SidePanelAction::Support => {
-                if router.routes().iter().any(|&r| r == Route::Support) {
+                if router.routes().iter().any(|r| *r == Route::Support) {
                     router.go_back();
                 } else {
                     support.refresh();
                     router.route_to(Route::Support);
                 }
             }
+            SidePanelAction::ToggleOffline => {} // handled by the app
         }
     }
 }
@@ -395,6 +413,38 @@ fn support_button() -> impl Widget {
     }
 }
 
+/// A dot showing whether we're online. Clicking it goes offline, or back
+/// online if we were asked to be offline.
+fn offline_indicator(offline: &OfflineMode) -> impl Widget + '_ {
+    move |ui: &mut egui::Ui| -> egui::Response {
+        let max_size = ICON_WIDTH * ICON_EXPANSION_MULTIPLE; // max size of the widget
+        let min_diameter = 12.0;
+
+        let helper = AnimationHelper::new(ui, "offline-indicator", vec2(max_size, max_size));
+        let painter = ui.painter_at(helper.get_animation_rect());
+        let radius = helper.scale_radius(min_diameter);
+
+        let hover_text = if offline.is_offline() {
+            painter.circle_stroke(
+                helper.center(),
+                radius,
+                Stroke::new(1.5, colors::GRAY_SECONDARY),
+            );
+
+            if offline.is_forced() {
+                "Offline. Click to connect to your relays again."
+            } else {
+                "Offline: none of your relays are reachable. Showing what's saved locally."
+            }
+        } else {
+            painter.circle_filled(helper.center(), radius, ONLINE_COLOR);
+            "Online. Click to work offline."
+        };
+
+        helper.take_animation_response().on_hover_text(hover_text)
+    }
+}
+
 mod preview {
 
     use egui_extras::{Size, StripBuilder};
@@ -430,6 +480,7 @@ mod preview {
                             &self.app.ndb,
                             &mut self.app.img_cache,
                             self.app.accounts.get_selected_account(),
+                            &self.app.offline,
                         );
                         let response = panel.show(ui);
 
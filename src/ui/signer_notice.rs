@@ -0,0 +1,44 @@
+use egui::{Align, Layout, RichText};
+
+/// Something the user has to know about their remote signer
+pub enum SignerNotice {
+    /// The signer wants the user to approve us at this url before it
+    /// answers our requests
+    Approve(String),
+
+    /// The signer didn't sign something that wasn't a post
+    Failed(String),
+}
+
+pub struct SignerNoticeView<'a> {
+    notice: &'a SignerNotice,
+}
+
+impl<'a> SignerNoticeView<'a> {
+    pub fn new(notice: &'a SignerNotice) -> Self {
+        SignerNoticeView { notice }
+    }
+
+    /// Returns true when the user dismisses the notice
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> bool {
+        ui.horizontal(|ui| {
+            match self.notice {
+                SignerNotice::Approve(url) => {
+                    ui.label("Your remote signer wants you to approve Notedeck first:");
+                    ui.hyperlink_to("Approve", url);
+                }
+                SignerNotice::Failed(err) => {
+                    ui.colored_label(ui.visuals().error_fg_color, err);
+                }
+            }
+
+            ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                ui.button(RichText::new("✕").small())
+                    .on_hover_text("Dismiss")
+                    .clicked()
+            })
+            .inner
+        })
+        .inner
+    }
+}
@@ -1,23 +1,38 @@
 use crate::{
     actionbar::NoteAction,
+    colors,
     imgcache::ImageCache,
+    missing_notes::{FetchState, MissingNotes},
+    nip05::Nip05Verifier,
+    note::NoteRef,
     notecache::NoteCache,
     notes_holder::{NotesHolder, NotesHolderStorage},
-    thread::Thread,
-    ui::note::NoteOptions,
+    thread::{Thread, ThreadRow},
+    ui::{self, note::NoteOptions},
     unknowns::UnknownIds,
 };
+use enostr::NoteId;
 use nostrdb::{Ndb, NoteKey, Transaction};
-use tracing::error;
+use tracing::{error, warn};
 
-use super::timeline::TimelineTabView;
+/// How far each level of replies is indented
+const INDENT: f32 = 12.0;
+
+/// Deeper replies line up with this level, so they still have room
+const MAX_INDENT_DEPTH: usize = 6;
+
+/// How far up the thread we go looking for missing notes, so a reply
+/// loop can't hang us
+const MAX_ANCESTORS: usize = 64;
 
 pub struct ThreadView<'a> {
     threads: &'a mut NotesHolderStorage<Thread>,
     ndb: &'a Ndb,
     note_cache: &'a mut NoteCache,
     unknown_ids: &'a mut UnknownIds,
+    missing_notes: &'a mut MissingNotes,
     img_cache: &'a mut ImageCache,
+    nip05: &'a mut Nip05Verifier,
     selected_note_id: &'a [u8; 32],
     textmode: bool,
     id_source: egui::Id,
@@ -30,7 +45,9 @@ impl<'a> ThreadView<'a> {
         ndb: &'a Ndb,
         note_cache: &'a mut NoteCache,
         unknown_ids: &'a mut UnknownIds,
+        missing_notes: &'a mut MissingNotes,
         img_cache: &'a mut ImageCache,
+        nip05: &'a mut Nip05Verifier,
         selected_note_id: &'a [u8; 32],
         textmode: bool,
     ) -> Self {
@@ -40,7 +57,9 @@ impl<'a> ThreadView<'a> {
             ndb,
             note_cache,
             unknown_ids,
+            missing_notes,
             img_cache,
+            nip05,
             selected_note_id,
             textmode,
             id_source,
@@ -62,7 +81,7 @@ impl<'a> ThreadView<'a> {
         {
             key
         } else {
-            // TODO: render 404 ?
+            self.missing_note_ui(ui);
             return None;
         };
 
@@ -110,22 +129,314 @@ impl<'a> ThreadView<'a> {
                     Err(err) => error!("{err}"),
                 };
 
+                fetch_ancestors(
+                    &txn,
+                    self.ndb,
+                    self.note_cache,
+                    self.missing_notes,
+                    selected_note_key,
+                    thread,
+                );
+
                 // This is threadview. We are not the universe view...
                 let is_universe = false;
                 let mut note_options = NoteOptions::new(is_universe);
                 note_options.set_textmode(self.textmode);
 
-                TimelineTabView::new(
-                    thread.view(),
-                    true,
-                    note_options,
-                    &txn,
-                    self.ndb,
-                    self.note_cache,
-                    self.img_cache,
-                )
-                .show(ui)
+                let root_id = NoteId::new(*root_id);
+                let selected_id = NoteId::new(*self.selected_note_id);
+
+                let scroll_to_selected = thread.scrolled_to != Some(selected_id);
+                let mut action: Option<NoteAction> = None;
+
+                if root_id != selected_id
+                    && self.missing_notes.state(&root_id) == Some(FetchState::NotFound)
+                {
+                    ui::padding(8.0, ui, |ui| {
+                        ui.horizontal_wrapped(|ui| {
+                            ui.label(
+                                egui::RichText::new(
+                                    "We couldn't find the start of this thread on any relay.",
+                                )
+                                .color(colors::GRAY_SECONDARY),
+                            );
+                            if ui.small_button("Try again").clicked() {
+                                self.missing_notes.retry(&root_id);
+                            }
+                        });
+                    });
+                    ui::hline(ui);
+                }
+
+                let list = thread.list.clone();
+                let rows = thread.rows(&txn, self.ndb, self.note_cache, &root_id, &selected_id);
+
+                let mut list = list.borrow_mut();
+                // lay out everything until we've scrolled to the selected
+                // note, since it may be far down the thread
+                list.over_scan(if scroll_to_selected { f32::MAX } else { 1000.0 });
+
+                let mut toggled: Option<NoteId> = None;
+                let mut scrolled = false;
+
+                list.ui_custom_layout(ui, rows.len(), |ui, index| {
+                    ui.spacing_mut().item_spacing.y = 0.0;
+                    ui.spacing_mut().item_spacing.x = 4.0;
+
+                    match rows[index] {
+                        ThreadRow::Note {
+                            key,
+                            id,
+                            depth,
+                            replies,
+                            collapsed,
+                        } => {
+                            let note = if let Ok(note) = self.ndb.get_note_by_key(&txn, key) {
+                                note
+                            } else {
+                                warn!("failed to query note {:?}", key);
+                                return 0;
+                            };
+
+                            let selected = id == selected_id;
+                            let resp = indented(ui, depth, selected, |ui| {
+                                let resp = ui::NoteView::new(
+                                    self.ndb,
+                                    self.note_cache,
+                                    self.img_cache,
+                                    self.nip05,
+                                    &note,
+                                )
+                                .note_options(note_options)
+                                .show(ui);
+
+                                if let Some(note_action) = resp.action {
+                                    action = Some(note_action);
+                                }
+
+                                if let Some(context) = resp.context_selection {
+                                    if let Some(note_action) = context.process(ui, &note) {
+                                        action = Some(note_action);
+                                    }
+                                }
+
+                                if replies > 0 && toggle_ui(ui, replies, collapsed) {
+                                    toggled = Some(id);
+                                }
+                            });
+
+                            if selected && scroll_to_selected {
+                                resp.response.scroll_to_me(Some(egui::Align::Center));
+                                scrolled = true;
+                            }
+                        }
+
+                        ThreadRow::Pending {
+                            id,
+                            depth,
+                            replies,
+                            collapsed,
+                        } => {
+                            self.missing_notes.want(&id, &[]);
+                            let not_found =
+                                self.missing_notes.state(&id) == Some(FetchState::NotFound);
+
+                            indented(ui, depth, false, |ui| {
+                                ui.horizontal(|ui| {
+                                    let text = if not_found {
+                                        "Note not found"
+                                    } else {
+                                        "Fetching a note in this thread..."
+                                    };
+                                    ui.label(
+                                        egui::RichText::new(text)
+                                            .color(colors::GRAY_SECONDARY)
+                                            .italics(),
+                                    );
+
+                                    if not_found && ui.small_button("Try again").clicked() {
+                                        self.missing_notes.retry(&id);
+                                    }
+                                });
+
+                                if replies > 0 && toggle_ui(ui, replies, collapsed) {
+                                    toggled = Some(id);
+                                }
+                            });
+                        }
+                    }
+
+                    ui::hline(ui);
+                    1
+                });
+
+                if scrolled {
+                    thread.scrolled_to = Some(selected_id);
+                }
+
+                if let Some(id) = toggled {
+                    thread.toggle(id);
+                }
+
+                action
             })
             .inner
     }
 }
+
+impl<'a> ThreadView<'a> {
+    /// We don't have the note that was opened. Go look for it, and say so
+    /// when nobody has it.
+    fn missing_note_ui(&mut self, ui: &mut egui::Ui) {
+        let id = NoteId::new(*self.selected_note_id);
+        self.missing_notes.want(&id, &[]);
+
+        ui::padding(16.0, ui, |ui| {
+            ui.vertical_centered(|ui| {
+                if self.missing_notes.state(&id) == Some(FetchState::NotFound) {
+                    ui.heading("Note not found");
+                    ui.label(
+                        egui::RichText::new(
+                            "None of the relays we asked have this note. It may have been deleted.",
+                        )
+                        .color(colors::GRAY_SECONDARY),
+                    );
+                    ui.add_space(8.0);
+                    if ui.button("Try again").clicked() {
+                        self.missing_notes.retry(&id);
+                    }
+                } else {
+                    ui.spinner();
+                    ui.label(
+                        egui::RichText::new("Looking for this note...")
+                            .color(colors::GRAY_SECONDARY),
+                    );
+                }
+            });
+        });
+    }
+}
+
+/// Walk up the thread from the selected note. The notes it replies to go
+/// into the thread, even when they don't tag the root, and the first one
+/// we don't have gets fetched, from the relay the reply says it's on too.
+fn fetch_ancestors(
+    txn: &Transaction,
+    ndb: &Ndb,
+    note_cache: &mut NoteCache,
+    missing_notes: &mut MissingNotes,
+    selected_note_key: NoteKey,
+    thread: &mut Thread,
+) {
+    let mut key = selected_note_key;
+
+    for _ in 0..MAX_ANCESTORS {
+        let note = if let Ok(note) = ndb.get_note_by_key(txn, key) {
+            note
+        } else {
+            return;
+        };
+
+        let (parent, root) = {
+            let cached_note = note_cache.cached_note_or_insert(key, &note);
+            let note_reply = cached_note.reply.borrow(note.tags());
+            let parent = note_reply
+                .reply()
+                .or_else(|| note_reply.root())
+                .map(|parent| (NoteId::new(*parent.id), parent.relay.map(str::to_owned)));
+            let root = note_reply
+                .root()
+                .map(|root| (NoteId::new(*root.id), root.relay.map(str::to_owned)));
+            (parent, root)
+        };
+
+        // we made it to the top
+        let (parent_id, parent_hint) = if let Some(parent) = parent {
+            parent
+        } else {
+            return;
+        };
+
+        if let Some((root_id, root_hint)) = root {
+            if ndb.get_note_by_id(txn, root_id.bytes()).is_err() {
+                missing_notes.want(
+                    &root_id,
+                    &root_hint.as_deref().into_iter().collect::<Vec<_>>(),
+                );
+            }
+        }
+
+        match ndb.get_note_by_id(txn, parent_id.bytes()) {
+            Ok(parent) => {
+                let note_ref = NoteRef::from_note(&parent);
+                thread.insert_note(note_ref);
+                key = note_ref.key;
+            }
+            Err(_) => {
+                missing_notes.want(
+                    &parent_id,
+                    &parent_hint.as_deref().into_iter().collect::<Vec<_>>(),
+                );
+                return;
+            }
+        }
+    }
+}
+
+/// Draw a row of the thread at its depth, with a line down the left of
+/// replies so you can follow the branch
+fn indented<R>(
+    ui: &mut egui::Ui,
+    depth: usize,
+    selected: bool,
+    add_contents: impl FnOnce(&mut egui::Ui) -> R,
+) -> egui::InnerResponse<R> {
+    let indent = INDENT * depth.min(MAX_INDENT_DEPTH) as f32;
+    let fill = if selected {
+        ui.visuals().selection.bg_fill.gamma_multiply(0.15)
+    } else {
+        egui::Color32::TRANSPARENT
+    };
+
+    let resp = egui::Frame::none()
+        .fill(fill)
+        .inner_margin(egui::Margin {
+            left: 8.0 + indent,
+            right: 8.0,
+            top: 8.0,
+            bottom: 8.0,
+        })
+        .show(ui, |ui| ui.vertical(add_contents).inner);
+
+    if depth > 0 {
+        let x = resp.response.rect.left() + indent;
+        let stroke = ui.style().visuals.widgets.noninteractive.bg_stroke;
+        ui.painter().vline(x, resp.response.rect.y_range(), stroke);
+    }
+
+    resp
+}
+
+/// Show or hide a note's replies. Returns true when clicked.
+fn toggle_ui(ui: &mut egui::Ui, replies: usize, collapsed: bool) -> bool {
+    let text = if collapsed {
+        if replies == 1 {
+            "▶ 1 more reply".to_owned()
+        } else {
+            format!("▶ {replies} more replies")
+        }
+    } else {
+        "▼ Hide replies".to_owned()
+    };
+
+    ui.add(
+        egui::Label::new(
+            egui::RichText::new(text)
+                .size(12.0)
+                .color(colors::GRAY_SECONDARY),
+        )
+        .sense(egui::Sense::click()),
+    )
+    .on_hover_cursor(egui::CursorIcon::PointingHand)
+    .clicked()
+}
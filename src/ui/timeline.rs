@@ -1,8 +1,9 @@
 use crate::actionbar::NoteAction;
-use crate::timeline::TimelineTab;
+use crate::timeline::{TabRow, TimelineTab, ViewFilter};
+use crate::wot::{TrustFilter, WebOfTrust};
 use crate::{
-    column::Columns, imgcache::ImageCache, notecache::NoteCache, timeline::TimelineId, ui,
-    ui::note::NoteOptions,
+    colors, column::Columns, imgcache::ImageCache, nip05::Nip05Verifier, notecache::NoteCache,
+    timeline::TimelineId, ui, ui::note::NoteOptions, ui::notifications::NotificationsView,
 };
 use egui::containers::scroll_area::ScrollBarVisibility;
 use egui::{Direction, Layout};
@@ -16,17 +17,22 @@ pub struct TimelineView<'a> {
     ndb: &'a Ndb,
     note_cache: &'a mut NoteCache,
     img_cache: &'a mut ImageCache,
+    nip05: &'a mut Nip05Verifier,
+    wot: &'a WebOfTrust,
     note_options: NoteOptions,
     reverse: bool,
 }
 
 impl<'a> TimelineView<'a> {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         timeline_id: TimelineId,
         columns: &'a mut Columns,
         ndb: &'a Ndb,
         note_cache: &'a mut NoteCache,
         img_cache: &'a mut ImageCache,
+        nip05: &'a mut Nip05Verifier,
+        wot: &'a WebOfTrust,
         note_options: NoteOptions,
     ) -> TimelineView<'a> {
         let reverse = false;
@@ -36,6 +42,8 @@ impl<'a> TimelineView<'a> {
             columns,
             note_cache,
             img_cache,
+            nip05,
+            wot,
             reverse,
             note_options,
         }
@@ -49,6 +57,8 @@ impl<'a> TimelineView<'a> {
             self.columns,
             self.note_cache,
             self.img_cache,
+            self.nip05,
+            self.wot,
             self.reverse,
             self.note_options,
         )
@@ -68,6 +78,8 @@ fn timeline_ui(
     columns: &mut Columns,
     note_cache: &mut NoteCache,
     img_cache: &mut ImageCache,
+    nip05: &mut Nip05Verifier,
+    wot: &WebOfTrust,
     reversed: bool,
     note_options: NoteOptions,
 ) -> Option<NoteAction> {
@@ -88,7 +100,8 @@ fn timeline_ui(
             return None;
         };
 
-        timeline.selected_view = tabs_ui(ui);
+        let view_filters = timeline.view_filters();
+        timeline.selected_view = tabs_ui(ui, &view_filters);
 
         // need this for some reason??
         ui.add_space(3.0);
@@ -112,6 +125,29 @@ fn timeline_ui(
             };
 
             let txn = Transaction::new(ndb).expect("failed to create txn");
+
+            // mentions are just notes, everything else gets grouped
+            if timeline.kind.is_notifications()
+                && timeline.current_view().filter != ViewFilter::Mentions
+            {
+                return NotificationsView::new(
+                    timeline.current_view(),
+                    timeline.view_id(),
+                    note_options,
+                    &txn,
+                    ndb,
+                    note_cache,
+                    img_cache,
+                    nip05,
+                )
+                .show(ui);
+            }
+
+            let trust_filter = timeline.trust_filter;
+            timeline
+                .current_view_mut()
+                .update_trust_rows(&txn, ndb, wot, trust_filter);
+
             TimelineTabView::new(
                 timeline.current_view(),
                 reversed,
@@ -120,17 +156,39 @@ fn timeline_ui(
                 ndb,
                 note_cache,
                 img_cache,
+                nip05,
             )
             .show(ui)
         })
         .inner
 }
 
-pub fn tabs_ui(ui: &mut egui::Ui) -> i32 {
+/// Pick whose notes a column shows, by the selected account's web of
+/// trust. Returns true when it changed.
+pub fn trust_filter_ui(ui: &mut egui::Ui, id: egui::Id, filter: &mut TrustFilter) -> bool {
+    let mut changed = false;
+
+    egui::ComboBox::from_id_source(id)
+        .selected_text(format!("🛡 {}", filter.name()))
+        .show_ui(ui, |ui| {
+            for choice in TrustFilter::CHOICES {
+                changed |= ui.selectable_value(filter, choice, choice.name()).changed();
+            }
+        })
+        .response
+        .on_hover_text("Hide notes from people outside the web of trust of your follows");
+
+    changed
+}
+
+pub fn tabs_ui(ui: &mut egui::Ui, tabs: &[ViewFilter]) -> i32 {
     ui.spacing_mut().item_spacing.y = 0.0;
 
-    let tab_res = egui_tabs::Tabs::new(2)
-        .selected(1)
+    // start on the tab that shows everything
+    let default_tab = tabs.iter().position(|t| t.is_unfiltered()).unwrap_or(0);
+
+    let tab_res = egui_tabs::Tabs::new(tabs.len() as i32)
+        .selected(default_tab as i32)
         .hover_bg(TabColor::none())
         .selected_fg(TabColor::none())
         .selected_bg(TabColor::none())
@@ -143,7 +201,7 @@ pub fn tabs_ui(ui: &mut egui::Ui) -> i32 {
 
             let ind = state.index();
 
-            let txt = if ind == 0 { "Notes" } else { "Notes & Replies" };
+            let txt = tabs[ind as usize].name();
 
             let res = ui.add(egui::Label::new(txt).selectable(false));
 
@@ -218,9 +276,11 @@ pub struct TimelineTabView<'a> {
     ndb: &'a Ndb,
     note_cache: &'a mut NoteCache,
     img_cache: &'a mut ImageCache,
+    nip05: &'a mut Nip05Verifier,
 }
 
 impl<'a> TimelineTabView<'a> {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         tab: &'a TimelineTab,
         reversed: bool,
@@ -229,6 +289,7 @@ impl<'a> TimelineTabView<'a> {
         ndb: &'a Ndb,
         note_cache: &'a mut NoteCache,
         img_cache: &'a mut ImageCache,
+        nip05: &'a mut Nip05Verifier,
     ) -> Self {
         Self {
             tab,
@@ -238,15 +299,17 @@ impl<'a> TimelineTabView<'a> {
             ndb,
             note_cache,
             img_cache,
+            nip05,
         }
     }
 
     pub fn show(&mut self, ui: &mut egui::Ui) -> Option<NoteAction> {
         let mut action: Option<NoteAction> = None;
-        let len = self.tab.notes.len();
+        let tab = self.tab;
+        let rows = tab.trust_rows();
+        let len = rows.map_or(tab.notes.len(), <[TabRow]>::len);
 
-        self.tab
-            .list
+        tab.list
             .clone()
             .borrow_mut()
             .ui_custom_layout(ui, len, |ui, start_index| {
@@ -259,7 +322,16 @@ impl<'a> TimelineTabView<'a> {
                     start_index
                 };
 
-                let note_key = self.tab.notes[ind].key;
+                let ind = match rows.map(|rows| &rows[ind]) {
+                    None => ind,
+                    Some(TabRow::Note(note_ind)) => *note_ind,
+                    Some(TabRow::Hidden { count, reasons }) => {
+                        hidden_notes_ui(ui, *count, reasons);
+                        return 1;
+                    }
+                };
+
+                let note_key = tab.notes[ind].key;
 
                 let note = if let Ok(note) = self.ndb.get_note_by_key(self.txn, note_key) {
                     note
@@ -269,16 +341,24 @@ impl<'a> TimelineTabView<'a> {
                 };
 
                 ui::padding(8.0, ui, |ui| {
-                    let resp = ui::NoteView::new(self.ndb, self.note_cache, self.img_cache, &note)
-                        .note_options(self.note_options)
-                        .show(ui);
+                    let resp = ui::NoteView::new(
+                        self.ndb,
+                        self.note_cache,
+                        self.img_cache,
+                        self.nip05,
+                        &note,
+                    )
+                    .note_options(self.note_options)
+                    .show(ui);
 
                     if let Some(note_action) = resp.action {
                         action = Some(note_action)
                     }
 
                     if let Some(context) = resp.context_selection {
-                        context.process(ui, &note);
+                        if let Some(note_action) = context.process(ui, &note) {
+                            action = Some(note_action);
+                        }
                     }
                 });
 
@@ -291,3 +371,41 @@ impl<'a> TimelineTabView<'a> {
         action
     }
 }
+
+/// How many hover reasons we list for a run of hidden notes
+const MAX_HIDDEN_REASONS: usize = 5;
+
+/// A run of notes a trust filter hides, and why when you hover it
+fn hidden_notes_ui(ui: &mut egui::Ui, count: usize, reasons: &[String]) {
+    let text = if count == 1 {
+        "1 note hidden by this column's trust filter".to_owned()
+    } else {
+        format!("{count} notes hidden by this column's trust filter")
+    };
+
+    let mut hover = reasons
+        .iter()
+        .take(MAX_HIDDEN_REASONS)
+        .cloned()
+        .collect::<Vec<_>>()
+        .join("\n");
+    if reasons.len() > MAX_HIDDEN_REASONS {
+        hover.push_str(&format!(
+            "\n…and {} more",
+            reasons.len() - MAX_HIDDEN_REASONS
+        ));
+    }
+
+    ui::padding(8.0, ui, |ui| {
+        ui.add(
+            egui::Label::new(
+                egui::RichText::new(text)
+                    .size(12.0)
+                    .color(colors::GRAY_SECONDARY),
+            )
+            .sense(egui::Sense::hover()),
+        )
+        .on_hover_text(hover);
+    });
+    ui::hline(ui);
+}
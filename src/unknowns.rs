@@ -1,12 +1,11 @@
 use crate::{
     column::Columns,
-    note::NoteRef,
+    note::{self, NoteRef},
     notecache::{CachedNote, NoteCache},
-    timeline::ViewFilter,
     Result,
 };
 
-use enostr::{Filter, NoteId, Pubkey};
+use enostr::{Filter, Nip19, NoteAddress, NoteId, Pubkey};
 use nostrdb::{BlockType, Mention, Ndb, Note, NoteKey, Transaction};
 use std::collections::HashSet;
 use std::time::{Duration, Instant};
@@ -193,6 +192,7 @@ impl UnknownIds {
         match unk_id {
             UnknownId::Pubkey(pk) => self.add_pubkey_if_missing(ndb, txn, pk),
             UnknownId::Id(note_id) => self.add_note_id_if_missing(ndb, txn, note_id),
+            UnknownId::Address(address) => self.add_address_if_missing(ndb, txn, address),
         }
     }
 
@@ -216,6 +216,16 @@ impl UnknownIds {
         self.mark_updated();
     }
 
+    pub fn add_address_if_missing(&mut self, ndb: &Ndb, txn: &Transaction, address: &NoteAddress) {
+        // we already have a version of this note, skip
+        if note::find_addressable(ndb, txn, address).is_some() {
+            return;
+        }
+
+        self.ids.insert(UnknownId::Address(address.clone()));
+        self.mark_updated();
+    }
+
     pub fn update(
         txn: &Transaction,
         unknown_ids: &mut UnknownIds,
@@ -238,10 +248,13 @@ impl UnknownIds {
     }
 }
 
-#[derive(Hash, Clone, Copy, PartialEq, Eq)]
+#[derive(Hash, Clone, PartialEq, Eq)]
 pub enum UnknownId {
     Pubkey(Pubkey),
     Id(NoteId),
+
+    /// An addressable note, like an article
+    Address(NoteAddress),
 }
 
 impl UnknownId {
@@ -258,6 +271,13 @@ impl UnknownId {
             _ => None,
         }
     }
+
+    pub fn is_address(&self) -> Option<&NoteAddress> {
+        match self {
+            UnknownId::Address(address) => Some(address),
+            _ => None,
+        }
+    }
 }
 
 /// Look for missing notes in various parts of notes that we see:
@@ -341,7 +361,13 @@ pub fn get_unknown_note_ids<'a>(
                     }
                 }
             },
-            _ => {}
+            _ => {
+                if let Ok(Nip19::Address(address)) = Nip19::parse(block.as_str()) {
+                    if note::find_addressable(ndb, txn, &address).is_none() {
+                        ids.insert(UnknownId::Address(address));
+                    }
+                }
+            }
         }
     }
 
@@ -361,7 +387,7 @@ fn get_unknown_ids(
     let mut new_cached_notes: Vec<(NoteKey, CachedNote)> = vec![];
 
     for timeline in columns.timelines() {
-        for noteref in timeline.notes(ViewFilter::NotesAndReplies) {
+        for noteref in timeline.all_notes() {
             let note = ndb.get_note_by_key(txn, noteref.key)?;
             let note_key = note.key().unwrap();
             let cached_note = note_cache.cached_note(noteref.key);
@@ -411,5 +437,11 @@ fn get_unknown_ids_filter(ids: &[&UnknownId]) -> Option<Vec<Filter>> {
         filters.push(Filter::new().ids(note_ids).build());
     }
 
+    filters.extend(
+        ids.iter()
+            .flat_map(|id| id.is_address())
+            .map(note::address_filter),
+    );
+
     Some(filters)
 }
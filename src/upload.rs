@@ -0,0 +1,133 @@
+//! Uploading images to a Blossom server (BUD-02), ie. for profile
+//! pictures and banners
+
+use crate::{proxy::Http, Error, Result};
+
+use base64::prelude::{Engine, BASE64_STANDARD};
+use enostr::hashes::{sha256, Hash};
+use enostr::FilledKeypair;
+use nostrdb::NoteBuilder;
+use poll_promise::Promise;
+use serde::Deserialize;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Where we upload to
+pub const BLOSSOM_SERVER: &str = "https://blossom.primal.net";
+
+/// How long the server may use our upload authorization for, in seconds
+const AUTH_EXPIRATION: u64 = 300;
+
+/// The server's description of a blob we uploaded
+#[derive(Deserialize)]
+struct BlobDescriptor {
+    url: String,
+}
+
+/// Upload a file and get its url back. The file is read and signed for
+/// off the UI thread.
+pub fn upload_file(http: &Http, path: &Path, kp: FilledKeypair) -> Promise<Result<String>> {
+    let http = http.clone();
+    let path = path.to_owned();
+    let kp = kp.to_full();
+    Promise::spawn_async(async move {
+        let bytes = std::fs::read(&path)
+            .map_err(|e| Error::Generic(format!("couldn't read {}: {e}", path.display())))?;
+        let hash = hex::encode(sha256::Hash::hash(&bytes).to_byte_array());
+        let auth = upload_auth(kp.to_filled(), &hash)?;
+        put_blob(http.client()?, bytes, auth, content_type(&path)).await
+    })
+}
+
+async fn put_blob(
+    client: reqwest::Client,
+    bytes: Vec<u8>,
+    auth: String,
+    content_type: &'static str,
+) -> Result<String> {
+    let response = client
+        .put(format!("{BLOSSOM_SERVER}/upload"))
+        .header("Authorization", auth)
+        .header("Content-Type", content_type)
+        .body(bytes)
+        .send()
+        .await
+        .map_err(|e| Error::Generic(format!("upload failed: {e}")))?;
+
+    let status = response.status();
+    let body = response
+        .bytes()
+        .await
+        .map_err(|e| Error::Generic(format!("upload failed: {e}")))?;
+
+    if !status.is_success() {
+        return Err(Error::Generic(format!(
+            "upload failed with {status}: {}",
+            String::from_utf8_lossy(&body)
+        )));
+    }
+
+    serde_json::from_slice::<BlobDescriptor>(&body)
+        .map(|blob| blob.url)
+        .map_err(|e| Error::Generic(format!("unexpected upload response: {e}")))
+}
+
+/// The Authorization header value: a signed kind 24242 event that lets
+/// the server store a blob with this hash
+fn upload_auth(kp: FilledKeypair, hash: &str) -> Result<String> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let expiration = (now + AUTH_EXPIRATION).to_string();
+    let seckey = kp.secret_key.to_secret_bytes();
+
+    let note = NoteBuilder::new()
+        .kind(24242)
+        .content("Upload image")
+        .start_tag()
+        .tag_str("t")
+        .tag_str("upload")
+        .start_tag()
+        .tag_str("x")
+        .tag_str(hash)
+        .start_tag()
+        .tag_str("expiration")
+        .tag_str(&expiration)
+        .sign(&seckey)
+        .build()
+        .ok_or_else(|| Error::Generic("failed to build upload authorization".to_owned()))?;
+
+    let json = note
+        .json()
+        .map_err(|e| Error::Generic(format!("error serializing upload authorization: {e}")))?;
+
+    Ok(format!("Nostr {}", BASE64_STANDARD.encode(json)))
+}
+
+fn content_type(path: &Path) -> &'static str {
+    let ext = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase());
+
+    match ext.as_deref() {
+        Some("png") => "image/png",
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_content_type() {
+        assert_eq!(content_type(Path::new("/tmp/me.PNG")), "image/png");
+        assert_eq!(content_type(Path::new("banner.jpeg")), "image/jpeg");
+        assert_eq!(content_type(Path::new("noext")), "application/octet-stream");
+    }
+}
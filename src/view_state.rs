@@ -1,6 +1,13 @@
 use std::collections::HashMap;
 
-use crate::login_manager::AcquireKeyState;
+use crate::{
+    login_manager::AcquireKeyState,
+    ui::{
+        add_relay::AddRelayState, create_account::CreateAccountDraft,
+        profile::edit::EditProfileDraft, proxy_settings::ProxySettingsDraft,
+        signer_notice::SignerNotice,
+    },
+};
 
 /// Various state for views
 #[derive(Default)]
@@ -8,6 +15,19 @@ pub struct ViewState {
     pub login: AcquireKeyState,
     pub id_state_map: HashMap<egui::Id, AcquireKeyState>,
     pub id_string_map: HashMap<egui::Id, String>,
+    pub add_relay: AddRelayState,
+
+    /// Proxy settings being edited, if the proxy settings are open
+    pub proxy_settings: Option<ProxySettingsDraft>,
+
+    /// The account being created, if we're onboarding a new one
+    pub create_account: Option<CreateAccountDraft>,
+
+    /// Our profile as it's being edited, if the profile editor is open
+    pub edit_profile: Option<EditProfileDraft>,
+
+    /// What our remote signer needs from the user, if anything
+    pub signer_notice: Option<SignerNotice>,
 }
 
 impl ViewState {
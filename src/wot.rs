@@ -0,0 +1,518 @@
+//! A web of trust for the selected account, to keep spam out of open
+//! feeds like universe and hashtag columns.
+//!
+//! It comes from the contact lists (kind 3) and mute lists (kind 10000)
+//! in nostrdb: who the account follows and mutes, and who its follows
+//! follow and mute. We count how many of the account's follows follow or
+//! mute each pubkey, and update the counts one list at a time as new
+//! lists come in.
+
+use crate::{
+    contacts::contact_list_filter,
+    lists::list_pubkeys,
+    subscription_manager::SubLifetime,
+    subscriptions::{self, SubKind, Subscriptions},
+};
+
+use enostr::{Filter, Pubkey};
+use nostrdb::{Ndb, Note, Subscription, Transaction};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use tracing::{debug, error};
+
+const CONTACT_LIST: u32 = 3;
+const MUTE_LIST: u32 = 10000;
+
+/// How many authors go in one request for their lists
+const LISTS_CHUNK: usize = 250;
+
+/// A follow's mute says more than a follow's follow
+const MUTE_WEIGHT: i32 = 2;
+
+/// Who a column shows notes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum TrustFilter {
+    #[default]
+    Everyone,
+
+    /// Only people this many follows away from the account
+    WithinHops(u8),
+
+    /// Hide people whose [`Trust::score`] is below this
+    MinScore(i32),
+}
+
+impl TrustFilter {
+    /// What we offer in column settings
+    pub const CHOICES: [TrustFilter; 5] = [
+        TrustFilter::Everyone,
+        TrustFilter::WithinHops(1),
+        TrustFilter::WithinHops(2),
+        TrustFilter::MinScore(1),
+        TrustFilter::MinScore(3),
+    ];
+
+    pub fn name(&self) -> String {
+        match self {
+            TrustFilter::Everyone => "Everyone".to_owned(),
+            TrustFilter::WithinHops(1) => "People you follow".to_owned(),
+            TrustFilter::WithinHops(hops) => format!("Within {hops} hops"),
+            TrustFilter::MinScore(score) => format!("Trust score {score} or more"),
+        }
+    }
+}
+
+/// What the account's social graph says about a pubkey
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Trust {
+    /// 0 for the account itself, 1 for its follows and 2 for the people
+    /// they follow. None when they're further out than that.
+    pub hops: Option<u8>,
+
+    /// How many of the account's follows follow them
+    pub followed_by: u32,
+
+    /// How many of the account's follows mute them
+    pub muted_by: u32,
+
+    /// The account mutes them
+    pub muted: bool,
+}
+
+impl Trust {
+    pub fn score(&self) -> i32 {
+        self.followed_by as i32 - MUTE_WEIGHT * self.muted_by as i32
+    }
+}
+
+struct List {
+    created_at: u64,
+    pubkeys: Vec<Pubkey>,
+}
+
+#[derive(Default)]
+pub struct WebOfTrust {
+    account: Option<Pubkey>,
+
+    /// Watches nostrdb for new contact and mute lists
+    subscription: Option<Subscription>,
+
+    /// When the account's own lists were made, so older copies don't
+    /// replace them
+    contact_list_at: u64,
+    mute_list_at: u64,
+
+    follows: HashSet<Pubkey>,
+    muted: HashSet<Pubkey>,
+
+    /// The contact and mute lists of the account's follows
+    lists: HashMap<(Pubkey, u32), List>,
+
+    followed_by: HashMap<Pubkey, u32>,
+    muted_by: HashMap<Pubkey, u32>,
+
+    /// Follows whose lists we asked relays for
+    fetched: HashSet<Pubkey>,
+
+    /// Goes up whenever who we trust changes
+    generation: u64,
+}
+
+impl WebOfTrust {
+    /// Keep the graph up to date with the selected account and the lists
+    /// that showed up in nostrdb
+    pub fn poll(&mut self, ndb: &Ndb, subs: &mut Subscriptions, account: Option<&Pubkey>) {
+        if self.account.as_ref() != account {
+            self.switch_account(ndb, account);
+        }
+
+        let sub = if let Some(sub) = self.subscription {
+            sub
+        } else {
+            return;
+        };
+
+        let keys = ndb.poll_for_notes(sub, 500);
+        if !keys.is_empty() {
+            if let Ok(txn) = Transaction::new(ndb) {
+                for key in keys {
+                    if let Ok(note) = ndb.get_note_by_key(&txn, key) {
+                        self.process_list(ndb, &txn, &note);
+                    }
+                }
+            }
+        }
+
+        self.fetch_lists(subs);
+    }
+
+    /// Changes whenever the graph does, so callers know when to look again
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// What we know about a pubkey, relative to the selected account
+    pub fn trust(&self, pk: &Pubkey) -> Trust {
+        let followed_by = self.followed_by.get(pk).copied().unwrap_or(0);
+        let hops = if self.account.as_ref() == Some(pk) {
+            Some(0)
+        } else if self.follows.contains(pk) {
+            Some(1)
+        } else if followed_by > 0 {
+            Some(2)
+        } else {
+            None
+        };
+
+        Trust {
+            hops,
+            followed_by,
+            muted_by: self.muted_by.get(pk).copied().unwrap_or(0),
+            muted: self.muted.contains(pk),
+        }
+    }
+
+    /// Why a column with this filter hides notes from a pubkey, if it
+    /// does
+    pub fn hidden_reason(&self, pk: &[u8; 32], filter: TrustFilter) -> Option<String> {
+        // without follows there's no graph to go by
+        if filter == TrustFilter::Everyone || self.follows.is_empty() {
+            return None;
+        }
+
+        let trust = self.trust(&Pubkey::new(*pk));
+        if trust.hops == Some(0) {
+            return None;
+        }
+
+        if trust.muted {
+            return Some("You muted them".to_owned());
+        }
+
+        match filter {
+            TrustFilter::Everyone => None,
+
+            TrustFilter::WithinHops(max) => {
+                if trust.hops.map_or(false, |hops| hops <= max) {
+                    None
+                } else if max <= 1 {
+                    Some("You don't follow them".to_owned())
+                } else {
+                    Some("Nobody you follow follows them".to_owned())
+                }
+            }
+
+            TrustFilter::MinScore(min) => {
+                if trust.hops == Some(1) || trust.score() >= min {
+                    return None;
+                }
+
+                Some(format!(
+                    "Their trust score is {}, this column needs {}. Followed by {} and muted by {} of the people you follow.",
+                    trust.score(),
+                    min,
+                    trust.followed_by,
+                    trust.muted_by
+                ))
+            }
+        }
+    }
+
+    fn switch_account(&mut self, ndb: &Ndb, account: Option<&Pubkey>) {
+        if let Some(sub) = self.subscription.take() {
+            if let Err(err) = ndb.unsubscribe(sub) {
+                error!("couldn't stop watching lists: {err}");
+            }
+        }
+
+        *self = WebOfTrust {
+            account: account.copied(),
+            generation: self.generation + 1,
+            ..Default::default()
+        };
+
+        let account = if let Some(account) = account {
+            account
+        } else {
+            return;
+        };
+
+        let filter = Filter::new()
+            .kinds([CONTACT_LIST as u64, MUTE_LIST as u64])
+            .build();
+        match ndb.subscribe(&[filter]) {
+            Ok(sub) => self.subscription = Some(sub),
+            Err(err) => error!("couldn't watch lists for the web of trust: {err}"),
+        }
+
+        let txn = if let Ok(txn) = Transaction::new(ndb) {
+            txn
+        } else {
+            return;
+        };
+
+        // the account's contact list brings in everyone else's lists
+        let filters = [
+            contact_list_filter(account.bytes()),
+            Filter::new()
+                .authors([account.bytes()])
+                .kinds([MUTE_LIST as u64])
+                .limit(1)
+                .build(),
+        ];
+        if let Ok(results) = ndb.query(&txn, &filters, 20) {
+            for result in results {
+                self.process_list(ndb, &txn, &result.note);
+            }
+        }
+    }
+
+    fn process_list(&mut self, ndb: &Ndb, txn: &Transaction, note: &Note) {
+        let author = Pubkey::new(*note.pubkey());
+        let created_at = note.created_at();
+
+        if self.account == Some(author) {
+            match note.kind() {
+                CONTACT_LIST if created_at > self.contact_list_at => {
+                    self.contact_list_at = created_at;
+                    let added = self.set_follows(list_pubkeys(note).into_iter().collect());
+                    self.load_lists(ndb, txn, &added);
+                    self.generation += 1;
+                }
+                MUTE_LIST if created_at > self.mute_list_at => {
+                    self.mute_list_at = created_at;
+                    self.muted = list_pubkeys(note).into_iter().collect();
+                    self.generation += 1;
+                }
+                _ => {}
+            }
+        } else if self.follows.contains(&author) {
+            self.add_list(author, note.kind(), created_at, list_pubkeys(note));
+            self.generation += 1;
+        }
+    }
+
+    /// Use a new set of follows. Lists of people we stopped following
+    /// stop counting. Returns the new follows.
+    fn set_follows(&mut self, follows: HashSet<Pubkey>) -> Vec<Pubkey> {
+        let removed: Vec<Pubkey> = self.follows.difference(&follows).copied().collect();
+        for pk in removed {
+            for kind in [CONTACT_LIST, MUTE_LIST] {
+                if let Some(list) = self.lists.remove(&(pk, kind)) {
+                    self.count(kind, &list.pubkeys, false);
+                }
+            }
+        }
+
+        let added = follows.difference(&self.follows).copied().collect();
+        self.follows = follows;
+        added
+    }
+
+    /// Count a follow's list, instead of any older one we had
+    fn add_list(&mut self, author: Pubkey, kind: u32, created_at: u64, pubkeys: Vec<Pubkey>) {
+        if kind != CONTACT_LIST && kind != MUTE_LIST {
+            return;
+        }
+
+        let key = (author, kind);
+        if let Some(old) = self.lists.get(&key) {
+            if old.created_at >= created_at {
+                return;
+            }
+        }
+
+        if let Some(old) = self.lists.remove(&key) {
+            self.count(kind, &old.pubkeys, false);
+        }
+
+        let mut seen = HashSet::new();
+        let pubkeys: Vec<Pubkey> = pubkeys.into_iter().filter(|pk| seen.insert(*pk)).collect();
+        self.count(kind, &pubkeys, true);
+        self.lists.insert(
+            key,
+            List {
+                created_at,
+                pubkeys,
+            },
+        );
+    }
+
+    fn count(&mut self, kind: u32, pubkeys: &[Pubkey], add: bool) {
+        let counts = if kind == CONTACT_LIST {
+            &mut self.followed_by
+        } else {
+            &mut self.muted_by
+        };
+
+        for pk in pubkeys {
+            if add {
+                *counts.entry(*pk).or_default() += 1;
+            } else if let Some(count) = counts.get_mut(pk) {
+                *count = count.saturating_sub(1);
+                if *count == 0 {
+                    counts.remove(pk);
+                }
+            }
+        }
+    }
+
+    /// Count the lists of new follows that we already have
+    fn load_lists(&mut self, ndb: &Ndb, txn: &Transaction, authors: &[Pubkey]) {
+        for chunk in authors.chunks(LISTS_CHUNK) {
+            let results = match ndb.query(txn, &[lists_filter(chunk)], (chunk.len() * 4) as i32) {
+                Ok(results) => results,
+                Err(err) => {
+                    error!("couldn't load lists for the web of trust: {err}");
+                    continue;
+                }
+            };
+
+            for result in results {
+                let note = result.note;
+                let author = Pubkey::new(*note.pubkey());
+                self.add_list(author, note.kind(), note.created_at(), list_pubkeys(&note));
+            }
+        }
+    }
+
+    /// Ask relays for the lists of follows we haven't asked about. They
+    /// come back through our nostrdb subscription.
+    fn fetch_lists(&mut self, subs: &mut Subscriptions) {
+        let wanted: Vec<Pubkey> = self
+            .follows
+            .iter()
+            .filter(|pk| !self.fetched.contains(*pk))
+            .copied()
+            .collect();
+
+        if wanted.is_empty() {
+            return;
+        }
+
+        debug!("fetching lists of {} follows", wanted.len());
+        for chunk in wanted.chunks(LISTS_CHUNK) {
+            let subid = subscriptions::new_sub_id();
+            subs.subs.insert(subid.clone(), SubKind::OneShot);
+            subs.manager
+                .subscribe(subid, vec![lists_filter(chunk)], SubLifetime::OneShot);
+        }
+
+        self.fetched.extend(wanted);
+    }
+}
+
+fn lists_filter(authors: &[Pubkey]) -> Filter {
+    Filter::new()
+        .authors(authors.iter().map(|pk| pk.bytes()))
+        .kinds([CONTACT_LIST as u64, MUTE_LIST as u64])
+        .build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pk(n: u8) -> Pubkey {
+        Pubkey::new([n; 32])
+    }
+
+    fn graph() -> WebOfTrust {
+        let mut wot = WebOfTrust {
+            account: Some(pk(0)),
+            ..Default::default()
+        };
+        wot.set_follows([pk(1), pk(2)].into_iter().collect());
+        wot.add_list(pk(1), CONTACT_LIST, 10, vec![pk(3), pk(4), pk(3)]);
+        wot.add_list(pk(2), CONTACT_LIST, 10, vec![pk(3)]);
+        wot.add_list(pk(2), MUTE_LIST, 10, vec![pk(4), pk(5)]);
+        wot
+    }
+
+    #[test]
+    fn test_trust() {
+        let wot = graph();
+
+        assert_eq!(wot.trust(&pk(0)).hops, Some(0));
+        assert_eq!(wot.trust(&pk(1)).hops, Some(1));
+
+        let trust = wot.trust(&pk(3));
+        assert_eq!(trust.hops, Some(2));
+        assert_eq!(trust.followed_by, 2);
+        assert_eq!(trust.score(), 2);
+
+        let trust = wot.trust(&pk(4));
+        assert_eq!((trust.followed_by, trust.muted_by), (1, 1));
+        assert_eq!(trust.score(), 1 - MUTE_WEIGHT);
+
+        assert_eq!(wot.trust(&pk(9)).hops, None);
+    }
+
+    #[test]
+    fn test_newer_lists_replace_older_ones() {
+        let mut wot = graph();
+
+        wot.add_list(pk(1), CONTACT_LIST, 5, vec![pk(9)]);
+        assert_eq!(wot.trust(&pk(9)).followed_by, 0);
+
+        wot.add_list(pk(1), CONTACT_LIST, 20, vec![pk(9)]);
+        assert_eq!(wot.trust(&pk(9)).followed_by, 1);
+        assert_eq!(wot.trust(&pk(3)).followed_by, 1);
+        assert_eq!(wot.trust(&pk(4)).followed_by, 0);
+    }
+
+    #[test]
+    fn test_unfollowing_stops_counting_their_lists() {
+        let mut wot = graph();
+
+        let added = wot.set_follows([pk(1), pk(7)].into_iter().collect());
+        assert_eq!(added, vec![pk(7)]);
+        assert_eq!(wot.trust(&pk(3)).followed_by, 1);
+        assert_eq!(wot.trust(&pk(5)).muted_by, 0);
+        assert_eq!(wot.trust(&pk(2)).hops, None);
+    }
+
+    #[test]
+    fn test_hidden_reason() {
+        let mut wot = graph();
+        wot.muted.insert(pk(8));
+
+        assert_eq!(
+            wot.hidden_reason(pk(9).bytes(), TrustFilter::Everyone),
+            None
+        );
+        assert_eq!(
+            wot.hidden_reason(pk(0).bytes(), TrustFilter::WithinHops(1)),
+            None
+        );
+        assert_eq!(
+            wot.hidden_reason(pk(1).bytes(), TrustFilter::MinScore(3)),
+            None
+        );
+        assert_eq!(
+            wot.hidden_reason(pk(3).bytes(), TrustFilter::WithinHops(2)),
+            None
+        );
+
+        assert!(wot
+            .hidden_reason(pk(3).bytes(), TrustFilter::WithinHops(1))
+            .is_some());
+        assert!(wot
+            .hidden_reason(pk(9).bytes(), TrustFilter::WithinHops(2))
+            .is_some());
+        assert!(wot
+            .hidden_reason(pk(4).bytes(), TrustFilter::MinScore(1))
+            .is_some());
+        assert_eq!(
+            wot.hidden_reason(pk(8).bytes(), TrustFilter::WithinHops(2)),
+            Some("You muted them".to_owned())
+        );
+
+        // no follows, no graph
+        let wot = WebOfTrust::default();
+        assert_eq!(
+            wot.hidden_reason(pk(9).bytes(), TrustFilter::WithinHops(1)),
+            None
+        );
+    }
+}